@@ -0,0 +1,297 @@
+//! CC mapping import/export
+//!
+//! JSON files hold the full mapping structure. CSV files hold one target per
+//! row so controller templates can be edited in a spreadsheet:
+//!
+//! ```text
+//! source_cc,target_cc,channels,mode,curve,offset
+//! 1,74,1 2,continuous,linear,0
+//! 64,64,1,toggle,linear,0
+//! 20,21,10,threshold:100,exponential,-5
+//! 65,80,1,momentary:50,linear,0
+//! ```
+//!
+//! Note triggers, high-res output, inversion, output ranges, encoder modes
+//! and calibration are only kept in JSON.
+
+use crate::types::{CcCurve, CcMapping, CcTarget, CcValueMode};
+use std::fs;
+use std::path::Path;
+
+const CSV_HEADER: &str = "source_cc,target_cc,channels,mode,curve,offset";
+
+/// Write mappings to a file, as CSV if the extension is `.csv`, JSON otherwise
+pub fn export_cc_mappings(mappings: &[CcMapping], path: &Path) -> Result<(), String> {
+    let contents = if is_csv(path) {
+        mappings_to_csv(mappings)
+    } else {
+        serde_json::to_string_pretty(mappings).map_err(|e| e.to_string())?
+    };
+    fs::write(path, contents).map_err(|e| e.to_string())
+}
+
+/// Read mappings from a CSV or JSON file
+pub fn import_cc_mappings(path: &Path) -> Result<Vec<CcMapping>, String> {
+    let contents = fs::read_to_string(path).map_err(|e| e.to_string())?;
+    if is_csv(path) {
+        mappings_from_csv(&contents)
+    } else {
+        serde_json::from_str(&contents).map_err(|e| e.to_string())
+    }
+}
+
+/// Merge imported mappings into existing ones.
+/// An imported mapping replaces an existing mapping for the same source CC.
+pub fn merge_cc_mappings(existing: &mut Vec<CcMapping>, imported: Vec<CcMapping>) {
+    for mapping in imported {
+        existing.retain(|m| m.source_cc != mapping.source_cc);
+        existing.push(mapping);
+    }
+    existing.sort_by_key(|m| m.source_cc);
+}
+
+fn is_csv(path: &Path) -> bool {
+    path.extension()
+        .and_then(|e| e.to_str())
+        .is_some_and(|e| e.eq_ignore_ascii_case("csv"))
+}
+
+pub fn mappings_to_csv(mappings: &[CcMapping]) -> String {
+    let mut lines = vec![CSV_HEADER.to_string()];
+    for mapping in mappings {
+        for target in &mapping.targets {
+            let channels: Vec<String> = target.channels.iter().map(|c| c.to_string()).collect();
+            let mode = match target.mode {
+                CcValueMode::Continuous => "continuous".to_string(),
+                CcValueMode::Toggle => "toggle".to_string(),
+                CcValueMode::Threshold { threshold } => format!("threshold:{}", threshold),
+                CcValueMode::Momentary { pulse_ms } => format!("momentary:{}", pulse_ms),
+            };
+            let curve = match target.curve {
+                CcCurve::Linear => "linear",
+                CcCurve::Exponential => "exponential",
+                CcCurve::Logarithmic => "logarithmic",
+                CcCurve::SCurve => "s-curve",
+            };
+            lines.push(format!(
+                "{},{},{},{},{},{}",
+                mapping.source_cc,
+                target.cc,
+                channels.join(" "),
+                mode,
+                curve,
+                target.offset
+            ));
+        }
+    }
+    lines.join("\n") + "\n"
+}
+
+/// Parse CSV rows into mappings. Rows with the same source CC are grouped
+/// into one mapping. Only the first two columns are required.
+pub fn mappings_from_csv(contents: &str) -> Result<Vec<CcMapping>, String> {
+    let mut mappings: Vec<CcMapping> = Vec::new();
+
+    for (index, line) in contents.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with("source_cc") {
+            continue;
+        }
+        let row = index + 1;
+        let fields: Vec<&str> = line.split(',').map(str::trim).collect();
+        let field = |i: usize| fields.get(i).copied().unwrap_or("");
+
+        let source_cc = parse_cc(field(0), row)?;
+        let target = CcTarget {
+            cc: parse_cc(field(1), row)?,
+            channels: parse_channels(field(2), row)?,
+            mode: parse_mode(field(3), row)?,
+            curve: parse_curve(field(4), row)?,
+            offset: parse_offset(field(5), row)?,
+            ..Default::default()
+        };
+
+        match mappings.iter_mut().find(|m| m.source_cc == source_cc) {
+            Some(mapping) => mapping.targets.push(target),
+            None => mappings.push(CcMapping {
+                source_cc,
+                targets: vec![target],
+                ..Default::default()
+            }),
+        }
+    }
+
+    Ok(mappings)
+}
+
+fn parse_cc(field: &str, row: usize) -> Result<u8, String> {
+    match field.parse::<u8>() {
+        Ok(cc) if cc <= 127 => Ok(cc),
+        _ => Err(format!("Row {}: invalid CC number '{}'", row, field)),
+    }
+}
+
+/// Channels are 1-16, separated by spaces or semicolons. Empty means channel 1.
+fn parse_channels(field: &str, row: usize) -> Result<Vec<u8>, String> {
+    let channels = field
+        .split([' ', ';'])
+        .filter(|s| !s.is_empty())
+        .map(|s| match s.parse::<u8>() {
+            Ok(ch) if (1..=16).contains(&ch) => Ok(ch),
+            _ => Err(format!("Row {}: invalid channel '{}'", row, s)),
+        })
+        .collect::<Result<Vec<u8>, String>>()?;
+    Ok(if channels.is_empty() {
+        vec![1]
+    } else {
+        channels
+    })
+}
+
+fn parse_mode(field: &str, row: usize) -> Result<CcValueMode, String> {
+    let lower = field.to_ascii_lowercase();
+    match lower.as_str() {
+        "" | "continuous" => Ok(CcValueMode::Continuous),
+        "toggle" => Ok(CcValueMode::Toggle),
+        _ => lower
+            .strip_prefix("threshold:")
+            .and_then(|t| t.parse::<u8>().ok())
+            .filter(|t| *t <= 127)
+            .map(|threshold| CcValueMode::Threshold { threshold })
+            .or_else(|| {
+                lower
+                    .strip_prefix("momentary:")
+                    .and_then(|p| p.parse::<u32>().ok())
+                    .map(|pulse_ms| CcValueMode::Momentary { pulse_ms })
+            })
+            .ok_or_else(|| format!("Row {}: invalid mode '{}'", row, field)),
+    }
+}
+
+fn parse_curve(field: &str, row: usize) -> Result<CcCurve, String> {
+    match field.to_ascii_lowercase().as_str() {
+        "" | "linear" => Ok(CcCurve::Linear),
+        "exponential" => Ok(CcCurve::Exponential),
+        "logarithmic" => Ok(CcCurve::Logarithmic),
+        "s-curve" => Ok(CcCurve::SCurve),
+        _ => Err(format!("Row {}: invalid curve '{}'", row, field)),
+    }
+}
+
+fn parse_offset(field: &str, row: usize) -> Result<i8, String> {
+    if field.is_empty() {
+        return Ok(0);
+    }
+    field
+        .parse::<i8>()
+        .map_err(|_| format!("Row {}: invalid offset '{}'", row, field))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn csv_round_trip() {
+        let mappings = vec![
+            CcMapping {
+                source_cc: 1,
+                targets: vec![
+                    CcTarget {
+                        cc: 74,
+                        channels: vec![1, 2],
+                        ..Default::default()
+                    },
+                    CcTarget {
+                        cc: 71,
+                        channels: vec![3],
+                        mode: CcValueMode::Threshold { threshold: 100 },
+                        curve: CcCurve::Exponential,
+                        offset: -5,
+                        ..Default::default()
+                    },
+                ],
+                ..Default::default()
+            },
+            CcMapping {
+                source_cc: 64,
+                targets: vec![CcTarget {
+                    cc: 64,
+                    channels: vec![10],
+                    mode: CcValueMode::Toggle,
+                    ..Default::default()
+                }],
+                ..Default::default()
+            },
+            CcMapping {
+                source_cc: 65,
+                targets: vec![CcTarget {
+                    cc: 80,
+                    channels: vec![1],
+                    mode: CcValueMode::Momentary { pulse_ms: 50 },
+                    ..Default::default()
+                }],
+                ..Default::default()
+            },
+        ];
+
+        let csv = mappings_to_csv(&mappings);
+        assert!(csv.starts_with(CSV_HEADER));
+        let parsed = mappings_from_csv(&csv).unwrap();
+
+        assert_eq!(parsed.len(), 3);
+        assert_eq!(parsed[0].targets.len(), 2);
+        assert_eq!(parsed[0].targets[0].channels, vec![1, 2]);
+        assert_eq!(
+            parsed[0].targets[1].mode,
+            CcValueMode::Threshold { threshold: 100 }
+        );
+        assert_eq!(parsed[0].targets[1].curve, CcCurve::Exponential);
+        assert_eq!(parsed[0].targets[1].offset, -5);
+        assert_eq!(parsed[1].targets[0].mode, CcValueMode::Toggle);
+        assert_eq!(
+            parsed[2].targets[0].mode,
+            CcValueMode::Momentary { pulse_ms: 50 }
+        );
+    }
+
+    #[test]
+    fn csv_optional_columns_default() {
+        let parsed = mappings_from_csv("7,11\n").unwrap();
+        let target = &parsed[0].targets[0];
+        assert_eq!(target.cc, 11);
+        assert_eq!(target.channels, vec![1]);
+        assert_eq!(target.mode, CcValueMode::Continuous);
+        assert_eq!(target.offset, 0);
+    }
+
+    #[test]
+    fn csv_errors_name_the_row() {
+        let err = mappings_from_csv("source_cc,target_cc\n1,74\n1,200\n").unwrap_err();
+        assert!(err.contains("Row 3"), "{}", err);
+
+        let err = mappings_from_csv("1,74,17\n").unwrap_err();
+        assert!(err.contains("channel"), "{}", err);
+    }
+
+    #[test]
+    fn merge_replaces_same_source_cc() {
+        let mapping = |source_cc, cc| CcMapping {
+            source_cc,
+            targets: vec![CcTarget {
+                cc,
+                channels: vec![1],
+                ..Default::default()
+            }],
+            ..Default::default()
+        };
+        let mut existing = vec![mapping(2, 20), mapping(1, 10)];
+        merge_cc_mappings(&mut existing, vec![mapping(1, 11), mapping(3, 30)]);
+
+        let summary: Vec<(u8, u8)> = existing
+            .iter()
+            .map(|m| (m.source_cc, m.targets[0].cc))
+            .collect();
+        assert_eq!(summary, vec![(1, 11), (2, 20), (3, 30)]);
+    }
+}
@@ -0,0 +1,214 @@
+//! MIDNAM (MIDI Name Document) import
+//!
+//! Reads controller names and patch lists from `.midnam` files. Only the
+//! parts needed for naming are read: `Manufacturer`, `Model`, `Control`,
+//! `PatchBank` (bank select from its `MIDICommands`) and `Patch`.
+
+use crate::types::{DeviceDefinition, PatchName};
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+
+/// Read a `.midnam` file into a device definition (not attached to any port)
+pub fn import_midnam(path: &Path) -> Result<DeviceDefinition, String> {
+    let xml = fs::read_to_string(path).map_err(|e| e.to_string())?;
+    parse_midnam(&xml)
+}
+
+pub fn parse_midnam(xml: &str) -> Result<DeviceDefinition, String> {
+    let mut manufacturer = None;
+    let mut model = None;
+    let mut cc_names = Vec::new();
+    let mut patches = Vec::new();
+    let mut bank: (Option<u8>, Option<u8>) = (None, None);
+
+    let mut rest = xml;
+    while let Some(start) = rest.find('<') {
+        let Some(end) = rest[start..].find('>') else {
+            return Err("Unterminated tag".to_string());
+        };
+        let tag = &rest[start + 1..start + end];
+        let after = &rest[start + end + 1..];
+        rest = after;
+
+        if tag.starts_with('?') || tag.starts_with('!') || tag.starts_with('/') {
+            continue;
+        }
+        let tag = tag.trim_end_matches('/');
+        let (name, attrs) = match tag.split_once(char::is_whitespace) {
+            Some((name, attrs)) => (name, parse_attributes(attrs)),
+            None => (tag, HashMap::new()),
+        };
+
+        match name {
+            "Manufacturer" => manufacturer = element_text(after),
+            "Model" => model = model.or_else(|| element_text(after)),
+            "PatchBank" => bank = (None, None),
+            "ControlChange" => {
+                let control = attrs.get("Control").and_then(|v| parse_u7(v));
+                let value = attrs.get("Value").and_then(|v| parse_u7(v));
+                match control {
+                    Some(0) => bank.0 = value,
+                    Some(32) => bank.1 = value,
+                    _ => {}
+                }
+            }
+            "Control" => {
+                let number = attrs.get("Number").and_then(|v| parse_u7(v));
+                if let (Some(number), Some(name)) = (number, attrs.get("Name")) {
+                    cc_names.push((number, name.clone()));
+                }
+            }
+            "Patch" => {
+                // ProgramChange is 0-based; Number is a display label
+                let program = attrs
+                    .get("ProgramChange")
+                    .or_else(|| attrs.get("Number"))
+                    .and_then(|v| parse_u7(v));
+                if let (Some(program), Some(name)) = (program, attrs.get("Name")) {
+                    patches.push(PatchName {
+                        bank_msb: bank.0,
+                        bank_lsb: bank.1,
+                        program,
+                        name: name.clone(),
+                    });
+                }
+            }
+            _ => {}
+        }
+    }
+
+    if cc_names.is_empty() && patches.is_empty() {
+        return Err("No controller or patch names found".to_string());
+    }
+
+    let name = match (manufacturer, model) {
+        (Some(manufacturer), Some(model)) => format!("{} {}", manufacturer, model),
+        (None, Some(model)) => model,
+        (Some(manufacturer), None) => manufacturer,
+        (None, None) => "Unnamed Device".to_string(),
+    };
+
+    let mut device = DeviceDefinition::new(name);
+    device.cc_names = cc_names.into_iter().collect();
+    device.patches = patches;
+    Ok(device)
+}
+
+fn parse_attributes(attrs: &str) -> HashMap<String, String> {
+    let mut result = HashMap::new();
+    let mut rest = attrs;
+    while let Some(eq) = rest.find('=') {
+        let key = rest[..eq].trim().to_string();
+        let value_part = rest[eq + 1..].trim_start();
+        let Some(quote) = value_part
+            .chars()
+            .next()
+            .filter(|c| *c == '"' || *c == '\'')
+        else {
+            break;
+        };
+        let Some(len) = value_part[1..].find(quote) else {
+            break;
+        };
+        result.insert(key, unescape(&value_part[1..1 + len]));
+        rest = &value_part[len + 2..];
+    }
+    result
+}
+
+/// Text content up to the next tag
+fn element_text(after: &str) -> Option<String> {
+    let text = after[..after.find('<').unwrap_or(after.len())].trim();
+    (!text.is_empty()).then(|| unescape(text))
+}
+
+fn parse_u7(value: &str) -> Option<u8> {
+    value.trim().parse::<u8>().ok().filter(|v| *v <= 127)
+}
+
+fn unescape(text: &str) -> String {
+    text.replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&apos;", "'")
+        .replace("&amp;", "&")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MIDNAM: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<!DOCTYPE MIDINameDocument PUBLIC "-//MIDI Manufacturers Association//DTD MIDINameDocument 1.0//EN" "http://www.midi.org/dtds/MIDINameDocument10.dtd">
+<MIDINameDocument>
+  <MasterDeviceNames>
+    <Manufacturer>Acme</Manufacturer>
+    <Model>Synth &amp; Co</Model>
+    <ChannelNameSet Name="Names">
+      <PatchBank Name="A">
+        <MIDICommands>
+          <ControlChange Control="0" Value="0"/>
+          <ControlChange Control="32" Value="1"/>
+        </MIDICommands>
+        <PatchNameList>
+          <Patch Number="001" Name="Piano" ProgramChange="0"/>
+          <Patch Number="002" Name="E. Piano" ProgramChange="1"/>
+        </PatchNameList>
+      </PatchBank>
+      <PatchBank Name="B">
+        <MIDICommands>
+          <ControlChange Control="0" Value="1"/>
+        </MIDICommands>
+        <PatchNameList>
+          <Patch Number="001" Name="Strings" ProgramChange="0"/>
+        </PatchNameList>
+      </PatchBank>
+    </ChannelNameSet>
+    <ControlNameList Name="Controls">
+      <Control Type="7bit" Number="74" Name="Filter Cutoff"/>
+      <Control Type="7bit" Number='71' Name='Resonance'/>
+    </ControlNameList>
+  </MasterDeviceNames>
+</MIDINameDocument>
+"#;
+
+    #[test]
+    fn parses_device_name() {
+        let device = parse_midnam(MIDNAM).unwrap();
+        assert_eq!(device.name, "Acme Synth & Co");
+        assert!(device.ports.is_empty());
+    }
+
+    #[test]
+    fn parses_control_names() {
+        let device = parse_midnam(MIDNAM).unwrap();
+        assert_eq!(device.cc_name(74), Some("Filter Cutoff"));
+        assert_eq!(device.cc_name(71), Some("Resonance"));
+        assert_eq!(device.cc_names.len(), 2);
+    }
+
+    #[test]
+    fn parses_patches_with_banks() {
+        let device = parse_midnam(MIDNAM).unwrap();
+        assert_eq!(device.patches.len(), 3);
+        assert_eq!(
+            device.patches[0],
+            PatchName {
+                bank_msb: Some(0),
+                bank_lsb: Some(1),
+                program: 0,
+                name: "Piano".to_string(),
+            }
+        );
+        assert_eq!(device.patches[2].bank_msb, Some(1));
+        assert_eq!(device.patches[2].bank_lsb, None);
+        assert_eq!(device.patch_name(Some((1, 0)), 0), Some("Strings"));
+    }
+
+    #[test]
+    fn rejects_documents_without_names() {
+        assert!(parse_midnam("<MIDINameDocument></MIDINameDocument>").is_err());
+        assert!(parse_midnam("<Control Number=\"1\"").is_err());
+    }
+}
@@ -0,0 +1,9 @@
+pub mod mapping_file;
+pub mod midnam;
+pub mod preset;
+pub mod report;
+pub mod setup_import;
+pub mod smf;
+pub mod storage;
+pub mod templates;
+pub mod tuning_file;
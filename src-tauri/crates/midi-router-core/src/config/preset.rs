@@ -0,0 +1,193 @@
+//! Preset load/save logic
+
+use crate::config::storage::{load_config, save_config};
+use crate::types::{
+    ClockSettings, ControlBindings, DeviceDefinition, GamepadMapping, MiddleC, MqttSettings,
+    NoteOffStyle, Preset, Route, WebBridgeSettings,
+};
+use std::collections::{BTreeMap, BTreeSet};
+use uuid::Uuid;
+
+pub fn list_presets() -> Vec<Preset> {
+    load_config().presets
+}
+
+pub fn get_preset(id: Uuid) -> Option<Preset> {
+    load_config().presets.into_iter().find(|p| p.id == id)
+}
+
+pub fn save_preset(
+    name: String,
+    routes: Vec<Route>,
+    gamepad: Vec<GamepadMapping>,
+) -> Result<Preset, String> {
+    let mut config = load_config();
+    let preset = Preset::new(name, routes, gamepad);
+    config.presets.push(preset.clone());
+    save_config(&config)?;
+    Ok(preset)
+}
+
+pub fn update_preset(
+    id: Uuid,
+    routes: Vec<Route>,
+    gamepad: Vec<GamepadMapping>,
+) -> Result<Preset, String> {
+    let mut config = load_config();
+
+    let preset = config
+        .presets
+        .iter_mut()
+        .find(|p| p.id == id)
+        .ok_or_else(|| "Preset not found".to_string())?;
+
+    preset.routes = routes;
+    preset.gamepad = gamepad;
+    preset.modified_at = chrono::Utc::now();
+
+    let updated = preset.clone();
+    save_config(&config)?;
+    Ok(updated)
+}
+
+pub fn delete_preset(id: Uuid) -> Result<(), String> {
+    let mut config = load_config();
+    config.presets.retain(|p| p.id != id);
+    save_config(&config)?;
+    Ok(())
+}
+
+pub fn set_active_preset(id: Option<Uuid>) -> Result<(), String> {
+    let mut config = load_config();
+    config.active_preset_id = id;
+    save_config(&config)?;
+    Ok(())
+}
+
+pub fn get_active_preset() -> Option<Preset> {
+    let config = load_config();
+    config
+        .active_preset_id
+        .and_then(|id| config.presets.into_iter().find(|p| p.id == id))
+}
+
+pub fn get_clock_bpm() -> f64 {
+    load_config().clock_bpm
+}
+
+pub fn set_clock_bpm(bpm: f64) -> Result<(), String> {
+    let mut config = load_config();
+    config.clock_bpm = bpm;
+    save_config(&config)?;
+    Ok(())
+}
+
+pub fn get_clock_settings() -> ClockSettings {
+    load_config().clock_settings
+}
+
+pub fn set_clock_settings(settings: ClockSettings) -> Result<(), String> {
+    let mut config = load_config();
+    config.clock_settings = settings;
+    save_config(&config)?;
+    Ok(())
+}
+
+pub fn get_clock_running() -> bool {
+    load_config().clock_running
+}
+
+pub fn set_clock_running(running: bool) -> Result<(), String> {
+    let mut config = load_config();
+    config.clock_running = running;
+    save_config(&config)?;
+    Ok(())
+}
+
+pub fn get_jitter_buffers() -> BTreeMap<String, u32> {
+    load_config().jitter_buffers
+}
+
+pub fn set_jitter_buffers(buffers: BTreeMap<String, u32>) -> Result<(), String> {
+    let mut config = load_config();
+    config.jitter_buffers = buffers;
+    save_config(&config)?;
+    Ok(())
+}
+
+pub fn get_note_off_styles() -> BTreeMap<String, NoteOffStyle> {
+    load_config().note_off_styles
+}
+
+pub fn set_note_off_styles(styles: BTreeMap<String, NoteOffStyle>) -> Result<(), String> {
+    let mut config = load_config();
+    config.note_off_styles = styles;
+    save_config(&config)?;
+    Ok(())
+}
+
+pub fn get_buses() -> BTreeSet<String> {
+    load_config().buses
+}
+
+pub fn set_buses(buses: BTreeSet<String>) -> Result<(), String> {
+    let mut config = load_config();
+    config.buses = buses;
+    save_config(&config)?;
+    Ok(())
+}
+
+pub fn get_web_bridge() -> Option<WebBridgeSettings> {
+    load_config().web_bridge
+}
+
+pub fn set_web_bridge(settings: Option<WebBridgeSettings>) -> Result<(), String> {
+    let mut config = load_config();
+    config.web_bridge = settings;
+    save_config(&config)?;
+    Ok(())
+}
+
+pub fn get_mqtt() -> Option<MqttSettings> {
+    load_config().mqtt
+}
+
+pub fn set_mqtt(settings: Option<MqttSettings>) -> Result<(), String> {
+    let mut config = load_config();
+    config.mqtt = settings;
+    save_config(&config)?;
+    Ok(())
+}
+
+pub fn get_control_bindings() -> ControlBindings {
+    load_config().control_bindings
+}
+
+pub fn set_control_bindings(bindings: ControlBindings) -> Result<(), String> {
+    let mut config = load_config();
+    config.control_bindings = bindings;
+    save_config(&config)?;
+    Ok(())
+}
+
+pub fn get_device_definitions() -> Vec<DeviceDefinition> {
+    load_config().device_definitions
+}
+
+pub fn set_device_definitions(devices: Vec<DeviceDefinition>) -> Result<(), String> {
+    let mut config = load_config();
+    config.device_definitions = devices;
+    save_config(&config)?;
+    Ok(())
+}
+
+pub fn get_middle_c() -> MiddleC {
+    load_config().middle_c
+}
+
+pub fn set_middle_c(middle_c: MiddleC) -> Result<(), String> {
+    let mut config = load_config();
+    config.middle_c = middle_c;
+    save_config(&config)?;
+    Ok(())
+}
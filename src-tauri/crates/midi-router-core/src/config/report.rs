@@ -0,0 +1,237 @@
+//! Setup report
+//!
+//! Renders the live routing setup as Markdown, for printing as stage
+//! documentation.
+
+use crate::types::{
+    CcMapping, CcTarget, CcValueMode, ChannelFilter, ControlBindings, ControlTrigger, MscFilter,
+    RouteStatus, RouteWithStatus,
+};
+use chrono::{DateTime, Utc};
+use std::fmt::Write;
+
+/// Everything the report describes
+pub struct SetupSnapshot<'a> {
+    pub preset_name: Option<&'a str>,
+    pub bpm: f64,
+    pub control_bindings: &'a ControlBindings,
+    pub routes: &'a [RouteWithStatus],
+    pub generated_at: DateTime<Utc>,
+}
+
+pub fn setup_report(setup: &SetupSnapshot) -> String {
+    let mut out = String::new();
+    let _ = writeln!(out, "# MIDI Routing Setup\n");
+    let _ = writeln!(out, "- Preset: {}", setup.preset_name.unwrap_or("(none)"));
+    let _ = writeln!(
+        out,
+        "- Generated: {}",
+        setup.generated_at.format("%Y-%m-%d %H:%M UTC")
+    );
+
+    let _ = writeln!(out, "\n## Clock\n");
+    let _ = writeln!(out, "- Tempo: {:.1} BPM", setup.bpm);
+    let bindings = setup.control_bindings;
+    if let Some(tempo) = &bindings.tempo_cc {
+        let _ = writeln!(
+            out,
+            "- Tempo CC: CC {} on {} ({}), {:.0}-{:.0} BPM",
+            tempo.cc,
+            tempo.port,
+            channel_label(tempo.channel),
+            tempo.min_bpm,
+            tempo.max_bpm
+        );
+    }
+    if let Some(tap) = &bindings.tap_tempo {
+        let _ = writeln!(
+            out,
+            "- Tap tempo: {} on {} ({})",
+            trigger_label(&tap.trigger),
+            tap.port,
+            channel_label(tap.channel)
+        );
+    }
+    for trigger in &bindings.transport_triggers {
+        let _ = writeln!(
+            out,
+            "- {:?}: {} on {} ({})",
+            trigger.action,
+            trigger_label(&trigger.trigger),
+            trigger.port,
+            channel_label(trigger.channel)
+        );
+    }
+
+    let _ = writeln!(out, "\n## Routes ({})", setup.routes.len());
+    for (index, RouteWithStatus { route, status }) in setup.routes.iter().enumerate() {
+        let _ = writeln!(
+            out,
+            "\n### {}. {} -> {}\n",
+            index + 1,
+            route.source.display_name,
+            route.destination.display_name
+        );
+        let state = match (route.enabled, status) {
+            (false, _) => "Disabled",
+            (true, RouteStatus::Active) => "Enabled",
+            (true, RouteStatus::Pending) => "Enabled (waiting for device)",
+            (true, RouteStatus::Errored) => "Enabled (destination failing)",
+        };
+        let _ = writeln!(out, "- State: {}", state);
+        let _ = writeln!(out, "- Channels: {}", channels_label(&route.channels));
+        let _ = writeln!(
+            out,
+            "- CC passthrough: {}",
+            if route.cc_passthrough { "on" } else { "off" }
+        );
+        if let Some(msc) = msc_label(&route.msc_filter) {
+            let _ = writeln!(out, "- MSC: {}", msc);
+        }
+        if !route.cc_mappings.is_empty() {
+            let _ = writeln!(out, "- CC mappings:");
+            for mapping in &route.cc_mappings {
+                write_mapping(&mut out, mapping);
+            }
+        }
+    }
+    out
+}
+
+fn write_mapping(out: &mut String, mapping: &CcMapping) {
+    for target in &mapping.targets {
+        let _ = writeln!(
+            out,
+            "  - CC {} -> {} ({})",
+            mapping.source_cc,
+            target_label(target),
+            channel_list(&target.channels)
+        );
+    }
+}
+
+fn target_label(target: &CcTarget) -> String {
+    let mut label = match &target.note {
+        Some(trigger) => format!("note {}", trigger.note),
+        None => format!("CC {}", target.cc),
+    };
+    if target.high_res.is_some() {
+        label.push_str(" 14-bit");
+    }
+    match target.mode {
+        CcValueMode::Continuous => {}
+        CcValueMode::Toggle => label.push_str(", toggle"),
+        CcValueMode::Momentary { pulse_ms } => {
+            let _ = write!(label, ", momentary {} ms", pulse_ms);
+        }
+        CcValueMode::Threshold { threshold } => {
+            let _ = write!(label, ", threshold {}", threshold);
+        }
+    }
+    let curve = format!("{:?}", target.curve).to_lowercase();
+    if target.invert {
+        label.push_str(", inverted");
+    }
+    let _ = write!(label, ", {} curve", curve);
+    if target.min.is_some() || target.max.is_some() {
+        let (min, max) = (target.scale_to_range(0), target.scale_to_range(127));
+        let _ = write!(label, ", range {}-{}", min, max);
+    }
+    if target.offset != 0 {
+        let _ = write!(label, ", offset {:+}", target.offset);
+    }
+    label
+}
+
+/// 0-indexed channels shown 1-indexed
+fn channel_list(channels: &[u8]) -> String {
+    let list: Vec<String> = channels.iter().map(|c| (c + 1).to_string()).collect();
+    format!("ch {}", list.join(", "))
+}
+
+fn channels_label(filter: &ChannelFilter) -> String {
+    match filter {
+        ChannelFilter::All => "all".to_string(),
+        ChannelFilter::Only(channels) => channel_list(channels),
+        ChannelFilter::Except(channels) => format!("all except {}", channel_list(channels)),
+    }
+}
+
+fn channel_label(channel: Option<u8>) -> String {
+    match channel {
+        Some(ch) => format!("ch {}", ch + 1),
+        None => "any channel".to_string(),
+    }
+}
+
+fn trigger_label(trigger: &ControlTrigger) -> String {
+    match trigger {
+        ControlTrigger::Note { note } => format!("note {}", note),
+        ControlTrigger::Cc { cc } => format!("CC {}", cc),
+    }
+}
+
+/// None when MSC passes unfiltered
+fn msc_label(filter: &MscFilter) -> Option<String> {
+    match filter {
+        MscFilter::All => None,
+        MscFilter::Only(commands) => Some(format!("only {:?}", commands)),
+        MscFilter::Except(commands) => Some(format!("all except {:?}", commands)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::{PortId, Route, TempoCcBinding};
+    use chrono::TimeZone;
+
+    #[test]
+    fn report_lists_clock_and_routes() {
+        let mut route = Route::new(
+            PortId::new("Keys".to_string()),
+            PortId::new("Synth".to_string()),
+        );
+        route.channels = ChannelFilter::Only(vec![0, 9]);
+        route.cc_mappings = vec![CcMapping {
+            source_cc: 1,
+            targets: vec![CcTarget {
+                cc: 74,
+                channels: vec![0],
+                offset: -5,
+                ..Default::default()
+            }],
+            ..Default::default()
+        }];
+        let routes = vec![RouteWithStatus {
+            route,
+            status: RouteStatus::Pending,
+        }];
+        let bindings = ControlBindings {
+            tempo_cc: Some(TempoCcBinding {
+                port: "Pads".to_string(),
+                channel: None,
+                cc: 20,
+                min_bpm: 60.0,
+                max_bpm: 180.0,
+            }),
+            ..Default::default()
+        };
+
+        let report = setup_report(&SetupSnapshot {
+            preset_name: Some("Stage"),
+            bpm: 128.0,
+            control_bindings: &bindings,
+            routes: &routes,
+            generated_at: Utc.with_ymd_and_hms(2024, 5, 1, 20, 30, 0).unwrap(),
+        });
+
+        assert!(report.contains("- Preset: Stage\n- Generated: 2024-05-01 20:30 UTC"));
+        assert!(report.contains("- Tempo: 128.0 BPM"));
+        assert!(report.contains("- Tempo CC: CC 20 on Pads (any channel), 60-180 BPM"));
+        assert!(report.contains("### 1. Keys -> Synth"));
+        assert!(report.contains("- State: Enabled (waiting for device)"));
+        assert!(report.contains("- Channels: ch 1, 10"));
+        assert!(report.contains("  - CC 1 -> CC 74, linear curve, offset -5 (ch 1)"));
+    }
+}
@@ -0,0 +1,233 @@
+//! Import routing setups from other tools
+//!
+//! Two formats are understood, detected from the file contents:
+//!
+//! Plain connection lists, one route per line with an optional channel list
+//! (1-16). Blank lines and `#` comments are ignored:
+//!
+//! ```text
+//! # Stage rig
+//! Keystation -> Synth
+//! Pads -> Drum Machine [10]
+//! Keystation -> Rack [1, 2]
+//! ```
+//!
+//! ALSA `aconnect -l` listings, where every "Connecting To" entry becomes a
+//! route. Port names follow the ALSA backend's `client:port id:port` format.
+
+use crate::types::{ChannelFilter, PortId, Route};
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+
+/// Read a setup file and translate it into routes
+pub fn import_setup(path: &Path) -> Result<Vec<Route>, String> {
+    let contents = fs::read_to_string(path).map_err(|e| e.to_string())?;
+    let routes = if is_aconnect_listing(&contents) {
+        parse_aconnect(&contents)?
+    } else {
+        parse_connection_list(&contents)?
+    };
+    if routes.is_empty() {
+        return Err("No connections found".to_string());
+    }
+    Ok(routes)
+}
+
+fn is_aconnect_listing(contents: &str) -> bool {
+    contents
+        .lines()
+        .any(|line| line.starts_with("client ") && line.contains(": '"))
+}
+
+fn route(source: &str, destination: &str, channels: ChannelFilter) -> Route {
+    Route {
+        channels,
+        ..Route::new(
+            PortId::new(source.to_string()),
+            PortId::new(destination.to_string()),
+        )
+    }
+}
+
+pub fn parse_connection_list(contents: &str) -> Result<Vec<Route>, String> {
+    let mut routes = Vec::new();
+    for (index, line) in contents.lines().enumerate() {
+        let line = line.split('#').next().unwrap_or("").trim();
+        if line.is_empty() {
+            continue;
+        }
+        let err = |msg: &str| format!("Line {}: {}", index + 1, msg);
+
+        let (source, rest) = line
+            .split_once("->")
+            .ok_or_else(|| err("expected 'A -> B'"))?;
+        let (destination, channels) = match rest.split_once('[') {
+            Some((destination, channels)) => {
+                let channels = channels
+                    .strip_suffix(']')
+                    .ok_or_else(|| err("unclosed channel list"))?;
+                (destination, parse_channels(channels).map_err(|e| err(&e))?)
+            }
+            None => (rest, ChannelFilter::All),
+        };
+
+        let (source, destination) = (source.trim(), destination.trim());
+        if source.is_empty() || destination.is_empty() {
+            return Err(err("missing port name"));
+        }
+        routes.push(route(source, destination, channels));
+    }
+    Ok(routes)
+}
+
+/// Parse "1, 2, 10" (1-indexed) into a channel filter
+fn parse_channels(list: &str) -> Result<ChannelFilter, String> {
+    let channels = list
+        .split(',')
+        .map(|c| match c.trim().parse::<u8>() {
+            Ok(ch @ 1..=16) => Ok(ch - 1),
+            _ => Err(format!("invalid channel '{}'", c.trim())),
+        })
+        .collect::<Result<Vec<u8>, String>>()?;
+    Ok(ChannelFilter::Only(channels))
+}
+
+pub fn parse_aconnect(contents: &str) -> Result<Vec<Route>, String> {
+    // "client:port" address -> port name, and the connections out of each port
+    let mut names: HashMap<String, String> = HashMap::new();
+    let mut connections: Vec<(String, String)> = Vec::new();
+    let mut client: Option<(String, String)> = None;
+    let mut port: Option<String> = None;
+
+    for line in contents.lines() {
+        let trimmed = line.trim();
+        if let Some(rest) = line.strip_prefix("client ") {
+            // client 20: 'Keystation' [type=kernel,card=1]
+            let (id, rest) = rest.split_once(':').ok_or("Malformed client line")?;
+            client = Some((id.trim().to_string(), quoted(rest).to_string()));
+            port = None;
+        } else if let Some(targets) = trimmed.strip_prefix("Connecting To:") {
+            let source = port.clone().ok_or("Connection outside a port")?;
+            for target in targets.split(',') {
+                // 128:0 or 128:0[real:0]
+                let target = target.split('[').next().unwrap_or("").trim();
+                connections.push((source.clone(), target.to_string()));
+            }
+        } else if trimmed.starts_with("Connected From:") {
+            // Same connection seen from the other end
+        } else if let Some((port_id, rest)) = trimmed.split_once(' ') {
+            // 0 'Keystation MIDI 1'
+            let (Ok(port_id), Some((client_id, client_name))) = (port_id.parse::<u32>(), &client)
+            else {
+                continue;
+            };
+            let address = format!("{}:{}", client_id, port_id);
+            names.insert(
+                address.clone(),
+                format!("{}:{} {}", client_name, quoted(rest), address),
+            );
+            port = Some(address);
+        }
+    }
+
+    connections
+        .into_iter()
+        .map(|(source, target)| {
+            let name = |address: &str| {
+                names
+                    .get(address)
+                    .cloned()
+                    .ok_or_else(|| format!("Unknown port {}", address))
+            };
+            Ok(route(&name(&source)?, &name(&target)?, ChannelFilter::All))
+        })
+        .collect()
+}
+
+/// Text between the first pair of single quotes (trailing padding trimmed)
+fn quoted(s: &str) -> &str {
+    s.split('\'').nth(1).unwrap_or("").trim_end()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn names(routes: &[Route]) -> Vec<(&str, &str)> {
+        routes
+            .iter()
+            .map(|r| (r.source.name.as_str(), r.destination.name.as_str()))
+            .collect()
+    }
+
+    #[test]
+    fn connection_list_parses_routes_and_channels() {
+        let routes = parse_connection_list(
+            "# Stage rig\n\nKeystation -> Synth\nPads -> Drum Machine [10]  # drums\nKeystation -> Rack [1, 2]\n",
+        )
+        .unwrap();
+
+        assert_eq!(
+            names(&routes),
+            vec![
+                ("Keystation", "Synth"),
+                ("Pads", "Drum Machine"),
+                ("Keystation", "Rack")
+            ]
+        );
+        assert!(matches!(routes[0].channels, ChannelFilter::All));
+        assert!(matches!(&routes[1].channels, ChannelFilter::Only(ch) if ch == &vec![9]));
+        assert!(matches!(&routes[2].channels, ChannelFilter::Only(ch) if ch == &vec![0, 1]));
+    }
+
+    #[test]
+    fn connection_list_reports_bad_lines() {
+        assert_eq!(
+            parse_connection_list("Keys -> Synth\nKeys Synth").unwrap_err(),
+            "Line 2: expected 'A -> B'"
+        );
+        assert!(parse_connection_list("Keys -> Synth [17]").is_err());
+        assert!(parse_connection_list(" -> Synth").is_err());
+    }
+
+    const ACONNECT: &str = "\
+client 0: 'System' [type=kernel]
+    0 'Timer           '
+    1 'Announce        '
+client 20: 'Keystation' [type=kernel,card=1]
+    0 'Keystation MIDI 1'
+\tConnecting To: 128:0, 129:0[real:0]
+client 128: 'TiMidity' [type=user,pid=1234]
+    0 'TiMidity port 0 '
+\tConnected From: 20:0
+client 129: 'Surge XT' [type=user,pid=5678]
+    0 'Surge XT Input'
+\tConnected From: 20:0
+";
+
+    #[test]
+    fn aconnect_listing_becomes_routes() {
+        assert!(is_aconnect_listing(ACONNECT));
+        let routes = parse_aconnect(ACONNECT).unwrap();
+        assert_eq!(
+            names(&routes),
+            vec![
+                (
+                    "Keystation:Keystation MIDI 1 20:0",
+                    "TiMidity:TiMidity port 0 128:0"
+                ),
+                (
+                    "Keystation:Keystation MIDI 1 20:0",
+                    "Surge XT:Surge XT Input 129:0"
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn aconnect_unknown_target_is_an_error() {
+        let listing = "client 20: 'Keys' [type=kernel]\n    0 'Keys'\n\tConnecting To: 99:0\n";
+        assert_eq!(parse_aconnect(listing).unwrap_err(), "Unknown port 99:0");
+    }
+}
@@ -0,0 +1,144 @@
+//! Standard MIDI File export
+//!
+//! Writes recorded messages as a format 1 file: a tempo track, then one
+//! track per input port, named after it. Times are converted to ticks at the
+//! clock's tempo, so a take played along to the router's clock lines up
+//! with the grid.
+
+use crate::midi::retrospective::RecordedMessage;
+
+/// Ticks per quarter note
+const DIVISION: u16 = 480;
+
+pub fn encode_smf(messages: &[RecordedMessage], bpm: f64) -> Vec<u8> {
+    let us_per_quarter = (60_000_000.0 / bpm).round() as u32;
+    let mut ports: Vec<&str> = Vec::new();
+    for message in messages {
+        if !ports.contains(&message.port.as_str()) {
+            ports.push(&message.port);
+        }
+    }
+
+    let mut file = Vec::new();
+    file.extend(b"MThd");
+    file.extend(6u32.to_be_bytes());
+    file.extend(1u16.to_be_bytes());
+    file.extend((ports.len() as u16 + 1).to_be_bytes());
+    file.extend(DIVISION.to_be_bytes());
+
+    let mut tempo = vec![0x00, 0xFF, 0x51, 0x03];
+    tempo.extend(&us_per_quarter.to_be_bytes()[1..]);
+    push_track(&mut file, tempo);
+
+    for port in ports {
+        let mut track = vec![0x00, 0xFF, 0x03];
+        write_var_len(&mut track, port.len() as u32);
+        track.extend(port.as_bytes());
+        let mut last_tick = 0;
+        for message in messages.iter().filter(|m| m.port == port) {
+            let Some(event) = track_event(&message.bytes) else {
+                continue;
+            };
+            let tick = message.offset_us * u64::from(DIVISION) / u64::from(us_per_quarter);
+            write_var_len(&mut track, (tick - last_tick) as u32);
+            last_tick = tick;
+            track.extend(event);
+        }
+        push_track(&mut file, track);
+    }
+    file
+}
+
+/// Track event for a message: channel messages as they are, SysEx with its
+/// length. Other system messages have no meaning in a file.
+fn track_event(bytes: &[u8]) -> Option<Vec<u8>> {
+    match bytes.first()? {
+        0x80..=0xEF => Some(bytes.to_vec()),
+        0xF0 => {
+            let mut event = vec![0xF0];
+            write_var_len(&mut event, (bytes.len() - 1) as u32);
+            event.extend(&bytes[1..]);
+            Some(event)
+        }
+        _ => None,
+    }
+}
+
+/// Append a track chunk, ending the track
+fn push_track(file: &mut Vec<u8>, mut events: Vec<u8>) {
+    events.extend([0x00, 0xFF, 0x2F, 0x00]);
+    file.extend(b"MTrk");
+    file.extend((events.len() as u32).to_be_bytes());
+    file.extend(events);
+}
+
+/// SMF variable-length quantity: 7 bits per byte, most significant first
+fn write_var_len(out: &mut Vec<u8>, value: u32) {
+    let mut shift = 28;
+    while shift > 0 && value >> shift == 0 {
+        shift -= 7;
+    }
+    while shift > 0 {
+        out.push(((value >> shift) as u8 & 0x7F) | 0x80);
+        shift -= 7;
+    }
+    out.push(value as u8 & 0x7F);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn message(port: &str, offset_us: u64, bytes: &[u8]) -> RecordedMessage {
+        RecordedMessage {
+            port: port.to_string(),
+            offset_us,
+            bytes: bytes.to_vec(),
+        }
+    }
+
+    #[test]
+    fn var_len_quantities() {
+        for (value, expected) in [
+            (0, vec![0x00]),
+            (0x7F, vec![0x7F]),
+            (0x80, vec![0x81, 0x00]),
+            (0x3FFF, vec![0xFF, 0x7F]),
+            (0x0FFF_FFFF, vec![0xFF, 0xFF, 0xFF, 0x7F]),
+        ] {
+            let mut out = Vec::new();
+            write_var_len(&mut out, value);
+            assert_eq!(out, expected);
+        }
+    }
+
+    #[test]
+    fn writes_a_track_per_port() {
+        // At 120 BPM a quarter note is 500 ms
+        let messages = [
+            message("Keys", 0, &[0x90, 60, 100]),
+            message("Pads", 250_000, &[0x99, 36, 127]),
+            message("Keys", 500_000, &[0x80, 60, 0]),
+            message("Keys", 500_000, &[0xF8]),
+        ];
+        let smf = encode_smf(&messages, 120.0);
+
+        assert_eq!(&smf[..14], b"MThd\0\0\0\x06\0\x01\0\x03\x01\xE0");
+        // Tempo track: 500000 us per quarter
+        assert_eq!(&smf[14..18], b"MTrk");
+        assert_eq!(
+            &smf[22..30],
+            &[0x00, 0xFF, 0x51, 0x03, 0x07, 0xA1, 0x20, 0x00]
+        );
+
+        let keys = 22 + 11;
+        assert_eq!(&smf[keys..keys + 4], b"MTrk");
+        let events = &smf[keys + 8..];
+        assert_eq!(&events[..7], &[0x00, 0xFF, 0x03, 0x04, b'K', b'e', b'y']);
+        // Note on at 0, note off a quarter (480 ticks) later; clock skipped
+        assert_eq!(
+            &events[8..20],
+            &[0x00, 0x90, 60, 100, 0x83, 0x60, 0x80, 60, 0, 0x00, 0xFF, 0x2F]
+        );
+    }
+}
@@ -0,0 +1,114 @@
+//! Built-in setup templates
+//!
+//! Templates describe common rigs in terms of port slots ("controller",
+//! "synth"). Instantiating one with concrete ports for each slot produces
+//! the routes and clock settings. The templates themselves are data, in
+//! `setup_templates.json`.
+
+use crate::types::{ClockMode, PortId, Route, SetupTemplate};
+use std::collections::{BTreeMap, HashMap};
+
+const BUILTIN_TEMPLATES: &str = include_str!("setup_templates.json");
+
+pub fn builtin_templates() -> Vec<SetupTemplate> {
+    serde_json::from_str(BUILTIN_TEMPLATES).expect("built-in setup templates are valid")
+}
+
+/// Routes and per-output clock modes a template sets up
+#[derive(Debug)]
+pub struct TemplateSetup {
+    pub routes: Vec<Route>,
+    pub clock_modes: BTreeMap<String, ClockMode>,
+}
+
+/// Build a template's setup, with `ports` mapping slot ids to port names.
+/// Every slot must have a port.
+pub fn instantiate(
+    template: &SetupTemplate,
+    ports: &HashMap<String, String>,
+) -> Result<TemplateSetup, String> {
+    let port = |slot: &str| {
+        ports
+            .get(slot)
+            .filter(|name| !name.is_empty())
+            .cloned()
+            .ok_or_else(|| format!("No port chosen for '{}'", slot))
+    };
+    for slot in &template.slots {
+        port(&slot.id)?;
+    }
+
+    let routes = template
+        .routes
+        .iter()
+        .map(|r| {
+            Ok(Route {
+                channels: r.channels.clone(),
+                strip_aftertouch: r.strip_aftertouch,
+                ..Route::new(
+                    PortId::new(port(&r.source)?),
+                    PortId::new(port(&r.destination)?),
+                )
+            })
+        })
+        .collect::<Result<Vec<_>, String>>()?;
+    let clock_modes = template
+        .clock
+        .iter()
+        .map(|(slot, mode)| Ok((port(slot)?, *mode)))
+        .collect::<Result<_, String>>()?;
+
+    Ok(TemplateSetup {
+        routes,
+        clock_modes,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::ChannelFilter;
+
+    fn template(id: &str) -> SetupTemplate {
+        builtin_templates()
+            .into_iter()
+            .find(|t| t.id == id)
+            .unwrap()
+    }
+
+    #[test]
+    fn builtin_templates_reference_their_slots() {
+        let templates = builtin_templates();
+        assert!(!templates.is_empty());
+        for template in &templates {
+            let has_slot = |id: &String| template.slots.iter().any(|s| &s.id == id);
+            for route in &template.routes {
+                assert!(has_slot(&route.source), "{}: {}", template.id, route.source);
+                assert!(has_slot(&route.destination));
+            }
+            assert!(template.clock.keys().all(has_slot));
+        }
+    }
+
+    #[test]
+    fn instantiate_fills_in_ports() {
+        let ports = HashMap::from([
+            ("pads".to_string(), "MPD218".to_string()),
+            ("drums".to_string(), "TR-8S".to_string()),
+        ]);
+        let setup = instantiate(&template("pads-drum-machine"), &ports).unwrap();
+
+        assert_eq!(setup.routes.len(), 1);
+        assert_eq!(setup.routes[0].source.name, "MPD218");
+        assert_eq!(setup.routes[0].destination.name, "TR-8S");
+        assert_eq!(setup.routes[0].channels, ChannelFilter::Only(vec![9]));
+        assert!(setup.routes[0].strip_aftertouch);
+    }
+
+    #[test]
+    fn instantiate_requires_every_slot() {
+        let ports = HashMap::from([("daw".to_string(), "DAW Out".to_string())]);
+        let result = instantiate(&template("daw-clock-grooveboxes"), &ports);
+        assert!(result.is_err());
+    }
+}
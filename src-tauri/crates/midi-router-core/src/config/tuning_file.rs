@@ -0,0 +1,188 @@
+//! Tuning file import
+//!
+//! Reads Scala scales (`.scl`) and AnaMark tuning files (`.tun`) into a
+//! per-note table of deviations from 12-tone equal temperament.
+//!
+//! Scala scales are mapped with degree 0 on middle C (note 60), which keeps
+//! its equal-tempered pitch. AnaMark files list every note's pitch directly,
+//! in cents above note 0 (8.1758 Hz):
+//!
+//! ```text
+//! [Exact Tuning]
+//! note 60 = 6000.0
+//! note 61 = 6111.73
+//! ```
+
+use crate::types::TuningTable;
+use std::fs;
+use std::path::Path;
+
+/// Note the first scale degree is mapped to
+const SCALA_ROOT_NOTE: i32 = 60;
+
+/// Read a tuning from a `.scl` or `.tun` file, by extension
+pub fn load_tuning(path: &Path) -> Result<TuningTable, String> {
+    let contents = fs::read_to_string(path).map_err(|e| e.to_string())?;
+    let extension = path
+        .extension()
+        .and_then(|e| e.to_str())
+        .map(|e| e.to_ascii_lowercase());
+    let name = path
+        .file_stem()
+        .and_then(|s| s.to_str())
+        .unwrap_or("Tuning")
+        .to_string();
+    match extension.as_deref() {
+        Some("scl") => parse_scala(&contents),
+        Some("tun") => parse_tun(&contents, name),
+        _ => Err("Unsupported tuning file (expected .scl or .tun)".to_string()),
+    }
+}
+
+pub fn parse_scala(contents: &str) -> Result<TuningTable, String> {
+    let mut lines = contents
+        .lines()
+        .filter(|line| !line.starts_with('!'))
+        .map(str::trim);
+    let name = lines.next().ok_or("Empty scale file")?.to_string();
+    let count: usize = lines
+        .next()
+        .and_then(|line| line.split_whitespace().next())
+        .and_then(|n| n.parse().ok())
+        .ok_or("Missing note count")?;
+    let degrees = lines
+        .filter(|line| !line.is_empty())
+        .take(count)
+        .map(parse_pitch)
+        .collect::<Result<Vec<f64>, String>>()?;
+    if count == 0 || degrees.len() != count {
+        return Err(format!(
+            "Expected {} pitches, found {}",
+            count,
+            degrees.len()
+        ));
+    }
+
+    // The last degree is the period (usually the octave)
+    let period = degrees[count - 1];
+    let cents = (0..128)
+        .map(|note| {
+            let steps = note - SCALA_ROOT_NOTE;
+            let (periods, degree) = (
+                steps.div_euclid(count as i32),
+                steps.rem_euclid(count as i32) as usize,
+            );
+            let within = degree.checked_sub(1).map_or(0.0, |d| degrees[d]);
+            periods as f64 * period + within - steps as f64 * 100.0
+        })
+        .collect();
+    Ok(TuningTable { name, cents })
+}
+
+/// A Scala pitch: cents if it contains a '.', otherwise a ratio or integer
+fn parse_pitch(line: &str) -> Result<f64, String> {
+    let value = line.split_whitespace().next().unwrap_or("");
+    let invalid = || format!("Invalid pitch '{}'", value);
+    if value.contains('.') {
+        return value.parse().map_err(|_| invalid());
+    }
+    let (numerator, denominator) = value.split_once('/').unwrap_or((value, "1"));
+    let numerator: f64 = numerator.parse().map_err(|_| invalid())?;
+    let denominator: f64 = denominator.parse().map_err(|_| invalid())?;
+    if numerator <= 0.0 || denominator <= 0.0 {
+        return Err(invalid());
+    }
+    Ok(1200.0 * (numerator / denominator).log2())
+}
+
+pub fn parse_tun(contents: &str, name: String) -> Result<TuningTable, String> {
+    // Pitches from [Tuning] (integer cents) and [Exact Tuning]; exact wins
+    let mut tuning: [Option<f64>; 128] = [None; 128];
+    let mut exact: [Option<f64>; 128] = [None; 128];
+    let mut section = String::new();
+    let mut has_section = false;
+    for line in contents.lines().map(str::trim) {
+        if line.starts_with('[') {
+            section = line.to_ascii_lowercase();
+            has_section |= section == "[tuning]" || section == "[exact tuning]";
+            continue;
+        }
+        let pitches = match section.as_str() {
+            "[tuning]" => &mut tuning,
+            "[exact tuning]" => &mut exact,
+            _ => continue,
+        };
+        let Some((key, value)) = line.split_once('=') else {
+            continue;
+        };
+        let Some(note) = key
+            .trim()
+            .strip_prefix("note")
+            .and_then(|n| n.trim().parse::<usize>().ok())
+            .filter(|n| *n < 128)
+        else {
+            continue;
+        };
+        let pitch: f64 = value
+            .trim()
+            .parse()
+            .map_err(|_| format!("Invalid pitch for note {}", note))?;
+        pitches[note] = Some(pitch);
+    }
+    if !has_section {
+        return Err("No tuning section found".to_string());
+    }
+
+    // Notes not listed stay equal-tempered
+    let cents = (0..128)
+        .map(|note| {
+            exact[note]
+                .or(tuning[note])
+                .map_or(0.0, |pitch| pitch - note as f64 * 100.0)
+        })
+        .collect();
+    Ok(TuningTable { name, cents })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn close(a: f64, b: f64) -> bool {
+        (a - b).abs() < 1e-6
+    }
+
+    #[test]
+    fn scala_scale_maps_around_middle_c() {
+        let scale = "! just.scl\n!\nJust major\n 7\n!\n9/8\n5/4\n4/3\n3/2\n5/3\n15/8\n2/1\n";
+        let table = parse_scala(scale).unwrap();
+
+        assert_eq!(table.name, "Just major");
+        assert_eq!(table.cents.len(), 128);
+        assert!(close(table.cents[60], 0.0));
+        // Degree 2 (5/4, 386.3 cents) lands on note 62
+        assert!(close(table.cents[62], 1200.0 * 1.25f64.log2() - 200.0));
+        // A 7-note scale repeats every 7 keys: note 67 is the octave
+        assert!(close(table.cents[67], 1200.0 - 700.0));
+        assert!(close(table.cents[53], -1200.0 + 700.0));
+    }
+
+    #[test]
+    fn scala_cents_and_errors() {
+        let table = parse_scala("Quarter tones\n2\n50.0\n100.0\n").unwrap();
+        assert!(close(table.cents[61], -50.0));
+        assert!(parse_scala("Bad\n3\n100.0\n").is_err());
+        assert!(parse_scala("Bad\n1\nabc\n").is_err());
+    }
+
+    #[test]
+    fn tun_exact_tuning_overrides_tuning() {
+        let tun = "[Tuning]\nnote 60 = 6010\nnote 61 = 6110\n\n[Exact Tuning]\nnote 60 = 6000.5\n";
+        let table = parse_tun(tun, "Test".to_string()).unwrap();
+
+        assert!(close(table.cents[60], 0.5));
+        assert!(close(table.cents[61], 10.0));
+        assert!(close(table.cents[62], 0.0));
+        assert!(parse_tun("note 60 = 6000", "Test".to_string()).is_err());
+    }
+}
@@ -0,0 +1,14 @@
+//! MIDI routing engine
+//!
+//! The engine runs on its own thread: drive it through a [`MidiEngine`]
+//! handle and read what it reports as [`EngineEvent`]s. Routes, filters and
+//! transforms are plain serde types in [`types`]; presets and settings are
+//! stored through [`config`].
+
+pub mod config;
+pub mod midi;
+pub mod types;
+
+pub use midi::engine::{EngineCommand, EngineEvent, MidiEngine};
+pub use midi::sequence::SequencedEvent;
+pub use types::{ChannelFilter, EngineError, MidiPort, PortId, Route};
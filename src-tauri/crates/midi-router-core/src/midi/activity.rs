@@ -0,0 +1,105 @@
+//! Port activity pulses
+//!
+//! Counts messages per port and direction and reports them in fixed windows,
+//! so the UI can drive activity LEDs without the full monitor stream.
+
+use crate::types::{PortDirection, PortPulse};
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+pub struct ActivityCounter {
+    window: Duration,
+    window_start: Instant,
+    counts: HashMap<(String, PortDirection), u32>,
+}
+
+impl ActivityCounter {
+    pub const DEFAULT_WINDOW: Duration = Duration::from_millis(100);
+
+    pub fn new(window: Duration, now: Instant) -> Self {
+        Self {
+            window,
+            window_start: now,
+            counts: HashMap::new(),
+        }
+    }
+
+    pub fn record(&mut self, port: &str, direction: PortDirection) {
+        *self
+            .counts
+            .entry((port.to_string(), direction))
+            .or_insert(0) += 1;
+    }
+
+    /// Close the current window if it has elapsed.
+    /// Returns the pulses for ports that saw traffic, or None if the window
+    /// is still open or nothing happened.
+    pub fn flush(&mut self, now: Instant) -> Option<Vec<PortPulse>> {
+        if now.duration_since(self.window_start) < self.window {
+            return None;
+        }
+        self.window_start = now;
+        if self.counts.is_empty() {
+            return None;
+        }
+
+        let window_ms = self.window.as_millis() as u32;
+        let mut pulses: Vec<PortPulse> = self
+            .counts
+            .drain()
+            .map(|((port, direction), count)| PortPulse {
+                port,
+                direction,
+                count,
+                window_ms,
+            })
+            .collect();
+        pulses.sort_by(|a, b| a.port.cmp(&b.port));
+        Some(pulses)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn flush_waits_for_window() {
+        let t0 = Instant::now();
+        let mut counter = ActivityCounter::new(Duration::from_millis(100), t0);
+        counter.record("Keys", PortDirection::Input);
+        assert!(counter.flush(t0 + Duration::from_millis(50)).is_none());
+        assert!(counter.flush(t0 + Duration::from_millis(100)).is_some());
+    }
+
+    #[test]
+    fn counts_per_port_and_direction() {
+        let t0 = Instant::now();
+        let mut counter = ActivityCounter::new(Duration::from_millis(100), t0);
+        counter.record("Keys", PortDirection::Input);
+        counter.record("Keys", PortDirection::Input);
+        counter.record("Synth", PortDirection::Output);
+
+        let pulses = counter.flush(t0 + Duration::from_millis(100)).unwrap();
+        assert_eq!(
+            pulses,
+            vec![
+                PortPulse {
+                    port: "Keys".to_string(),
+                    direction: PortDirection::Input,
+                    count: 2,
+                    window_ms: 100,
+                },
+                PortPulse {
+                    port: "Synth".to_string(),
+                    direction: PortDirection::Output,
+                    count: 1,
+                    window_ms: 100,
+                },
+            ]
+        );
+
+        // Counts reset for the next window
+        assert!(counter.flush(t0 + Duration::from_millis(200)).is_none());
+    }
+}
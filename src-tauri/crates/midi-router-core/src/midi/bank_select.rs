@@ -0,0 +1,138 @@
+//! Bank Select
+//!
+//! A bank change is only complete with the Program Change after it. Routes
+//! that remap or block banks hold CC0 (MSB) and CC32 (LSB) until that
+//! Program Change, then send the bank and program as a unit, so the
+//! destination never ends up on a bank meant for a different program.
+
+use crate::midi::router::bank_program_messages;
+use crate::types::{BankProgram, BankSelectSettings};
+use std::collections::{HashMap, HashSet};
+
+#[derive(Debug, Default)]
+pub struct BankSelectState {
+    /// Last (MSB, LSB) received on each channel
+    banks: HashMap<u8, (Option<u8>, Option<u8>)>,
+    /// Channels with a bank change waiting for its Program Change
+    pending: HashSet<u8>,
+}
+
+impl BankSelectState {
+    /// Handle a Bank Select or Program Change. Returns None for other
+    /// messages, and for everything when the settings aren't in use.
+    pub fn handle(&mut self, bytes: &[u8], settings: &BankSelectSettings) -> Option<Vec<Vec<u8>>> {
+        if !settings.is_active() {
+            return None;
+        }
+        match *bytes {
+            [status, cc @ (0 | 32), value] if status & 0xF0 == 0xB0 => {
+                let channel = status & 0x0F;
+                let bank = self.banks.entry(channel).or_default();
+                if cc == 0 {
+                    bank.0 = Some(value);
+                } else {
+                    bank.1 = Some(value);
+                }
+                self.pending.insert(channel);
+                Some(Vec::new())
+            }
+            [status, program] if status & 0xF0 == 0xC0 => {
+                let channel = status & 0x0F;
+                let (msb, lsb) = self.banks.get(&channel).copied().unwrap_or_default();
+                let pending = self.pending.remove(&channel);
+                let remap = settings.remaps.iter().find(|remap| {
+                    remap.from.program == program
+                        && remap.from.msb.is_none_or(|m| msb == Some(m))
+                        && remap.from.lsb.is_none_or(|l| lsb == Some(l))
+                });
+                let selected = match remap {
+                    Some(remap) => remap.to,
+                    // Unmapped programs keep their bank, sent only if it changed
+                    None if pending => BankProgram { msb, lsb, program },
+                    None => BankProgram {
+                        msb: None,
+                        lsb: None,
+                        program,
+                    },
+                };
+                Some(select_messages(channel, &selected, settings.block))
+            }
+            _ => None,
+        }
+    }
+}
+
+/// Bank Select (unless blocked) followed by the Program Change
+fn select_messages(channel: u8, selected: &BankProgram, block: bool) -> Vec<Vec<u8>> {
+    if block {
+        return vec![vec![0xC0 | channel, selected.program.min(127)]];
+    }
+    bank_program_messages(channel, selected)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::BankRemap;
+
+    fn bank(msb: Option<u8>, lsb: Option<u8>, program: u8) -> BankProgram {
+        BankProgram { msb, lsb, program }
+    }
+
+    #[test]
+    fn inactive_settings_pass_everything() {
+        let mut state = BankSelectState::default();
+        let settings = BankSelectSettings::default();
+        assert_eq!(state.handle(&[0xB0, 0, 1], &settings), None);
+        assert_eq!(state.handle(&[0xC0, 5], &settings), None);
+    }
+
+    #[test]
+    fn remaps_bank_and_program_together() {
+        let settings = BankSelectSettings {
+            block: false,
+            remaps: vec![BankRemap {
+                from: bank(Some(1), None, 10),
+                to: bank(Some(4), Some(2), 20),
+            }],
+        };
+        let mut state = BankSelectState::default();
+
+        // The bank is held until its Program Change
+        assert_eq!(state.handle(&[0xB2, 0, 1], &settings), Some(vec![]));
+        assert_eq!(state.handle(&[0xB2, 32, 7], &settings), Some(vec![]));
+        assert_eq!(
+            state.handle(&[0xC2, 10], &settings),
+            Some(vec![vec![0xB2, 0, 4], vec![0xB2, 32, 2], vec![0xC2, 20]])
+        );
+
+        // An unmapped program keeps the bank that came with it
+        state.handle(&[0xB2, 0, 3], &settings);
+        assert_eq!(
+            state.handle(&[0xC2, 10], &settings),
+            Some(vec![vec![0xB2, 0, 3], vec![0xB2, 32, 7], vec![0xC2, 10]])
+        );
+        // A Program Change alone stays alone
+        assert_eq!(
+            state.handle(&[0xC2, 11], &settings),
+            Some(vec![vec![0xC2, 11]])
+        );
+        assert_eq!(state.handle(&[0xB2, 7, 100], &settings), None);
+    }
+
+    #[test]
+    fn blocking_drops_bank_changes() {
+        let settings = BankSelectSettings {
+            block: true,
+            remaps: Vec::new(),
+        };
+        let mut state = BankSelectState::default();
+
+        assert_eq!(state.handle(&[0xB0, 0, 1], &settings), Some(vec![]));
+        assert_eq!(state.handle(&[0xB0, 32, 1], &settings), Some(vec![]));
+        assert_eq!(
+            state.handle(&[0xC0, 5], &settings),
+            Some(vec![vec![0xC0, 5]])
+        );
+    }
+}
@@ -0,0 +1,195 @@
+//! Internal buses
+//!
+//! A bus is an output and an input the router provides itself: what routes
+//! send to it comes back in as input from it, so transforms can be chained
+//! across routes (Keys → Bus A → Bus B → Synth). A bus that enabled routes
+//! lead back into isn't fed, so a loop can't circulate messages forever.
+
+use crate::midi::port_manager::MidiMessage;
+use crate::midi::ports::{set_virtual_input, set_virtual_output};
+use crate::midi::timestamps::wall_clock_us;
+use crate::types::Route;
+use crossbeam_channel::Sender;
+use std::collections::{BTreeSet, HashMap};
+use std::sync::{Arc, RwLock};
+
+#[derive(Debug, Default)]
+struct Buses {
+    names: BTreeSet<String>,
+    /// Buses on a loop of enabled routes
+    looping: BTreeSet<String>,
+}
+
+/// Feeds messages sent to buses back into the engine. Clones share the
+/// same buses, so the scheduler's timing thread can feed them too.
+#[derive(Clone)]
+pub struct BusFeed {
+    midi_tx: Sender<MidiMessage>,
+    buses: Arc<RwLock<Buses>>,
+}
+
+impl BusFeed {
+    pub fn new(midi_tx: Sender<MidiMessage>) -> Self {
+        Self {
+            midi_tx,
+            buses: Arc::default(),
+        }
+    }
+
+    /// Replace the buses, listing them with the virtual ports
+    pub fn set_buses(&self, names: BTreeSet<String>) {
+        let buses = &mut *self.buses.write().unwrap();
+        for name in buses.names.difference(&names) {
+            set_virtual_input(name, false);
+            set_virtual_output(name, false);
+        }
+        for name in &names {
+            set_virtual_input(name, true);
+            set_virtual_output(name, true);
+        }
+        buses.names = names;
+        buses.looping.retain(|name| buses.names.contains(name));
+    }
+
+    pub fn names(&self) -> BTreeSet<String> {
+        self.buses.read().unwrap().names.clone()
+    }
+
+    pub fn is_bus(&self, name: &str) -> bool {
+        self.buses.read().unwrap().names.contains(name)
+    }
+
+    /// Find the buses the routes loop through
+    pub fn update_loops(&self, routes: &[Route]) {
+        let buses = &mut *self.buses.write().unwrap();
+        buses.looping = looping_buses(&buses.names, routes);
+        if !buses.looping.is_empty() {
+            eprintln!("[BUS] Not feeding looping buses: {:?}", buses.looping);
+        }
+    }
+
+    /// Feed a message in as input from a bus. None when `name` isn't a bus.
+    pub fn send(&self, name: &str, bytes: &[u8]) -> Option<Result<(), String>> {
+        let buses = self.buses.read().unwrap();
+        if !buses.names.contains(name) {
+            return None;
+        }
+        if buses.looping.contains(name) {
+            return Some(Err("Bus loops back into itself".to_string()));
+        }
+        let message = (
+            name.to_string(),
+            wall_clock_us(),
+            bytes.to_vec(),
+            Vec::new(),
+        );
+        Some(
+            self.midi_tx
+                .try_send(message)
+                .map_err(|_| "Bus queue full".to_string()),
+        )
+    }
+}
+
+/// Buses that enabled routes lead from back to themselves
+pub fn looping_buses(buses: &BTreeSet<String>, routes: &[Route]) -> BTreeSet<String> {
+    let mut edges: HashMap<&str, BTreeSet<String>> = HashMap::new();
+    for route in routes.iter().filter(|r| r.enabled) {
+        if buses.contains(&route.source.name) {
+            edges.entry(route.source.name.as_str()).or_default().extend(
+                route
+                    .output_ports()
+                    .into_iter()
+                    .filter(|port| buses.contains(port)),
+            );
+        }
+    }
+
+    buses
+        .iter()
+        .filter(|bus| {
+            let mut stack: Vec<&str> = vec![bus.as_str()];
+            let mut visited: BTreeSet<&str> = BTreeSet::new();
+            while let Some(node) = stack.pop() {
+                for next in edges.get(node).into_iter().flatten() {
+                    if next == *bus {
+                        return true;
+                    }
+                    if visited.insert(next.as_str()) {
+                        stack.push(next.as_str());
+                    }
+                }
+            }
+            false
+        })
+        .cloned()
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::PortId;
+    use crossbeam_channel::bounded;
+
+    fn route(source: &str, dest: &str) -> Route {
+        Route::new(
+            PortId::new(source.to_string()),
+            PortId::new(dest.to_string()),
+        )
+    }
+
+    fn buses(names: &[&str]) -> BTreeSet<String> {
+        names.iter().map(|name| name.to_string()).collect()
+    }
+
+    #[test]
+    fn finds_buses_on_a_loop() {
+        let routes = vec![
+            route("Keys", "Bus A"),
+            route("Bus A", "Bus B"),
+            route("Bus B", "Bus C"),
+            route("Bus C", "Bus B"),
+            route("Bus C", "Synth"),
+        ];
+        assert_eq!(
+            looping_buses(&buses(&["Bus A", "Bus B", "Bus C"]), &routes),
+            buses(&["Bus B", "Bus C"])
+        );
+    }
+
+    #[test]
+    fn chains_and_disabled_routes_dont_loop() {
+        let mut back = route("Bus B", "Bus A");
+        back.enabled = false;
+        let routes = vec![route("Bus A", "Bus B"), back, route("Bus B", "Synth")];
+        assert!(looping_buses(&buses(&["Bus A", "Bus B"]), &routes).is_empty());
+        // A bus into itself is the shortest loop
+        let routes = vec![route("Bus A", "Bus A")];
+        assert_eq!(
+            looping_buses(&buses(&["Bus A"]), &routes),
+            buses(&["Bus A"])
+        );
+    }
+
+    #[test]
+    fn feeds_buses_back_as_input() {
+        let (tx, rx) = bounded(4);
+        let feed = BusFeed::new(tx);
+        feed.set_buses(buses(&["Feed Test"]));
+        assert_eq!(feed.send("Synth", &[0x90, 60, 100]), None);
+        assert_eq!(feed.send("Feed Test", &[0x90, 60, 100]), Some(Ok(())));
+        let (port, _, bytes, fast_routed) = rx.try_recv().unwrap();
+        assert_eq!(port, "Feed Test");
+        assert_eq!(bytes, vec![0x90, 60, 100]);
+        assert!(fast_routed.is_empty());
+
+        feed.update_loops(&[route("Feed Test", "Feed Test")]);
+        assert!(matches!(
+            feed.send("Feed Test", &[0x90, 60, 0]),
+            Some(Err(_))
+        ));
+        assert!(rx.try_recv().is_err());
+        feed.set_buses(BTreeSet::new());
+    }
+}
@@ -0,0 +1,88 @@
+//! CC range learn
+//!
+//! Records the lowest and highest values a route's source CC sends while the
+//! user sweeps the control, so the measured range can become the mapping's
+//! calibration.
+
+use crate::types::Route;
+use uuid::Uuid;
+
+#[derive(Debug)]
+pub struct RangeLearner {
+    route_id: Uuid,
+    source_cc: u8,
+    /// Lowest and highest values seen so far
+    range: Option<(u8, u8)>,
+}
+
+impl RangeLearner {
+    pub fn new(route_id: Uuid, source_cc: u8) -> Self {
+        Self {
+            route_id,
+            source_cc,
+            range: None,
+        }
+    }
+
+    /// Record a message if it's the learned CC arriving at the route's source
+    pub fn observe(&mut self, port: &str, bytes: &[u8], routes: &[Route]) {
+        let [status, cc, value] = *bytes else {
+            return;
+        };
+        if status & 0xF0 != 0xB0 || cc != self.source_cc {
+            return;
+        }
+        let from_source = routes
+            .iter()
+            .any(|r| r.id == self.route_id && r.source.name == port);
+        if !from_source {
+            return;
+        }
+        self.range = Some(match self.range {
+            Some((min, max)) => (min.min(value), max.max(value)),
+            None => (value, value),
+        });
+    }
+
+    /// The (min, max) swept, if any values arrived
+    pub fn range(&self) -> Option<(u8, u8)> {
+        self.range
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::PortId;
+
+    fn route(source: &str) -> Route {
+        Route::new(
+            PortId::new(source.to_string()),
+            PortId::new("Synth".to_string()),
+        )
+    }
+
+    #[test]
+    fn records_sweep_of_learned_cc() {
+        let routes = vec![route("Pedal")];
+        let mut learner = RangeLearner::new(routes[0].id, 11);
+
+        assert_eq!(learner.range(), None);
+        for value in [40, 12, 90, 115, 60] {
+            learner.observe("Pedal", &[0xB3, 11, value], &routes);
+        }
+        assert_eq!(learner.range(), Some((12, 115)));
+    }
+
+    #[test]
+    fn ignores_other_ccs_ports_and_messages() {
+        let routes = vec![route("Pedal")];
+        let mut learner = RangeLearner::new(routes[0].id, 11);
+
+        learner.observe("Pedal", &[0xB0, 7, 0], &routes);
+        learner.observe("Keys", &[0xB0, 11, 0], &routes);
+        learner.observe("Pedal", &[0x90, 11, 100], &routes);
+        learner.observe("Pedal", &[0xC0, 11], &routes);
+        assert_eq!(learner.range(), None);
+    }
+}
@@ -0,0 +1,176 @@
+//! Debug capture
+//!
+//! For a bounded time window, records every incoming message along with
+//! the engine's routing decisions, applied transforms, output bytes and send
+//! results. The capture is kept until it's collected for a bug report.
+//!
+//! A trap traces just the first message matching its condition, along with
+//! the messages that came before it, then disarms.
+
+use crate::types::{
+    CaptureEntry, CaptureHandling, DebugCapture, PrecedingMessage, TrapCondition, TrapHit,
+};
+use std::collections::VecDeque;
+use std::time::{Duration, Instant};
+
+#[derive(Debug, Default)]
+pub struct CaptureRecorder {
+    capture: Option<DebugCapture>,
+    /// When recording stops
+    until: Option<Instant>,
+    trap: Option<TrapCondition>,
+    /// Recent messages while a trap is armed, oldest first
+    preceding: VecDeque<PrecedingMessage>,
+}
+
+impl CaptureRecorder {
+    pub const MAX_DURATION: Duration = Duration::from_secs(300);
+    /// Entries kept per capture; later messages are counted as truncated
+    const MAX_ENTRIES: usize = 100_000;
+    /// Messages kept as context for a trap hit
+    const TRAP_CONTEXT: usize = 32;
+
+    /// Start a new capture, discarding any previous one
+    pub fn start(&mut self, duration: Duration, started_us: u64, now: Instant) {
+        let duration = duration.min(Self::MAX_DURATION);
+        self.capture = Some(DebugCapture {
+            started_us,
+            duration_ms: duration.as_millis() as u64,
+            truncated: false,
+            entries: Vec::new(),
+        });
+        self.until = Some(now + duration);
+    }
+
+    /// Arm a trap, replacing any armed one, or disarm with `None`
+    pub fn set_trap(&mut self, trap: Option<TrapCondition>) {
+        self.trap = trap;
+        self.preceding.clear();
+    }
+
+    /// Begin tracing a message, if a capture is recording or it fires the trap
+    pub fn trace(
+        &mut self,
+        now: Instant,
+        timestamp_us: u64,
+        port: &str,
+        bytes: &[u8],
+    ) -> Option<CaptureEntry> {
+        let recording = self.until.is_some_and(|until| now < until);
+        let trapped = self.trap_matches(port, bytes);
+        if self.trap.is_some() && !trapped {
+            if self.preceding.len() == Self::TRAP_CONTEXT {
+                self.preceding.pop_front();
+            }
+            self.preceding.push_back(PrecedingMessage {
+                timestamp_us,
+                port: port.to_string(),
+                bytes: bytes.to_vec(),
+            });
+        }
+        if !recording && !trapped {
+            return None;
+        }
+        Some(CaptureEntry {
+            timestamp_us,
+            port: port.to_string(),
+            bytes: bytes.to_vec(),
+            handling: CaptureHandling::Routed,
+            routes: Vec::new(),
+        })
+    }
+
+    /// Finish tracing a message. Returns the trap hit if it fired the trap.
+    pub fn record(&mut self, now: Instant, entry: CaptureEntry) -> Option<TrapHit> {
+        let hit = self.trap_matches(&entry.port, &entry.bytes).then(|| {
+            self.trap = None;
+            TrapHit {
+                entry: entry.clone(),
+                preceding: self.preceding.drain(..).collect(),
+            }
+        });
+        let recording = self.until.is_some_and(|until| now < until);
+        if let Some(capture) = self.capture.as_mut().filter(|_| recording) {
+            if capture.entries.len() < Self::MAX_ENTRIES {
+                capture.entries.push(entry);
+            } else {
+                capture.truncated = true;
+            }
+        }
+        hit
+    }
+
+    fn trap_matches(&self, port: &str, bytes: &[u8]) -> bool {
+        self.trap
+            .as_ref()
+            .is_some_and(|trap| trap.matches(port, bytes))
+    }
+
+    /// End the capture and hand it over
+    pub fn take(&mut self) -> Option<DebugCapture> {
+        self.until = None;
+        self.capture.take()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::{RouteDecision, RouteTrace};
+    use uuid::Uuid;
+
+    #[test]
+    fn records_only_within_window() {
+        let t0 = Instant::now();
+        let mut recorder = CaptureRecorder::default();
+        assert!(recorder.trace(t0, 0, "Keys", &[0x90, 60, 100]).is_none());
+
+        recorder.start(Duration::from_millis(100), 1_000, t0);
+        let mut entry = recorder.trace(t0, 1_010, "Keys", &[0x90, 60, 100]).unwrap();
+        entry.routes.push(RouteTrace::new(
+            Uuid::new_v4(),
+            "Synth",
+            RouteDecision::ChannelFiltered,
+        ));
+        assert!(recorder.record(t0, entry).is_none());
+        assert!(recorder
+            .trace(t0 + Duration::from_millis(100), 0, "Keys", &[0x80, 60, 0])
+            .is_none());
+
+        let capture = recorder.take().unwrap();
+        assert_eq!(capture.started_us, 1_000);
+        assert_eq!(capture.duration_ms, 100);
+        assert_eq!(capture.entries.len(), 1);
+        assert_eq!(
+            capture.entries[0].routes[0].decision,
+            RouteDecision::ChannelFiltered
+        );
+        assert!(recorder.take().is_none());
+    }
+
+    #[test]
+    fn trap_fires_once_with_context() {
+        let t0 = Instant::now();
+        let mut recorder = CaptureRecorder::default();
+        recorder.set_trap(Some(TrapCondition {
+            port: Some("Keys".to_string()),
+            channel: Some(2),
+            message_type: Some(0xB0),
+            data1: Some(64),
+        }));
+
+        assert!(recorder.trace(t0, 1, "Keys", &[0x90, 60, 100]).is_none());
+        assert!(recorder.trace(t0, 2, "Keys", &[0xB0, 64, 127]).is_none());
+        assert!(recorder.trace(t0, 3, "Pads", &[0xB2, 64, 127]).is_none());
+
+        let entry = recorder.trace(t0, 4, "Keys", &[0xB2, 64, 127]).unwrap();
+        let hit = recorder.record(t0, entry).unwrap();
+        assert_eq!(hit.entry.timestamp_us, 4);
+        assert_eq!(hit.preceding.len(), 3);
+        assert_eq!(hit.preceding[0].bytes, vec![0x90, 60, 100]);
+
+        // Disarmed, and nothing was recorded without a capture
+        assert!(recorder.trace(t0, 5, "Keys", &[0xB2, 64, 0]).is_none());
+        assert!(recorder.take().is_none());
+    }
+}
@@ -0,0 +1,124 @@
+//! CC ramps
+//!
+//! Sweeps a controller from one value to another over a set time, for fades
+//! run from a remote. A value goes out only when it changes, so a slow ramp
+//! sends one message per step of the controller.
+
+use std::time::{Duration, Instant};
+
+#[derive(Debug)]
+pub struct CcRamp {
+    port: String,
+    channel: u8,
+    cc: u8,
+    from: u8,
+    to: u8,
+    duration: Duration,
+    started: Instant,
+    last: Option<u8>,
+    finished: bool,
+}
+
+impl CcRamp {
+    /// `channel` is 1-16
+    pub fn new(
+        port: String,
+        channel: u8,
+        cc: u8,
+        from: u8,
+        to: u8,
+        duration: Duration,
+        now: Instant,
+    ) -> Self {
+        Self {
+            port,
+            channel: channel.clamp(1, 16) - 1,
+            cc: cc.min(127),
+            from: from.min(127),
+            to: to.min(127),
+            duration,
+            started: now,
+            last: None,
+            finished: false,
+        }
+    }
+
+    pub fn port(&self) -> &str {
+        &self.port
+    }
+
+    /// Whether both ramps drive the same controller, so the newer should
+    /// take over
+    pub fn same_control(&self, other: &CcRamp) -> bool {
+        self.port == other.port && self.channel == other.channel && self.cc == other.cc
+    }
+
+    /// The controller's message, if its value has moved on by `now`
+    pub fn poll(&mut self, now: Instant) -> Option<Vec<u8>> {
+        let elapsed = now.saturating_duration_since(self.started);
+        self.finished = elapsed >= self.duration;
+        let value = if self.finished {
+            self.to
+        } else {
+            let progress = elapsed.as_secs_f64() / self.duration.as_secs_f64();
+            let span = self.to as f64 - self.from as f64;
+            (self.from as f64 + span * progress).round() as u8
+        };
+        if self.last == Some(value) {
+            return None;
+        }
+        self.last = Some(value);
+        Some(vec![0xB0 | self.channel, self.cc, value])
+    }
+
+    /// Whether the end value has been sent
+    pub fn is_finished(&self) -> bool {
+        self.finished
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ramp(from: u8, to: u8, duration_ms: u64, now: Instant) -> CcRamp {
+        CcRamp::new(
+            "Synth".to_string(),
+            2,
+            7,
+            from,
+            to,
+            Duration::from_millis(duration_ms),
+            now,
+        )
+    }
+
+    #[test]
+    fn sweeps_and_sends_only_changes() {
+        let start = Instant::now();
+        let mut fade = ramp(100, 0, 1000, start);
+        assert_eq!(fade.poll(start), Some(vec![0xB1, 7, 100]));
+        assert_eq!(fade.poll(start), None);
+        let halfway = start + Duration::from_millis(500);
+        assert_eq!(fade.poll(halfway), Some(vec![0xB1, 7, 50]));
+        let end = start + Duration::from_secs(2);
+        assert_eq!(fade.poll(end), Some(vec![0xB1, 7, 0]));
+        assert!(fade.is_finished());
+    }
+
+    #[test]
+    fn zero_duration_jumps_to_the_end() {
+        let start = Instant::now();
+        let mut jump = ramp(0, 127, 0, start);
+        assert_eq!(jump.poll(start), Some(vec![0xB1, 7, 127]));
+        assert!(jump.is_finished());
+    }
+
+    #[test]
+    fn matches_ramps_on_the_same_controller() {
+        let now = Instant::now();
+        assert!(ramp(0, 127, 100, now).same_control(&ramp(127, 0, 50, now)));
+        let other = CcRamp::new("Synth".to_string(), 2, 11, 0, 127, Duration::ZERO, now);
+        assert!(!ramp(0, 127, 100, now).same_control(&other));
+    }
+}
@@ -0,0 +1,175 @@
+//! Chord detection
+//!
+//! Groups NoteOns that arrive on a port within a short window and names the
+//! resulting chord (or interval, for two notes).
+
+use crate::types::{DetectedChord, PITCH_CLASSES};
+use std::collections::{BTreeSet, HashMap};
+use std::time::{Duration, Instant};
+
+/// Chord qualities as semitones above the root
+const CHORDS: [(&str, &[u8]); 13] = [
+    ("", &[0, 4, 7]),
+    ("m", &[0, 3, 7]),
+    ("dim", &[0, 3, 6]),
+    ("aug", &[0, 4, 8]),
+    ("sus2", &[0, 2, 7]),
+    ("sus4", &[0, 5, 7]),
+    ("6", &[0, 4, 7, 9]),
+    ("m6", &[0, 3, 7, 9]),
+    ("7", &[0, 4, 7, 10]),
+    ("maj7", &[0, 4, 7, 11]),
+    ("m7", &[0, 3, 7, 10]),
+    ("m7b5", &[0, 3, 6, 10]),
+    ("dim7", &[0, 3, 6, 9]),
+];
+
+const INTERVALS: [&str; 12] = [
+    "unison", "m2", "M2", "m3", "M3", "P4", "tritone", "P5", "m6", "M6", "m7", "M7",
+];
+
+/// Name the chord formed by the given notes, or None if it isn't recognized.
+/// Chords not in root position are named as slash chords over the bass note.
+pub fn chord_name(notes: &[u8]) -> Option<String> {
+    let bass = *notes.iter().min()?;
+    let classes: BTreeSet<u8> = notes.iter().map(|n| n % 12).collect();
+
+    if classes.len() == 2 {
+        let top = notes.iter().map(|n| (n - bass) % 12).find(|i| *i != 0)?;
+        return Some(format!(
+            "{} {}",
+            PITCH_CLASSES[(bass % 12) as usize],
+            INTERVALS[top as usize]
+        ));
+    }
+
+    // Prefer the bass note as root, then any other note (inversions)
+    let bass_class = bass % 12;
+    let roots =
+        std::iter::once(bass_class).chain(classes.iter().copied().filter(|c| *c != bass_class));
+    for root in roots {
+        let shape: BTreeSet<u8> = classes.iter().map(|c| (c + 12 - root) % 12).collect();
+        let Some((quality, _)) = CHORDS
+            .iter()
+            .find(|(_, intervals)| intervals.iter().copied().collect::<BTreeSet<u8>>() == shape)
+        else {
+            continue;
+        };
+        let name = format!("{}{}", PITCH_CLASSES[root as usize], quality);
+        return Some(if root == bass_class {
+            name
+        } else {
+            format!("{}/{}", name, PITCH_CLASSES[bass_class as usize])
+        });
+    }
+    None
+}
+
+/// Collects near-simultaneous NoteOns per port
+pub struct ChordDetector {
+    window: Duration,
+    /// First NoteOn time and the notes gathered since, per port
+    pending: HashMap<String, (Instant, BTreeSet<u8>)>,
+}
+
+impl ChordDetector {
+    /// Notes starting within this long of the first count as one chord
+    pub const DEFAULT_WINDOW: Duration = Duration::from_millis(40);
+
+    pub fn new(window: Duration) -> Self {
+        Self {
+            window,
+            pending: HashMap::new(),
+        }
+    }
+
+    pub fn note_on(&mut self, port: &str, note: u8, now: Instant) {
+        self.pending
+            .entry(port.to_string())
+            .or_insert_with(|| (now, BTreeSet::new()))
+            .1
+            .insert(note);
+    }
+
+    /// Chords whose window has closed. Single notes are discarded.
+    pub fn flush(&mut self, now: Instant) -> Vec<DetectedChord> {
+        if self.pending.is_empty() {
+            return Vec::new();
+        }
+        let closed: Vec<String> = self
+            .pending
+            .iter()
+            .filter(|(_, (start, _))| now.duration_since(*start) >= self.window)
+            .map(|(port, _)| port.clone())
+            .collect();
+
+        let mut chords: Vec<DetectedChord> = closed
+            .into_iter()
+            .filter_map(|port| {
+                let (_, notes) = self.pending.remove(&port)?;
+                if notes.len() < 2 {
+                    return None;
+                }
+                let notes: Vec<u8> = notes.into_iter().collect();
+                Some(DetectedChord {
+                    name: chord_name(&notes),
+                    port,
+                    notes,
+                })
+            })
+            .collect();
+        chords.sort_by(|a, b| a.port.cmp(&b.port));
+        chords
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn names_triads_and_sevenths() {
+        assert_eq!(chord_name(&[60, 64, 67]).as_deref(), Some("C"));
+        assert_eq!(chord_name(&[57, 60, 64]).as_deref(), Some("Am"));
+        assert_eq!(chord_name(&[55, 59, 62, 65]).as_deref(), Some("G7"));
+        assert_eq!(chord_name(&[62, 65, 69, 72]).as_deref(), Some("Dm7"));
+        // Doubled notes don't matter
+        assert_eq!(chord_name(&[48, 60, 64, 67, 72]).as_deref(), Some("C"));
+    }
+
+    #[test]
+    fn inversions_are_slash_chords() {
+        assert_eq!(chord_name(&[64, 67, 72]).as_deref(), Some("C/E"));
+        assert_eq!(chord_name(&[55, 60, 64]).as_deref(), Some("C/G"));
+    }
+
+    #[test]
+    fn two_notes_name_the_interval() {
+        assert_eq!(chord_name(&[60, 67]).as_deref(), Some("C P5"));
+        assert_eq!(chord_name(&[60, 76]).as_deref(), Some("C M3"));
+        assert_eq!(chord_name(&[60, 61, 62]), None);
+    }
+
+    #[test]
+    fn detector_groups_notes_within_window() {
+        let t0 = Instant::now();
+        let mut detector = ChordDetector::new(Duration::from_millis(40));
+        detector.note_on("Keys", 60, t0);
+        detector.note_on("Keys", 64, t0 + Duration::from_millis(10));
+        detector.note_on("Keys", 67, t0 + Duration::from_millis(20));
+        detector.note_on("Pads", 36, t0);
+
+        assert!(detector.flush(t0 + Duration::from_millis(30)).is_empty());
+        let chords = detector.flush(t0 + Duration::from_millis(40));
+        assert_eq!(
+            chords,
+            vec![DetectedChord {
+                port: "Keys".to_string(),
+                notes: vec![60, 64, 67],
+                name: Some("C".to_string()),
+            }]
+        );
+        // The single Pads note was dropped with its window
+        assert!(detector.flush(t0 + Duration::from_secs(1)).is_empty());
+    }
+}
@@ -0,0 +1,318 @@
+//! MIDI Clock generator
+//!
+//! Handles timing, tick calculation, and clock pulse generation at 24 PPQ.
+//! Pulses sent while running are counted into bars and beats (4/4).
+
+use crate::types::ClockPosition;
+use std::time::{Duration, Instant};
+
+/// MIDI Clock generator - produces 24 pulses per quarter note
+pub struct ClockGenerator {
+    bpm: f64,
+    running: bool,
+    /// Keep ticking while stopped (for outputs in always-on clock mode)
+    free_running: bool,
+    last_tick: Option<Instant>,
+    /// Pulses sent while running since the last Start
+    pulses: u64,
+    /// A Stop is waiting for the end of the current bar
+    stop_at_bar_end: bool,
+}
+
+impl ClockGenerator {
+    pub const PULSES_PER_QUARTER_NOTE: u32 = 24;
+    pub const BEATS_PER_BAR: u32 = 4;
+
+    pub fn new(bpm: f64) -> Self {
+        Self {
+            bpm: bpm.clamp(20.0, 300.0),
+            running: false,
+            free_running: false,
+            last_tick: None,
+            pulses: 0,
+            stop_at_bar_end: false,
+        }
+    }
+
+    /// Set the BPM (clamped to 20-300)
+    pub fn set_bpm(&mut self, bpm: f64) {
+        self.bpm = bpm.clamp(20.0, 300.0);
+    }
+
+    pub fn bpm(&self) -> f64 {
+        self.bpm
+    }
+
+    pub fn is_running(&self) -> bool {
+        self.running
+    }
+
+    /// Start the clock (resets timing and position)
+    pub fn start(&mut self) {
+        self.running = true;
+        self.last_tick = None;
+        self.pulses = 0;
+        self.stop_at_bar_end = false;
+    }
+
+    /// Continue the clock (preserves timing and position)
+    pub fn continue_playback(&mut self) {
+        self.running = true;
+        // Don't reset last_tick for continue
+    }
+
+    /// Stop the clock
+    pub fn stop(&mut self) {
+        self.running = false;
+        self.stop_at_bar_end = false;
+    }
+
+    /// Defer a Stop to the end of the current bar. Returns false if the clock
+    /// is stopped or already at a bar line, where it should stop right away.
+    pub fn stop_at_bar_end(&mut self) -> bool {
+        if !self.running || self.at_bar_line() {
+            return false;
+        }
+        self.stop_at_bar_end = true;
+        true
+    }
+
+    /// Whether a deferred Stop is due: the next pulse would start a new bar
+    pub fn bar_end_reached(&self) -> bool {
+        self.running && self.stop_at_bar_end && self.at_bar_line() && self.tick_due(Instant::now())
+    }
+
+    fn at_bar_line(&self) -> bool {
+        let pulses_per_bar = u64::from(Self::PULSES_PER_QUARTER_NOTE * Self::BEATS_PER_BAR);
+        self.pulses.is_multiple_of(pulses_per_bar)
+    }
+
+    /// Tick even while stopped. Start still resets the timing.
+    pub fn set_free_running(&mut self, free_running: bool) {
+        self.free_running = free_running;
+    }
+
+    /// Calculate the interval between clock pulses
+    pub fn clock_interval(&self) -> Duration {
+        // 60 seconds / BPM / 24 PPQ
+        Duration::from_secs_f64(60.0 / self.bpm / Self::PULSES_PER_QUARTER_NOTE as f64)
+    }
+
+    /// Check if a clock tick should be generated, and update timing if so.
+    /// Returns true if a tick should be sent.
+    pub fn should_tick(&mut self) -> bool {
+        if !self.running && !self.free_running {
+            return false;
+        }
+        // Hold the next bar's first pulse for a deferred Stop
+        if self.running && self.stop_at_bar_end && self.at_bar_line() {
+            return false;
+        }
+
+        let now = Instant::now();
+        let interval = self.clock_interval();
+        let should_tick = self.tick_due(now);
+
+        if should_tick {
+            // Increment by interval instead of setting to now to prevent drift
+            self.last_tick = Some(match self.last_tick {
+                None => now,
+                Some(last) => {
+                    // If we've fallen too far behind (>2 intervals), reset to now
+                    let next = last + interval;
+                    if now.duration_since(next) > interval {
+                        now
+                    } else {
+                        next
+                    }
+                }
+            });
+            if self.running {
+                self.pulses += 1;
+            }
+        }
+
+        should_tick
+    }
+
+    fn tick_due(&self, now: Instant) -> bool {
+        match self.last_tick {
+            None => true,
+            Some(last) => now.duration_since(last) >= self.clock_interval(),
+        }
+    }
+
+    /// Position of the last pulse sent while running
+    pub fn position(&self) -> ClockPosition {
+        position_of(self.pulses.saturating_sub(1))
+    }
+}
+
+/// Bar and beat (counted from 1) and tick within the beat of a pulse
+/// counted from 0
+fn position_of(pulse: u64) -> ClockPosition {
+    let ppq = u64::from(ClockGenerator::PULSES_PER_QUARTER_NOTE);
+    let beats = pulse / ppq;
+    let beats_per_bar = u64::from(ClockGenerator::BEATS_PER_BAR);
+    ClockPosition {
+        bar: (beats / beats_per_bar + 1) as u32,
+        beat: (beats % beats_per_bar + 1) as u32,
+        tick: (pulse % ppq) as u32,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::thread;
+
+    #[test]
+    fn new_clock_is_stopped() {
+        let clock = ClockGenerator::new(120.0);
+        assert!(!clock.is_running());
+        assert_eq!(clock.bpm(), 120.0);
+    }
+
+    #[test]
+    fn bpm_is_clamped() {
+        let clock = ClockGenerator::new(10.0);
+        assert_eq!(clock.bpm(), 20.0);
+
+        let clock = ClockGenerator::new(500.0);
+        assert_eq!(clock.bpm(), 300.0);
+    }
+
+    #[test]
+    fn start_enables_clock() {
+        let mut clock = ClockGenerator::new(120.0);
+        clock.start();
+        assert!(clock.is_running());
+    }
+
+    #[test]
+    fn stop_disables_clock() {
+        let mut clock = ClockGenerator::new(120.0);
+        clock.start();
+        clock.stop();
+        assert!(!clock.is_running());
+    }
+
+    #[test]
+    fn should_tick_returns_false_when_stopped() {
+        let mut clock = ClockGenerator::new(120.0);
+        assert!(!clock.should_tick());
+    }
+
+    #[test]
+    fn should_tick_returns_true_on_first_tick() {
+        let mut clock = ClockGenerator::new(120.0);
+        clock.start();
+        assert!(clock.should_tick());
+    }
+
+    #[test]
+    fn should_tick_respects_interval() {
+        let mut clock = ClockGenerator::new(120.0);
+        clock.start();
+
+        // First tick always returns true
+        assert!(clock.should_tick());
+
+        // Immediately after, should not tick
+        assert!(!clock.should_tick());
+
+        // At 120 BPM with 24 PPQ, interval is 60/120/24 = 0.0208333s ≈ 20.8ms
+        // Wait slightly longer than the interval
+        thread::sleep(Duration::from_millis(25));
+
+        // Now should tick
+        assert!(clock.should_tick());
+    }
+
+    #[test]
+    fn set_bpm_updates_interval() {
+        let mut clock = ClockGenerator::new(120.0);
+        clock.set_bpm(60.0);
+        assert_eq!(clock.bpm(), 60.0);
+    }
+
+    #[test]
+    fn continue_preserves_timing() {
+        let mut clock = ClockGenerator::new(120.0);
+        clock.start();
+        clock.should_tick(); // Get first tick
+
+        clock.stop();
+        clock.continue_playback();
+
+        // After continue, last_tick should still be set
+        assert!(clock.is_running());
+    }
+
+    #[test]
+    fn pulses_count_into_bars_and_beats() {
+        assert_eq!(
+            position_of(0),
+            ClockPosition {
+                bar: 1,
+                beat: 1,
+                tick: 0
+            }
+        );
+        assert_eq!(
+            position_of(24 * 3 + 5),
+            ClockPosition {
+                bar: 1,
+                beat: 4,
+                tick: 5
+            }
+        );
+        assert_eq!(
+            position_of(24 * 4),
+            ClockPosition {
+                bar: 2,
+                beat: 1,
+                tick: 0
+            }
+        );
+
+        let mut clock = ClockGenerator::new(120.0);
+        clock.set_free_running(true);
+        clock.should_tick();
+        assert_eq!(clock.pulses, 0);
+        clock.start();
+        clock.should_tick();
+        assert_eq!(clock.pulses, 1);
+        assert_eq!(clock.position().tick, 0);
+    }
+
+    #[test]
+    fn stop_waits_for_the_bar_line() {
+        let mut clock = ClockGenerator::new(120.0);
+        clock.start();
+        assert!(!clock.stop_at_bar_end());
+
+        clock.should_tick();
+        assert!(clock.stop_at_bar_end());
+        assert!(!clock.bar_end_reached());
+
+        // Last pulse of the bar sent, so the next one would start bar 2
+        clock.pulses = 96;
+        thread::sleep(Duration::from_millis(25));
+        assert!(clock.bar_end_reached());
+        assert!(!clock.should_tick());
+
+        clock.stop();
+        assert!(!clock.bar_end_reached());
+    }
+
+    #[test]
+    fn free_running_clock_ticks_while_stopped() {
+        let mut clock = ClockGenerator::new(120.0);
+        assert!(!clock.should_tick());
+
+        clock.set_free_running(true);
+        assert!(!clock.is_running());
+        assert!(clock.should_tick());
+    }
+}
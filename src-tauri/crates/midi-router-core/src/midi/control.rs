@@ -0,0 +1,301 @@
+//! Control input handling
+//!
+//! Messages on designated control inputs can drive the engine itself
+//! (tempo, transport) instead of being routed to outputs.
+
+use crate::midi::router::{get_channel_from_bytes, is_cc_message};
+use crate::types::{ControlBindings, ControlTrigger, TransportAction};
+use std::collections::HashSet;
+use uuid::Uuid;
+
+/// Action the engine should take in response to a control message
+#[derive(Debug, Clone, PartialEq)]
+pub enum ControlAction {
+    SetBpm(f64),
+    TapTempo,
+    Transport(TransportAction),
+    /// Matched a binding but requires no action (e.g. button release)
+    Consumed,
+}
+
+/// Input ports that must be connected for the bindings to work
+pub fn control_input_ports(bindings: &ControlBindings) -> HashSet<String> {
+    let mut ports = HashSet::new();
+    if let Some(binding) = &bindings.tempo_cc {
+        ports.insert(binding.port.clone());
+    }
+    if let Some(binding) = &bindings.tap_tempo {
+        ports.insert(binding.port.clone());
+    }
+    for binding in &bindings.transport_triggers {
+        ports.insert(binding.port.clone());
+    }
+    if let Some(binding) = &bindings.song_select {
+        ports.insert(binding.port.clone());
+    }
+    ports
+}
+
+/// Preset mapped to a Song Select message, if the port has a Song Select binding
+pub fn song_select_preset(
+    bindings: &ControlBindings,
+    port_name: &str,
+    bytes: &[u8],
+) -> Option<Uuid> {
+    let binding = bindings.song_select.as_ref()?;
+    match bytes {
+        [0xF3, song, ..] if binding.port == port_name => binding.presets.get(song).copied(),
+        _ => None,
+    }
+}
+
+/// Check an incoming message against the control bindings.
+/// Returns the action to perform if the message is a control message.
+pub fn match_control_message(
+    bindings: &ControlBindings,
+    port_name: &str,
+    bytes: &[u8],
+) -> Option<ControlAction> {
+    if let Some(binding) = &bindings.tempo_cc {
+        if binding.port == port_name
+            && is_cc_message(bytes)
+            && bytes[1] == binding.cc
+            && channel_matches(binding.channel, bytes)
+        {
+            return Some(ControlAction::SetBpm(binding.bpm_for_value(bytes[2])));
+        }
+    }
+
+    if let Some(binding) = &bindings.tap_tempo {
+        if binding.port == port_name && channel_matches(binding.channel, bytes) {
+            match trigger_state(&binding.trigger, bytes) {
+                Some(true) => return Some(ControlAction::TapTempo),
+                Some(false) => return Some(ControlAction::Consumed),
+                None => {}
+            }
+        }
+    }
+
+    for binding in &bindings.transport_triggers {
+        if binding.port == port_name && channel_matches(binding.channel, bytes) {
+            match trigger_state(&binding.trigger, bytes) {
+                Some(true) => return Some(ControlAction::Transport(binding.action)),
+                Some(false) => return Some(ControlAction::Consumed),
+                None => {}
+            }
+        }
+    }
+
+    None
+}
+
+/// Check a message against a trigger.
+/// Returns Some(true) if it fires, Some(false) if it matches without firing
+/// (NoteOff / button release), None if unrelated.
+pub fn trigger_state(trigger: &ControlTrigger, bytes: &[u8]) -> Option<bool> {
+    if bytes.len() < 3 {
+        return None;
+    }
+    let status = bytes[0] & 0xF0;
+    match trigger {
+        ControlTrigger::Note { note } if bytes[1] == *note => match status {
+            0x90 => Some(bytes[2] > 0),
+            0x80 => Some(false),
+            _ => None,
+        },
+        ControlTrigger::Cc { cc } if status == 0xB0 && bytes[1] == *cc => Some(bytes[2] >= 64),
+        _ => None,
+    }
+}
+
+fn channel_matches(channel: Option<u8>, bytes: &[u8]) -> bool {
+    match channel {
+        Some(ch) => get_channel_from_bytes(bytes) == Some(ch),
+        None => true,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::{
+        SongSelectBinding, TapTempoBinding, TempoCcBinding, TransportTriggerBinding,
+    };
+
+    fn tempo_bindings(channel: Option<u8>) -> ControlBindings {
+        ControlBindings {
+            tempo_cc: Some(TempoCcBinding {
+                port: "Controller".to_string(),
+                channel,
+                cc: 20,
+                min_bpm: 60.0,
+                max_bpm: 187.0,
+            }),
+            ..ControlBindings::default()
+        }
+    }
+
+    fn tap_bindings(trigger: ControlTrigger) -> ControlBindings {
+        ControlBindings {
+            tap_tempo: Some(TapTempoBinding {
+                port: "Pads".to_string(),
+                channel: Some(9),
+                trigger,
+            }),
+            ..ControlBindings::default()
+        }
+    }
+
+    #[test]
+    fn tempo_cc_maps_value_to_range() {
+        let bindings = tempo_bindings(None);
+        assert_eq!(
+            match_control_message(&bindings, "Controller", &[0xB0, 20, 0]),
+            Some(ControlAction::SetBpm(60.0))
+        );
+        assert_eq!(
+            match_control_message(&bindings, "Controller", &[0xB3, 20, 127]),
+            Some(ControlAction::SetBpm(187.0))
+        );
+    }
+
+    #[test]
+    fn tempo_cc_ignores_other_ports_and_ccs() {
+        let bindings = tempo_bindings(None);
+        assert_eq!(
+            match_control_message(&bindings, "Keyboard", &[0xB0, 20, 64]),
+            None
+        );
+        assert_eq!(
+            match_control_message(&bindings, "Controller", &[0xB0, 21, 64]),
+            None
+        );
+        assert_eq!(
+            match_control_message(&bindings, "Controller", &[0x90, 20, 64]),
+            None
+        );
+    }
+
+    #[test]
+    fn tempo_cc_respects_channel() {
+        let bindings = tempo_bindings(Some(2));
+        assert!(match_control_message(&bindings, "Controller", &[0xB2, 20, 64]).is_some());
+        assert!(match_control_message(&bindings, "Controller", &[0xB0, 20, 64]).is_none());
+    }
+
+    #[test]
+    fn tempo_cc_result_is_clamped_to_valid_bpm() {
+        let binding = TempoCcBinding {
+            port: "Controller".to_string(),
+            channel: None,
+            cc: 20,
+            min_bpm: 0.0,
+            max_bpm: 1000.0,
+        };
+        assert_eq!(binding.bpm_for_value(0), 20.0);
+        assert_eq!(binding.bpm_for_value(127), 300.0);
+    }
+
+    #[test]
+    fn tap_tempo_note_fires_on_note_on_and_consumes_note_off() {
+        let bindings = tap_bindings(ControlTrigger::Note { note: 36 });
+        assert_eq!(
+            match_control_message(&bindings, "Pads", &[0x99, 36, 100]),
+            Some(ControlAction::TapTempo)
+        );
+        assert_eq!(
+            match_control_message(&bindings, "Pads", &[0x89, 36, 0]),
+            Some(ControlAction::Consumed)
+        );
+        assert_eq!(
+            match_control_message(&bindings, "Pads", &[0x99, 36, 0]),
+            Some(ControlAction::Consumed)
+        );
+        // Other notes and channels are routed normally
+        assert_eq!(
+            match_control_message(&bindings, "Pads", &[0x99, 38, 100]),
+            None
+        );
+        assert_eq!(
+            match_control_message(&bindings, "Pads", &[0x90, 36, 100]),
+            None
+        );
+    }
+
+    #[test]
+    fn tap_tempo_cc_fires_on_press() {
+        let bindings = tap_bindings(ControlTrigger::Cc { cc: 80 });
+        assert_eq!(
+            match_control_message(&bindings, "Pads", &[0xB9, 80, 127]),
+            Some(ControlAction::TapTempo)
+        );
+        assert_eq!(
+            match_control_message(&bindings, "Pads", &[0xB9, 80, 0]),
+            Some(ControlAction::Consumed)
+        );
+    }
+
+    #[test]
+    fn transport_trigger_notes_map_to_actions() {
+        let trigger = |note, action| TransportTriggerBinding {
+            port: "Footswitch".to_string(),
+            channel: None,
+            trigger: ControlTrigger::Note { note },
+            action,
+        };
+        let bindings = ControlBindings {
+            transport_triggers: vec![
+                trigger(60, TransportAction::Start),
+                trigger(62, TransportAction::Stop),
+                trigger(64, TransportAction::Panic),
+            ],
+            ..ControlBindings::default()
+        };
+
+        assert_eq!(
+            match_control_message(&bindings, "Footswitch", &[0x90, 60, 127]),
+            Some(ControlAction::Transport(TransportAction::Start))
+        );
+        assert_eq!(
+            match_control_message(&bindings, "Footswitch", &[0x90, 64, 127]),
+            Some(ControlAction::Transport(TransportAction::Panic))
+        );
+        assert_eq!(
+            match_control_message(&bindings, "Footswitch", &[0x80, 62, 0]),
+            Some(ControlAction::Consumed)
+        );
+        assert_eq!(
+            match_control_message(&bindings, "Footswitch", &[0x90, 61, 127]),
+            None
+        );
+        assert!(control_input_ports(&bindings).contains("Footswitch"));
+    }
+
+    #[test]
+    fn control_input_ports_lists_bound_ports() {
+        let ports = control_input_ports(&tempo_bindings(None));
+        assert!(ports.contains("Controller"));
+        assert!(control_input_ports(&ControlBindings::default()).is_empty());
+    }
+
+    #[test]
+    fn song_select_maps_to_preset() {
+        let preset_id = Uuid::new_v4();
+        let bindings = ControlBindings {
+            song_select: Some(SongSelectBinding {
+                port: "Sequencer".to_string(),
+                presets: [(3, preset_id)].into_iter().collect(),
+            }),
+            ..ControlBindings::default()
+        };
+
+        assert_eq!(
+            song_select_preset(&bindings, "Sequencer", &[0xF3, 3]),
+            Some(preset_id)
+        );
+        // Unmapped songs and other ports are ignored
+        assert_eq!(song_select_preset(&bindings, "Sequencer", &[0xF3, 4]), None);
+        assert_eq!(song_select_preset(&bindings, "Keys", &[0xF3, 3]), None);
+        assert!(control_input_ports(&bindings).contains("Sequencer"));
+    }
+}
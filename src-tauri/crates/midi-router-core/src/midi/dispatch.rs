@@ -0,0 +1,65 @@
+//! Route dispatch table
+//!
+//! Compiled from the route list whenever it changes, so routing a message
+//! looks up the routes for its source port instead of scanning and
+//! string-comparing every route.
+
+use crate::types::Route;
+use std::collections::HashMap;
+
+#[derive(Debug, Default)]
+pub struct RouteDispatch {
+    /// Indices into the route list of the enabled routes from each source,
+    /// in route order
+    by_source: HashMap<String, Vec<usize>>,
+}
+
+impl RouteDispatch {
+    pub fn compile(routes: &[Route]) -> Self {
+        let mut by_source: HashMap<String, Vec<usize>> = HashMap::new();
+        for (index, route) in routes.iter().enumerate().filter(|(_, r)| r.enabled) {
+            by_source
+                .entry(route.source.name.clone())
+                .or_default()
+                .push(index);
+        }
+        Self { by_source }
+    }
+
+    /// Routes to dispatch a message from `port` to, as indices into the
+    /// route list the table was compiled from
+    pub fn routes_from(&self, port: &str) -> &[usize] {
+        self.by_source.get(port).map_or(&[], Vec::as_slice)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::PortId;
+
+    fn route(source: &str, destination: &str) -> Route {
+        Route::new(
+            PortId::new(source.to_string()),
+            PortId::new(destination.to_string()),
+        )
+    }
+
+    #[test]
+    fn groups_enabled_routes_by_source() {
+        let mut disabled = route("Keys", "Rack");
+        disabled.enabled = false;
+        let routes = vec![
+            route("Keys", "Synth"),
+            route("Pads", "Drums"),
+            disabled,
+            route("Keys", "Sampler"),
+        ];
+
+        let dispatch = RouteDispatch::compile(&routes);
+
+        assert_eq!(dispatch.routes_from("Keys"), &[0, 3]);
+        assert_eq!(dispatch.routes_from("Pads"), &[1]);
+        assert!(dispatch.routes_from("Clock").is_empty());
+    }
+}
@@ -0,0 +1,101 @@
+//! Duplicate note filter
+//!
+//! Bouncy pads and worn keybed contacts fire a note twice in quick
+//! succession. A route can drop a Note On that arrives too soon after the
+//! last one for the same note, together with the Note Off that pairs with
+//! it, and optionally any other message that exactly repeats the previous
+//! one.
+
+use crate::types::DuplicateFilter;
+use std::collections::{HashMap, HashSet};
+use std::time::{Duration, Instant};
+
+#[derive(Debug, Default)]
+pub struct DuplicateGate {
+    /// When each (channel, note) last started
+    note_ons: HashMap<(u8, u8), Instant>,
+    /// Notes whose double trigger was dropped; their next Note Off is too
+    dropped: HashSet<(u8, u8)>,
+    /// The last message let through, and when
+    last: Option<(Vec<u8>, Instant)>,
+}
+
+impl DuplicateGate {
+    /// Whether a message passes the filter
+    pub fn admit(&mut self, bytes: &[u8], filter: &DuplicateFilter, now: Instant) -> bool {
+        let window = Duration::from_millis(u64::from(filter.window_ms));
+        let within = |then: Instant| now.saturating_duration_since(then) < window;
+
+        let admitted = match *bytes {
+            [status, note, velocity] if status & 0xF0 == 0x90 && velocity > 0 => {
+                let key = (status & 0x0F, note);
+                if self.note_ons.get(&key).is_some_and(|at| within(*at)) {
+                    self.dropped.insert(key);
+                    false
+                } else {
+                    self.note_ons.insert(key, now);
+                    true
+                }
+            }
+            [status, note, _] if status & 0xE0 == 0x80 => {
+                !self.dropped.remove(&(status & 0x0F, note))
+            }
+            _ => {
+                let repeat = self
+                    .last
+                    .as_ref()
+                    .is_some_and(|(last, at)| last == bytes && within(*at));
+                !(filter.identical && repeat)
+            }
+        };
+        if admitted {
+            self.last = Some((bytes.to_vec(), now));
+        }
+        admitted
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn filter(identical: bool) -> DuplicateFilter {
+        DuplicateFilter {
+            window_ms: 30,
+            identical,
+        }
+    }
+
+    #[test]
+    fn drops_double_triggers_and_their_note_offs() {
+        let t0 = Instant::now();
+        let at = |ms| t0 + Duration::from_millis(ms);
+        let mut gate = DuplicateGate::default();
+        let filter = filter(false);
+
+        assert!(gate.admit(&[0x99, 36, 100], &filter, at(0)));
+        assert!(gate.admit(&[0x89, 36, 0], &filter, at(5)));
+        // The bounce and its release
+        assert!(!gate.admit(&[0x99, 36, 80], &filter, at(10)));
+        assert!(!gate.admit(&[0x89, 36, 0], &filter, at(15)));
+        // Other notes and channels aren't affected
+        assert!(gate.admit(&[0x99, 38, 100], &filter, at(12)));
+        assert!(gate.admit(&[0x90, 36, 100], &filter, at(12)));
+        // Outside the window it's a new hit
+        assert!(gate.admit(&[0x99, 36, 100], &filter, at(40)));
+    }
+
+    #[test]
+    fn identical_messages_within_the_window() {
+        let t0 = Instant::now();
+        let at = |ms| t0 + Duration::from_millis(ms);
+        let mut gate = DuplicateGate::default();
+
+        assert!(gate.admit(&[0xB0, 7, 100], &filter(true), at(0)));
+        assert!(!gate.admit(&[0xB0, 7, 100], &filter(true), at(10)));
+        assert!(gate.admit(&[0xB0, 7, 101], &filter(true), at(20)));
+        assert!(gate.admit(&[0xB0, 7, 101], &filter(true), at(60)));
+        // Without the option repeats pass
+        assert!(gate.admit(&[0xB0, 7, 101], &filter(false), at(61)));
+    }
+}
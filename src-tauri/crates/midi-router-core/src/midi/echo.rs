@@ -0,0 +1,71 @@
+//! Tempo-synced note echo
+//!
+//! Repeats each note a few times at a beat interval of the clock's tempo,
+//! each repeat softer than the last. Note Offs are repeated at the same
+//! offsets, so every echo keeps the played note's length.
+
+use crate::types::NoteEcho;
+use std::time::Duration;
+
+/// The delayed repeats of a note message at `bpm`. Repeats that decay
+/// below velocity 1 are left out; anything but notes isn't echoed.
+pub fn echoes(bytes: &[u8], echo: &NoteEcho, bpm: f64) -> Vec<(Duration, Vec<u8>)> {
+    let &[status, note, velocity] = bytes else {
+        return Vec::new();
+    };
+    let interval = Duration::from_secs_f64(60.0 / bpm * echo.beats);
+    let at = |repeat: u8| interval * repeat as u32;
+
+    match status & 0xF0 {
+        0x90 if velocity > 0 => (1..=echo.repeats)
+            .map_while(|repeat| {
+                let level = velocity as f64 * echo.decay.powi(repeat as i32);
+                let velocity = level.round().min(127.0) as u8;
+                (velocity > 0).then(|| (at(repeat), vec![status, note, velocity]))
+            })
+            .collect(),
+        0x80 | 0x90 => (1..=echo.repeats)
+            .map(|repeat| (at(repeat), bytes.to_vec()))
+            .collect(),
+        _ => Vec::new(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn echo(repeats: u8, decay: f64) -> NoteEcho {
+        NoteEcho {
+            repeats,
+            beats: 0.5,
+            decay,
+        }
+    }
+
+    #[test]
+    fn repeats_on_the_beat_grid_and_decays() {
+        // An eighth note at 120 BPM is 250 ms
+        assert_eq!(
+            echoes(&[0x91, 60, 100], &echo(3, 0.5), 120.0),
+            vec![
+                (Duration::from_millis(250), vec![0x91, 60, 50]),
+                (Duration::from_millis(500), vec![0x91, 60, 25]),
+                (Duration::from_millis(750), vec![0x91, 60, 13]),
+            ]
+        );
+        assert_eq!(
+            echoes(&[0x81, 60, 0], &echo(2, 0.5), 120.0),
+            vec![
+                (Duration::from_millis(250), vec![0x81, 60, 0]),
+                (Duration::from_millis(500), vec![0x81, 60, 0]),
+            ]
+        );
+    }
+
+    #[test]
+    fn stops_when_repeats_fade_out() {
+        assert_eq!(echoes(&[0x90, 60, 4], &echo(8, 0.25), 120.0).len(), 1);
+        assert!(echoes(&[0xB0, 7, 100], &echo(3, 0.5), 120.0).is_empty());
+    }
+}
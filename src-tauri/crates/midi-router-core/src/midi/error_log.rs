@@ -0,0 +1,74 @@
+//! Recent engine errors
+//!
+//! Keeps the last errors the engine reported so the UI can show what went
+//! wrong even if nothing was listening on the error stream at the time.
+
+use crate::types::{EngineError, RecentError};
+use std::collections::VecDeque;
+
+#[derive(Debug)]
+pub struct ErrorLog {
+    errors: VecDeque<RecentError>,
+    capacity: usize,
+}
+
+impl ErrorLog {
+    pub const DEFAULT_CAPACITY: usize = 100;
+
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            errors: VecDeque::with_capacity(capacity),
+            capacity,
+        }
+    }
+
+    pub fn record(&mut self, error: EngineError, timestamp_us: u64) {
+        if self.capacity == 0 {
+            return;
+        }
+        if self.errors.len() == self.capacity {
+            self.errors.pop_front();
+        }
+        self.errors.push_back(RecentError {
+            timestamp_us,
+            message: error.to_string(),
+            error,
+        });
+    }
+
+    /// Recorded errors, oldest first
+    pub fn recent(&self) -> Vec<RecentError> {
+        self.errors.iter().cloned().collect()
+    }
+}
+
+impl Default for ErrorLog {
+    fn default() -> Self {
+        Self::new(Self::DEFAULT_CAPACITY)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn disconnected(port_name: &str) -> EngineError {
+        EngineError::PortDisconnected {
+            port_name: port_name.to_string(),
+        }
+    }
+
+    #[test]
+    fn keeps_most_recent_errors() {
+        let mut log = ErrorLog::new(2);
+        log.record(disconnected("A"), 1);
+        log.record(disconnected("B"), 2);
+        log.record(disconnected("C"), 3);
+
+        let recent = log.recent();
+        assert_eq!(recent.len(), 2);
+        assert_eq!(recent[0].timestamp_us, 2);
+        assert_eq!(recent[1].error, disconnected("C"));
+        assert_eq!(recent[1].message, "Port 'C' was disconnected");
+    }
+}
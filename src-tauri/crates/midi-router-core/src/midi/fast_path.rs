@@ -0,0 +1,180 @@
+//! Low-latency fast path
+//!
+//! Routes that only filter can be sent straight from the input callback,
+//! skipping the hop through the engine loop. On each route change the engine
+//! compiles them into an immutable per-source table that the callbacks
+//! share. The engine still sees every message, and does the bookkeeping for
+//! routes the callback already sent on.
+
+use crate::midi::msc::should_route_msc;
+use crate::midi::router::{
+    is_aftertouch, should_route, should_route_key_range, should_route_real_time,
+    should_route_system_common,
+};
+use crate::midi::transport::is_transport_message;
+use crate::types::{
+    ChannelFilter, KeyRange, MscFilter, ProgramChangeFilter, RealTimeStrip, Route,
+    SystemCommonFilter,
+};
+use std::collections::{HashMap, HashSet};
+use std::sync::{Arc, RwLock};
+use uuid::Uuid;
+
+/// A route the input callback can send on directly
+#[derive(Debug, Clone)]
+pub struct FastRoute {
+    pub route_id: Uuid,
+    pub destination: String,
+    channels: ChannelFilter,
+    msc_filter: MscFilter,
+    system_common_filter: SystemCommonFilter,
+    real_time_strip: RealTimeStrip,
+    key_range: Option<KeyRange>,
+    strip_aftertouch: bool,
+}
+
+impl FastRoute {
+    fn passes(&self, bytes: &[u8]) -> bool {
+        should_route(bytes, &self.channels)
+            && !(self.strip_aftertouch && is_aftertouch(bytes))
+            && should_route_msc(bytes, &self.msc_filter)
+            && should_route_system_common(bytes, &self.system_common_filter)
+            && should_route_real_time(bytes, &self.real_time_strip)
+            && should_route_key_range(bytes, self.key_range.as_ref())
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct FastPathTable {
+    by_source: HashMap<String, Vec<FastRoute>>,
+}
+
+/// The current table, swapped as a whole when routes change
+pub type SharedFastPath = Arc<RwLock<Arc<FastPathTable>>>;
+
+impl FastPathTable {
+    /// Compile the enabled routes that only filter. Routes from or to a port
+    /// in `excluded` (control inputs, jitter-buffered inputs, buses) always
+    /// go through the engine, as do routes sharing an output, so the engine
+    /// can merge their notes.
+    pub fn compile(routes: &[Route], excluded: &HashSet<String>) -> Self {
+        let mut outputs: HashMap<String, usize> = HashMap::new();
+        for route in routes.iter().filter(|r| r.enabled) {
+            for port in route.output_ports() {
+                *outputs.entry(port).or_default() += 1;
+            }
+        }
+        let mut by_source: HashMap<String, Vec<FastRoute>> = HashMap::new();
+        for route in routes.iter().filter(|r| {
+            is_eligible(r)
+                && !excluded.contains(&r.source.name)
+                && !excluded.contains(&r.destination.name)
+                && outputs.get(&r.destination.name) == Some(&1)
+        }) {
+            by_source
+                .entry(route.source.name.clone())
+                .or_default()
+                .push(FastRoute {
+                    route_id: route.id,
+                    destination: route.destination.name.clone(),
+                    channels: route.channels.clone(),
+                    msc_filter: route.msc_filter.clone(),
+                    system_common_filter: route.system_common_filter.clone(),
+                    real_time_strip: route.real_time_strip,
+                    key_range: route.key_range,
+                    strip_aftertouch: route.strip_aftertouch,
+                });
+        }
+        Self { by_source }
+    }
+
+    /// Fast routes from `port` that forward this message. Transport and
+    /// clock are left to the engine, which handles them itself.
+    pub fn matching<'a>(&'a self, port: &str, bytes: &'a [u8]) -> Vec<&'a FastRoute> {
+        if is_transport_message(bytes) {
+            return Vec::new();
+        }
+        self.by_source
+            .get(port)
+            .map(|routes| routes.iter().filter(|r| r.passes(bytes)).collect())
+            .unwrap_or_default()
+    }
+}
+
+/// Whether a route does nothing but filter
+fn is_eligible(route: &Route) -> bool {
+    route.enabled
+        && route.cc_mappings.is_empty()
+        && route.microtuning.is_none()
+        && !route.latch
+        && !route.sustain
+        && route.mono.is_none()
+        && route.program_change_filter == ProgramChangeFilter::default()
+        && route.program_steppers.is_empty()
+        && route.note_mappings.is_empty()
+        && !route.bank_select.is_active()
+        && route.channel_map.is_none()
+        && route.channel_rotation.is_none()
+        && route.harmony.is_empty()
+        && route.voice_split.is_none()
+        && route.duplicate_filter.is_none()
+        && route.velocity_curve.is_none()
+        && route.velocity_humanize.is_none()
+        && route.echo.is_none()
+        && route.velocity_gate.is_none()
+        && route.layer.is_none()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::{CcMapping, PortId};
+
+    fn route(source: &str, destination: &str) -> Route {
+        Route::new(
+            PortId::new(source.to_string()),
+            PortId::new(destination.to_string()),
+        )
+    }
+
+    #[test]
+    fn compiles_filter_only_routes() {
+        let mut filtered = route("Keys", "Synth");
+        filtered.channels = ChannelFilter::Only(vec![0]);
+        let mut mapped = route("Keys", "Rack");
+        mapped.cc_mappings.push(CcMapping::default());
+        let control = route("Pedal", "Lights");
+        let excluded = HashSet::from(["Pedal".to_string()]);
+
+        let table = FastPathTable::compile(&[filtered, mapped, control], &excluded);
+
+        let matching = table.matching("Keys", &[0x90, 60, 100]);
+        assert_eq!(matching.len(), 1);
+        assert_eq!(matching[0].destination, "Synth");
+        assert!(table.matching("Keys", &[0x91, 60, 100]).is_empty());
+        assert!(table.matching("Pedal", &[0x90, 60, 100]).is_empty());
+        assert!(table.matching("Keys", &[0xFA]).is_empty());
+    }
+
+    #[test]
+    fn routes_sharing_an_output_stay_in_the_engine() {
+        let keys = route("Keys", "Synth");
+        let pads = route("Pads", "Synth");
+        let mut disabled = route("Drums", "Rack");
+        disabled.enabled = false;
+        let rack = route("Knobs", "Rack");
+
+        let table = FastPathTable::compile(&[keys, pads, disabled, rack], &HashSet::new());
+
+        assert!(table.matching("Keys", &[0x90, 60, 100]).is_empty());
+        assert!(table.matching("Pads", &[0x90, 60, 100]).is_empty());
+        assert_eq!(table.matching("Knobs", &[0xB0, 1, 64]).len(), 1);
+    }
+
+    #[test]
+    fn routes_into_excluded_ports_stay_in_the_engine() {
+        let excluded = HashSet::from(["Bus A".to_string()]);
+        let table = FastPathTable::compile(&[route("Keys", "Bus A")], &excluded);
+        assert!(table.matching("Keys", &[0x90, 60, 100]).is_empty());
+    }
+}
@@ -0,0 +1,286 @@
+//! Gamepad input
+//!
+//! Turns gamepad buttons and axes into notes and CCs on a virtual "Gamepad"
+//! input, so cheap controllers become expression sources routed like any
+//! other device. Every connected pad plays into the one port.
+
+use crate::midi::port_manager::MidiMessage;
+use crate::types::{GamepadControl, GamepadMapping, GamepadTarget};
+use crossbeam_channel::{bounded, unbounded, Sender};
+use gilrs::{Axis, Button, EventType, Gilrs};
+use std::collections::{HashMap, HashSet};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// Name of the virtual input gamepad messages arrive on
+pub const GAMEPAD_PORT: &str = "Gamepad";
+
+/// Plays a preset's gamepad mappings
+#[derive(Debug, Default)]
+pub struct GamepadMapper {
+    mappings: Vec<GamepadMapping>,
+    /// Mappings whose note is on, by index
+    notes_on: HashSet<usize>,
+    /// Last CC value each mapping sent, by index
+    last_cc: HashMap<usize, u8>,
+}
+
+impl GamepadMapper {
+    pub fn new(mappings: Vec<GamepadMapping>) -> Self {
+        Self {
+            mappings,
+            ..Self::default()
+        }
+    }
+
+    /// Messages for a control moving to `travel`, 0.0-1.0
+    pub fn input(&mut self, control: GamepadControl, travel: f32) -> Vec<Vec<u8>> {
+        let mut out = Vec::new();
+        for (index, mapping) in self.mappings.iter().enumerate() {
+            if mapping.control != control {
+                continue;
+            }
+            let channel = mapping.channel & 0x0F;
+            match mapping.target {
+                GamepadTarget::Note { note, velocity } => {
+                    let on = travel >= 0.5;
+                    if on && self.notes_on.insert(index) {
+                        out.push(vec![0x90 | channel, note, velocity.max(1)]);
+                    } else if !on && self.notes_on.remove(&index) {
+                        out.push(vec![0x80 | channel, note, 0]);
+                    }
+                }
+                GamepadTarget::Cc { cc } => {
+                    let value = (travel.clamp(0.0, 1.0) * 127.0).round() as u8;
+                    // Axes report tiny movements; only whole steps are sent
+                    if self.last_cc.insert(index, value) != Some(value) {
+                        out.push(vec![0xB0 | channel, cc, value]);
+                    }
+                }
+            }
+        }
+        out
+    }
+
+    /// Note Offs for every held note
+    pub fn release_all(&mut self) -> Vec<Vec<u8>> {
+        let mut held: Vec<usize> = self.notes_on.drain().collect();
+        held.sort();
+        held.into_iter()
+            .filter_map(|index| match self.mappings[index].target {
+                GamepadTarget::Note { note, .. } => {
+                    Some(vec![0x80 | (self.mappings[index].channel & 0x0F), note, 0])
+                }
+                GamepadTarget::Cc { .. } => None,
+            })
+            .collect()
+    }
+}
+
+/// Reads gamepads on a thread of its own and plays their mappings into the
+/// engine's incoming MIDI, as messages from `GAMEPAD_PORT`
+pub struct GamepadInput {
+    mappings_tx: Sender<Vec<GamepadMapping>>,
+    stop: Arc<AtomicBool>,
+    thread_handle: Option<thread::JoinHandle<()>>,
+}
+
+impl GamepadInput {
+    /// How long the thread waits for a gamepad event before checking for
+    /// new mappings and shutdown
+    const POLL_INTERVAL: Duration = Duration::from_millis(5);
+
+    pub fn start(
+        mappings: Vec<GamepadMapping>,
+        midi_tx: Sender<MidiMessage>,
+    ) -> Result<Self, String> {
+        let (mappings_tx, mappings_rx) = unbounded::<Vec<GamepadMapping>>();
+        let (ready_tx, ready_rx) = bounded::<Result<(), String>>(1);
+        let stop = Arc::new(AtomicBool::new(false));
+        let thread_stop = stop.clone();
+
+        // Gilrs can't move between threads, so it's created on the one that reads it
+        let thread_handle = thread::spawn(move || {
+            let mut gilrs = match Gilrs::new() {
+                Ok(gilrs) => gilrs,
+                Err(e) => {
+                    let _ = ready_tx.send(Err(format!("Gamepad input unavailable: {}", e)));
+                    return;
+                }
+            };
+            let _ = ready_tx.send(Ok(()));
+            for (_, gamepad) in gilrs.gamepads() {
+                eprintln!("[GAMEPAD] Found {}", gamepad.name());
+            }
+
+            let started = Instant::now();
+            // Never blocks: the engine stops this thread by joining it
+            let send = |messages: Vec<Vec<u8>>| {
+                for bytes in messages {
+                    let timestamp = started.elapsed().as_micros() as u64;
+                    let _ =
+                        midi_tx.try_send((GAMEPAD_PORT.to_string(), timestamp, bytes, Vec::new()));
+                }
+            };
+            let mut mapper = GamepadMapper::new(mappings);
+            while !thread_stop.load(Ordering::Relaxed) {
+                if let Some(mappings) = mappings_rx.try_iter().last() {
+                    send(mapper.release_all());
+                    mapper = GamepadMapper::new(mappings);
+                }
+                let Some(event) = gilrs.next_event_blocking(Some(Self::POLL_INTERVAL)) else {
+                    continue;
+                };
+                let (control, travel) = match event.event {
+                    EventType::ButtonChanged(button, value, _) => (button_control(button), value),
+                    // Axes rest centered, at half travel
+                    EventType::AxisChanged(axis, value, _) => {
+                        (axis_control(axis), (value + 1.0) / 2.0)
+                    }
+                    EventType::Connected => {
+                        eprintln!("[GAMEPAD] Connected {}", gilrs.gamepad(event.id).name());
+                        continue;
+                    }
+                    EventType::Disconnected => {
+                        eprintln!("[GAMEPAD] Disconnected {}", gilrs.gamepad(event.id).name());
+                        continue;
+                    }
+                    _ => continue,
+                };
+                if let Some(control) = control {
+                    send(mapper.input(control, travel));
+                }
+            }
+            send(mapper.release_all());
+        });
+
+        ready_rx
+            .recv()
+            .map_err(|_| "Gamepad input failed to start".to_string())??;
+        Ok(Self {
+            mappings_tx,
+            stop,
+            thread_handle: Some(thread_handle),
+        })
+    }
+
+    /// Replace the mappings, releasing notes the old ones held
+    pub fn set_mappings(&self, mappings: Vec<GamepadMapping>) {
+        let _ = self.mappings_tx.send(mappings);
+    }
+}
+
+impl Drop for GamepadInput {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+        if let Some(handle) = self.thread_handle.take() {
+            let _ = handle.join();
+        }
+    }
+}
+
+fn button_control(button: Button) -> Option<GamepadControl> {
+    Some(match button {
+        Button::South => GamepadControl::South,
+        Button::East => GamepadControl::East,
+        Button::North => GamepadControl::North,
+        Button::West => GamepadControl::West,
+        Button::C => GamepadControl::C,
+        Button::Z => GamepadControl::Z,
+        Button::LeftTrigger => GamepadControl::LeftTrigger,
+        Button::LeftTrigger2 => GamepadControl::LeftTrigger2,
+        Button::RightTrigger => GamepadControl::RightTrigger,
+        Button::RightTrigger2 => GamepadControl::RightTrigger2,
+        Button::Select => GamepadControl::Select,
+        Button::Start => GamepadControl::Start,
+        Button::Mode => GamepadControl::Mode,
+        Button::LeftThumb => GamepadControl::LeftThumb,
+        Button::RightThumb => GamepadControl::RightThumb,
+        Button::DPadUp => GamepadControl::DPadUp,
+        Button::DPadDown => GamepadControl::DPadDown,
+        Button::DPadLeft => GamepadControl::DPadLeft,
+        Button::DPadRight => GamepadControl::DPadRight,
+        Button::Unknown => return None,
+    })
+}
+
+fn axis_control(axis: Axis) -> Option<GamepadControl> {
+    Some(match axis {
+        Axis::LeftStickX => GamepadControl::LeftStickX,
+        Axis::LeftStickY => GamepadControl::LeftStickY,
+        Axis::LeftZ => GamepadControl::LeftZ,
+        Axis::RightStickX => GamepadControl::RightStickX,
+        Axis::RightStickY => GamepadControl::RightStickY,
+        Axis::RightZ => GamepadControl::RightZ,
+        Axis::DPadX => GamepadControl::DPadX,
+        Axis::DPadY => GamepadControl::DPadY,
+        Axis::Unknown => return None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn mapping(control: GamepadControl, channel: u8, target: GamepadTarget) -> GamepadMapping {
+        GamepadMapping {
+            control,
+            channel,
+            target,
+        }
+    }
+
+    #[test]
+    fn buttons_play_notes() {
+        let mut mapper = GamepadMapper::new(vec![mapping(
+            GamepadControl::South,
+            9,
+            GamepadTarget::Note {
+                note: 36,
+                velocity: 100,
+            },
+        )]);
+
+        assert_eq!(
+            mapper.input(GamepadControl::South, 1.0),
+            vec![vec![0x99, 36, 100]]
+        );
+        // Analog buttons report travel on the way down
+        assert!(mapper.input(GamepadControl::South, 0.8).is_empty());
+        assert!(mapper.input(GamepadControl::East, 0.0).is_empty());
+        assert_eq!(
+            mapper.input(GamepadControl::South, 0.0),
+            vec![vec![0x89, 36, 0]]
+        );
+
+        mapper.input(GamepadControl::South, 1.0);
+        assert_eq!(mapper.release_all(), vec![vec![0x89, 36, 0]]);
+        assert!(mapper.release_all().is_empty());
+    }
+
+    #[test]
+    fn axes_follow_as_cc() {
+        let mut mapper = GamepadMapper::new(vec![mapping(
+            GamepadControl::LeftStickX,
+            0,
+            GamepadTarget::Cc { cc: 1 },
+        )]);
+
+        assert_eq!(
+            mapper.input(GamepadControl::LeftStickX, 0.5),
+            vec![vec![0xB0, 1, 64]]
+        );
+        // Jitter within one step sends nothing
+        assert!(mapper.input(GamepadControl::LeftStickX, 0.502).is_empty());
+        assert_eq!(
+            mapper.input(GamepadControl::LeftStickX, 1.0),
+            vec![vec![0xB0, 1, 127]]
+        );
+        assert_eq!(
+            mapper.input(GamepadControl::LeftStickX, 0.0),
+            vec![vec![0xB0, 1, 0]]
+        );
+    }
+}
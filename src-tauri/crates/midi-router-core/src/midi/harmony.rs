@@ -0,0 +1,137 @@
+//! Interval harmonizer
+//!
+//! Stacks transposed copies on each note, each voice at its own interval
+//! and optionally on its own channel, so a fifth can go to a pad part while
+//! the octave doubles the lead. Note Offs and poly aftertouch follow the
+//! voices their note started, even if the intervals change in between.
+
+use crate::types::HarmonyVoice;
+use std::collections::HashMap;
+
+#[derive(Debug, Default)]
+pub struct HarmonyState {
+    /// Sounding voices: (input channel, note) -> (output channel, note)s
+    voices: HashMap<(u8, u8), Vec<(u8, u8)>>,
+}
+
+/// The message followed by its harmony voices. Voices landing outside
+/// 0-127, or on the played note itself, are left out.
+pub fn harmonize(bytes: &[u8], voices: &[HarmonyVoice], state: &mut HarmonyState) -> Vec<Vec<u8>> {
+    let mut out = vec![bytes.to_vec()];
+    let &[status, note, value] = bytes else {
+        return out;
+    };
+    let key = (status & 0x0F, note);
+
+    let sounding = match status & 0xF0 {
+        0x90 if value > 0 => {
+            let sounding: Vec<(u8, u8)> = voices
+                .iter()
+                .filter_map(|voice| {
+                    let channel = voice.channel.map_or(key.0, |ch| ch.clamp(1, 16) - 1);
+                    let note = u8::try_from(note as i16 + voice.interval as i16).ok()?;
+                    (note <= 127 && (channel, note) != key).then_some((channel, note))
+                })
+                .collect();
+            state.voices.insert(key, sounding.clone());
+            sounding
+        }
+        0x80 | 0x90 => state.voices.remove(&key).unwrap_or_default(),
+        0xA0 => state.voices.get(&key).cloned().unwrap_or_default(),
+        _ => Vec::new(),
+    };
+    out.extend(
+        sounding
+            .into_iter()
+            .map(|(channel, note)| vec![(status & 0xF0) | channel, note, value]),
+    );
+    out
+}
+
+/// Note Offs for every sounding harmony voice
+pub fn release_all(state: &mut HarmonyState) -> Vec<Vec<u8>> {
+    let mut out: Vec<Vec<u8>> = state
+        .voices
+        .drain()
+        .flat_map(|(_, sounding)| sounding)
+        .map(|(channel, note)| vec![0x80 | channel, note, 0])
+        .collect();
+    out.sort();
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn voices() -> Vec<HarmonyVoice> {
+        vec![
+            HarmonyVoice {
+                interval: 7,
+                channel: None,
+            },
+            HarmonyVoice {
+                interval: 12,
+                channel: Some(5),
+            },
+        ]
+    }
+
+    #[test]
+    fn stacks_voices_on_their_channels() {
+        let mut state = HarmonyState::default();
+        assert_eq!(
+            harmonize(&[0x90, 60, 100], &voices(), &mut state),
+            vec![
+                vec![0x90, 60, 100],
+                vec![0x90, 67, 100],
+                vec![0x94, 72, 100]
+            ]
+        );
+        assert_eq!(
+            harmonize(&[0xA0, 60, 30], &voices(), &mut state),
+            vec![vec![0xA0, 60, 30], vec![0xA0, 67, 30], vec![0xA4, 72, 30]]
+        );
+        // Note Offs release what the Note On started, not the current voices
+        assert_eq!(
+            harmonize(&[0x90, 60, 0], &[], &mut state),
+            vec![vec![0x90, 60, 0], vec![0x90, 67, 0], vec![0x94, 72, 0]]
+        );
+        assert_eq!(
+            harmonize(&[0x80, 60, 0], &voices(), &mut state),
+            vec![vec![0x80, 60, 0]]
+        );
+    }
+
+    #[test]
+    fn skips_voices_out_of_range() {
+        let mut state = HarmonyState::default();
+        let down = [HarmonyVoice {
+            interval: -12,
+            channel: None,
+        }];
+        assert_eq!(
+            harmonize(&[0x90, 5, 100], &down, &mut state),
+            vec![vec![0x90, 5, 100]]
+        );
+        assert_eq!(
+            harmonize(&[0x90, 120, 100], &voices(), &mut state),
+            vec![vec![0x90, 120, 100], vec![0x90, 127, 100]]
+        );
+        assert_eq!(
+            harmonize(&[0xB0, 7, 100], &voices(), &mut state),
+            vec![vec![0xB0, 7, 100]]
+        );
+    }
+
+    #[test]
+    fn release_all_silences_voices() {
+        let mut state = HarmonyState::default();
+        harmonize(&[0x90, 60, 100], &voices(), &mut state);
+        assert_eq!(
+            release_all(&mut state),
+            vec![vec![0x80, 67, 0], vec![0x84, 72, 0]]
+        );
+        assert!(release_all(&mut state).is_empty());
+    }
+}
@@ -0,0 +1,110 @@
+//! Velocity humanizing
+//!
+//! Nudges each Note On's velocity by a random amount so a static sequence
+//! doesn't hit every note identically. A seeded route nudges the same way
+//! every run, which keeps replays of a capture comparable.
+
+use crate::types::VelocityHumanize;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Xorshift generator for a route's nudges
+#[derive(Debug)]
+pub struct Humanizer {
+    seed: Option<u64>,
+    state: u64,
+}
+
+impl Humanizer {
+    /// Unseeded humanizers start from the clock
+    pub fn new(seed: Option<u64>) -> Self {
+        let start = seed.unwrap_or_else(|| {
+            SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map_or(0, |elapsed| elapsed.as_nanos() as u64)
+        });
+        // Xorshift sticks at zero, so mix the seed into a nonzero state
+        let state = (start ^ 0x9E37_79B9_7F4A_7C15).max(1);
+        Self { seed, state }
+    }
+
+    fn next(&mut self) -> u64 {
+        self.state ^= self.state << 13;
+        self.state ^= self.state >> 7;
+        self.state ^= self.state << 17;
+        self.state
+    }
+
+    /// Offset in -amount..=amount
+    fn offset(&mut self, amount: u8) -> i16 {
+        let span = 2 * amount as u64 + 1;
+        (self.next() % span) as i16 - amount as i16
+    }
+}
+
+/// Nudge a Note On's velocity, keeping it within 1-127. Anything else,
+/// including Note Ons with velocity 0, passes unchanged.
+pub fn humanize_velocity(
+    bytes: &[u8],
+    settings: &VelocityHumanize,
+    humanizer: &mut Option<Humanizer>,
+) -> Vec<u8> {
+    match *bytes {
+        [status, note, velocity] if status & 0xF0 == 0x90 && velocity > 0 => {
+            // A changed seed starts its sequence over
+            let humanizer = match humanizer {
+                Some(humanizer) if humanizer.seed == settings.seed => humanizer,
+                _ => humanizer.insert(Humanizer::new(settings.seed)),
+            };
+            let nudged = velocity as i16 + humanizer.offset(settings.amount);
+            vec![status, note, nudged.clamp(1, 127) as u8]
+        }
+        _ => bytes.to_vec(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn settings(amount: u8, seed: Option<u64>) -> VelocityHumanize {
+        VelocityHumanize { amount, seed }
+    }
+
+    fn velocities(settings: &VelocityHumanize, velocity: u8) -> Vec<u8> {
+        let mut humanizer = None;
+        (0..50)
+            .map(|_| humanize_velocity(&[0x90, 60, velocity], settings, &mut humanizer)[2])
+            .collect()
+    }
+
+    #[test]
+    fn nudges_within_the_amount() {
+        let played = velocities(&settings(10, Some(7)), 100);
+        assert!(played.iter().all(|v| (90..=110).contains(v)));
+        assert!(played.iter().any(|&v| v != played[0]));
+    }
+
+    #[test]
+    fn seeded_routes_repeat() {
+        let seeded = settings(10, Some(42));
+        assert_eq!(velocities(&seeded, 64), velocities(&seeded, 64));
+        assert_ne!(
+            velocities(&seeded, 64),
+            velocities(&settings(10, Some(43)), 64)
+        );
+    }
+
+    #[test]
+    fn stays_in_range_and_leaves_other_messages() {
+        assert!(velocities(&settings(20, None), 3).iter().all(|&v| v >= 1));
+        assert!(velocities(&settings(20, None), 120)
+            .iter()
+            .all(|&v| v <= 127));
+
+        let mut humanizer = None;
+        let humanize = settings(20, Some(1));
+        for bytes in [[0x90, 60, 0], [0x80, 60, 100], [0xB0, 7, 100]] {
+            assert_eq!(humanize_velocity(&bytes, &humanize, &mut humanizer), bytes);
+        }
+    }
+}
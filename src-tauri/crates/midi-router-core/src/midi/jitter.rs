@@ -0,0 +1,101 @@
+//! Jitter buffering for network and BLE inputs
+//!
+//! These transports deliver messages in bursts. A jitter buffer holds each
+//! message until its source timestamp plus a fixed latency, measured from
+//! the fastest delivery seen so far, which restores the original spacing.
+
+use std::collections::VecDeque;
+use std::time::{Duration, Instant};
+
+pub struct JitterBuffer {
+    latency: Duration,
+    /// Smallest (arrival - source stamp) seen, in microseconds
+    min_transit: Option<i128>,
+    /// Messages waiting for their release time, in arrival order
+    held: VecDeque<(Instant, u64, Vec<u8>)>,
+    epoch: Instant,
+}
+
+impl JitterBuffer {
+    pub const MAX_LATENCY: Duration = Duration::from_millis(50);
+
+    pub fn new(latency: Duration, now: Instant) -> Self {
+        Self {
+            latency,
+            min_transit: None,
+            held: VecDeque::new(),
+            epoch: now,
+        }
+    }
+
+    pub fn latency(&self) -> Duration {
+        self.latency
+    }
+
+    /// Hold a message stamped `stamp_us` by the backend
+    pub fn push(&mut self, stamp_us: u64, bytes: Vec<u8>, now: Instant) {
+        let arrival = now.duration_since(self.epoch).as_micros() as i128;
+        let transit = arrival - stamp_us as i128;
+        let min_transit = self.min_transit.map_or(transit, |min| min.min(transit));
+        self.min_transit = Some(min_transit);
+
+        // Messages delayed by `transit - min_transit` are released that much
+        // sooner, but never before they arrived nor after the full latency.
+        // Releases also never overtake an earlier message.
+        let early = Duration::from_micros((transit - min_transit) as u64);
+        let mut release = now + self.latency.saturating_sub(early);
+        if let Some((previous, _, _)) = self.held.back() {
+            release = release.max(*previous);
+        }
+        self.held.push_back((release, stamp_us, bytes));
+    }
+
+    /// Messages whose release time has come, oldest first
+    pub fn pop_due(&mut self, now: Instant) -> Vec<(u64, Vec<u8>)> {
+        let mut due = Vec::new();
+        while self
+            .held
+            .front()
+            .is_some_and(|(release, _, _)| *release <= now)
+        {
+            if let Some((_, stamp, bytes)) = self.held.pop_front() {
+                due.push((stamp, bytes));
+            }
+        }
+        due
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MS: Duration = Duration::from_millis(1);
+
+    #[test]
+    fn bursts_are_spread_back_out() {
+        let t0 = Instant::now();
+        let mut buffer = JitterBuffer::new(10 * MS, t0);
+        // Sent 5 ms apart; the first arrives promptly, the other two in a
+        // burst 8 ms late
+        buffer.push(1_000, vec![1], t0);
+        buffer.push(6_000, vec![2], t0 + 13 * MS);
+        buffer.push(11_000, vec![3], t0 + 13 * MS);
+
+        assert!(buffer.pop_due(t0 + 9 * MS).is_empty());
+        assert_eq!(buffer.pop_due(t0 + 10 * MS), vec![(1_000, vec![1])]);
+        assert_eq!(buffer.pop_due(t0 + 15 * MS), vec![(6_000, vec![2])]);
+        assert!(buffer.pop_due(t0 + 19 * MS).is_empty());
+        assert_eq!(buffer.pop_due(t0 + 20 * MS), vec![(11_000, vec![3])]);
+    }
+
+    #[test]
+    fn very_late_messages_are_released_on_arrival() {
+        let t0 = Instant::now();
+        let mut buffer = JitterBuffer::new(10 * MS, t0);
+        buffer.push(0, vec![1], t0);
+        buffer.push(1_000, vec![2], t0 + 30 * MS);
+
+        assert_eq!(buffer.pop_due(t0 + 30 * MS).len(), 2);
+    }
+}
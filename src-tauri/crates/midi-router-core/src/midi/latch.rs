@@ -0,0 +1,72 @@
+//! Note latch
+//!
+//! On a latching route each Note On toggles its note: the first press holds
+//! it, the next releases it. The source's Note Offs are dropped, so drones
+//! can be held from a controller without a sustain pedal.
+
+use std::collections::BTreeSet;
+
+/// Latch a message against the route's held notes, as (channel, note).
+/// Anything other than notes passes through unchanged.
+pub fn latch(bytes: &[u8], held: &mut BTreeSet<(u8, u8)>) -> Vec<Vec<u8>> {
+    let (status, note, velocity) = match bytes {
+        [status, note, velocity] if status & 0xE0 == 0x80 => (*status, *note, *velocity),
+        _ => return vec![bytes.to_vec()],
+    };
+    let channel = status & 0x0F;
+    // Note Offs, including Note On with velocity 0
+    if status & 0xF0 == 0x80 || velocity == 0 {
+        return Vec::new();
+    }
+    if held.remove(&(channel, note)) {
+        vec![vec![0x80 | channel, note, 0]]
+    } else {
+        held.insert((channel, note));
+        vec![bytes.to_vec()]
+    }
+}
+
+/// Note Offs for every latched note, leaving none held
+pub fn release_all(held: &mut BTreeSet<(u8, u8)>) -> Vec<Vec<u8>> {
+    std::mem::take(held)
+        .into_iter()
+        .map(|(channel, note)| vec![0x80 | channel, note, 0])
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn note_ons_toggle_and_note_offs_are_dropped() {
+        let mut held = BTreeSet::new();
+        assert_eq!(
+            latch(&[0x90, 60, 100], &mut held),
+            vec![vec![0x90, 60, 100]]
+        );
+        assert!(latch(&[0x80, 60, 64], &mut held).is_empty());
+        assert!(latch(&[0x90, 60, 0], &mut held).is_empty());
+        assert_eq!(latch(&[0x91, 60, 90], &mut held), vec![vec![0x91, 60, 90]]);
+
+        // Pressing the first note again releases it
+        assert_eq!(latch(&[0x90, 60, 80], &mut held), vec![vec![0x80, 60, 0]]);
+        assert_eq!(held, BTreeSet::from([(1, 60)]));
+
+        assert_eq!(
+            latch(&[0xB0, 64, 127], &mut held),
+            vec![vec![0xB0, 64, 127]]
+        );
+    }
+
+    #[test]
+    fn release_all_clears_held_notes() {
+        let mut held = BTreeSet::from([(0, 48), (0, 55), (2, 60)]);
+        assert_eq!(
+            release_all(&mut held),
+            vec![vec![0x80, 48, 0], vec![0x80, 55, 0], vec![0x82, 60, 0]]
+        );
+        assert!(held.is_empty());
+        assert!(release_all(&mut held).is_empty());
+    }
+}
@@ -0,0 +1,79 @@
+//! Auto-route learn mode
+//!
+//! While learning, the engine listens on every input. The first message from
+//! an input that no route or control binding uses becomes a suggestion, so
+//! the UI can offer to create a route from that device.
+
+use crate::midi::control::control_input_ports;
+use crate::midi::router::parse_midi_message;
+use crate::types::{ControlBindings, Route, RouteSuggestion};
+use std::collections::HashSet;
+
+#[derive(Debug, Default)]
+pub struct RouteLearner {
+    /// Ports already suggested this session
+    suggested: HashSet<String>,
+}
+
+impl RouteLearner {
+    /// Suggest a route for `port` if it's unused and hasn't been suggested yet.
+    /// Clock and Active Sensing don't count as activity, since devices send
+    /// them without being played.
+    pub fn observe(
+        &mut self,
+        port: &str,
+        bytes: &[u8],
+        routes: &[Route],
+        bindings: &ControlBindings,
+    ) -> Option<RouteSuggestion> {
+        if matches!(bytes.first(), None | Some(0xF8) | Some(0xFE)) {
+            return None;
+        }
+        if self.suggested.contains(port)
+            || routes.iter().any(|r| r.enabled && r.source.name == port)
+            || control_input_ports(bindings).contains(port)
+        {
+            return None;
+        }
+
+        self.suggested.insert(port.to_string());
+        Some(RouteSuggestion {
+            port: port.to_string(),
+            sample: bytes.to_vec(),
+            kind: parse_midi_message(0, port, bytes).map(|activity| activity.kind),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::PortId;
+
+    #[test]
+    fn suggests_unrouted_ports_once() {
+        let mut learner = RouteLearner::default();
+        let routes = vec![Route::new(
+            PortId::new("Keys".to_string()),
+            PortId::new("Synth".to_string()),
+        )];
+        let bindings = ControlBindings::default();
+
+        assert!(learner
+            .observe("Keys", &[0x90, 60, 100], &routes, &bindings)
+            .is_none());
+        assert!(learner
+            .observe("Pads", &[0xF8], &routes, &bindings)
+            .is_none());
+
+        let suggestion = learner
+            .observe("Pads", &[0x99, 36, 127], &routes, &bindings)
+            .unwrap();
+        assert_eq!(suggestion.port, "Pads");
+        assert_eq!(suggestion.sample, vec![0x99, 36, 127]);
+
+        assert!(learner
+            .observe("Pads", &[0x89, 36, 0], &routes, &bindings)
+            .is_none());
+    }
+}
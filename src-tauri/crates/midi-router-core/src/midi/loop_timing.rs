@@ -0,0 +1,130 @@
+//! Engine loop timing
+//!
+//! Measures how long each engine loop iteration spends working (not counting
+//! the wait for commands), for worst-case and p99 loop latency and an
+//! estimate of the engine thread's CPU usage, and notices when iterations
+//! keep running longer than the clock interval.
+
+use crate::types::LoopStats;
+use std::collections::VecDeque;
+use std::time::{Duration, Instant};
+
+pub struct LoopTimer {
+    /// Busy time of recent iterations, in microseconds
+    samples: VecDeque<u64>,
+    window_start: Instant,
+    /// Busy time in the current CPU window
+    busy: Duration,
+    cpu_percent: f64,
+    /// Consecutive iterations longer than the clock interval
+    overruns: u32,
+}
+
+impl LoopTimer {
+    const MAX_SAMPLES: usize = 1000;
+    const CPU_WINDOW: Duration = Duration::from_secs(1);
+    /// Overrunning iterations in a row before warning
+    const OVERRUN_WARNING: u32 = 3;
+
+    pub fn new(now: Instant) -> Self {
+        Self {
+            samples: VecDeque::with_capacity(Self::MAX_SAMPLES),
+            window_start: now,
+            busy: Duration::ZERO,
+            cpu_percent: 0.0,
+            overruns: 0,
+        }
+    }
+
+    /// Record one iteration's busy time. Returns true once per overrun
+    /// streak, when iterations have exceeded the clock interval several
+    /// times in a row.
+    pub fn record(&mut self, busy: Duration, clock_interval: Duration, now: Instant) -> bool {
+        if self.samples.len() == Self::MAX_SAMPLES {
+            self.samples.pop_front();
+        }
+        self.samples.push_back(busy.as_micros() as u64);
+
+        self.busy += busy;
+        let elapsed = now.duration_since(self.window_start);
+        if elapsed >= Self::CPU_WINDOW {
+            self.cpu_percent = self.busy.as_secs_f64() / elapsed.as_secs_f64() * 100.0;
+            self.busy = Duration::ZERO;
+            self.window_start = now;
+        }
+
+        if busy > clock_interval {
+            self.overruns += 1;
+            self.overruns == Self::OVERRUN_WARNING
+        } else {
+            self.overruns = 0;
+            false
+        }
+    }
+
+    /// Latency over the recent iterations and CPU over the last full window
+    pub fn stats(&self) -> LoopStats {
+        let mut sorted: Vec<u64> = self.samples.iter().copied().collect();
+        sorted.sort_unstable();
+        let p99_us = match sorted.len() {
+            0 => 0,
+            n => sorted[(n * 99).div_ceil(100) - 1],
+        };
+        LoopStats {
+            worst_us: sorted.last().copied().unwrap_or(0),
+            p99_us,
+            cpu_percent: self.cpu_percent,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const US: Duration = Duration::from_micros(1);
+    const INTERVAL: Duration = Duration::from_millis(20);
+
+    #[test]
+    fn stats_report_worst_and_p99() {
+        let t0 = Instant::now();
+        let mut timer = LoopTimer::new(t0);
+        for i in 1..=100 {
+            timer.record(i * 10 * US, INTERVAL, t0);
+        }
+
+        let stats = timer.stats();
+        assert_eq!(stats.worst_us, 1000);
+        assert_eq!(stats.p99_us, 990);
+    }
+
+    #[test]
+    fn cpu_is_busy_share_of_window() {
+        let t0 = Instant::now();
+        let mut timer = LoopTimer::new(t0);
+        timer.record(Duration::from_millis(100), Duration::from_secs(1), t0);
+        timer.record(
+            Duration::from_millis(150),
+            Duration::from_secs(1),
+            t0 + Duration::from_secs(1),
+        );
+
+        assert!((timer.stats().cpu_percent - 25.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn warns_once_per_overrun_streak() {
+        let t0 = Instant::now();
+        let mut timer = LoopTimer::new(t0);
+        let slow = INTERVAL + US;
+        let warnings: Vec<bool> = [slow, slow, slow, slow, US, slow, slow, slow]
+            .into_iter()
+            .map(|busy| timer.record(busy, INTERVAL, t0))
+            .collect();
+
+        assert_eq!(
+            warnings,
+            vec![false, false, true, false, false, false, false, true]
+        );
+    }
+}
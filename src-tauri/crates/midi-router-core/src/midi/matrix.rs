@@ -0,0 +1,184 @@
+//! Source x destination matrix view of routes
+//!
+//! Lets a grid UI toggle connections without managing individual routes.
+
+use crate::types::{MatrixCell, MidiPort, PortId, Route, RoutingMatrix};
+
+/// Build the matrix from routes and the available ports.
+/// Ports referenced by routes but not currently present are still listed.
+pub fn build_matrix(routes: &[Route], inputs: &[MidiPort], outputs: &[MidiPort]) -> RoutingMatrix {
+    let mut sources: Vec<String> = inputs.iter().map(|p| p.id.name.clone()).collect();
+    let mut destinations: Vec<String> = outputs.iter().map(|p| p.id.name.clone()).collect();
+
+    for route in routes {
+        if !sources.contains(&route.source.name) {
+            sources.push(route.source.name.clone());
+        }
+        if !destinations.contains(&route.destination.name) {
+            destinations.push(route.destination.name.clone());
+        }
+    }
+
+    let cells = routes
+        .iter()
+        .map(|r| MatrixCell {
+            source: r.source.name.clone(),
+            destination: r.destination.name.clone(),
+            route_id: r.id,
+            enabled: r.enabled,
+        })
+        .collect();
+
+    RoutingMatrix {
+        sources,
+        destinations,
+        cells,
+    }
+}
+
+/// Connect or disconnect a source/destination pair.
+/// Enabling reuses (and enables) an existing route for the pair, or creates one.
+/// Disabling removes every route for the pair.
+/// Returns true if the routes changed.
+pub fn set_matrix_cell(
+    routes: &mut Vec<Route>,
+    source: &str,
+    destination: &str,
+    enabled: bool,
+) -> bool {
+    if enabled {
+        if let Some(route) = routes.iter_mut().find(|r| is_pair(r, source, destination)) {
+            if route.enabled {
+                return false;
+            }
+            route.enabled = true;
+            return true;
+        }
+        routes.push(Route::new(
+            PortId::new(source.to_string()),
+            PortId::new(destination.to_string()),
+        ));
+        true
+    } else {
+        let before = routes.len();
+        routes.retain(|r| !is_pair(r, source, destination));
+        routes.len() != before
+    }
+}
+
+/// Connect every source/destination pair, as a rig's starting point.
+/// Pairs that are already connected are left alone.
+/// Returns true if the routes changed.
+pub fn quick_connect(routes: &mut Vec<Route>, sources: &[String], destinations: &[String]) -> bool {
+    let mut changed = false;
+    for source in sources {
+        for destination in destinations {
+            changed |= set_matrix_cell(routes, source, destination, true);
+        }
+    }
+    changed
+}
+
+fn is_pair(route: &Route, source: &str, destination: &str) -> bool {
+    route.source.name == source && route.destination.name == destination
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn port(name: &str, is_input: bool) -> MidiPort {
+        MidiPort {
+            id: PortId::new(name.to_string()),
+            is_input,
+        }
+    }
+
+    #[test]
+    fn build_matrix_lists_ports_and_cells() {
+        let routes = vec![Route::new(
+            PortId::new("Keys".to_string()),
+            PortId::new("Synth".to_string()),
+        )];
+        let matrix = build_matrix(
+            &routes,
+            &[port("Keys", true), port("Pads", true)],
+            &[port("Synth", false)],
+        );
+        assert_eq!(matrix.sources, vec!["Keys", "Pads"]);
+        assert_eq!(matrix.destinations, vec!["Synth"]);
+        assert_eq!(matrix.cells.len(), 1);
+        assert_eq!(matrix.cells[0].route_id, routes[0].id);
+    }
+
+    #[test]
+    fn build_matrix_includes_missing_route_ports() {
+        let routes = vec![Route::new(
+            PortId::new("Unplugged".to_string()),
+            PortId::new("Synth".to_string()),
+        )];
+        let matrix = build_matrix(&routes, &[], &[]);
+        assert_eq!(matrix.sources, vec!["Unplugged"]);
+        assert_eq!(matrix.destinations, vec!["Synth"]);
+    }
+
+    #[test]
+    fn set_cell_creates_route() {
+        let mut routes = Vec::new();
+        assert!(set_matrix_cell(&mut routes, "Keys", "Synth", true));
+        assert_eq!(routes.len(), 1);
+        assert_eq!(routes[0].source.name, "Keys");
+        assert_eq!(routes[0].destination.name, "Synth");
+
+        // Already connected - no change
+        assert!(!set_matrix_cell(&mut routes, "Keys", "Synth", true));
+        assert_eq!(routes.len(), 1);
+    }
+
+    #[test]
+    fn set_cell_enables_disabled_route() {
+        let mut route = Route::new(
+            PortId::new("Keys".to_string()),
+            PortId::new("Synth".to_string()),
+        );
+        route.enabled = false;
+        let id = route.id;
+        let mut routes = vec![route];
+
+        assert!(set_matrix_cell(&mut routes, "Keys", "Synth", true));
+        assert_eq!(routes.len(), 1);
+        assert_eq!(routes[0].id, id);
+        assert!(routes[0].enabled);
+    }
+
+    #[test]
+    fn set_cell_removes_all_routes_for_pair() {
+        let mut routes = Vec::new();
+        set_matrix_cell(&mut routes, "Keys", "Synth", true);
+        routes.push(Route::new(
+            PortId::new("Keys".to_string()),
+            PortId::new("Synth".to_string()),
+        ));
+        set_matrix_cell(&mut routes, "Keys", "Drums", true);
+
+        assert!(set_matrix_cell(&mut routes, "Keys", "Synth", false));
+        assert_eq!(routes.len(), 1);
+        assert_eq!(routes[0].destination.name, "Drums");
+
+        assert!(!set_matrix_cell(&mut routes, "Keys", "Synth", false));
+    }
+
+    #[test]
+    fn quick_connect_fills_missing_pairs() {
+        let mut routes = Vec::new();
+        set_matrix_cell(&mut routes, "Pads", "DAW", true);
+        let inputs = vec!["Keys".to_string(), "Pads".to_string()];
+        let output = vec!["DAW".to_string()];
+
+        assert!(quick_connect(&mut routes, &inputs, &output));
+        assert_eq!(routes.len(), 2);
+        assert_eq!(routes[1].source.name, "Keys");
+
+        assert!(!quick_connect(&mut routes, &inputs, &output));
+    }
+}
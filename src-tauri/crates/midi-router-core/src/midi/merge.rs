@@ -0,0 +1,97 @@
+//! Note merging on shared destinations
+//!
+//! When several routes play into one output, one route's Note Off would
+//! cut a note another route is still holding. Each note on each output
+//! keeps the set of routes holding it, and a Note Off only goes out once
+//! the last of them lets go.
+
+use std::collections::{HashMap, HashSet};
+use uuid::Uuid;
+
+#[derive(Debug, Default)]
+pub struct NoteMerge {
+    /// (destination, channel, note) -> routes holding it
+    holders: HashMap<(String, u8, u8), HashSet<Uuid>>,
+}
+
+impl NoteMerge {
+    /// Whether a route's message should go out to `destination`. Anything
+    /// other than notes always does.
+    pub fn admit(&mut self, destination: &str, route_id: Uuid, bytes: &[u8]) -> bool {
+        let &[status, note, velocity] = bytes else {
+            return true;
+        };
+        if !matches!(status & 0xF0, 0x80 | 0x90) {
+            return true;
+        }
+        let key = (destination.to_string(), status & 0x0F, note);
+        if status & 0xF0 == 0x90 && velocity > 0 {
+            self.holders.entry(key).or_default().insert(route_id);
+            return true;
+        }
+        let Some(holders) = self.holders.get_mut(&key) else {
+            return true;
+        };
+        holders.remove(&route_id);
+        if holders.is_empty() {
+            self.holders.remove(&key);
+            return true;
+        }
+        false
+    }
+
+    /// Let go of every note a route holds, e.g. after a panic on it
+    pub fn forget_route(&mut self, route_id: Uuid) {
+        self.retain_routes(|id| id != route_id);
+    }
+
+    /// Drop holds of routes that no longer exist
+    pub fn retain_routes(&mut self, keep: impl Fn(Uuid) -> bool) {
+        self.holders.retain(|_, holders| {
+            holders.retain(|id| keep(*id));
+            !holders.is_empty()
+        });
+    }
+
+    pub fn clear(&mut self) {
+        self.holders.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn note_off_waits_for_the_last_holder() {
+        let (keys, pads) = (Uuid::new_v4(), Uuid::new_v4());
+        let mut merge = NoteMerge::default();
+        assert!(merge.admit("Synth", keys, &[0x90, 60, 100]));
+        assert!(merge.admit("Synth", pads, &[0x90, 60, 90]));
+        assert!(!merge.admit("Synth", keys, &[0x80, 60, 0]));
+        assert!(merge.admit("Synth", pads, &[0x90, 60, 0]));
+        // Nobody holds it any more
+        assert!(merge.admit("Synth", keys, &[0x80, 60, 0]));
+    }
+
+    #[test]
+    fn destinations_and_channels_are_separate() {
+        let (keys, pads) = (Uuid::new_v4(), Uuid::new_v4());
+        let mut merge = NoteMerge::default();
+        merge.admit("Synth", keys, &[0x90, 60, 100]);
+        merge.admit("Drums", pads, &[0x90, 60, 100]);
+        merge.admit("Synth", pads, &[0x91, 60, 100]);
+        assert!(merge.admit("Synth", keys, &[0x80, 60, 0]));
+        assert!(merge.admit("Synth", keys, &[0xB0, 64, 0]));
+    }
+
+    #[test]
+    fn forgotten_routes_stop_holding() {
+        let (keys, pads) = (Uuid::new_v4(), Uuid::new_v4());
+        let mut merge = NoteMerge::default();
+        merge.admit("Synth", keys, &[0x90, 60, 100]);
+        merge.admit("Synth", pads, &[0x90, 60, 100]);
+        merge.forget_route(pads);
+        assert!(merge.admit("Synth", keys, &[0x80, 60, 0]));
+    }
+}
@@ -0,0 +1,56 @@
+pub mod activity;
+pub mod bank_select;
+pub mod bus;
+pub mod calibration;
+pub mod capture;
+pub mod cc_ramp;
+pub mod chords;
+pub mod clock;
+pub mod control;
+pub mod dispatch;
+pub mod duplicates;
+pub mod echo;
+pub mod engine;
+pub mod error_log;
+pub mod fast_path;
+pub mod gamepad;
+pub mod harmony;
+pub mod humanize;
+pub mod jitter;
+pub mod latch;
+pub mod learn;
+pub mod loop_timing;
+pub mod matrix;
+pub mod merge;
+pub mod monitor;
+pub mod mono;
+pub mod mqtt;
+pub mod msc;
+pub mod note_off;
+pub mod notes;
+pub mod port_manager;
+pub mod port_watch;
+pub mod ports;
+pub mod preset_queue;
+pub mod program_change;
+pub mod program_step;
+pub mod reconnect;
+pub mod replay;
+pub mod retrospective;
+pub mod rotation;
+pub mod route_state;
+pub mod router;
+pub mod scheduler;
+pub mod sequence;
+pub mod session_stats;
+pub mod stats;
+pub mod sustain;
+pub mod tap_tempo;
+pub mod test_signal;
+pub mod timestamps;
+pub mod transport;
+pub mod tuning;
+pub mod validation;
+pub mod voices;
+pub mod wake;
+pub mod web_bridge;
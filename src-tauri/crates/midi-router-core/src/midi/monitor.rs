@@ -0,0 +1,130 @@
+//! Monitor history
+//!
+//! Keeps a ring of recent monitor events. While the monitor is paused,
+//! events are only buffered; resuming hands back everything that was missed.
+
+use crate::types::MidiActivity;
+use std::collections::VecDeque;
+
+/// Ring buffer of recent activity with pause support
+#[derive(Debug)]
+pub struct MonitorHistory {
+    events: VecDeque<MidiActivity>,
+    capacity: usize,
+    paused: bool,
+    /// Events buffered since the monitor was paused
+    missed: usize,
+}
+
+impl MonitorHistory {
+    pub const DEFAULT_CAPACITY: usize = 2000;
+
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            events: VecDeque::with_capacity(capacity),
+            capacity,
+            paused: false,
+            missed: 0,
+        }
+    }
+
+    /// Record an event. Returns true if it should be streamed now
+    /// (false while paused).
+    pub fn record(&mut self, activity: MidiActivity) -> bool {
+        if self.capacity == 0 {
+            return !self.paused;
+        }
+        if self.events.len() == self.capacity {
+            self.events.pop_front();
+        }
+        self.events.push_back(activity);
+        if self.paused {
+            self.missed = (self.missed + 1).min(self.capacity);
+        }
+        !self.paused
+    }
+
+    pub fn pause(&mut self) {
+        if !self.paused {
+            self.paused = true;
+            self.missed = 0;
+        }
+    }
+
+    /// Resume streaming. Returns the events buffered while paused, oldest first.
+    /// If more arrived than the ring holds, only the most recent are returned.
+    pub fn resume(&mut self) -> Vec<MidiActivity> {
+        if !self.paused {
+            return Vec::new();
+        }
+        self.paused = false;
+        let skip = self.events.len() - self.missed;
+        self.missed = 0;
+        self.events.iter().skip(skip).cloned().collect()
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.paused
+    }
+}
+
+impl Default for MonitorHistory {
+    fn default() -> Self {
+        Self::new(Self::DEFAULT_CAPACITY)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::MessageKind;
+
+    fn activity(timestamp: u64) -> MidiActivity {
+        MidiActivity {
+            timestamp,
+            delta_us: None,
+            port: "Keys".to_string(),
+            channel: None,
+            kind: MessageKind::Clock,
+            raw: vec![0xF8],
+            label: None,
+            note_name: None,
+        }
+    }
+
+    fn timestamps(events: &[MidiActivity]) -> Vec<u64> {
+        events.iter().map(|a| a.timestamp).collect()
+    }
+
+    #[test]
+    fn streams_while_running() {
+        let mut history = MonitorHistory::new(10);
+        assert!(history.record(activity(1)));
+        assert!(history.resume().is_empty());
+    }
+
+    #[test]
+    fn resume_returns_missed_events() {
+        let mut history = MonitorHistory::new(10);
+        history.record(activity(1));
+        history.pause();
+        assert!(history.is_paused());
+        assert!(!history.record(activity(2)));
+        assert!(!history.record(activity(3)));
+
+        assert_eq!(timestamps(&history.resume()), vec![2, 3]);
+        assert!(!history.is_paused());
+        assert!(history.record(activity(4)));
+    }
+
+    #[test]
+    fn overflow_keeps_most_recent() {
+        let mut history = MonitorHistory::new(3);
+        history.record(activity(1));
+        history.pause();
+        for t in 2..=6 {
+            history.record(activity(t));
+        }
+        assert_eq!(timestamps(&history.resume()), vec![4, 5, 6]);
+    }
+}
@@ -0,0 +1,142 @@
+//! Mono/legato converter
+//!
+//! Collapses polyphonic playing to one note per channel for mono synths.
+//! The keys held down are remembered, so releasing the sounding note falls
+//! back to the one priority picks next. Note changes send the new Note On
+//! before the old Note Off, which mono synths read as legato.
+
+use crate::types::NotePriority;
+use std::collections::HashMap;
+
+/// One channel's mono voice
+#[derive(Debug, Default)]
+pub struct MonoVoice {
+    /// Keys held down, as (note, velocity), in the order they were pressed
+    held: Vec<(u8, u8)>,
+    /// Note sounding on the destination
+    sounding: Option<u8>,
+}
+
+impl MonoVoice {
+    /// The held key priority wants sounding
+    fn target(&self, priority: NotePriority) -> Option<(u8, u8)> {
+        match priority {
+            NotePriority::Last => self.held.last().copied(),
+            NotePriority::Low => self.held.iter().min_by_key(|(note, _)| *note).copied(),
+            NotePriority::High => self.held.iter().max_by_key(|(note, _)| *note).copied(),
+        }
+    }
+
+    /// Sound the target if it changed: its Note On, then the old Note Off
+    fn follow(&mut self, channel: u8, priority: NotePriority) -> Vec<Vec<u8>> {
+        let target = self.target(priority);
+        if target.map(|(note, _)| note) == self.sounding {
+            return Vec::new();
+        }
+        let mut out = Vec::new();
+        if let Some((note, velocity)) = target {
+            out.push(vec![0x90 | channel, note, velocity]);
+        }
+        if let Some(old) = self.sounding {
+            out.push(vec![0x80 | channel, old, 0]);
+        }
+        self.sounding = target.map(|(note, _)| note);
+        out
+    }
+}
+
+/// Pass a message through the route's mono voices, keyed by channel.
+/// Anything other than notes passes through unchanged.
+pub fn mono(
+    bytes: &[u8],
+    priority: NotePriority,
+    voices: &mut HashMap<u8, MonoVoice>,
+) -> Vec<Vec<u8>> {
+    let (status, note, velocity) = match bytes {
+        [status, note, velocity] if status & 0xE0 == 0x80 => (*status, *note, *velocity),
+        _ => return vec![bytes.to_vec()],
+    };
+    let channel = status & 0x0F;
+    let voice = voices.entry(channel).or_default();
+    voice.held.retain(|(held, _)| *held != note);
+    if status & 0xF0 == 0x90 && velocity > 0 {
+        voice.held.push((note, velocity));
+    }
+    voice.follow(channel, priority)
+}
+
+/// Note Offs for every sounding mono voice, forgetting the held keys
+pub fn release_all(voices: &mut HashMap<u8, MonoVoice>) -> Vec<Vec<u8>> {
+    let mut out: Vec<Vec<u8>> = voices
+        .drain()
+        .filter_map(|(channel, voice)| Some(vec![0x80 | channel, voice.sounding?, 0]))
+        .collect();
+    out.sort();
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn last_note_priority_plays_legato() {
+        let mut voices = HashMap::new();
+        let mut play = |bytes: &[u8]| mono(bytes, NotePriority::Last, &mut voices);
+
+        assert_eq!(play(&[0x90, 60, 100]), vec![vec![0x90, 60, 100]]);
+        // New note on before the old note off
+        assert_eq!(
+            play(&[0x90, 64, 90]),
+            vec![vec![0x90, 64, 90], vec![0x80, 60, 0]]
+        );
+        // Releasing a key that isn't sounding changes nothing
+        assert!(play(&[0x80, 60, 0]).is_empty());
+        assert_eq!(
+            play(&[0x90, 67, 80]),
+            vec![vec![0x90, 67, 80], vec![0x80, 64, 0]]
+        );
+        // Releasing the sounding key falls back to the one still held
+        assert_eq!(
+            play(&[0x90, 67, 0]),
+            vec![vec![0x90, 64, 90], vec![0x80, 67, 0]]
+        );
+        assert_eq!(play(&[0x80, 64, 0]), vec![vec![0x80, 64, 0]]);
+        assert_eq!(play(&[0xB0, 1, 64]), vec![vec![0xB0, 1, 64]]);
+    }
+
+    #[test]
+    fn low_and_high_priority_keep_their_note() {
+        let mut voices = HashMap::new();
+        assert_eq!(
+            mono(&[0x90, 60, 100], NotePriority::Low, &mut voices),
+            vec![vec![0x90, 60, 100]]
+        );
+        assert!(mono(&[0x90, 64, 100], NotePriority::Low, &mut voices).is_empty());
+        assert_eq!(
+            mono(&[0x90, 55, 100], NotePriority::Low, &mut voices),
+            vec![vec![0x90, 55, 100], vec![0x80, 60, 0]]
+        );
+
+        let mut voices = HashMap::new();
+        mono(&[0x91, 60, 100], NotePriority::High, &mut voices);
+        assert!(mono(&[0x91, 55, 100], NotePriority::High, &mut voices).is_empty());
+        assert_eq!(
+            mono(&[0x81, 60, 0], NotePriority::High, &mut voices),
+            vec![vec![0x91, 55, 100], vec![0x81, 60, 0]]
+        );
+    }
+
+    #[test]
+    fn release_all_silences_each_channel() {
+        let mut voices = HashMap::new();
+        mono(&[0x90, 60, 100], NotePriority::Last, &mut voices);
+        mono(&[0x92, 48, 100], NotePriority::Last, &mut voices);
+
+        assert_eq!(
+            release_all(&mut voices),
+            vec![vec![0x80, 60, 0], vec![0x82, 48, 0]]
+        );
+        assert!(voices.is_empty());
+    }
+}
@@ -0,0 +1,306 @@
+//! MQTT integration
+//!
+//! Publishes notes, CCs and transport from chosen inputs to an MQTT broker,
+//! and sends what's published to command topics out of chosen outputs, so
+//! home-automation and installation systems can react to and generate MIDI.
+//!
+//! Topics, with port names made topic-safe:
+//! - `{prefix}/in/{input}/note` `{"channel":1,"note":60,"velocity":100}`,
+//!   velocity 0 for Note Off
+//! - `{prefix}/in/{input}/cc` `{"channel":1,"controller":7,"value":100}`
+//! - `{prefix}/in/{input}/transport` `"start"`, `"continue"` or `"stop"`
+//! - `{prefix}/out/{output}/note` and `.../cc` take the same payloads, and
+//!   `{prefix}/out/{output}` takes one raw message as `[144,60,100]`
+
+use crate::types::MqttSettings;
+use crossbeam_channel::Sender;
+use rumqttc::{Client, Event, MqttOptions, Packet, QoS};
+use serde::{Deserialize, Serialize};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+struct NoteEvent {
+    /// 1-16
+    channel: u8,
+    note: u8,
+    velocity: u8,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+struct CcEvent {
+    /// 1-16
+    channel: u8,
+    controller: u8,
+    value: u8,
+}
+
+pub struct MqttBridge {
+    settings: MqttSettings,
+    client: Client,
+    stop: Arc<AtomicBool>,
+}
+
+impl MqttBridge {
+    /// Publishes are dropped rather than block the engine once this many
+    /// are waiting for the connection
+    const QUEUE_CAPACITY: usize = 256;
+    /// Wait between attempts while the broker can't be reached
+    const RETRY_INTERVAL: Duration = Duration::from_secs(2);
+
+    /// Start connecting to the broker. Messages published to an exposed
+    /// output's topics are handed to `to_engine` as (output name, bytes).
+    pub fn start(settings: MqttSettings, to_engine: Sender<(String, Vec<u8>)>) -> Self {
+        let client_id = format!("rust-midi-router-{}", std::process::id());
+        let mut options = MqttOptions::new(client_id, settings.host.clone(), settings.port);
+        options.set_keep_alive(Duration::from_secs(30));
+        if let Some(username) = &settings.username {
+            options.set_credentials(username, settings.password.clone().unwrap_or_default());
+        }
+        let (client, mut connection) = Client::new(options, Self::QUEUE_CAPACITY);
+        eprintln!("[MQTT] Connecting to {}:{}", settings.host, settings.port);
+
+        let stop = Arc::new(AtomicBool::new(false));
+        {
+            let (settings, client, stop) = (settings.clone(), client.clone(), stop.clone());
+            let commands = format!("{}/out/#", settings.topic_prefix);
+            // Not joined on stop: a connection attempt can take a while to
+            // give up, and nothing waits on this thread
+            thread::spawn(move || {
+                for event in connection.iter() {
+                    if stop.load(Ordering::Relaxed) {
+                        break;
+                    }
+                    match event {
+                        // Subscriptions don't outlive the session
+                        Ok(Event::Incoming(Packet::ConnAck(_))) => {
+                            eprintln!("[MQTT] Connected");
+                            if let Err(e) = client.try_subscribe(commands.as_str(), QoS::AtMostOnce)
+                            {
+                                eprintln!("[MQTT] Subscribe failed: {}", e);
+                            }
+                        }
+                        Ok(Event::Incoming(Packet::Publish(publish))) => {
+                            match decode_command(
+                                &settings.topic_prefix,
+                                &publish.topic,
+                                &publish.payload,
+                                &settings.outputs,
+                            ) {
+                                Some(command) => {
+                                    let _ = to_engine.send(command);
+                                }
+                                None => eprintln!("[MQTT] Ignoring message on {}", publish.topic),
+                            }
+                        }
+                        Ok(_) => {}
+                        Err(e) => {
+                            eprintln!("[MQTT] Connection error: {}", e);
+                            thread::sleep(Self::RETRY_INTERVAL);
+                        }
+                    }
+                }
+                eprintln!("[MQTT] Stopped");
+            });
+        }
+
+        Self {
+            settings,
+            client,
+            stop,
+        }
+    }
+
+    pub fn settings(&self) -> &MqttSettings {
+        &self.settings
+    }
+
+    /// Publish a message the router received, if its input is exposed and
+    /// its kind is selected
+    pub fn publish(&self, port: &str, bytes: &[u8]) {
+        if !self.settings.inputs.iter().any(|p| p == port) {
+            return;
+        }
+        let Some((topic, payload)) = event_message(&self.settings, port, bytes) else {
+            return;
+        };
+        if let Err(e) = self
+            .client
+            .try_publish(topic, QoS::AtMostOnce, false, payload)
+        {
+            eprintln!("[MQTT] Publish dropped: {}", e);
+        }
+    }
+}
+
+impl Drop for MqttBridge {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+        let _ = self.client.try_disconnect();
+    }
+}
+
+/// A port name as one topic level, with the separator and wildcards replaced
+fn topic_segment(port: &str) -> String {
+    port.replace(['/', '+', '#'], "_")
+}
+
+/// The topic and JSON payload a received message is published as, if its
+/// kind is selected
+fn event_message(settings: &MqttSettings, port: &str, bytes: &[u8]) -> Option<(String, String)> {
+    let (kind, payload) = match *bytes {
+        [status, note, velocity] if status & 0xE0 == 0x80 && settings.publish_notes => {
+            let velocity = if status & 0xF0 == 0x80 { 0 } else { velocity };
+            let event = NoteEvent {
+                channel: (status & 0x0F) + 1,
+                note,
+                velocity,
+            };
+            ("note", serde_json::to_string(&event).ok()?)
+        }
+        [status, controller, value] if status & 0xF0 == 0xB0 && settings.publish_ccs => {
+            let event = CcEvent {
+                channel: (status & 0x0F) + 1,
+                controller,
+                value,
+            };
+            ("cc", serde_json::to_string(&event).ok()?)
+        }
+        [0xFA] if settings.publish_transport => ("transport", "\"start\"".to_string()),
+        [0xFB] if settings.publish_transport => ("transport", "\"continue\"".to_string()),
+        [0xFC] if settings.publish_transport => ("transport", "\"stop\"".to_string()),
+        _ => return None,
+    };
+    let topic = format!(
+        "{}/in/{}/{}",
+        settings.topic_prefix,
+        topic_segment(port),
+        kind
+    );
+    Some((topic, payload))
+}
+
+/// The output and message a command topic addresses, if the output is one
+/// of `outputs` and the payload is well formed
+fn decode_command(
+    prefix: &str,
+    topic: &str,
+    payload: &[u8],
+    outputs: &[String],
+) -> Option<(String, Vec<u8>)> {
+    let rest = topic.strip_prefix(prefix)?.strip_prefix("/out/")?;
+    let (segment, kind) = match rest.split_once('/') {
+        Some((segment, kind)) => (segment, Some(kind)),
+        None => (rest, None),
+    };
+    let output = outputs.iter().find(|o| topic_segment(o) == segment)?;
+    let bytes = match kind {
+        Some("note") => {
+            let event: NoteEvent = serde_json::from_slice(payload).ok()?;
+            if !(1..=16).contains(&event.channel) || event.note > 127 || event.velocity > 127 {
+                return None;
+            }
+            vec![0x90 | (event.channel - 1), event.note, event.velocity]
+        }
+        Some("cc") => {
+            let event: CcEvent = serde_json::from_slice(payload).ok()?;
+            if !(1..=16).contains(&event.channel) || event.controller > 127 || event.value > 127 {
+                return None;
+            }
+            vec![0xB0 | (event.channel - 1), event.controller, event.value]
+        }
+        None => {
+            let bytes: Vec<u8> = serde_json::from_slice(payload).ok()?;
+            match bytes.first() {
+                Some(status) if *status >= 0x80 => bytes,
+                _ => return None,
+            }
+        }
+        Some(_) => return None,
+    };
+    Some((output.clone(), bytes))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn settings() -> MqttSettings {
+        MqttSettings {
+            topic_prefix: "studio".to_string(),
+            inputs: vec!["Keys/1".to_string()],
+            outputs: vec!["Lights #2".to_string()],
+            ..MqttSettings::default()
+        }
+    }
+
+    #[test]
+    fn publishes_selected_kinds() {
+        let mut settings = settings();
+        assert_eq!(
+            event_message(&settings, "Keys/1", &[0x91, 60, 100]),
+            Some((
+                "studio/in/Keys_1/note".to_string(),
+                r#"{"channel":2,"note":60,"velocity":100}"#.to_string()
+            ))
+        );
+        // Note Off is published as velocity 0
+        assert_eq!(
+            event_message(&settings, "Keys/1", &[0x81, 60, 64]).map(|m| m.1),
+            Some(r#"{"channel":2,"note":60,"velocity":0}"#.to_string())
+        );
+        assert_eq!(
+            event_message(&settings, "Keys/1", &[0xFC]),
+            Some((
+                "studio/in/Keys_1/transport".to_string(),
+                "\"stop\"".to_string()
+            ))
+        );
+        assert_eq!(event_message(&settings, "Keys/1", &[0xE0, 0, 64]), None);
+
+        settings.publish_notes = false;
+        assert_eq!(event_message(&settings, "Keys/1", &[0x90, 60, 100]), None);
+        assert!(event_message(&settings, "Keys/1", &[0xB0, 7, 100]).is_some());
+    }
+
+    #[test]
+    fn command_topics_address_exposed_outputs() {
+        let outputs = settings().outputs;
+        let decode = |topic: &str, payload: &str| {
+            decode_command("studio", topic, payload.as_bytes(), &outputs)
+        };
+
+        assert_eq!(
+            decode(
+                "studio/out/Lights _2/note",
+                r#"{"channel":10,"note":36,"velocity":127}"#
+            ),
+            Some(("Lights #2".to_string(), vec![0x99, 36, 127]))
+        );
+        assert_eq!(
+            decode(
+                "studio/out/Lights _2/cc",
+                r#"{"channel":1,"controller":1,"value":0}"#
+            ),
+            Some(("Lights #2".to_string(), vec![0xB0, 1, 0]))
+        );
+        assert_eq!(
+            decode("studio/out/Lights _2", "[192, 5]"),
+            Some(("Lights #2".to_string(), vec![0xC0, 5]))
+        );
+
+        // Unknown outputs, bad channels and bare data are ignored
+        assert_eq!(decode("studio/out/Synth", "[144, 60, 100]"), None);
+        assert_eq!(
+            decode(
+                "studio/out/Lights _2/note",
+                r#"{"channel":0,"note":36,"velocity":127}"#
+            ),
+            None
+        );
+        assert_eq!(decode("studio/out/Lights _2", "[60, 100]"), None);
+        assert_eq!(decode("other/out/Lights _2", "[192, 5]"), None);
+    }
+}
@@ -0,0 +1,139 @@
+//! MIDI Show Control (MSC) parsing
+//!
+//! MSC messages are universal real-time SysEx with sub-ID 0x02:
+//! `F0 7F <device_id> 02 <command_format> <command> <data...> F7`
+
+use crate::types::{MscCommand, MscFilter};
+
+/// Universal real-time SysEx ID
+const UNIVERSAL_REAL_TIME: u8 = 0x7F;
+/// MSC sub-ID #1
+const MSC_SUB_ID: u8 = 0x02;
+
+/// A decoded MIDI Show Control message
+#[derive(Debug, Clone, PartialEq)]
+pub struct MscMessage {
+    pub device_id: u8,
+    pub command_format: u8,
+    pub command: MscCommand,
+    pub cue_number: Option<String>,
+    pub cue_list: Option<String>,
+    pub cue_path: Option<String>,
+}
+
+/// Check if bytes are an MSC SysEx message. The shortest carries a command
+/// byte between the header and the closing F7.
+pub fn is_msc_message(bytes: &[u8]) -> bool {
+    bytes.len() >= 7
+        && bytes[0] == 0xF0
+        && bytes[1] == UNIVERSAL_REAL_TIME
+        && bytes[3] == MSC_SUB_ID
+        && bytes[bytes.len() - 1] == 0xF7
+}
+
+/// Parse an MSC SysEx message, returning None for anything else
+pub fn parse_msc(bytes: &[u8]) -> Option<MscMessage> {
+    if !is_msc_message(bytes) {
+        return None;
+    }
+
+    let command = MscCommand::from_byte(bytes[5]);
+    let mut data = &bytes[6..bytes.len() - 1];
+
+    // Timed Go carries 5 bytes of SMPTE time before the cue fields
+    if command == MscCommand::TimedGo {
+        data = data.get(5..).unwrap_or(&[]);
+    }
+
+    // Cue number, cue list and cue path are ASCII, separated by 0x00
+    let mut fields = data.split(|b| *b == 0x00).map(|field| {
+        if field.is_empty() {
+            None
+        } else {
+            Some(String::from_utf8_lossy(field).into_owned())
+        }
+    });
+
+    Some(MscMessage {
+        device_id: bytes[2],
+        command_format: bytes[4],
+        command,
+        cue_number: fields.next().flatten(),
+        cue_list: fields.next().flatten(),
+        cue_path: fields.next().flatten(),
+    })
+}
+
+/// Check whether a message passes a route's MSC filter.
+/// Non-MSC messages always pass.
+pub fn should_route_msc(bytes: &[u8], filter: &MscFilter) -> bool {
+    match parse_msc(bytes) {
+        Some(msg) => filter.passes(msg.command),
+        None => true,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // GO cue 1.5 in list 2, device 0x01, lighting (general) format 0x01
+    const GO_CUE: [u8; 12] = [0xF0, 0x7F, 0x01, 0x02, 0x01, 0x01, b'1', b'.', b'5', 0x00, b'2', 0xF7];
+
+    #[test]
+    fn parse_go_with_cue_and_list() {
+        let msg = parse_msc(&GO_CUE).unwrap();
+        assert_eq!(msg.device_id, 0x01);
+        assert_eq!(msg.command_format, 0x01);
+        assert_eq!(msg.command, MscCommand::Go);
+        assert_eq!(msg.cue_number.as_deref(), Some("1.5"));
+        assert_eq!(msg.cue_list.as_deref(), Some("2"));
+        assert_eq!(msg.cue_path, None);
+    }
+
+    #[test]
+    fn parse_all_off_without_data() {
+        let bytes = [0xF0, 0x7F, 0x7F, 0x02, 0x7F, 0x08, 0xF7];
+        let msg = parse_msc(&bytes).unwrap();
+        assert_eq!(msg.command, MscCommand::AllOff);
+        assert_eq!(msg.cue_number, None);
+    }
+
+    #[test]
+    fn parse_timed_go_skips_time_bytes() {
+        let bytes = [
+            0xF0, 0x7F, 0x01, 0x02, 0x01, 0x04, 0x01, 0x02, 0x03, 0x04, 0x00, b'7', 0xF7,
+        ];
+        let msg = parse_msc(&bytes).unwrap();
+        assert_eq!(msg.command, MscCommand::TimedGo);
+        assert_eq!(msg.cue_number.as_deref(), Some("7"));
+    }
+
+    #[test]
+    fn parse_non_msc_returns_none() {
+        // Universal non-real-time identity request
+        assert!(parse_msc(&[0xF0, 0x7E, 0x00, 0x06, 0x01, 0xF7]).is_none());
+        // MTC full frame (real-time, sub-ID 0x01)
+        assert!(parse_msc(&[0xF0, 0x7F, 0x7F, 0x01, 0x01, 0x00, 0x00, 0x00, 0x00, 0xF7]).is_none());
+        assert!(parse_msc(&[0x90, 60, 100]).is_none());
+    }
+
+    #[test]
+    fn parse_msc_without_command_returns_none() {
+        assert!(parse_msc(&[0xF0, 0x7F, 0x00, 0x02, 0x01, 0xF7]).is_none());
+    }
+
+    #[test]
+    fn should_route_msc_applies_filter() {
+        let filter = MscFilter::Except(vec![MscCommand::Go]);
+        assert!(!should_route_msc(&GO_CUE, &filter));
+        assert!(should_route_msc(&GO_CUE, &MscFilter::All));
+    }
+
+    #[test]
+    fn should_route_msc_passes_non_msc() {
+        let filter = MscFilter::Only(vec![]);
+        assert!(should_route_msc(&[0x90, 60, 100], &filter));
+        assert!(should_route_msc(&[0xF0, 0x7E, 0x00, 0x06, 0x01, 0xF7], &filter));
+    }
+}
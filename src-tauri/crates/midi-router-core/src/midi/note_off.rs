@@ -0,0 +1,93 @@
+//! Note Off normalization
+//!
+//! A Note Off can be sent as either 0x8n or a Note On with velocity 0.
+//! Outputs can be set to always receive one form, converted as they're sent.
+
+use crate::types::NoteOffStyle;
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::sync::{Arc, RwLock};
+
+/// Output port name -> Note Off style, shared with the input callbacks and
+/// the scheduler, which send on connections directly
+pub type SharedNoteOffStyles = Arc<RwLock<HashMap<String, NoteOffStyle>>>;
+
+/// Rewrite a Note Off in the given style. Other messages are unchanged.
+pub fn normalize_note_off(bytes: &[u8], style: NoteOffStyle) -> Cow<'_, [u8]> {
+    match (style, bytes) {
+        (NoteOffStyle::NoteOff, &[status, note, 0]) if status & 0xF0 == 0x90 => {
+            Cow::Owned(vec![0x80 | (status & 0x0F), note, 0])
+        }
+        (NoteOffStyle::VelocityZero, &[status, note, _]) if status & 0xF0 == 0x80 => {
+            Cow::Owned(vec![0x90 | (status & 0x0F), note, 0])
+        }
+        _ => Cow::Borrowed(bytes),
+    }
+}
+
+/// Rewrite a message for an output, if the output has a style set
+pub fn normalize_for<'a>(
+    styles: &SharedNoteOffStyles,
+    output: &str,
+    bytes: &'a [u8],
+) -> Cow<'a, [u8]> {
+    match styles.read().unwrap().get(output) {
+        Some(style) => normalize_note_off(bytes, *style),
+        None => Cow::Borrowed(bytes),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn converts_between_conventions() {
+        let note_off = NoteOffStyle::NoteOff;
+        let velocity_zero = NoteOffStyle::VelocityZero;
+
+        assert_eq!(
+            normalize_note_off(&[0x93, 60, 0], note_off)[..],
+            [0x83, 60, 0]
+        );
+        assert_eq!(
+            normalize_note_off(&[0x83, 60, 0], note_off)[..],
+            [0x83, 60, 0]
+        );
+        assert_eq!(
+            normalize_note_off(&[0x83, 60, 64], velocity_zero)[..],
+            [0x93, 60, 0]
+        );
+        assert_eq!(
+            normalize_note_off(&[0x93, 60, 0], velocity_zero)[..],
+            [0x93, 60, 0]
+        );
+        // Notes that start, and other messages, pass unchanged
+        assert_eq!(
+            normalize_note_off(&[0x90, 60, 100], note_off)[..],
+            [0x90, 60, 100]
+        );
+        assert_eq!(
+            normalize_note_off(&[0xB0, 7, 0], velocity_zero)[..],
+            [0xB0, 7, 0]
+        );
+    }
+
+    #[test]
+    fn only_styled_outputs_are_rewritten() {
+        let styles = SharedNoteOffStyles::default();
+        styles
+            .write()
+            .unwrap()
+            .insert("Vintage".to_string(), NoteOffStyle::VelocityZero);
+
+        assert_eq!(
+            normalize_for(&styles, "Vintage", &[0x80, 60, 0])[..],
+            [0x90, 60, 0]
+        );
+        assert_eq!(
+            normalize_for(&styles, "Modern", &[0x80, 60, 0])[..],
+            [0x80, 60, 0]
+        );
+    }
+}
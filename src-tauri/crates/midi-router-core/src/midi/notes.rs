@@ -0,0 +1,109 @@
+//! Sounding-note tracking
+//!
+//! Follows the NoteOn/NoteOff traffic a route sends so the notes still held
+//! on its destination can be released individually.
+
+use std::collections::{BTreeMap, BTreeSet};
+
+/// Notes currently held, per 0-indexed channel
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct SoundingNotes {
+    notes: BTreeMap<u8, BTreeSet<u8>>,
+}
+
+impl SoundingNotes {
+    /// Update from an outgoing message
+    pub fn track(&mut self, bytes: &[u8]) {
+        let [status, data1, data2] = match bytes {
+            [status, data1, data2, ..] => [*status, *data1, *data2],
+            _ => return,
+        };
+        let channel = status & 0x0F;
+        match status & 0xF0 {
+            0x90 if data2 > 0 => {
+                self.notes.entry(channel).or_default().insert(data1);
+            }
+            0x80 | 0x90 => self.release(channel, Some(data1)),
+            // All Sound Off / All Notes Off
+            0xB0 if data1 == 120 || data1 == 123 => self.release(channel, None),
+            _ => {}
+        }
+    }
+
+    fn release(&mut self, channel: u8, note: Option<u8>) {
+        let Some(held) = self.notes.get_mut(&channel) else {
+            return;
+        };
+        match note {
+            Some(note) => {
+                held.remove(&note);
+            }
+            None => held.clear(),
+        }
+        if held.is_empty() {
+            self.notes.remove(&channel);
+        }
+    }
+
+    /// Channels with held notes
+    pub fn channels(&self) -> impl Iterator<Item = u8> + '_ {
+        self.notes.keys().copied()
+    }
+
+    /// Held notes per channel, lowest channel first
+    pub fn by_channel(&self) -> impl Iterator<Item = (u8, Vec<u8>)> + '_ {
+        self.notes
+            .iter()
+            .map(|(channel, notes)| (*channel, notes.iter().copied().collect()))
+    }
+
+    /// NoteOff for every held note; the notes are forgotten
+    pub fn release_all(&mut self) -> Vec<[u8; 3]> {
+        let offs = self
+            .notes
+            .iter()
+            .flat_map(|(channel, notes)| notes.iter().map(move |note| [0x80 | channel, *note, 0]))
+            .collect();
+        self.notes.clear();
+        offs
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn note_on_and_off_are_tracked() {
+        let mut notes = SoundingNotes::default();
+        notes.track(&[0x90, 60, 100]);
+        notes.track(&[0x90, 64, 100]);
+        notes.track(&[0x91, 36, 90]);
+        notes.track(&[0x80, 60, 0]);
+        // NoteOn with velocity 0 is a NoteOff
+        notes.track(&[0x91, 36, 0]);
+
+        assert_eq!(notes.release_all(), vec![[0x80, 64, 0]]);
+    }
+
+    #[test]
+    fn all_notes_off_clears_channel() {
+        let mut notes = SoundingNotes::default();
+        notes.track(&[0x90, 60, 100]);
+        notes.track(&[0x91, 62, 100]);
+        notes.track(&[0xB0, 123, 0]);
+
+        assert_eq!(notes.by_channel().collect::<Vec<_>>(), vec![(1, vec![62])]);
+    }
+
+    #[test]
+    fn release_all_sends_note_offs() {
+        let mut notes = SoundingNotes::default();
+        notes.track(&[0x90, 60, 100]);
+        notes.track(&[0x99, 36, 100]);
+        notes.track(&[0xB0, 1, 64]);
+
+        assert_eq!(notes.release_all(), vec![[0x80, 60, 0], [0x89, 36, 0]]);
+        assert!(notes.release_all().is_empty());
+    }
+}
@@ -0,0 +1,1149 @@
+//! MIDI Port connection management
+//!
+//! Handles connecting, disconnecting, and sending to MIDI ports.
+
+use crate::midi::bus::BusFeed;
+use crate::midi::fast_path::{FastPathTable, SharedFastPath};
+use crate::midi::note_off::{normalize_for, SharedNoteOffStyles};
+use crate::midi::ports::{
+    is_virtual_input, is_virtual_output, list_input_ports, list_output_ports,
+};
+use crate::midi::reconnect::ReconnectSchedule;
+use crate::midi::stats::ThroughputMeter;
+use crate::types::{
+    DeviceDefinition, EngineError, NoteOffStyle, PortDirection, PortThroughput, Route,
+};
+use crossbeam_channel::Sender;
+use midir::{MidiInput, MidiInputConnection, MidiOutput, MidiOutputConnection};
+use std::collections::{BTreeSet, HashMap, HashSet, VecDeque};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use uuid::Uuid;
+
+/// Message type for MIDI input callbacks: port, timestamp, bytes, and the
+/// fast-path routes the callback already sent it on
+pub type MidiMessage = (String, u64, Vec<u8>, Vec<Uuid>);
+
+type PendingSends = Arc<Mutex<VecDeque<PendingSend>>>;
+
+/// Messages sent to an output once its connection opens and before the
+/// router closes it
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ConnectionHooks {
+    pub on_connect: Vec<Vec<u8>>,
+    pub on_disconnect: Vec<Vec<u8>>,
+}
+
+/// Hooks for each port of the devices that define any
+pub fn connection_hooks(devices: &[DeviceDefinition]) -> HashMap<String, ConnectionHooks> {
+    devices
+        .iter()
+        .filter(|d| !d.on_connect.is_empty() || !d.on_disconnect.is_empty())
+        .flat_map(|d| {
+            d.ports.iter().map(|port| {
+                let hooks = ConnectionHooks {
+                    on_connect: d.on_connect.clone(),
+                    on_disconnect: d.on_disconnect.clone(),
+                };
+                (port.clone(), hooks)
+            })
+        })
+        .collect()
+}
+
+/// A message waiting to be retried after a failed send
+struct PendingSend {
+    output: String,
+    bytes: Vec<u8>,
+    since: Instant,
+}
+
+/// Manages MIDI port connections
+pub struct PortManager {
+    input_connections: HashMap<String, MidiInputConnection<()>>,
+    output_connections: Arc<Mutex<HashMap<String, MidiOutputConnection>>>,
+    midi_tx: Sender<MidiMessage>,
+    error_tx: Sender<EngineError>,
+    /// Inputs kept open for engine control bindings, independent of routes
+    control_inputs: HashSet<String>,
+    /// Inputs kept open while route learn mode listens on every port
+    learn_inputs: HashSet<String>,
+    /// Ports served by the web bridge and MQTT, independent of routes
+    bridge_inputs: HashSet<String>,
+    bridge_outputs: HashSet<String>,
+    /// Output the test signal generator plays to
+    test_output: Option<String>,
+    /// Per-port message/byte rates (outputs recorded on send)
+    throughput: Mutex<ThroughputMeter>,
+    /// Failed connections waiting to be retried
+    reconnect: ReconnectSchedule,
+    /// Consecutive send failures per output
+    send_failures: Mutex<HashMap<String, u32>>,
+    /// Needed ports that are not present on the system; connected when they appear
+    pending_ports: HashSet<(String, PortDirection)>,
+    /// Outputs dropped after repeated send failures, until they reconnect
+    failed_outputs: HashSet<String>,
+    /// Failed sends waiting to be retried, oldest first.
+    /// Shared with input callbacks so fast-path sends never overtake them.
+    pending_sends: PendingSends,
+    /// Filter-only routes sent straight from the input callbacks
+    fast_path: SharedFastPath,
+    /// How Note Offs are written to each output
+    note_off_styles: SharedNoteOffStyles,
+    /// When each input last delivered a message (or was connected)
+    input_last_seen: HashMap<String, Instant>,
+    last_health_check: Instant,
+    /// Output port name -> messages sent on connecting and disconnecting.
+    /// These outputs are kept connected so the hooks run when the device
+    /// appears.
+    hooks: HashMap<String, ConnectionHooks>,
+    /// Buses, whose sends come back in as input
+    buses: BusFeed,
+}
+
+impl PortManager {
+    /// Consecutive send failures before an output is considered disconnected
+    pub const MAX_SEND_FAILURES: u32 = 3;
+    /// Inputs silent for this long are checked against the system port list
+    pub const STALL_TIMEOUT: Duration = Duration::from_secs(5);
+    const HEALTH_CHECK_INTERVAL: Duration = Duration::from_secs(2);
+    /// How long a failed send is retried before it is dropped
+    pub const SEND_RETRY_WINDOW: Duration = Duration::from_millis(100);
+    /// Queued sends kept at most; the oldest is dropped beyond this
+    pub const MAX_PENDING_SENDS: usize = 256;
+
+    pub fn new(midi_tx: Sender<MidiMessage>, error_tx: Sender<EngineError>) -> Self {
+        Self {
+            input_connections: HashMap::new(),
+            output_connections: Arc::new(Mutex::new(HashMap::new())),
+            midi_tx: midi_tx.clone(),
+            error_tx,
+            control_inputs: HashSet::new(),
+            learn_inputs: HashSet::new(),
+            bridge_inputs: HashSet::new(),
+            bridge_outputs: HashSet::new(),
+            test_output: None,
+            throughput: Mutex::new(ThroughputMeter::new(
+                ThroughputMeter::DEFAULT_WINDOW,
+                Instant::now(),
+            )),
+            reconnect: ReconnectSchedule::new(),
+            send_failures: Mutex::new(HashMap::new()),
+            pending_ports: HashSet::new(),
+            failed_outputs: HashSet::new(),
+            pending_sends: Arc::new(Mutex::new(VecDeque::new())),
+            fast_path: SharedFastPath::default(),
+            note_off_styles: SharedNoteOffStyles::default(),
+            input_last_seen: HashMap::new(),
+            last_health_check: Instant::now(),
+            hooks: HashMap::new(),
+            buses: BusFeed::new(midi_tx),
+        }
+    }
+
+    /// Get a clone of the output connections (for use in clock/transport)
+    pub fn output_connections(&self) -> Arc<Mutex<HashMap<String, MidiOutputConnection>>> {
+        self.output_connections.clone()
+    }
+
+    /// Close connections to ports that are no longer present (for port
+    /// refresh). Connections to ports still present stay open so live routes
+    /// aren't interrupted; the next `sync_with_routes` opens any new ones.
+    pub fn close_missing(&mut self, inputs: &HashSet<String>, outputs: &HashSet<String>) {
+        let mut outputs_guard = self.output_connections.lock().unwrap();
+        let before = (self.input_connections.len(), outputs_guard.len());
+        self.input_connections
+            .retain(|name, _| inputs.contains(name));
+        self.input_last_seen.retain(|name, _| inputs.contains(name));
+        outputs_guard.retain(|name, _| outputs.contains(name));
+        self.send_failures
+            .lock()
+            .unwrap()
+            .retain(|name, _| outputs.contains(name));
+        self.pending_sends
+            .lock()
+            .unwrap()
+            .retain(|send| outputs.contains(&send.output));
+        eprintln!(
+            "[PORT_MGR] Closed {} missing inputs, {} missing outputs",
+            before.0 - self.input_connections.len(),
+            before.1 - outputs_guard.len()
+        );
+    }
+
+    /// Drop every connection and reconnect what's needed, for after system
+    /// sleep, when connections can be dead without having reported an error
+    pub fn reconnect_all(&mut self, routes: &[Route]) {
+        self.input_connections.clear();
+        self.input_last_seen.clear();
+        self.output_connections.lock().unwrap().clear();
+        self.send_failures.lock().unwrap().clear();
+        self.pending_sends.lock().unwrap().clear();
+        self.sync_with_routes(routes);
+    }
+
+    /// Names of the open input and output connections, sorted
+    pub fn connected_ports(&self) -> (Vec<String>, Vec<String>) {
+        let mut inputs: Vec<String> = self.input_connections.keys().cloned().collect();
+        let mut outputs: Vec<String> = self
+            .output_connections
+            .lock()
+            .unwrap()
+            .keys()
+            .cloned()
+            .collect();
+        inputs.sort();
+        outputs.sort();
+        (inputs, outputs)
+    }
+
+    /// Set the inputs that must stay connected for control bindings.
+    /// Takes effect on the next `sync_with_routes`.
+    pub fn set_control_inputs(&mut self, inputs: HashSet<String>) {
+        self.control_inputs = inputs;
+    }
+
+    /// Set the inputs to keep connected for learn mode.
+    /// Takes effect on the next `sync_with_routes`.
+    pub fn set_learn_inputs(&mut self, inputs: HashSet<String>) {
+        self.learn_inputs = inputs;
+    }
+
+    /// Set the ports to keep connected for the web bridge and MQTT.
+    /// Takes effect on the next `sync_with_routes`.
+    pub fn set_bridge_ports(&mut self, inputs: HashSet<String>, outputs: HashSet<String>) {
+        self.bridge_inputs = inputs;
+        self.bridge_outputs = outputs;
+    }
+
+    /// Set the output to keep connected for the test signal generator.
+    /// Takes effect on the next `sync_with_routes`.
+    pub fn set_test_output(&mut self, output: Option<String>) {
+        self.test_output = output;
+    }
+
+    /// Set the connect/disconnect messages for device outputs.
+    /// Takes effect on the next `sync_with_routes`.
+    pub fn set_connection_hooks(&mut self, hooks: HashMap<String, ConnectionHooks>) {
+        self.hooks = hooks;
+    }
+
+    /// Set the internal buses. Takes effect on the next `sync_with_routes`.
+    pub fn set_buses(&mut self, buses: BTreeSet<String>) {
+        self.buses.set_buses(buses);
+    }
+
+    /// Get a clone of the bus feed (for sends made outside the engine loop)
+    pub fn bus_feed(&self) -> BusFeed {
+        self.buses.clone()
+    }
+
+    pub fn is_bus(&self, name: &str) -> bool {
+        self.buses.is_bus(name)
+    }
+
+    /// Close every output, sending their disconnect messages (for shutdown)
+    pub fn close_outputs(&mut self) {
+        self.sync_outputs(HashSet::new());
+    }
+
+    /// Replace the routes input callbacks send on directly
+    pub fn set_fast_path(&self, table: FastPathTable) {
+        *self.fast_path.write().unwrap() = Arc::new(table);
+    }
+
+    /// Set how Note Offs are written to each output
+    pub fn set_note_off_styles(&self, styles: HashMap<String, NoteOffStyle>) {
+        *self.note_off_styles.write().unwrap() = styles;
+    }
+
+    /// Get a clone of the Note Off styles (for sends made directly on a connection)
+    pub fn note_off_styles(&self) -> SharedNoteOffStyles {
+        self.note_off_styles.clone()
+    }
+
+    /// Synchronize connections with the given routes
+    /// Returns errors for any failed connections
+    pub fn sync_with_routes(&mut self, routes: &[Route]) {
+        let mut needed_inputs = Self::needed_input_ports(routes);
+        needed_inputs.extend(self.control_inputs.iter().cloned());
+        needed_inputs.extend(self.learn_inputs.iter().cloned());
+        needed_inputs.extend(self.bridge_inputs.iter().cloned());
+        let mut needed_outputs = Self::needed_output_ports(routes);
+        needed_outputs.extend(self.hooks.keys().cloned());
+        needed_outputs.extend(self.bridge_outputs.iter().cloned());
+        needed_outputs.extend(self.test_output.iter().cloned());
+
+        self.buses.update_loops(routes);
+        self.sync_inputs(needed_inputs);
+        self.sync_outputs(needed_outputs);
+    }
+
+    /// Calculate input ports needed for the given routes
+    pub fn needed_input_ports(routes: &[Route]) -> HashSet<String> {
+        routes
+            .iter()
+            .filter(|r| r.enabled)
+            .map(|r| r.source.name.clone())
+            .collect()
+    }
+
+    /// Calculate output ports needed for the given routes
+    pub fn needed_output_ports(routes: &[Route]) -> HashSet<String> {
+        routes
+            .iter()
+            .filter(|r| r.enabled)
+            .flat_map(|r| r.output_ports())
+            .collect()
+    }
+
+    /// Synchronize input connections with needed ports
+    fn sync_inputs(&mut self, needed: HashSet<String>) {
+        // Remove connections no longer needed
+        self.input_connections
+            .retain(|name, _| needed.contains(name));
+        self.input_last_seen.retain(|name, _| needed.contains(name));
+        self.reconnect
+            .retain(|name, dir| dir != PortDirection::Input || needed.contains(name));
+        self.pending_ports
+            .retain(|(name, dir)| *dir != PortDirection::Input || needed.contains(name));
+
+        // Add new connections
+        for input_name in needed {
+            if self.input_connections.contains_key(&input_name) {
+                eprintln!("[PORT_MGR] Already connected to input: {}", input_name);
+                continue;
+            }
+
+            self.try_connect(&input_name, PortDirection::Input);
+        }
+    }
+
+    /// Synchronize output connections with needed ports
+    fn sync_outputs(&mut self, needed: HashSet<String>) {
+        let missing: Vec<String> = {
+            let mut outputs_guard = self.output_connections.lock().unwrap();
+
+            // Remove connections no longer needed, letting the device know
+            outputs_guard.retain(|name, conn| {
+                if needed.contains(name) {
+                    return true;
+                }
+                if let Some(hooks) = self.hooks.get(name) {
+                    for msg in &hooks.on_disconnect {
+                        if let Err(e) = conn.send(msg) {
+                            eprintln!("[PORT_MGR] Disconnect message to {} failed: {}", name, e);
+                        }
+                    }
+                }
+                false
+            });
+
+            needed
+                .iter()
+                .filter(|name| {
+                    let connected = outputs_guard.contains_key(*name);
+                    if connected {
+                        eprintln!("[PORT_MGR] Already connected to output: {}", name);
+                    }
+                    !connected
+                })
+                .cloned()
+                .collect()
+        };
+        self.reconnect
+            .retain(|name, dir| dir != PortDirection::Output || needed.contains(name));
+        self.failed_outputs.retain(|name| needed.contains(name));
+        self.pending_ports
+            .retain(|(name, dir)| *dir != PortDirection::Output || needed.contains(name));
+
+        // Add new connections
+        for output_name in missing {
+            self.try_connect(&output_name, PortDirection::Output);
+        }
+    }
+
+    /// Retry failed connections whose backoff has elapsed
+    pub fn retry_due(&mut self, now: Instant) {
+        if self.reconnect.is_empty() {
+            return;
+        }
+        for (name, direction) in self.reconnect.due(now) {
+            eprintln!("[PORT_MGR] Retrying {:?} connection: {}", direction, name);
+            self.try_connect(&name, direction);
+        }
+    }
+
+    /// Connect a port, scheduling a retry with backoff if it fails.
+    /// Ports that are not present are left pending until they appear.
+    fn try_connect(&mut self, name: &str, direction: PortDirection) -> bool {
+        let key = (name.to_string(), direction);
+        if !Self::port_present(name, direction) {
+            eprintln!("[PORT_MGR] {:?} not present, pending: {}", direction, name);
+            self.reconnect.clear(name, direction);
+            self.pending_ports.insert(key);
+            return false;
+        }
+        self.pending_ports.remove(&key);
+        // Virtual ports deliver straight to the engine; nothing to open
+        let is_virtual = match direction {
+            PortDirection::Input => is_virtual_input(name),
+            PortDirection::Output => is_virtual_output(name),
+        };
+        if is_virtual {
+            return true;
+        }
+
+        let connected = match direction {
+            PortDirection::Input => self.connect_input(name),
+            PortDirection::Output => match self.connect_output(name) {
+                Some(conn) => {
+                    self.output_connections
+                        .lock()
+                        .unwrap()
+                        .insert(name.to_string(), conn);
+                    true
+                }
+                None => false,
+            },
+        };
+        if connected {
+            self.reconnect.clear(name, direction);
+            if direction == PortDirection::Output {
+                self.failed_outputs.remove(name);
+                for msg in self.hooks.get(name).map_or(&[][..], |h| &h.on_connect) {
+                    if let Err(e) = self.send_to(name, msg) {
+                        eprintln!("[PORT_MGR] Connect message to {} failed: {}", name, e);
+                    }
+                }
+            }
+        } else {
+            self.reconnect.failed(name, direction, Instant::now());
+        }
+        connected
+    }
+
+    fn port_present(name: &str, direction: PortDirection) -> bool {
+        let ports = match direction {
+            PortDirection::Input => list_input_ports(),
+            PortDirection::Output => list_output_ports(),
+        };
+        ports.iter().any(|p| p.id.name == name)
+    }
+
+    /// Connect pending ports that have appeared since the last check
+    fn connect_appeared(&mut self) {
+        if self.pending_ports.is_empty() {
+            return;
+        }
+        let pending: Vec<(String, PortDirection)> = self.pending_ports.iter().cloned().collect();
+        for (name, direction) in pending {
+            if self.try_connect(&name, direction) {
+                eprintln!("[PORT_MGR] Pending {:?} appeared: {}", direction, name);
+            }
+        }
+    }
+
+    /// Needed ports that are not present on the system
+    pub fn pending_ports(&self) -> &HashSet<(String, PortDirection)> {
+        &self.pending_ports
+    }
+
+    /// Connect to an input port, returning true if successful
+    fn connect_input(&mut self, input_name: &str) -> bool {
+        eprintln!("[PORT_MGR] Connecting to input: {}", input_name);
+
+        let midi_in = match MidiInput::new("midi-router") {
+            Ok(mut m) => {
+                // Don't filter any messages - we want clock, sysex, active sense, etc.
+                m.ignore(midir::Ignore::None);
+                m
+            }
+            Err(e) => {
+                eprintln!("[PORT_MGR] Failed to create MidiInput: {}", e);
+                let _ = self.error_tx.send(EngineError::PortConnectionFailed {
+                    port_name: input_name.to_string(),
+                    reason: e.to_string(),
+                });
+                return false;
+            }
+        };
+
+        let port = midi_in
+            .ports()
+            .into_iter()
+            .find(|p| midi_in.port_name(p).ok().as_ref() == Some(&input_name.to_string()));
+
+        let Some(port) = port else {
+            eprintln!("[PORT_MGR] Input port not found: {}", input_name);
+            return false;
+        };
+
+        let tx = self.midi_tx.clone();
+        let name = input_name.to_string();
+        let name_for_closure = name.clone();
+        let fast_path = self.fast_path.clone();
+        let outputs = self.output_connections.clone();
+        let pending_sends = self.pending_sends.clone();
+        let note_off_styles = self.note_off_styles.clone();
+
+        match midi_in.connect(
+            &port,
+            "midi-router-in",
+            move |timestamp, bytes, _| {
+                eprintln!(
+                    "[CALLBACK] {} bytes from {}: {:02X?}",
+                    bytes.len(),
+                    name_for_closure,
+                    bytes
+                );
+                let table = fast_path.read().unwrap().clone();
+                let fast_routed = send_fast_path(
+                    &table,
+                    &name_for_closure,
+                    bytes,
+                    &outputs,
+                    &pending_sends,
+                    &note_off_styles,
+                );
+                let _ = tx.send((
+                    name_for_closure.clone(),
+                    timestamp,
+                    bytes.to_vec(),
+                    fast_routed,
+                ));
+            },
+            (),
+        ) {
+            Ok(conn) => {
+                eprintln!("[PORT_MGR] Successfully connected to input: {}", input_name);
+                self.input_last_seen.insert(name.clone(), Instant::now());
+                self.input_connections.insert(name, conn);
+                true
+            }
+            Err(e) => {
+                eprintln!("[PORT_MGR] Failed to connect input {}: {}", input_name, e);
+                let _ = self.error_tx.send(EngineError::PortConnectionFailed {
+                    port_name: input_name.to_string(),
+                    reason: e.to_string(),
+                });
+                false
+            }
+        }
+    }
+
+    /// Connect to an output port, returning the connection if successful
+    fn connect_output(&self, output_name: &str) -> Option<MidiOutputConnection> {
+        eprintln!("[PORT_MGR] Connecting to output: {}", output_name);
+
+        let midi_out = match MidiOutput::new("midi-router") {
+            Ok(m) => m,
+            Err(e) => {
+                eprintln!("[PORT_MGR] Failed to create MidiOutput: {}", e);
+                let _ = self.error_tx.send(EngineError::PortConnectionFailed {
+                    port_name: output_name.to_string(),
+                    reason: e.to_string(),
+                });
+                return None;
+            }
+        };
+
+        let port = midi_out
+            .ports()
+            .into_iter()
+            .find(|p| midi_out.port_name(p).ok().as_ref() == Some(&output_name.to_string()));
+
+        let Some(port) = port else {
+            eprintln!("[PORT_MGR] Output port not found: {}", output_name);
+            return None;
+        };
+
+        match midi_out.connect(&port, "midi-router-out") {
+            Ok(conn) => {
+                eprintln!(
+                    "[PORT_MGR] Successfully connected to output: {}",
+                    output_name
+                );
+                Some(conn)
+            }
+            Err(e) => {
+                eprintln!(
+                    "[PORT_MGR] Failed to connect output {}: {}",
+                    output_name, e
+                );
+                let _ = self.error_tx.send(EngineError::PortConnectionFailed {
+                    port_name: output_name.to_string(),
+                    reason: e.to_string(),
+                });
+                None
+            }
+        }
+    }
+
+    /// Send a MIDI message to all connected outputs
+    pub fn send_to_all(&self, bytes: &[u8]) {
+        self.send_to_all_matching(bytes, |_| true);
+    }
+
+    /// Send a MIDI message to the connected outputs the filter accepts
+    pub fn send_to_all_matching(&self, bytes: &[u8], accept: impl Fn(&str) -> bool) {
+        let mut outputs_guard = self.output_connections.lock().unwrap();
+        for (name, conn) in outputs_guard.iter_mut() {
+            if accept(name) {
+                self.send_or_queue(name, conn, bytes);
+            }
+        }
+    }
+
+    /// Send a MIDI message to a specific output.
+    /// Transient send failures are queued for retry rather than reported here.
+    pub fn send_to(&self, output_name: &str, bytes: &[u8]) -> Result<(), EngineError> {
+        if let Some(fed) = self.buses.send(output_name, bytes) {
+            return fed.map_err(|reason| EngineError::SendFailed {
+                port_name: output_name.to_string(),
+                reason,
+            });
+        }
+        let mut outputs_guard = self.output_connections.lock().unwrap();
+        if let Some(conn) = outputs_guard.get_mut(output_name) {
+            self.send_or_queue(output_name, conn, bytes);
+            Ok(())
+        } else {
+            Err(EngineError::SendFailed {
+                port_name: output_name.to_string(),
+                reason: "Port not connected".to_string(),
+            })
+        }
+    }
+
+    /// Send now, or queue behind earlier messages that are still waiting
+    /// so a port never receives messages out of order
+    fn send_or_queue(&self, output_name: &str, conn: &mut MidiOutputConnection, bytes: &[u8]) {
+        let bytes = &*normalize_for(&self.note_off_styles, output_name, bytes);
+        if self.has_pending_sends(output_name) {
+            self.queue_send(output_name, bytes);
+            return;
+        }
+        match conn.send(bytes) {
+            Ok(()) => self.record_sent(output_name, bytes),
+            Err(e) => {
+                eprintln!(
+                    "[PORT_MGR] Send to {} failed, will retry: {}",
+                    output_name, e
+                );
+                self.send_failed(output_name);
+                self.queue_send(output_name, bytes);
+            }
+        }
+    }
+
+    /// Count a successful send, including ones made directly on a connection
+    pub fn record_sent(&self, output_name: &str, bytes: &[u8]) {
+        self.send_succeeded(output_name);
+        self.throughput
+            .lock()
+            .unwrap()
+            .record(output_name, PortDirection::Output, bytes.len());
+    }
+
+    fn has_pending_sends(&self, output_name: &str) -> bool {
+        let pending = self.pending_sends.lock().unwrap();
+        pending.iter().any(|p| p.output == output_name)
+    }
+
+    fn queue_send(&self, output_name: &str, bytes: &[u8]) {
+        let mut pending = self.pending_sends.lock().unwrap();
+        if pending.len() >= Self::MAX_PENDING_SENDS {
+            if let Some(dropped) = pending.pop_front() {
+                let _ = self.error_tx.send(EngineError::SendFailed {
+                    port_name: dropped.output,
+                    reason: "Send queue full".to_string(),
+                });
+            }
+        }
+        pending.push_back(PendingSend {
+            output: output_name.to_string(),
+            bytes: bytes.to_vec(),
+            since: Instant::now(),
+        });
+    }
+
+    /// Retry queued sends. Messages still failing after `SEND_RETRY_WINDOW`
+    /// are dropped with a `SendFailed` error.
+    pub fn retry_pending_sends(&self, now: Instant) {
+        let mut outputs_guard = self.output_connections.lock().unwrap();
+        let mut pending = self.pending_sends.lock().unwrap();
+        if pending.is_empty() {
+            return;
+        }
+
+        // Once a message for a port fails, later ones for it wait their turn
+        let mut blocked: HashSet<String> = HashSet::new();
+        pending.retain(|send| {
+            let result = match outputs_guard.get_mut(&send.output) {
+                _ if blocked.contains(&send.output) => Err(None),
+                Some(conn) => conn.send(&send.bytes).map_err(|e| Some(e.to_string())),
+                None => Err(Some("Port not connected".to_string())),
+            };
+            match result {
+                Ok(()) => {
+                    self.record_sent(&send.output, &send.bytes);
+                    false
+                }
+                Err(reason) if now.duration_since(send.since) >= Self::SEND_RETRY_WINDOW => {
+                    self.send_failed(&send.output);
+                    let _ = self.error_tx.send(EngineError::SendFailed {
+                        port_name: send.output.clone(),
+                        reason: reason.unwrap_or_else(|| "Timed out waiting to send".to_string()),
+                    });
+                    false
+                }
+                Err(_) => {
+                    blocked.insert(send.output.clone());
+                    true
+                }
+            }
+        });
+    }
+
+    fn send_failed(&self, output_name: &str) {
+        *self
+            .send_failures
+            .lock()
+            .unwrap()
+            .entry(output_name.to_string())
+            .or_insert(0) += 1;
+    }
+
+    fn send_succeeded(&self, output_name: &str) {
+        let mut failures = self.send_failures.lock().unwrap();
+        if !failures.is_empty() {
+            failures.remove(output_name);
+        }
+    }
+
+    /// Drop connections that have gone dead and schedule their reconnection:
+    /// outputs that keep failing to send, and silent inputs whose port has
+    /// disappeared from the system. Also connects pending ports that appeared.
+    pub fn check_connections(&mut self, now: Instant) {
+        let dead_outputs: Vec<String> = {
+            let mut failures = self.send_failures.lock().unwrap();
+            let dead: Vec<String> = failures
+                .iter()
+                .filter(|(_, count)| **count >= Self::MAX_SEND_FAILURES)
+                .map(|(name, _)| name.clone())
+                .collect();
+            for name in &dead {
+                failures.remove(name);
+            }
+            dead
+        };
+        for name in dead_outputs {
+            self.output_connections.lock().unwrap().remove(&name);
+            self.failed_outputs.insert(name.clone());
+            self.disconnected(&name, PortDirection::Output, now);
+        }
+
+        if now.duration_since(self.last_health_check) < Self::HEALTH_CHECK_INTERVAL {
+            return;
+        }
+        self.last_health_check = now;
+        self.connect_appeared();
+
+        let stalled: Vec<String> = self
+            .input_connections
+            .keys()
+            .filter(|name| match self.input_last_seen.get(*name) {
+                Some(seen) => now.duration_since(*seen) >= Self::STALL_TIMEOUT,
+                None => true,
+            })
+            .cloned()
+            .collect();
+        if stalled.is_empty() {
+            return;
+        }
+
+        let present: HashSet<String> = list_input_ports().into_iter().map(|p| p.id.name).collect();
+        for name in stalled {
+            if present.contains(&name) {
+                // Just idle - check again after another timeout
+                self.input_last_seen.insert(name, now);
+            } else {
+                self.input_connections.remove(&name);
+                self.input_last_seen.remove(&name);
+                self.disconnected(&name, PortDirection::Input, now);
+            }
+        }
+    }
+
+    fn disconnected(&mut self, name: &str, direction: PortDirection, now: Instant) {
+        eprintln!("[PORT_MGR] {:?} disconnected: {}", direction, name);
+        let _ = self.error_tx.send(EngineError::PortDisconnected {
+            port_name: name.to_string(),
+        });
+        self.reconnect.failed(name, direction, now);
+    }
+
+    /// Outputs that were dropped for failing to send and have not reconnected
+    pub fn failed_outputs(&self) -> &HashSet<String> {
+        &self.failed_outputs
+    }
+
+    /// Count a message received on an input
+    pub fn record_input(&mut self, input_name: &str, bytes: &[u8]) {
+        self.input_last_seen
+            .insert(input_name.to_string(), Instant::now());
+        self.throughput
+            .lock()
+            .unwrap()
+            .record(input_name, PortDirection::Input, bytes.len());
+    }
+
+    /// Close the throughput window if it has elapsed.
+    /// Returns the new rates when they were updated.
+    pub fn roll_throughput(&self, now: Instant) -> Option<Vec<PortThroughput>> {
+        let mut throughput = self.throughput.lock().unwrap();
+        throughput.roll(now).then(|| throughput.rates().to_vec())
+    }
+
+    /// Rates from the last completed window
+    pub fn throughput(&self) -> Vec<PortThroughput> {
+        self.throughput.lock().unwrap().rates().to_vec()
+    }
+}
+
+/// Send a message on its fast-path routes from the input callback.
+/// Returns the routes it was sent on; the rest are left to the engine,
+/// including any whose output has sends queued for retry.
+fn send_fast_path(
+    table: &FastPathTable,
+    port: &str,
+    bytes: &[u8],
+    outputs: &Mutex<HashMap<String, MidiOutputConnection>>,
+    pending_sends: &PendingSends,
+    note_off_styles: &SharedNoteOffStyles,
+) -> Vec<Uuid> {
+    let routes = table.matching(port, bytes);
+    if routes.is_empty() {
+        return Vec::new();
+    }
+    // Same lock order as `retry_pending_sends`
+    let mut outputs = outputs.lock().unwrap();
+    let pending = pending_sends.lock().unwrap();
+    routes
+        .into_iter()
+        .filter(|route| {
+            let bytes = normalize_for(note_off_styles, &route.destination, bytes);
+            !pending.iter().any(|p| p.output == route.destination)
+                && outputs
+                    .get_mut(&route.destination)
+                    .is_some_and(|conn| conn.send(&bytes).is_ok())
+        })
+        .map(|route| route.route_id)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::{ChannelFilter, PortId, VoiceSplit};
+    use crossbeam_channel::bounded;
+    use uuid::Uuid;
+
+    fn make_test_route(source: &str, dest: &str, enabled: bool) -> Route {
+        Route {
+            id: Uuid::new_v4(),
+            source: PortId::new(source.to_string()),
+            destination: PortId::new(dest.to_string()),
+            enabled,
+            channels: ChannelFilter::All,
+            cc_passthrough: true,
+            cc_mappings: vec![],
+            ..Route::default()
+        }
+    }
+
+    #[test]
+    fn needed_input_ports_filters_enabled() {
+        let routes = vec![
+            make_test_route("Input A", "Output A", true),
+            make_test_route("Input B", "Output B", false),
+            make_test_route("Input C", "Output C", true),
+        ];
+
+        let needed = PortManager::needed_input_ports(&routes);
+        assert!(needed.contains("Input A"));
+        assert!(!needed.contains("Input B")); // disabled
+        assert!(needed.contains("Input C"));
+    }
+
+    #[test]
+    fn needed_output_ports_filters_enabled() {
+        let routes = vec![
+            make_test_route("Input A", "Output A", true),
+            make_test_route("Input B", "Output B", false),
+            make_test_route("Input C", "Output C", true),
+        ];
+
+        let needed = PortManager::needed_output_ports(&routes);
+        assert!(needed.contains("Output A"));
+        assert!(!needed.contains("Output B")); // disabled
+        assert!(needed.contains("Output C"));
+    }
+
+    #[test]
+    fn needed_output_ports_include_voice_split_outputs() {
+        let mut route = make_test_route("Keys", "Mono A", true);
+        route.voice_split = Some(VoiceSplit {
+            outputs: vec!["Mono B".to_string(), "Mono A".to_string()],
+            ..VoiceSplit::default()
+        });
+
+        assert_eq!(route.output_ports(), vec!["Mono A", "Mono B"]);
+        let needed = PortManager::needed_output_ports(&[route]);
+        assert_eq!(needed.len(), 2);
+        assert!(needed.contains("Mono B"));
+    }
+
+    #[test]
+    fn needed_ports_deduplicates() {
+        let routes = vec![
+            make_test_route("Input A", "Output A", true),
+            make_test_route("Input A", "Output B", true), // same input
+        ];
+
+        let needed_inputs = PortManager::needed_input_ports(&routes);
+        assert_eq!(needed_inputs.len(), 1);
+        assert!(needed_inputs.contains("Input A"));
+
+        let needed_outputs = PortManager::needed_output_ports(&routes);
+        assert_eq!(needed_outputs.len(), 2);
+    }
+
+    #[test]
+    fn needed_input_ports_empty_routes() {
+        let routes: Vec<Route> = vec![];
+        let needed = PortManager::needed_input_ports(&routes);
+        assert!(needed.is_empty());
+    }
+
+    #[test]
+    fn needed_output_ports_empty_routes() {
+        let routes: Vec<Route> = vec![];
+        let needed = PortManager::needed_output_ports(&routes);
+        assert!(needed.is_empty());
+    }
+
+    #[test]
+    fn needed_ports_all_disabled() {
+        let routes = vec![
+            make_test_route("Input A", "Output A", false),
+            make_test_route("Input B", "Output B", false),
+        ];
+
+        let needed_inputs = PortManager::needed_input_ports(&routes);
+        assert!(needed_inputs.is_empty());
+
+        let needed_outputs = PortManager::needed_output_ports(&routes);
+        assert!(needed_outputs.is_empty());
+    }
+
+    #[test]
+    fn port_manager_close_missing_drops_only_absent_ports() {
+        let (midi_tx, _midi_rx) = bounded(10);
+        let (error_tx, _error_rx) = bounded(10);
+
+        let mut manager = PortManager::new(midi_tx, error_tx);
+        {
+            let mut failures = manager.send_failures.lock().unwrap();
+            failures.insert("Gone".to_string(), 1);
+            failures.insert("Synth".to_string(), 1);
+            let mut pending = manager.pending_sends.lock().unwrap();
+            pending.push_back(PendingSend {
+                output: "Gone".to_string(),
+                bytes: vec![0x90, 60, 100],
+                since: Instant::now(),
+            });
+        }
+
+        manager.close_missing(&HashSet::new(), &HashSet::from(["Synth".to_string()]));
+
+        let failures = manager.send_failures.lock().unwrap();
+        assert!(failures.contains_key("Synth"));
+        assert!(!failures.contains_key("Gone"));
+        assert!(manager.pending_sends.lock().unwrap().is_empty());
+    }
+
+    #[test]
+    fn port_manager_sync_with_routes_handles_nonexistent_ports() {
+        let (midi_tx, _midi_rx) = bounded(10);
+        let (error_tx, _error_rx) = bounded(10);
+
+        let mut manager = PortManager::new(midi_tx, error_tx);
+
+        let routes = vec![
+            make_test_route("Nonexistent Input", "Nonexistent Output", true),
+        ];
+
+        // Should not panic; missing ports wait for the device instead of retrying
+        manager.sync_with_routes(&routes);
+        assert!(manager
+            .pending_ports()
+            .contains(&("Nonexistent Input".to_string(), PortDirection::Input)));
+        assert!(manager
+            .pending_ports()
+            .contains(&("Nonexistent Output".to_string(), PortDirection::Output)));
+        assert!(manager.reconnect.is_empty());
+
+        // No longer needed: no longer pending
+        manager.sync_with_routes(&[]);
+        assert!(manager.pending_ports().is_empty());
+    }
+
+    #[test]
+    fn port_manager_sync_with_control_inputs_handles_nonexistent_ports() {
+        let (midi_tx, _midi_rx) = bounded(10);
+        let (error_tx, _error_rx) = bounded(10);
+
+        let mut manager = PortManager::new(midi_tx, error_tx);
+        manager.set_control_inputs(HashSet::from(["Nonexistent Control".to_string()]));
+
+        // Should not panic with no routes and a missing control input
+        manager.sync_with_routes(&[]);
+    }
+
+    #[test]
+    fn devices_with_hooks_keep_their_outputs_wanted() {
+        let (midi_tx, _midi_rx) = bounded(10);
+        let (error_tx, _error_rx) = bounded(10);
+
+        let mut keyboard = DeviceDefinition::new("Keyboard".to_string());
+        keyboard.ports = vec!["Nonexistent Keyboard".to_string()];
+        keyboard.on_connect = vec![vec![0xB0, 122, 0]];
+        let mut synth = DeviceDefinition::new("Synth".to_string());
+        synth.ports = vec!["Nonexistent Synth".to_string()];
+        let hooks = connection_hooks(&[keyboard, synth]);
+        assert_eq!(hooks.len(), 1);
+        assert_eq!(
+            hooks["Nonexistent Keyboard"].on_connect,
+            vec![vec![0xB0, 122, 0]]
+        );
+
+        // Waits for the keyboard to appear, with no route to it
+        let mut manager = PortManager::new(midi_tx, error_tx);
+        manager.set_connection_hooks(hooks);
+        manager.sync_with_routes(&[]);
+        assert!(manager
+            .pending_ports()
+            .contains(&("Nonexistent Keyboard".to_string(), PortDirection::Output)));
+    }
+
+    #[test]
+    fn bridge_ports_stay_wanted_without_routes() {
+        let (midi_tx, _midi_rx) = bounded(10);
+        let (error_tx, _error_rx) = bounded(10);
+
+        let mut manager = PortManager::new(midi_tx, error_tx);
+        manager.set_bridge_ports(
+            HashSet::from(["Nonexistent Editor In".to_string()]),
+            HashSet::from(["Nonexistent Editor Out".to_string()]),
+        );
+        manager.sync_with_routes(&[]);
+        assert!(manager
+            .pending_ports()
+            .contains(&("Nonexistent Editor In".to_string(), PortDirection::Input)));
+        assert!(manager
+            .pending_ports()
+            .contains(&("Nonexistent Editor Out".to_string(), PortDirection::Output)));
+
+        manager.set_bridge_ports(HashSet::new(), HashSet::new());
+        manager.sync_with_routes(&[]);
+        assert!(manager.pending_ports().is_empty());
+    }
+
+    #[test]
+    fn port_manager_send_to_nonexistent_returns_error() {
+        let (midi_tx, _midi_rx) = bounded(10);
+        let (error_tx, _error_rx) = bounded(10);
+
+        let manager = PortManager::new(midi_tx, error_tx);
+
+        let result = manager.send_to("Nonexistent Port", &[0x90, 60, 100]);
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn port_manager_send_to_all_empty_does_not_panic() {
+        let (midi_tx, _midi_rx) = bounded(10);
+        let (error_tx, _error_rx) = bounded(10);
+
+        let manager = PortManager::new(midi_tx, error_tx);
+
+        // Should not panic with no connections
+        manager.send_to_all(&[0x90, 60, 100]);
+    }
+
+    #[test]
+    fn repeated_send_failures_report_disconnect() {
+        let (midi_tx, _midi_rx) = bounded(16);
+        let (error_tx, error_rx) = bounded(16);
+        let mut manager = PortManager::new(midi_tx, error_tx);
+
+        for _ in 0..PortManager::MAX_SEND_FAILURES - 1 {
+            manager.send_failed("Synth");
+        }
+        manager.check_connections(Instant::now());
+        assert!(error_rx.try_recv().is_err());
+
+        manager.send_failed("Synth");
+        manager.check_connections(Instant::now());
+        assert!(matches!(
+            error_rx.try_recv(),
+            Ok(EngineError::PortDisconnected { port_name }) if port_name == "Synth"
+        ));
+        assert!(manager.failed_outputs().contains("Synth"));
+        // Reconnection is scheduled
+        assert!(!manager.reconnect.is_empty());
+    }
+
+    #[test]
+    fn queued_sends_to_missing_port_are_dropped_with_error() {
+        let (midi_tx, _midi_rx) = bounded(16);
+        let (error_tx, error_rx) = bounded(16);
+        let manager = PortManager::new(midi_tx, error_tx);
+
+        manager.queue_send("Synth", &[0x90, 60, 100]);
+        assert!(manager.has_pending_sends("Synth"));
+        assert!(!manager.has_pending_sends("Drums"));
+
+        // Still inside the retry window: kept
+        let queued_at = Instant::now();
+        manager.retry_pending_sends(queued_at);
+        assert!(manager.has_pending_sends("Synth"));
+        assert!(error_rx.try_recv().is_err());
+
+        manager.retry_pending_sends(queued_at + PortManager::SEND_RETRY_WINDOW);
+        assert!(!manager.has_pending_sends("Synth"));
+        assert!(matches!(
+            error_rx.try_recv(),
+            Ok(EngineError::SendFailed { port_name, .. }) if port_name == "Synth"
+        ));
+    }
+
+    #[test]
+    fn full_send_queue_drops_oldest() {
+        let (midi_tx, _midi_rx) = bounded(16);
+        let (error_tx, error_rx) = bounded(16);
+        let manager = PortManager::new(midi_tx, error_tx);
+
+        manager.queue_send("A", &[0xF8]);
+        for _ in 0..PortManager::MAX_PENDING_SENDS {
+            manager.queue_send("B", &[0xF8]);
+        }
+        assert!(!manager.has_pending_sends("A"));
+        assert!(matches!(
+            error_rx.try_recv(),
+            Ok(EngineError::SendFailed { port_name, .. }) if port_name == "A"
+        ));
+    }
+}
@@ -0,0 +1,75 @@
+//! Port list watching
+//!
+//! Keeps the engine's current view of the system's ports, rescanned every
+//! few seconds so devices that appear or disappear are reported without the
+//! UI having to poll.
+
+use crate::types::MidiPort;
+use std::time::{Duration, Instant};
+
+#[derive(Debug)]
+pub struct PortWatcher {
+    inputs: Vec<MidiPort>,
+    outputs: Vec<MidiPort>,
+    last_scan: Instant,
+}
+
+impl PortWatcher {
+    pub const SCAN_INTERVAL: Duration = Duration::from_secs(2);
+
+    pub fn new(inputs: Vec<MidiPort>, outputs: Vec<MidiPort>, now: Instant) -> Self {
+        Self {
+            inputs,
+            outputs,
+            last_scan: now,
+        }
+    }
+
+    /// Whether it's time for another scan
+    pub fn due(&self, now: Instant) -> bool {
+        now.duration_since(self.last_scan) >= Self::SCAN_INTERVAL
+    }
+
+    /// Store a scan's result, returning whether the port list changed
+    pub fn update(&mut self, inputs: Vec<MidiPort>, outputs: Vec<MidiPort>, now: Instant) -> bool {
+        self.last_scan = now;
+        if inputs == self.inputs && outputs == self.outputs {
+            return false;
+        }
+        self.inputs = inputs;
+        self.outputs = outputs;
+        true
+    }
+
+    pub fn ports(&self) -> (Vec<MidiPort>, Vec<MidiPort>) {
+        (self.inputs.clone(), self.outputs.clone())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::PortId;
+
+    fn port(name: &str) -> MidiPort {
+        MidiPort {
+            id: PortId::new(name.to_string()),
+            is_input: true,
+        }
+    }
+
+    #[test]
+    fn reports_only_changes() {
+        let t0 = Instant::now();
+        let mut watcher = PortWatcher::new(vec![port("Keys")], Vec::new(), t0);
+        assert!(!watcher.due(t0));
+
+        let t1 = t0 + PortWatcher::SCAN_INTERVAL;
+        assert!(watcher.due(t1));
+        assert!(!watcher.update(vec![port("Keys")], Vec::new(), t1));
+        assert!(!watcher.due(t1));
+
+        assert!(watcher.update(vec![port("Keys"), port("Pads")], Vec::new(), t1));
+        assert_eq!(watcher.ports().0.len(), 2);
+    }
+}
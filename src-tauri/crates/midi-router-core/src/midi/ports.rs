@@ -0,0 +1,157 @@
+//! Port enumeration and connection
+
+use crate::types::{MidiPort, PortId};
+use std::collections::BTreeSet;
+use std::sync::Mutex;
+
+/// Inputs the router provides itself, such as the gamepad adapter's. They're
+/// listed with the system's ports but have no connection to open.
+static VIRTUAL_INPUTS: Mutex<BTreeSet<String>> = Mutex::new(BTreeSet::new());
+
+/// Outputs the router provides itself: the buses, which feed back into the
+/// engine instead of a connection
+static VIRTUAL_OUTPUTS: Mutex<BTreeSet<String>> = Mutex::new(BTreeSet::new());
+
+/// Add or remove a virtual input from the port list
+pub fn set_virtual_input(name: &str, present: bool) {
+    let mut inputs = VIRTUAL_INPUTS.lock().unwrap();
+    if present {
+        inputs.insert(name.to_string());
+    } else {
+        inputs.remove(name);
+    }
+}
+
+pub fn is_virtual_input(name: &str) -> bool {
+    VIRTUAL_INPUTS.lock().unwrap().contains(name)
+}
+
+/// Add or remove a virtual output from the port list
+pub fn set_virtual_output(name: &str, present: bool) {
+    let mut outputs = VIRTUAL_OUTPUTS.lock().unwrap();
+    if present {
+        outputs.insert(name.to_string());
+    } else {
+        outputs.remove(name);
+    }
+}
+
+pub fn is_virtual_output(name: &str) -> bool {
+    VIRTUAL_OUTPUTS.lock().unwrap().contains(name)
+}
+
+/// List input ports using platform-specific implementation, followed by
+/// the virtual inputs
+pub fn list_input_ports() -> Vec<MidiPort> {
+    #[cfg(target_os = "macos")]
+    let mut ports = list_input_ports_coremidi();
+    #[cfg(not(target_os = "macos"))]
+    let mut ports = list_input_ports_midir();
+    ports.extend(VIRTUAL_INPUTS.lock().unwrap().iter().map(|name| MidiPort {
+        id: PortId::new(name.clone()),
+        is_input: true,
+    }));
+    ports
+}
+
+/// List output ports using platform-specific implementation, followed by
+/// the virtual outputs
+pub fn list_output_ports() -> Vec<MidiPort> {
+    #[cfg(target_os = "macos")]
+    let mut ports = list_output_ports_coremidi();
+    #[cfg(not(target_os = "macos"))]
+    let mut ports = list_output_ports_midir();
+    ports.extend(VIRTUAL_OUTPUTS.lock().unwrap().iter().map(|name| MidiPort {
+        id: PortId::new(name.clone()),
+        is_input: false,
+    }));
+    ports
+}
+
+// macOS implementation using coremidi for better hot-plug support
+#[cfg(target_os = "macos")]
+fn list_input_ports_coremidi() -> Vec<MidiPort> {
+    use coremidi::Sources;
+
+    let ports: Vec<MidiPort> = Sources
+        .into_iter()
+        .filter_map(|source| {
+            source.display_name().map(|name| MidiPort {
+                id: PortId::new(name),
+                is_input: true,
+            })
+        })
+        .collect();
+
+    eprintln!("[PORTS] Input ports (coremidi): {:?}", ports.iter().map(|p| &p.id.name).collect::<Vec<_>>());
+    ports
+}
+
+#[cfg(target_os = "macos")]
+fn list_output_ports_coremidi() -> Vec<MidiPort> {
+    use coremidi::Destinations;
+
+    let ports: Vec<MidiPort> = Destinations
+        .into_iter()
+        .filter_map(|dest| {
+            dest.display_name().map(|name| MidiPort {
+                id: PortId::new(name),
+                is_input: false,
+            })
+        })
+        .collect();
+
+    eprintln!("[PORTS] Output ports (coremidi): {:?}", ports.iter().map(|p| &p.id.name).collect::<Vec<_>>());
+    ports
+}
+
+// Fallback implementation using midir (for non-macOS platforms)
+#[cfg(not(target_os = "macos"))]
+fn list_input_ports_midir() -> Vec<MidiPort> {
+    use midir::MidiInput;
+
+    let Ok(midi_in) = MidiInput::new("midi-router-enum") else {
+        return Vec::new();
+    };
+
+    let ports: Vec<MidiPort> = midi_in
+        .ports()
+        .iter()
+        .filter_map(|port| {
+            midi_in.port_name(port).ok().map(|name| MidiPort {
+                id: PortId::new(name),
+                is_input: true,
+            })
+        })
+        .collect();
+
+    eprintln!("[PORTS] Input ports (midir): {:?}", ports.iter().map(|p| &p.id.name).collect::<Vec<_>>());
+    ports
+}
+
+#[cfg(not(target_os = "macos"))]
+fn list_output_ports_midir() -> Vec<MidiPort> {
+    use midir::MidiOutput;
+
+    let Ok(midi_out) = MidiOutput::new("midi-router-enum") else {
+        return Vec::new();
+    };
+
+    let ports: Vec<MidiPort> = midi_out
+        .ports()
+        .iter()
+        .filter_map(|port| {
+            midi_out.port_name(port).ok().map(|name| MidiPort {
+                id: PortId::new(name),
+                is_input: false,
+            })
+        })
+        .collect();
+
+    eprintln!("[PORTS] Output ports (midir): {:?}", ports.iter().map(|p| &p.id.name).collect::<Vec<_>>());
+    ports
+}
+
+pub fn list_all_ports() -> (Vec<MidiPort>, Vec<MidiPort>) {
+    (list_input_ports(), list_output_ports())
+}
@@ -0,0 +1,60 @@
+//! Bar-scheduled preset switching
+//!
+//! A queued preset waits for a number of bar lines on the running clock, so
+//! the routing for the next song section lands on a downbeat.
+
+use crate::types::{ClockPosition, Route};
+use uuid::Uuid;
+
+#[derive(Debug, Clone)]
+pub struct QueuedPreset {
+    pub preset_id: Uuid,
+    pub routes: Vec<Route>,
+    /// Bar lines still to pass; counted down rather than stored as a target
+    /// bar so a Start in between doesn't strand the switch
+    bars_left: u32,
+}
+
+impl QueuedPreset {
+    /// Queue a switch at the `bars`th bar line from now (at least the next)
+    pub fn new(preset_id: Uuid, routes: Vec<Route>, bars: u32) -> Self {
+        Self {
+            preset_id,
+            routes,
+            bars_left: bars.max(1),
+        }
+    }
+
+    /// Count a running clock pulse, returning whether the switch is due
+    pub fn on_pulse(&mut self, position: ClockPosition) -> bool {
+        if position.beat != 1 || position.tick != 0 {
+            return false;
+        }
+        self.bars_left -= 1;
+        self.bars_left == 0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(bar: u32, beat: u32, tick: u32) -> ClockPosition {
+        ClockPosition { bar, beat, tick }
+    }
+
+    #[test]
+    fn waits_for_the_given_bar_lines() {
+        let mut queued = QueuedPreset::new(Uuid::new_v4(), Vec::new(), 2);
+        assert!(!queued.on_pulse(at(3, 2, 12)));
+        assert!(!queued.on_pulse(at(4, 1, 0)));
+        assert!(!queued.on_pulse(at(4, 1, 1)));
+        assert!(queued.on_pulse(at(5, 1, 0)));
+    }
+
+    #[test]
+    fn zero_bars_means_the_next_bar_line() {
+        let mut queued = QueuedPreset::new(Uuid::new_v4(), Vec::new(), 0);
+        assert!(queued.on_pulse(at(1, 1, 0)));
+    }
+}
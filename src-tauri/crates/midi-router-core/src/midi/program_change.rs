@@ -0,0 +1,122 @@
+//! Program Change thinning
+//!
+//! Some foot controllers repeat Program Changes or send them in bursts, and
+//! every one makes the destination reload its patch. A route can drop repeats
+//! of the program a channel is already on, and hold Program Changes until a
+//! burst settles so only the last is sent.
+
+use crate::types::ProgramChangeFilter;
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+#[derive(Debug, Default)]
+pub struct ProgramChangeGate {
+    /// Last program sent on each channel
+    sent: HashMap<u8, u8>,
+    /// Program waiting for its burst to settle on each channel, with when
+    /// it's due
+    pending: HashMap<u8, (Instant, u8)>,
+}
+
+impl ProgramChangeGate {
+    /// Pass a message through the filter. Anything other than Program
+    /// Change passes unchanged.
+    pub fn admit(
+        &mut self,
+        bytes: &[u8],
+        filter: &ProgramChangeFilter,
+        now: Instant,
+    ) -> Vec<Vec<u8>> {
+        let (channel, program) = match bytes {
+            [status, program] if status & 0xF0 == 0xC0 => (status & 0x0F, *program),
+            // The same program in a new bank is a different patch
+            [status, 0 | 32, _] if status & 0xF0 == 0xB0 => {
+                self.sent.remove(&(status & 0x0F));
+                return vec![bytes.to_vec()];
+            }
+            _ => return vec![bytes.to_vec()],
+        };
+        if filter.debounce_ms > 0 {
+            let due = now + Duration::from_millis(u64::from(filter.debounce_ms));
+            self.pending.insert(channel, (due, program));
+            return Vec::new();
+        }
+        self.send(channel, program, filter).into_iter().collect()
+    }
+
+    /// Program Changes whose burst has settled
+    pub fn due(&mut self, filter: &ProgramChangeFilter, now: Instant) -> Vec<Vec<u8>> {
+        let settled: Vec<(u8, u8)> = self
+            .pending
+            .iter()
+            .filter(|(_, (due, _))| *due <= now)
+            .map(|(channel, (_, program))| (*channel, *program))
+            .collect();
+        let mut out = Vec::new();
+        for (channel, program) in settled {
+            self.pending.remove(&channel);
+            out.extend(self.send(channel, program, filter));
+        }
+        out.sort();
+        out
+    }
+
+    fn send(&mut self, channel: u8, program: u8, filter: &ProgramChangeFilter) -> Option<Vec<u8>> {
+        if filter.dedupe && self.sent.get(&channel) == Some(&program) {
+            return None;
+        }
+        self.sent.insert(channel, program);
+        Some(vec![0xC0 | channel, program])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn drops_repeated_programs() {
+        let filter = ProgramChangeFilter {
+            dedupe: true,
+            debounce_ms: 0,
+        };
+        let now = Instant::now();
+        let mut gate = ProgramChangeGate::default();
+
+        assert_eq!(gate.admit(&[0xC0, 5], &filter, now), vec![vec![0xC0, 5]]);
+        assert!(gate.admit(&[0xC0, 5], &filter, now).is_empty());
+        assert_eq!(gate.admit(&[0xC1, 5], &filter, now), vec![vec![0xC1, 5]]);
+        assert_eq!(gate.admit(&[0xC0, 6], &filter, now), vec![vec![0xC0, 6]]);
+        // A bank change makes the same program new again
+        gate.admit(&[0xB0, 0, 2], &filter, now);
+        assert_eq!(gate.admit(&[0xC0, 6], &filter, now), vec![vec![0xC0, 6]]);
+        assert_eq!(
+            gate.admit(&[0x90, 60, 100], &filter, now),
+            vec![vec![0x90, 60, 100]]
+        );
+    }
+
+    #[test]
+    fn sends_only_the_last_of_a_burst() {
+        let filter = ProgramChangeFilter {
+            dedupe: true,
+            debounce_ms: 50,
+        };
+        let t0 = Instant::now();
+        let mut gate = ProgramChangeGate::default();
+
+        assert!(gate.admit(&[0xC0, 1], &filter, t0).is_empty());
+        let t1 = t0 + Duration::from_millis(30);
+        assert!(gate.admit(&[0xC0, 2], &filter, t1).is_empty());
+        assert!(gate.due(&filter, t0 + Duration::from_millis(60)).is_empty());
+        assert_eq!(
+            gate.due(&filter, t1 + Duration::from_millis(50)),
+            vec![vec![0xC0, 2]]
+        );
+
+        // A settled repeat of the current program is still dropped
+        let t2 = t1 + Duration::from_millis(100);
+        gate.admit(&[0xC0, 2], &filter, t2);
+        assert!(gate.due(&filter, t2 + Duration::from_millis(50)).is_empty());
+    }
+}
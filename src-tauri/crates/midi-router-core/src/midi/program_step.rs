@@ -0,0 +1,124 @@
+//! Program stepping
+//!
+//! Pairs of buttons (notes or CCs) send Program Change +1 / -1. The route
+//! follows every Program Change it sends, so stepping continues from a
+//! program selected some other way.
+
+use crate::types::{ProgramStepper, StepButton};
+use std::collections::HashMap;
+
+/// Handle a stepper button. Returns None for messages that aren't one, which
+/// route as usual; presses become a Program Change and releases are dropped.
+pub fn program_step(
+    bytes: &[u8],
+    steppers: &[ProgramStepper],
+    programs: &HashMap<u8, u8>,
+) -> Option<Vec<Vec<u8>>> {
+    let (button, pressed) = match *bytes {
+        [status, note, velocity] if status & 0xF0 == 0x90 => {
+            (StepButton::Note { note }, velocity > 0)
+        }
+        [status, note, _] if status & 0xF0 == 0x80 => (StepButton::Note { note }, false),
+        [status, cc, value] if status & 0xF0 == 0xB0 => (StepButton::Cc { cc }, value >= 64),
+        _ => return None,
+    };
+    let (stepper, up) = steppers.iter().find_map(|stepper| {
+        if stepper.increment == button {
+            Some((stepper, true))
+        } else if stepper.decrement == button {
+            Some((stepper, false))
+        } else {
+            None
+        }
+    })?;
+    if !pressed {
+        return Some(Vec::new());
+    }
+
+    let channel = stepper.channel.clamp(1, 16) - 1;
+    let current = programs.get(&channel).copied().unwrap_or(0);
+    let program = match (up, stepper.wrap) {
+        (true, true) => (current + 1) % 128,
+        (true, false) => (current + 1).min(127),
+        (false, true) => current.checked_sub(1).unwrap_or(127),
+        (false, false) => current.saturating_sub(1),
+    };
+    Some(vec![vec![0xC0 | channel, program]])
+}
+
+/// Follow a Program Change the route sent
+pub fn track_program(bytes: &[u8], programs: &mut HashMap<u8, u8>) {
+    if let [status, program] = *bytes {
+        if status & 0xF0 == 0xC0 {
+            programs.insert(status & 0x0F, program);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn stepper(wrap: bool) -> ProgramStepper {
+        ProgramStepper {
+            increment: StepButton::Note { note: 60 },
+            decrement: StepButton::Cc { cc: 80 },
+            channel: 2,
+            wrap,
+        }
+    }
+
+    #[test]
+    fn buttons_step_from_the_current_program() {
+        let steppers = vec![stepper(false)];
+        let mut programs = HashMap::new();
+
+        let up = program_step(&[0x90, 60, 100], &steppers, &programs);
+        assert_eq!(up, Some(vec![vec![0xC1, 1]]));
+        track_program(&[0xC1, 1], &mut programs);
+        // Releases are swallowed
+        assert_eq!(
+            program_step(&[0x80, 60, 0], &steppers, &programs),
+            Some(vec![])
+        );
+        assert_eq!(
+            program_step(&[0xB0, 80, 0], &steppers, &programs),
+            Some(vec![])
+        );
+
+        // A Program Change from elsewhere moves the starting point
+        track_program(&[0xC1, 40], &mut programs);
+        let down = program_step(&[0xB0, 80, 127], &steppers, &programs);
+        assert_eq!(down, Some(vec![vec![0xC1, 39]]));
+
+        // Anything else routes as usual
+        assert_eq!(program_step(&[0x90, 61, 100], &steppers, &programs), None);
+        assert_eq!(program_step(&[0xC0, 5], &steppers, &programs), None);
+    }
+
+    #[test]
+    fn ends_clamp_or_wrap() {
+        let mut programs = HashMap::new();
+        let down = [0xB0, 80, 127];
+
+        assert_eq!(
+            program_step(&down, &[stepper(false)], &programs),
+            Some(vec![vec![0xC1, 0]])
+        );
+        assert_eq!(
+            program_step(&down, &[stepper(true)], &programs),
+            Some(vec![vec![0xC1, 127]])
+        );
+
+        programs.insert(1, 127);
+        let up = [0x90, 60, 100];
+        assert_eq!(
+            program_step(&up, &[stepper(false)], &programs),
+            Some(vec![vec![0xC1, 127]])
+        );
+        assert_eq!(
+            program_step(&up, &[stepper(true)], &programs),
+            Some(vec![vec![0xC1, 0]])
+        );
+    }
+}
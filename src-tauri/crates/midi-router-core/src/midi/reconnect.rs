@@ -0,0 +1,126 @@
+//! Reconnection scheduling
+//!
+//! Ports that fail to connect are retried with exponential backoff, so a
+//! device that comes back (flaky USB hub, power cycle) is picked up again
+//! without the user touching the routes.
+
+use crate::types::PortDirection;
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+#[derive(Debug, Clone, Copy)]
+struct Retry {
+    attempts: u32,
+    next_at: Instant,
+}
+
+/// Pending reconnection attempts, keyed by port name and direction
+#[derive(Debug, Default)]
+pub struct ReconnectSchedule {
+    pending: HashMap<(String, PortDirection), Retry>,
+}
+
+impl ReconnectSchedule {
+    /// Delay before the first retry
+    pub const INITIAL_DELAY: Duration = Duration::from_millis(500);
+    /// Upper bound for the delay between retries
+    pub const MAX_DELAY: Duration = Duration::from_secs(30);
+
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Delay before retry number `attempts` (1-based)
+    pub fn delay_for(attempts: u32) -> Duration {
+        let factor = 1u32 << attempts.saturating_sub(1).min(16);
+        (Self::INITIAL_DELAY * factor).min(Self::MAX_DELAY)
+    }
+
+    /// Record a failed connection attempt and schedule the next one
+    pub fn failed(&mut self, port: &str, direction: PortDirection, now: Instant) {
+        let retry = self
+            .pending
+            .entry((port.to_string(), direction))
+            .or_insert(Retry {
+                attempts: 0,
+                next_at: now,
+            });
+        retry.attempts += 1;
+        retry.next_at = now + Self::delay_for(retry.attempts);
+    }
+
+    /// Forget a port (connected, or no longer needed)
+    pub fn clear(&mut self, port: &str, direction: PortDirection) {
+        self.pending.remove(&(port.to_string(), direction));
+    }
+
+    /// Keep only entries the predicate accepts
+    pub fn retain(&mut self, mut keep: impl FnMut(&str, PortDirection) -> bool) {
+        self.pending
+            .retain(|(port, direction), _| keep(port, *direction));
+    }
+
+    /// Ports whose next attempt is due
+    pub fn due(&self, now: Instant) -> Vec<(String, PortDirection)> {
+        self.pending
+            .iter()
+            .filter(|(_, retry)| retry.next_at <= now)
+            .map(|(key, _)| key.clone())
+            .collect()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.pending.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn delay_doubles_up_to_max() {
+        assert_eq!(ReconnectSchedule::delay_for(1), Duration::from_millis(500));
+        assert_eq!(ReconnectSchedule::delay_for(2), Duration::from_secs(1));
+        assert_eq!(ReconnectSchedule::delay_for(3), Duration::from_secs(2));
+        assert_eq!(
+            ReconnectSchedule::delay_for(10),
+            ReconnectSchedule::MAX_DELAY
+        );
+        assert_eq!(
+            ReconnectSchedule::delay_for(100),
+            ReconnectSchedule::MAX_DELAY
+        );
+    }
+
+    #[test]
+    fn failures_back_off() {
+        let t0 = Instant::now();
+        let mut schedule = ReconnectSchedule::new();
+        schedule.failed("Synth", PortDirection::Output, t0);
+        assert!(schedule.due(t0).is_empty());
+        assert_eq!(schedule.due(t0 + Duration::from_millis(500)).len(), 1);
+
+        // Second failure waits twice as long
+        let t1 = t0 + Duration::from_millis(500);
+        schedule.failed("Synth", PortDirection::Output, t1);
+        assert!(schedule.due(t1 + Duration::from_millis(999)).is_empty());
+        assert_eq!(
+            schedule.due(t1 + Duration::from_secs(1)),
+            vec![("Synth".to_string(), PortDirection::Output)]
+        );
+    }
+
+    #[test]
+    fn clear_and_retain_remove_entries() {
+        let t0 = Instant::now();
+        let mut schedule = ReconnectSchedule::new();
+        schedule.failed("Keys", PortDirection::Input, t0);
+        schedule.failed("Synth", PortDirection::Output, t0);
+
+        schedule.clear("Keys", PortDirection::Input);
+        assert!(!schedule.is_empty());
+        schedule.retain(|_, direction| direction == PortDirection::Input);
+        assert!(schedule.is_empty());
+    }
+}
@@ -0,0 +1,140 @@
+//! Replay of a captured session through the current routes
+//!
+//! Messages come back out with the spacing they were captured with, so a
+//! routing bug from a user's debug bundle can be reproduced message for
+//! message.
+
+use crate::midi::route_state::RouteStates;
+use crate::types::DebugCapture;
+use std::collections::VecDeque;
+use std::time::{Duration, Instant};
+
+/// A captured message waiting for its turn
+#[derive(Debug)]
+struct Pending {
+    offset: Duration,
+    port: String,
+    bytes: Vec<u8>,
+}
+
+/// Plays a capture's incoming messages back in real time
+#[derive(Debug)]
+pub struct Replay {
+    pending: VecDeque<Pending>,
+    total: usize,
+    started: Instant,
+}
+
+impl Replay {
+    pub fn new(capture: &DebugCapture, now: Instant) -> Self {
+        let mut entries: Vec<_> = capture.entries.iter().collect();
+        entries.sort_by_key(|entry| entry.timestamp_us);
+        let first = entries.first().map_or(0, |entry| entry.timestamp_us);
+        let pending: VecDeque<Pending> = entries
+            .into_iter()
+            .map(|entry| Pending {
+                offset: Duration::from_micros(entry.timestamp_us - first),
+                port: entry.port.clone(),
+                bytes: entry.bytes.clone(),
+            })
+            .collect();
+        Self {
+            total: pending.len(),
+            pending,
+            started: now,
+        }
+    }
+
+    /// Messages whose captured time has come, as (microseconds since the
+    /// first message, port, bytes)
+    pub fn due(&mut self, now: Instant) -> Vec<(u64, String, Vec<u8>)> {
+        let elapsed = now.saturating_duration_since(self.started);
+        let mut due = Vec::new();
+        while let Some(pending) = self.pending.pop_front() {
+            if pending.offset > elapsed {
+                self.pending.push_front(pending);
+                break;
+            }
+            due.push((
+                pending.offset.as_micros() as u64,
+                pending.port,
+                pending.bytes,
+            ));
+        }
+        due
+    }
+
+    /// How many messages have been played back so far
+    pub fn replayed(&self) -> usize {
+        self.total - self.pending.len()
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.pending.is_empty()
+    }
+}
+
+/// A replay that sends nothing. Routes run on their own state, leaving the
+/// live routes alone, and what they would have sent is traced.
+#[derive(Debug, Default)]
+pub struct DryRun {
+    pub states: RouteStates,
+    pub capture: DebugCapture,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::{CaptureEntry, CaptureHandling};
+
+    fn entry(timestamp_us: u64, note: u8) -> CaptureEntry {
+        CaptureEntry {
+            timestamp_us,
+            port: "Keys".to_string(),
+            bytes: vec![0x90, note, 100],
+            handling: CaptureHandling::Routed,
+            routes: Vec::new(),
+        }
+    }
+
+    fn capture(entries: Vec<CaptureEntry>) -> DebugCapture {
+        DebugCapture {
+            entries,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn keeps_the_captured_spacing() {
+        let start = Instant::now();
+        let mut replay = Replay::new(
+            &capture(vec![entry(5_000_000, 60), entry(5_250_000, 62)]),
+            start,
+        );
+
+        let first = replay.due(start);
+        assert_eq!(first, vec![(0, "Keys".to_string(), vec![0x90, 60, 100])]);
+        assert!(replay.due(start + Duration::from_millis(249)).is_empty());
+        let second = replay.due(start + Duration::from_millis(250));
+        assert_eq!(second[0].0, 250_000);
+        assert_eq!(replay.replayed(), 2);
+        assert!(replay.is_finished());
+    }
+
+    #[test]
+    fn plays_out_of_order_entries_by_timestamp() {
+        let start = Instant::now();
+        let mut replay = Replay::new(&capture(vec![entry(2_000, 62), entry(1_000, 60)]), start);
+
+        let due = replay.due(start + Duration::from_secs(1));
+        let notes: Vec<u8> = due.iter().map(|(_, _, bytes)| bytes[1]).collect();
+        assert_eq!(notes, vec![60, 62]);
+    }
+
+    #[test]
+    fn empty_capture_finishes_at_once() {
+        let mut replay = Replay::new(&DebugCapture::default(), Instant::now());
+        assert!(replay.due(Instant::now()).is_empty());
+        assert!(replay.is_finished());
+    }
+}
@@ -0,0 +1,87 @@
+//! Retrospective capture
+//!
+//! Always keeps the last few minutes of incoming MIDI, so a take played
+//! before anyone hit record can still be saved.
+
+use std::collections::VecDeque;
+use std::time::{Duration, Instant};
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct RecordedMessage {
+    pub port: String,
+    /// Microseconds since the oldest message kept
+    pub offset_us: u64,
+    pub bytes: Vec<u8>,
+}
+
+#[derive(Debug, Default)]
+pub struct RetrospectiveBuffer {
+    messages: VecDeque<(Instant, String, Vec<u8>)>,
+}
+
+impl RetrospectiveBuffer {
+    /// How far back the buffer reaches
+    pub const WINDOW: Duration = Duration::from_secs(10 * 60);
+    /// Messages kept at most; the oldest are dropped beyond this
+    const MAX_MESSAGES: usize = 500_000;
+
+    /// Keep an incoming message. Clock and Active Sensing aren't musical
+    /// content and would crowd out the take, so they're skipped.
+    pub fn push(&mut self, now: Instant, port: &str, bytes: &[u8]) {
+        if matches!(bytes.first(), None | Some(0xF8) | Some(0xFE)) {
+            return;
+        }
+        while let Some((at, _, _)) = self.messages.front() {
+            if now.duration_since(*at) <= Self::WINDOW && self.messages.len() < Self::MAX_MESSAGES {
+                break;
+            }
+            self.messages.pop_front();
+        }
+        self.messages
+            .push_back((now, port.to_string(), bytes.to_vec()));
+    }
+
+    /// Everything still in the window, oldest first
+    pub fn messages(&self, now: Instant) -> Vec<RecordedMessage> {
+        let mut kept = self
+            .messages
+            .iter()
+            .filter(|(at, _, _)| now.duration_since(*at) <= Self::WINDOW)
+            .peekable();
+        let Some(start) = kept.peek().map(|(at, _, _)| *at) else {
+            return Vec::new();
+        };
+        kept.map(|(at, port, bytes)| RecordedMessage {
+            port: port.clone(),
+            offset_us: at.duration_since(start).as_micros() as u64,
+            bytes: bytes.clone(),
+        })
+        .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn keeps_only_the_window() {
+        let t0 = Instant::now();
+        let mut buffer = RetrospectiveBuffer::default();
+        buffer.push(t0, "Keys", &[0x90, 60, 100]);
+        buffer.push(t0, "Keys", &[0xF8]);
+        buffer.push(t0 + Duration::from_secs(60), "Keys", &[0x80, 60, 0]);
+        buffer.push(t0 + Duration::from_secs(61), "Pads", &[0x99, 36, 127]);
+
+        let messages = buffer.messages(t0 + Duration::from_secs(61));
+        assert_eq!(messages.len(), 3);
+        assert_eq!(messages[1].offset_us, 60_000_000);
+
+        let later = t0 + RetrospectiveBuffer::WINDOW + Duration::from_secs(30);
+        let messages = buffer.messages(later);
+        assert_eq!(messages.len(), 2);
+        assert_eq!(messages[0].bytes, vec![0x80, 60, 0]);
+        assert_eq!(messages[0].offset_us, 0);
+        assert_eq!(messages[1].port, "Pads");
+    }
+}
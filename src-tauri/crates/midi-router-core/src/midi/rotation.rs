@@ -0,0 +1,129 @@
+//! Round-robin channel rotation
+//!
+//! Deals each Note On to the next channel in a rotation, so a device with
+//! several mono-timbral parts plays polyphonically. Note Offs follow their
+//! note to the channel it went to, and channel-wide messages (CCs, pitch
+//! bend, pressure) go to every channel in the rotation.
+
+use crate::types::ChannelRotation;
+use std::collections::HashMap;
+
+#[derive(Debug, Default)]
+pub struct RotationState {
+    /// Sounding notes: (input channel, note) -> output channel
+    voices: HashMap<(u8, u8), u8>,
+    /// Index of the channel the next Note On goes to
+    next: usize,
+}
+
+/// Pass a message through the rotation. System messages pass unchanged.
+pub fn rotate(bytes: &[u8], rotation: &ChannelRotation, state: &mut RotationState) -> Vec<Vec<u8>> {
+    let channels: Vec<u8> = rotation
+        .channels
+        .iter()
+        .filter(|ch| (1..=16).contains(*ch))
+        .map(|ch| ch - 1)
+        .collect();
+    let status = match bytes.first() {
+        Some(status) if !channels.is_empty() && (0x80..0xF0).contains(status) => *status,
+        _ => return vec![bytes.to_vec()],
+    };
+    let on_channel = |channel: u8| {
+        let mut msg = bytes.to_vec();
+        msg[0] = (status & 0xF0) | channel;
+        msg
+    };
+
+    match (status & 0xF0, bytes) {
+        (0x90, [_, note, velocity]) if *velocity > 0 => {
+            let key = (status & 0x0F, *note);
+            // A retriggered note stays where it's sounding
+            let channel = match state.voices.get(&key) {
+                Some(channel) => *channel,
+                None => {
+                    let channel = channels[state.next % channels.len()];
+                    state.next = (state.next + 1) % channels.len();
+                    state.voices.insert(key, channel);
+                    channel
+                }
+            };
+            vec![on_channel(channel)]
+        }
+        (0x80 | 0x90, [_, note, _]) => match state.voices.remove(&(status & 0x0F, *note)) {
+            Some(channel) => vec![on_channel(channel)],
+            None => vec![bytes.to_vec()],
+        },
+        (0xA0, [_, note, _]) => match state.voices.get(&(status & 0x0F, *note)) {
+            Some(channel) => vec![on_channel(*channel)],
+            None => vec![bytes.to_vec()],
+        },
+        _ => channels
+            .iter()
+            .map(|channel| on_channel(*channel))
+            .collect(),
+    }
+}
+
+/// Note Offs for every sounding note, on the channel it was dealt to
+pub fn release_all(state: &mut RotationState) -> Vec<Vec<u8>> {
+    let mut out: Vec<Vec<u8>> = state
+        .voices
+        .drain()
+        .map(|((_, note), channel)| vec![0x80 | channel, note, 0])
+        .collect();
+    out.sort();
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rotation() -> ChannelRotation {
+        ChannelRotation {
+            channels: vec![2, 3, 4],
+        }
+    }
+
+    #[test]
+    fn deals_notes_and_follows_note_offs() {
+        let mut state = RotationState::default();
+        let mut play = |bytes: &[u8]| rotate(bytes, &rotation(), &mut state);
+
+        assert_eq!(play(&[0x90, 60, 100]), vec![vec![0x91, 60, 100]]);
+        assert_eq!(play(&[0x90, 64, 100]), vec![vec![0x92, 64, 100]]);
+        assert_eq!(play(&[0x90, 67, 100]), vec![vec![0x93, 67, 100]]);
+        assert_eq!(play(&[0x80, 64, 0]), vec![vec![0x82, 64, 0]]);
+        // Back around to the first channel
+        assert_eq!(play(&[0x90, 72, 100]), vec![vec![0x91, 72, 100]]);
+        // Velocity 0 is a Note Off too, and keeps its status
+        assert_eq!(play(&[0x90, 60, 0]), vec![vec![0x91, 60, 0]]);
+        assert_eq!(play(&[0xA0, 67, 50]), vec![vec![0xA3, 67, 50]]);
+    }
+
+    #[test]
+    fn channel_wide_messages_reach_every_part() {
+        let mut state = RotationState::default();
+        assert_eq!(
+            rotate(&[0xB0, 64, 127], &rotation(), &mut state),
+            vec![
+                vec![0xB1, 64, 127],
+                vec![0xB2, 64, 127],
+                vec![0xB3, 64, 127]
+            ]
+        );
+        assert_eq!(rotate(&[0xF8], &rotation(), &mut state), vec![vec![0xF8]]);
+    }
+
+    #[test]
+    fn release_all_silences_dealt_notes() {
+        let mut state = RotationState::default();
+        rotate(&[0x90, 60, 100], &rotation(), &mut state);
+        rotate(&[0x90, 62, 100], &rotation(), &mut state);
+        assert_eq!(
+            release_all(&mut state),
+            vec![vec![0x81, 60, 0], vec![0x82, 62, 0]]
+        );
+        assert!(release_all(&mut state).is_empty());
+    }
+}
@@ -0,0 +1,363 @@
+//! Per-route runtime state
+//!
+//! Stateful transforms keep their state here, owned by the engine and keyed by
+//! route ID so it survives route edits but is dropped when a route is removed.
+
+use crate::midi::bank_select::BankSelectState;
+use crate::midi::duplicates::DuplicateGate;
+use crate::midi::harmony::HarmonyState;
+use crate::midi::humanize::Humanizer;
+use crate::midi::merge::NoteMerge;
+use crate::midi::mono::MonoVoice;
+use crate::midi::notes::SoundingNotes;
+use crate::midi::program_change::ProgramChangeGate;
+use crate::midi::rotation::RotationState;
+use crate::midi::sustain::SustainState;
+use crate::midi::voices::VoiceAllocator;
+use crate::types::{
+    HeldNotes, PortDirection, Route, RouteStats, RouteStatus, RouteStatusChange, TuningTable,
+};
+use std::collections::{BTreeSet, HashMap, HashSet};
+use std::time::Duration;
+use uuid::Uuid;
+
+/// Mutable state for a single route
+#[derive(Debug, Default)]
+pub struct RouteState {
+    /// Current output of toggle-mode CC targets, keyed by (source CC, target index)
+    pub cc_toggles: HashMap<(u8, usize), bool>,
+    /// Whether threshold-mode note triggers are on, keyed by (source CC, target index)
+    pub note_triggers_on: HashMap<(u8, usize), bool>,
+    /// Current output of toggle-mode note mappings, keyed by mapping index
+    pub note_toggles: HashMap<usize, bool>,
+    /// Absolute value tracked for relative-encoder mappings, keyed by source CC
+    pub encoder_values: HashMap<u8, u8>,
+    /// Last 14-bit value sent by high-res CC targets, keyed by (source CC, target index)
+    pub high_res_values: HashMap<(u8, usize), u16>,
+    /// Messages a transform wants sent to the destination after a delay.
+    /// Drained and scheduled by the engine after each routed message.
+    pub delayed: Vec<(Duration, Vec<u8>)>,
+    pub forwarded: u64,
+    pub dropped: u64,
+    /// Wall-clock microseconds of the last message from the route's source
+    pub last_activity: Option<u64>,
+    pub status: RouteStatus,
+    /// Notes the route has left held on its destination
+    pub sounding: SoundingNotes,
+    /// Pitch-bend microtuning voices: (input channel, note) -> (output channel, note)
+    pub tuning_voices: HashMap<(u8, u8), (u8, u8)>,
+    /// Where the search for a free microtuning channel starts
+    pub next_tuning_channel: usize,
+    /// Tuning last sent to the destination as MTS SysEx
+    pub mts_sent: Option<TuningTable>,
+    /// Notes held by the latch, as (channel, note)
+    pub latched: BTreeSet<(u8, u8)>,
+    /// Sustain pedals down and the Note Offs they hold back
+    pub sustain: SustainState,
+    /// Mono converter voices, keyed by channel
+    pub mono_voices: HashMap<u8, MonoVoice>,
+    /// Program Changes sent and waiting out a debounce
+    pub program_changes: ProgramChangeGate,
+    /// Program each destination channel is on, as far as the route has
+    /// sent, for program steppers to step from
+    pub programs: HashMap<u8, u8>,
+    /// Bank changes waiting for their Program Change
+    pub bank_select: BankSelectState,
+    /// Notes dealt out by channel rotation
+    pub rotation: RotationState,
+    /// Harmony voices sounding for each played note
+    pub harmony: HarmonyState,
+    /// Notes playing on voice split outputs
+    pub voices: VoiceAllocator,
+    /// Recent notes and messages, for the duplicate filter
+    pub duplicates: DuplicateGate,
+    /// Notes the velocity gate or layer dropped, as (channel, note)
+    pub velocity_gated: HashSet<(u8, u8)>,
+    /// Random source for velocity humanizing, made on first use
+    pub humanizer: Option<Humanizer>,
+}
+
+impl RouteState {
+    /// Forget every note the route's transforms hold, after a panic has
+    /// silenced them. A latched note then plays again on its next press
+    /// instead of being released.
+    pub fn forget_held_notes(&mut self) {
+        self.sounding = SoundingNotes::default();
+        self.tuning_voices.clear();
+        self.latched.clear();
+        self.sustain = SustainState::default();
+        self.mono_voices.clear();
+        self.rotation = RotationState::default();
+        self.harmony = HarmonyState::default();
+        self.voices = VoiceAllocator::default();
+        self.velocity_gated.clear();
+    }
+}
+
+/// Runtime state for all routes
+#[derive(Debug, Default)]
+pub struct RouteStates {
+    states: HashMap<Uuid, RouteState>,
+    /// Which routes hold each note on each destination
+    merge: NoteMerge,
+}
+
+impl RouteStates {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Get (or create) the state for a route
+    pub fn get_mut(&mut self, route_id: Uuid) -> &mut RouteState {
+        self.states.entry(route_id).or_default()
+    }
+
+    /// A route's state along with the note holds shared by all routes
+    pub fn get_mut_with_merge(&mut self, route_id: Uuid) -> (&mut RouteState, &mut NoteMerge) {
+        (self.states.entry(route_id).or_default(), &mut self.merge)
+    }
+
+    /// Forget the notes one route holds, after a panic on it
+    pub fn forget_route_notes(&mut self, route_id: Uuid) {
+        self.get_mut(route_id).forget_held_notes();
+        self.merge.forget_route(route_id);
+    }
+
+    /// Forget held notes on every route, for a panic on all outputs
+    pub fn forget_held_notes(&mut self) {
+        for state in self.states.values_mut() {
+            state.forget_held_notes();
+        }
+        self.merge.clear();
+    }
+
+    /// Drop state for routes that no longer exist
+    pub fn retain_routes(&mut self, routes: &[Route]) {
+        self.states
+            .retain(|id, _| routes.iter().any(|r| r.id == *id));
+        self.merge
+            .retain_routes(|id| routes.iter().any(|r| r.id == id));
+    }
+
+    /// Counters for each route, in route order (zero for routes with no traffic yet)
+    pub fn stats(&self, routes: &[Route]) -> Vec<RouteStats> {
+        routes
+            .iter()
+            .map(|route| {
+                let state = self.states.get(&route.id);
+                RouteStats {
+                    route_id: route.id,
+                    status: state.map_or(RouteStatus::Active, |s| s.status),
+                    forwarded: state.map_or(0, |s| s.forwarded),
+                    dropped: state.map_or(0, |s| s.dropped),
+                    last_activity: state.and_then(|s| s.last_activity),
+                }
+            })
+            .collect()
+    }
+
+    /// Recompute route status from port health: enabled routes with a port
+    /// that is not present are pending, routes whose destination has failed
+    /// are errored, everything else is active.
+    /// Returns the routes whose status changed.
+    pub fn update_status(
+        &mut self,
+        routes: &[Route],
+        pending_ports: &HashSet<(String, PortDirection)>,
+        failed_outputs: &HashSet<String>,
+    ) -> Vec<RouteStatusChange> {
+        let mut changes = Vec::new();
+        for route in routes {
+            let source = (route.source.name.clone(), PortDirection::Input);
+            let destination = (route.destination.name.clone(), PortDirection::Output);
+            let status = if !route.enabled {
+                RouteStatus::Active
+            } else if pending_ports.contains(&source) || pending_ports.contains(&destination) {
+                RouteStatus::Pending
+            } else if failed_outputs.contains(&route.destination.name) {
+                RouteStatus::Errored
+            } else {
+                RouteStatus::Active
+            };
+            let state = self.get_mut(route.id);
+            if state.status != status {
+                state.status = status;
+                changes.push(RouteStatusChange {
+                    route_id: route.id,
+                    status,
+                });
+            }
+        }
+        changes
+    }
+
+    /// Held notes for each route and channel, in route order
+    pub fn sounding_notes(&self, routes: &[Route]) -> Vec<HeldNotes> {
+        routes
+            .iter()
+            .filter_map(|route| Some((route, self.states.get(&route.id)?)))
+            .flat_map(|(route, state)| {
+                state
+                    .sounding
+                    .by_channel()
+                    .map(|(channel, notes)| HeldNotes {
+                        route_id: route.id,
+                        destination: route.destination.name.clone(),
+                        channel,
+                        notes,
+                    })
+            })
+            .collect()
+    }
+
+    pub fn len(&self) -> usize {
+        self.states.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.states.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::PortId;
+
+    #[test]
+    fn retain_routes_drops_removed_routes() {
+        let kept = Route::new(PortId::new("A".to_string()), PortId::new("B".to_string()));
+        let removed = Route::new(PortId::new("C".to_string()), PortId::new("D".to_string()));
+
+        let mut states = RouteStates::new();
+        states.get_mut(kept.id).cc_toggles.insert((1, 0), true);
+        states.get_mut(removed.id);
+        assert_eq!(states.len(), 2);
+
+        states.retain_routes(std::slice::from_ref(&kept));
+        assert_eq!(states.len(), 1);
+        assert_eq!(states.get_mut(kept.id).cc_toggles.get(&(1, 0)), Some(&true));
+    }
+
+    #[test]
+    fn panic_forgets_latched_notes() {
+        let route = Route::new(PortId::new("A".to_string()), PortId::new("B".to_string()));
+        let mut states = RouteStates::new();
+        let state = states.get_mut(route.id);
+        state.latched.insert((0, 60));
+        state.sounding.track(&[0x90, 60, 100]);
+
+        states.forget_held_notes();
+        let state = states.get_mut(route.id);
+        assert!(state.latched.is_empty());
+        assert_eq!(state.sounding.channels().count(), 0);
+    }
+
+    #[test]
+    fn stats_cover_every_route() {
+        let active = Route::new(PortId::new("A".to_string()), PortId::new("B".to_string()));
+        let idle = Route::new(PortId::new("C".to_string()), PortId::new("D".to_string()));
+
+        let mut states = RouteStates::new();
+        let state = states.get_mut(active.id);
+        state.forwarded = 3;
+        state.dropped = 1;
+        state.last_activity = Some(42);
+
+        let stats = states.stats(&[active.clone(), idle.clone()]);
+        assert_eq!(stats.len(), 2);
+        assert_eq!(stats[0].route_id, active.id);
+        assert_eq!(stats[0].forwarded, 3);
+        assert_eq!(stats[0].dropped, 1);
+        assert_eq!(stats[0].last_activity, Some(42));
+        assert_eq!(stats[1].forwarded, 0);
+        assert_eq!(stats[1].last_activity, None);
+    }
+
+    #[test]
+    fn routes_to_failed_outputs_are_errored_until_they_recover() {
+        let to_synth = Route::new(
+            PortId::new("Keys".to_string()),
+            PortId::new("Synth".to_string()),
+        );
+        let to_drums = Route::new(
+            PortId::new("Keys".to_string()),
+            PortId::new("Drums".to_string()),
+        );
+        let routes = vec![to_synth.clone(), to_drums];
+        let mut states = RouteStates::new();
+
+        let failed: HashSet<String> = ["Synth".to_string()].into_iter().collect();
+        assert_eq!(
+            states.update_status(&routes, &HashSet::new(), &failed),
+            vec![RouteStatusChange {
+                route_id: to_synth.id,
+                status: RouteStatus::Errored,
+            }]
+        );
+        // No change reported while the output stays down
+        assert!(states
+            .update_status(&routes, &HashSet::new(), &failed)
+            .is_empty());
+        assert_eq!(states.stats(&routes)[0].status, RouteStatus::Errored);
+
+        let changes = states.update_status(&routes, &HashSet::new(), &HashSet::new());
+        assert_eq!(changes.len(), 1);
+        assert_eq!(changes[0].status, RouteStatus::Active);
+    }
+
+    #[test]
+    fn routes_with_missing_ports_are_pending() {
+        let route = Route::new(
+            PortId::new("Keys".to_string()),
+            PortId::new("Synth".to_string()),
+        );
+        let routes = vec![route.clone()];
+        let mut states = RouteStates::new();
+
+        let pending = HashSet::from([("Keys".to_string(), PortDirection::Input)]);
+        // A missing device takes precedence over a failed destination
+        let failed = HashSet::from(["Synth".to_string()]);
+        let changes = states.update_status(&routes, &pending, &failed);
+        assert_eq!(changes[0].status, RouteStatus::Pending);
+
+        // Device appeared
+        let changes = states.update_status(&routes, &HashSet::new(), &HashSet::new());
+        assert_eq!(changes[0].status, RouteStatus::Active);
+    }
+
+    #[test]
+    fn sounding_notes_per_route_and_channel() {
+        let route = Route::new(
+            PortId::new("Keys".to_string()),
+            PortId::new("Synth".to_string()),
+        );
+        let idle = Route::new(
+            PortId::new("Pads".to_string()),
+            PortId::new("Drums".to_string()),
+        );
+        let mut states = RouteStates::new();
+        let sounding = &mut states.get_mut(route.id).sounding;
+        sounding.track(&[0x90, 64, 100]);
+        sounding.track(&[0x90, 60, 100]);
+        sounding.track(&[0x92, 48, 100]);
+
+        assert_eq!(
+            states.sounding_notes(&[route.clone(), idle]),
+            vec![
+                HeldNotes {
+                    route_id: route.id,
+                    destination: "Synth".to_string(),
+                    channel: 0,
+                    notes: vec![60, 64],
+                },
+                HeldNotes {
+                    route_id: route.id,
+                    destination: "Synth".to_string(),
+                    channel: 2,
+                    notes: vec![48],
+                },
+            ]
+        );
+    }
+}
@@ -0,0 +1,1564 @@
+//! Route matching and message forwarding
+
+use crate::midi::msc::parse_msc;
+use crate::midi::route_state::RouteState;
+use crate::types::{
+    BankProgram, CcNoteTrigger, CcTarget, CcValueMode, ChannelMap, KeyRange, Layer, MessageKind,
+    MidiActivity, NoteCcMode, NoteMapping, RealTimeStrip, Route, SystemCommon, SystemCommonFilter,
+    VelocityTransform,
+};
+use std::collections::{HashMap, HashSet};
+use std::time::Duration;
+use wmidi::MidiMessage;
+
+pub fn parse_midi_message(timestamp: u64, port: &str, bytes: &[u8]) -> Option<MidiActivity> {
+    // Handle system real-time messages first (single byte, 0xF8-0xFF)
+    // These may not be parsed by wmidi but are important for transport
+    if bytes.len() == 1 {
+        let kind = match bytes[0] {
+            0xF8 => Some(MessageKind::Clock),
+            0xFA => Some(MessageKind::Start),
+            0xFB => Some(MessageKind::Continue),
+            0xFC => Some(MessageKind::Stop),
+            _ => None,
+        };
+        if let Some(kind) = kind {
+            return Some(MidiActivity {
+                timestamp,
+                delta_us: None,
+                port: port.to_string(),
+                channel: None,
+                kind,
+                raw: bytes.to_vec(),
+                label: None,
+                note_name: None,
+            });
+        }
+    }
+
+    // MIDI Show Control is SysEx, but worth decoding for the monitor
+    if let Some(msc) = parse_msc(bytes) {
+        return Some(MidiActivity {
+            timestamp,
+            delta_us: None,
+            port: port.to_string(),
+            channel: None,
+            kind: MessageKind::ShowControl {
+                device_id: msc.device_id,
+                command: msc.command,
+                cue_number: msc.cue_number,
+                cue_list: msc.cue_list,
+            },
+            raw: bytes.to_vec(),
+            label: None,
+            note_name: None,
+        });
+    }
+
+    let msg = MidiMessage::try_from(bytes).ok()?;
+
+    let (channel, kind) = match msg {
+        MidiMessage::NoteOn(ch, note, vel) => (
+            Some(ch.index()),
+            MessageKind::NoteOn {
+                note: u8::from(note),
+                velocity: u8::from(vel),
+            },
+        ),
+        MidiMessage::NoteOff(ch, note, vel) => (
+            Some(ch.index()),
+            MessageKind::NoteOff {
+                note: u8::from(note),
+                velocity: u8::from(vel),
+            },
+        ),
+        MidiMessage::ControlChange(ch, ctrl, val) => (
+            Some(ch.index()),
+            MessageKind::ControlChange {
+                controller: u8::from(ctrl),
+                value: u8::from(val),
+            },
+        ),
+        MidiMessage::ProgramChange(ch, prog) => (
+            Some(ch.index()),
+            MessageKind::ProgramChange {
+                program: u8::from(prog),
+            },
+        ),
+        MidiMessage::PitchBendChange(ch, bend) => (
+            Some(ch.index()),
+            MessageKind::PitchBend {
+                value: u16::from(bend),
+            },
+        ),
+        MidiMessage::ChannelPressure(ch, val) => (
+            Some(ch.index()),
+            MessageKind::Aftertouch {
+                value: u8::from(val),
+            },
+        ),
+        MidiMessage::PolyphonicKeyPressure(ch, note, val) => (
+            Some(ch.index()),
+            MessageKind::PolyAftertouch {
+                note: u8::from(note),
+                value: u8::from(val),
+            },
+        ),
+        MidiMessage::SysEx(_) => (None, MessageKind::SysEx),
+        MidiMessage::SongSelect(song) => (
+            None,
+            MessageKind::SongSelect {
+                song: u8::from(song),
+            },
+        ),
+        MidiMessage::TimingClock => (None, MessageKind::Clock),
+        MidiMessage::Start => (None, MessageKind::Start),
+        MidiMessage::Continue => (None, MessageKind::Continue),
+        MidiMessage::Stop => (None, MessageKind::Stop),
+        _ => (None, MessageKind::Other),
+    };
+
+    Some(MidiActivity {
+        timestamp,
+        delta_us: None,
+        port: port.to_string(),
+        channel,
+        kind,
+        raw: bytes.to_vec(),
+        label: None,
+        note_name: None,
+    })
+}
+
+pub fn get_channel_from_bytes(bytes: &[u8]) -> Option<u8> {
+    if bytes.is_empty() {
+        return None;
+    }
+    let status = bytes[0];
+    // Channel messages have status 0x80-0xEF, channel is low nibble
+    if (0x80..0xF0).contains(&status) {
+        Some(status & 0x0F)
+    } else {
+        None
+    }
+}
+
+pub fn should_route(bytes: &[u8], filter: &crate::types::ChannelFilter) -> bool {
+    match get_channel_from_bytes(bytes) {
+        Some(ch) => filter.passes(ch),
+        None => true, // System messages always pass
+    }
+}
+
+/// Check whether a message passes a route's System Common filter.
+/// Other messages always pass.
+pub fn should_route_system_common(bytes: &[u8], filter: &SystemCommonFilter) -> bool {
+    let status = bytes.first().copied().unwrap_or(0);
+    match SystemCommon::from_status(status) {
+        Some(message) => filter.passes(message),
+        None => true,
+    }
+}
+
+/// Check whether a message passes a route's real-time stripping.
+/// Other messages always pass.
+pub fn should_route_real_time(bytes: &[u8], strip: &RealTimeStrip) -> bool {
+    !matches!(*bytes, [status] if strip.strips(status))
+}
+
+/// Check whether a message passes a route's key range. Messages without a
+/// note number always pass.
+pub fn should_route_key_range(bytes: &[u8], range: Option<&KeyRange>) -> bool {
+    match (range, bytes) {
+        (Some(range), &[status, note, _]) if matches!(status & 0xF0, 0x80 | 0x90 | 0xA0) => {
+            range.contains(note)
+        }
+        _ => true,
+    }
+}
+
+/// Check whether a message passes a route's velocity gate. Note Ons softer
+/// than the gate are dropped and remembered, as (channel, note), so their
+/// Note Offs and poly aftertouch are dropped too.
+pub fn should_route_velocity(
+    bytes: &[u8],
+    gate: Option<u8>,
+    gated: &mut HashSet<(u8, u8)>,
+) -> bool {
+    match gate {
+        Some(gate) => should_route_velocity_zone(bytes, gate, 127, gated),
+        None => true,
+    }
+}
+
+/// Check whether a message passes the velocity zone of a route's layer,
+/// remembering dropped notes like `should_route_velocity`
+pub fn should_route_layer(
+    bytes: &[u8],
+    layer: Option<&Layer>,
+    gated: &mut HashSet<(u8, u8)>,
+) -> bool {
+    match layer {
+        Some(layer) => {
+            should_route_velocity_zone(bytes, layer.low_velocity, layer.high_velocity, gated)
+        }
+        None => true,
+    }
+}
+
+fn should_route_velocity_zone(
+    bytes: &[u8],
+    low: u8,
+    high: u8,
+    gated: &mut HashSet<(u8, u8)>,
+) -> bool {
+    let &[status, note, value] = bytes else {
+        return true;
+    };
+    let key = (status & 0x0F, note);
+    match status & 0xF0 {
+        0x90 if value > 0 => {
+            if !(low..=high).contains(&value) {
+                gated.insert(key);
+                return false;
+            }
+            gated.remove(&key);
+            true
+        }
+        0x80 | 0x90 => !gated.remove(&key),
+        0xA0 => !gated.contains(&key),
+        _ => true,
+    }
+}
+
+/// Check if a message is channel pressure or poly aftertouch
+pub fn is_aftertouch(bytes: &[u8]) -> bool {
+    matches!(bytes.first().map(|status| status & 0xF0), Some(0xA0 | 0xD0))
+}
+
+/// Check if a message is a Control Change message
+pub fn is_cc_message(bytes: &[u8]) -> bool {
+    if bytes.len() >= 3 {
+        let status = bytes[0];
+        // CC messages have status 0xB0-0xBF
+        (status & 0xF0) == 0xB0
+    } else {
+        false
+    }
+}
+
+/// Bank Select MSB and LSB (where set) followed by the Program Change, in
+/// the order destinations expect them. `channel` is 0-15.
+pub fn bank_program_messages(channel: u8, selected: &BankProgram) -> Vec<Vec<u8>> {
+    let channel = channel & 0x0F;
+    let mut out = Vec::new();
+    if let Some(msb) = selected.msb {
+        out.push(vec![0xB0 | channel, 0, msb.min(127)]);
+    }
+    if let Some(lsb) = selected.lsb {
+        out.push(vec![0xB0 | channel, 32, lsb.min(127)]);
+    }
+    out.push(vec![0xC0 | channel, selected.program.min(127)]);
+    out
+}
+
+/// Move a channel voice message to its mapped channel. System messages are
+/// unchanged.
+pub fn remap_channel(bytes: &[u8], map: &ChannelMap) -> Vec<u8> {
+    match bytes.first() {
+        Some(&status) if (0x80..0xF0).contains(&status) => {
+            let mut msg = bytes.to_vec();
+            msg[0] = (status & 0xF0) | map.output_channel(status & 0x0F);
+            msg
+        }
+        _ => bytes.to_vec(),
+    }
+}
+
+/// Reshape a Note On's velocity. Note Ons with velocity 0 and other
+/// messages are unchanged.
+pub fn apply_velocity_curve(bytes: &[u8], transform: &VelocityTransform) -> Vec<u8> {
+    match *bytes {
+        [status, note, velocity] if status & 0xF0 == 0x90 && velocity > 0 => {
+            vec![status, note, transform.apply(velocity)]
+        }
+        _ => bytes.to_vec(),
+    }
+}
+
+/// Apply CC mappings to transform incoming CC messages.
+/// Returns a list of output messages (may be empty, one, or multiple).
+/// Non-CC messages are returned unchanged.
+///
+/// Stateless variant: toggle-mode targets always start from "off".
+pub fn apply_cc_mappings(bytes: &[u8], route: &Route) -> Vec<Vec<u8>> {
+    apply_cc_mappings_with_state(bytes, route, &mut RouteState::default())
+}
+
+/// Apply CC mappings, keeping stateful transforms (toggle mode) in `state`.
+pub fn apply_cc_mappings_with_state(
+    bytes: &[u8],
+    route: &Route,
+    state: &mut RouteState,
+) -> Vec<Vec<u8>> {
+    // Non-CC messages always pass through unchanged
+    if !is_cc_message(bytes) {
+        return vec![bytes.to_vec()];
+    }
+
+    let cc_num = bytes[1];
+    let value = bytes[2];
+
+    // Check if this CC has mappings
+    if let Some(mapping) = route.cc_mappings.iter().find(|m| m.handles(cc_num)) {
+        // Relative encoders update a tracked absolute value
+        let value = match mapping.encoder.delta(cc_num, value) {
+            Some(delta) => {
+                let current = state.encoder_values.entry(mapping.source_cc).or_insert(64);
+                *current = (*current as i16 + delta).clamp(0, 127) as u8;
+                *current
+            }
+            None => mapping
+                .calibration
+                .as_ref()
+                .map_or(value, |calibration| calibration.apply(value)),
+        };
+
+        // Generate output messages for each target
+        let mut output = Vec::new();
+        for (index, target) in mapping.targets.iter().enumerate() {
+            let toggle = state
+                .cc_toggles
+                .entry((mapping.source_cc, index))
+                .or_insert(false);
+            let Some(out_value) = transform_cc_value(value, target, toggle) else {
+                continue;
+            };
+            // A threshold note trigger only fires when the value crosses the
+            // threshold, not on every message past it
+            if target.note.is_some() && matches!(target.mode, CcValueMode::Threshold { .. }) {
+                let on = out_value > 0;
+                let key = (mapping.source_cc, index);
+                if state.note_triggers_on.insert(key, on) == Some(on) {
+                    continue;
+                }
+            }
+            let high_res_values = match &target.high_res {
+                Some(high_res) => {
+                    let value = upscale_to_14bit(out_value);
+                    let previous = state
+                        .high_res_values
+                        .insert((mapping.source_cc, index), value);
+                    smoothing_ramp(previous, value, high_res.smoothing_steps)
+                }
+                None => Vec::new(),
+            };
+            for ch in &target.channels {
+                // Channel in mapping is 1-16, MIDI uses 0-15
+                let channel = if *ch > 0 { ch - 1 } else { 0 };
+                match (&target.note, &target.high_res) {
+                    (Some(trigger), _) => output.extend(note_trigger_messages(
+                        channel,
+                        out_value,
+                        trigger,
+                        &mut state.delayed,
+                    )),
+                    (None, Some(high_res)) => {
+                        let lsb_cc = high_res.lsb_for(target.cc);
+                        for value in &high_res_values {
+                            output.push(vec![0xB0 | channel, target.cc, (value >> 7) as u8]);
+                            output.push(vec![0xB0 | channel, lsb_cc, (value & 0x7F) as u8]);
+                        }
+                    }
+                    (None, None) => output.push(vec![0xB0 | channel, target.cc, out_value]),
+                }
+            }
+
+            // A latching switch's press is released after the pulse
+            if let CcValueMode::Momentary { pulse_ms } = target.mode {
+                let delay = Duration::from_millis(pulse_ms as u64);
+                let release =
+                    (target.scale_to_range(0) as i16 + target.offset as i16).clamp(0, 127) as u8;
+                for ch in &target.channels {
+                    let channel = if *ch > 0 { ch - 1 } else { 0 };
+                    let messages = release_messages(channel, release, target);
+                    state
+                        .delayed
+                        .extend(messages.into_iter().map(|bytes| (delay, bytes)));
+                }
+                if target.high_res.is_some() {
+                    state
+                        .high_res_values
+                        .insert((mapping.source_cc, index), upscale_to_14bit(release));
+                }
+            }
+        }
+        output
+    } else if route.cc_passthrough {
+        // No mapping, pass through unchanged
+        vec![bytes.to_vec()]
+    } else {
+        // No mapping, block
+        vec![]
+    }
+}
+
+/// Convert a mapped note into CCs. Returns None for messages that aren't a
+/// mapped note, which route as usual.
+pub fn apply_note_mappings(
+    bytes: &[u8],
+    mappings: &[NoteMapping],
+    toggles: &mut HashMap<usize, bool>,
+) -> Option<Vec<Vec<u8>>> {
+    let (status, note, velocity) = match *bytes {
+        [status, note, velocity] if status & 0xF0 == 0x90 => (status, note, velocity),
+        [status, note, _] if status & 0xF0 == 0x80 => (status, note, 0),
+        _ => return None,
+    };
+    let index = mappings.iter().position(|m| m.note == note)?;
+    let mapping = &mappings[index];
+    let value = match mapping.mode {
+        NoteCcMode::Velocity => velocity,
+        NoteCcMode::Toggle { .. } if velocity == 0 => return Some(Vec::new()),
+        NoteCcMode::Toggle { value } => {
+            let on = toggles.entry(index).or_insert(false);
+            *on = !*on;
+            if *on {
+                value.min(127)
+            } else {
+                0
+            }
+        }
+    };
+
+    let cc = mapping.cc.min(127);
+    let messages = if mapping.channels.is_empty() {
+        vec![vec![0xB0 | (status & 0x0F), cc, value]]
+    } else {
+        mapping
+            .channels
+            .iter()
+            .map(|ch| vec![0xB0 | (ch.clamp(&1, &16) - 1), cc, value])
+            .collect()
+    };
+    Some(messages)
+}
+
+/// Convert a transformed CC value into NoteOn/NoteOff for a note trigger.
+/// Gated triggers push their NoteOff onto `delayed` instead of waiting for 0.
+fn note_trigger_messages(
+    channel: u8,
+    value: u8,
+    trigger: &CcNoteTrigger,
+    delayed: &mut Vec<(Duration, Vec<u8>)>,
+) -> Vec<Vec<u8>> {
+    let note = trigger.note.min(127);
+    if value == 0 {
+        return match trigger.gate_ms {
+            Some(_) => vec![],
+            None => vec![vec![0x80 | channel, note, 0]],
+        };
+    }
+
+    let velocity = trigger.velocity.unwrap_or(value).clamp(1, 127);
+    if let Some(gate_ms) = trigger.gate_ms {
+        delayed.push((
+            Duration::from_millis(gate_ms as u64),
+            vec![0x80 | channel, note, 0],
+        ));
+    }
+    vec![vec![0x90 | channel, note, velocity]]
+}
+
+/// Messages returning a momentary target to `value` once its pulse ends.
+/// Gated note triggers already send their own NoteOff.
+fn release_messages(channel: u8, value: u8, target: &CcTarget) -> Vec<Vec<u8>> {
+    match (&target.note, &target.high_res) {
+        (Some(trigger), _) => match trigger.gate_ms {
+            Some(_) => vec![],
+            None => vec![vec![0x80 | channel, trigger.note.min(127), 0]],
+        },
+        (None, Some(high_res)) => {
+            let value = upscale_to_14bit(value);
+            let lsb_cc = high_res.lsb_for(target.cc);
+            vec![
+                vec![0xB0 | channel, target.cc, (value >> 7) as u8],
+                vec![0xB0 | channel, lsb_cc, (value & 0x7F) as u8],
+            ]
+        }
+        (None, None) => vec![vec![0xB0 | channel, target.cc, value]],
+    }
+}
+
+/// Scale a 7-bit value to the full 14-bit range (127 maps to 16383)
+pub fn upscale_to_14bit(value: u8) -> u16 {
+    (value.min(127) as u32 * 16383 / 127) as u16
+}
+
+/// Values to send when moving from `previous` to `value`.
+/// With smoothing, `steps` evenly spaced values are inserted before `value`.
+fn smoothing_ramp(previous: Option<u16>, value: u16, steps: u8) -> Vec<u16> {
+    match previous {
+        Some(previous) if steps > 0 && previous != value => {
+            let (from, to) = (previous as i32, value as i32);
+            let count = steps as i32 + 1;
+            (1..=count)
+                .map(|i| (from + (to - from) * i / count) as u16)
+                .collect()
+        }
+        _ => vec![value],
+    }
+}
+
+/// Run a CC value through a target's mode, curve and offset.
+/// Returns None when the target should not emit (toggle release).
+pub fn transform_cc_value(value: u8, target: &CcTarget, toggle: &mut bool) -> Option<u8> {
+    let value = if target.invert {
+        127 - value.min(127)
+    } else {
+        value
+    };
+    let value = match target.mode {
+        CcValueMode::Continuous => target.curve.apply(value),
+        CcValueMode::Threshold { threshold } => {
+            if value >= threshold {
+                127
+            } else {
+                0
+            }
+        }
+        CcValueMode::Momentary { .. } => 127,
+        CcValueMode::Toggle => {
+            if value < 64 {
+                return None;
+            }
+            *toggle = !*toggle;
+            if *toggle {
+                127
+            } else {
+                0
+            }
+        }
+    };
+
+    let value = target.scale_to_range(value);
+    Some((value as i16 + target.offset as i16).clamp(0, 127) as u8)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::ChannelFilter;
+
+    // get_channel_from_bytes tests
+    #[test]
+    fn get_channel_from_note_on() {
+        // Note On, channel 0: 0x90
+        assert_eq!(get_channel_from_bytes(&[0x90, 60, 100]), Some(0));
+        // Note On, channel 5: 0x95
+        assert_eq!(get_channel_from_bytes(&[0x95, 60, 100]), Some(5));
+        // Note On, channel 15: 0x9F
+        assert_eq!(get_channel_from_bytes(&[0x9F, 60, 100]), Some(15));
+    }
+
+    #[test]
+    fn get_channel_from_control_change() {
+        // CC, channel 0: 0xB0
+        assert_eq!(get_channel_from_bytes(&[0xB0, 1, 64]), Some(0));
+        // CC, channel 9: 0xB9
+        assert_eq!(get_channel_from_bytes(&[0xB9, 1, 64]), Some(9));
+    }
+
+    #[test]
+    fn get_channel_from_system_message_returns_none() {
+        // SysEx start: 0xF0
+        assert_eq!(get_channel_from_bytes(&[0xF0, 0x7E, 0xF7]), None);
+        // Clock: 0xF8
+        assert_eq!(get_channel_from_bytes(&[0xF8]), None);
+    }
+
+    #[test]
+    fn get_channel_from_empty_returns_none() {
+        assert_eq!(get_channel_from_bytes(&[]), None);
+    }
+
+    // parse_midi_message tests
+    #[test]
+    fn parse_note_on() {
+        let bytes = [0x90, 60, 100]; // Ch 0, note 60, vel 100
+        let activity = parse_midi_message(1000, "Test Port", &bytes).unwrap();
+
+        assert_eq!(activity.channel, Some(0));
+        assert_eq!(activity.port, "Test Port");
+        assert!(matches!(
+            activity.kind,
+            MessageKind::NoteOn {
+                note: 60,
+                velocity: 100
+            }
+        ));
+    }
+
+    #[test]
+    fn parse_note_off() {
+        let bytes = [0x85, 64, 0]; // Ch 5, note 64, vel 0
+        let activity = parse_midi_message(1000, "Port", &bytes).unwrap();
+
+        assert_eq!(activity.channel, Some(5));
+        assert!(matches!(
+            activity.kind,
+            MessageKind::NoteOff {
+                note: 64,
+                velocity: 0
+            }
+        ));
+    }
+
+    #[test]
+    fn parse_control_change() {
+        let bytes = [0xB0, 74, 127]; // Ch 0, CC 74, val 127
+        let activity = parse_midi_message(1000, "Port", &bytes).unwrap();
+
+        assert_eq!(activity.channel, Some(0));
+        assert!(matches!(
+            activity.kind,
+            MessageKind::ControlChange {
+                controller: 74,
+                value: 127
+            }
+        ));
+    }
+
+    #[test]
+    fn parse_program_change() {
+        let bytes = [0xC3, 42]; // Ch 3, program 42
+        let activity = parse_midi_message(1000, "Port", &bytes).unwrap();
+
+        assert_eq!(activity.channel, Some(3));
+        assert!(matches!(
+            activity.kind,
+            MessageKind::ProgramChange { program: 42 }
+        ));
+    }
+
+    #[test]
+    fn parse_invalid_bytes_returns_none() {
+        assert!(parse_midi_message(1000, "Port", &[]).is_none());
+        assert!(parse_midi_message(1000, "Port", &[0x00]).is_none());
+    }
+
+    // should_route tests
+    #[test]
+    fn should_route_all_passes_everything() {
+        let filter = ChannelFilter::All;
+        assert!(should_route(&[0x90, 60, 100], &filter)); // Note On ch 0
+        assert!(should_route(&[0x9F, 60, 100], &filter)); // Note On ch 15
+        assert!(should_route(&[0xF0, 0x7E, 0xF7], &filter)); // SysEx
+    }
+
+    #[test]
+    fn should_route_only_filters_channels() {
+        let filter = ChannelFilter::Only(vec![0, 1]);
+        assert!(should_route(&[0x90, 60, 100], &filter)); // Ch 0 - pass
+        assert!(should_route(&[0x91, 60, 100], &filter)); // Ch 1 - pass
+        assert!(!should_route(&[0x92, 60, 100], &filter)); // Ch 2 - block
+    }
+
+    #[test]
+    fn should_route_system_messages_always_pass() {
+        let filter = ChannelFilter::Only(vec![0]); // Only ch 0
+        assert!(should_route(&[0xF0, 0x7E, 0xF7], &filter)); // SysEx passes
+        assert!(should_route(&[0xF8], &filter)); // Clock passes
+    }
+
+    // is_cc_message tests
+    #[test]
+    fn is_cc_message_identifies_cc() {
+        assert!(is_cc_message(&[0xB0, 1, 64])); // CC ch 0
+        assert!(is_cc_message(&[0xBF, 74, 127])); // CC ch 15
+    }
+
+    #[test]
+    fn is_cc_message_rejects_non_cc() {
+        assert!(!is_cc_message(&[0x90, 60, 100])); // Note On
+        assert!(!is_cc_message(&[0x80, 60, 0])); // Note Off
+        assert!(!is_cc_message(&[0xC0, 5])); // Program Change
+        assert!(!is_cc_message(&[0xF8])); // Clock
+        assert!(!is_cc_message(&[])); // Empty
+    }
+
+    // apply_cc_mappings tests
+    use crate::types::{
+        CcCalibration, CcCurve, CcHighRes, CcMapping, CcNoteTrigger, CcTarget, EncoderMode, PortId,
+        Route,
+    };
+
+    fn make_test_route(cc_passthrough: bool, mappings: Vec<CcMapping>) -> Route {
+        Route {
+            id: uuid::Uuid::new_v4(),
+            source: PortId::new("Test In".to_string()),
+            destination: PortId::new("Test Out".to_string()),
+            enabled: true,
+            channels: ChannelFilter::All,
+            cc_passthrough,
+            cc_mappings: mappings,
+            ..Route::default()
+        }
+    }
+
+    #[test]
+    fn apply_cc_mappings_non_cc_passes_through() {
+        let route = make_test_route(false, vec![]);
+        let note_on = [0x90, 60, 100];
+        let result = apply_cc_mappings(&note_on, &route);
+        assert_eq!(result, vec![note_on.to_vec()]);
+    }
+
+    #[test]
+    fn apply_cc_mappings_unmapped_passthrough_true() {
+        let route = make_test_route(true, vec![]);
+        let cc = [0xB0, 7, 100]; // CC 7 on ch 0
+        let result = apply_cc_mappings(&cc, &route);
+        assert_eq!(result, vec![cc.to_vec()]);
+    }
+
+    #[test]
+    fn apply_cc_mappings_unmapped_passthrough_false() {
+        let route = make_test_route(false, vec![]);
+        let cc = [0xB0, 7, 100]; // CC 7 on ch 0
+        let result = apply_cc_mappings(&cc, &route);
+        assert!(result.is_empty());
+    }
+
+    #[test]
+    fn apply_cc_mappings_single_target() {
+        let mapping = CcMapping {
+            source_cc: 1,
+            targets: vec![CcTarget {
+                cc: 74,
+                channels: vec![1], // Ch 1 (1-indexed)
+                ..Default::default()
+            }],
+            ..Default::default()
+        };
+        let route = make_test_route(true, vec![mapping]);
+        let cc = [0xB5, 1, 100]; // CC 1 on ch 5 (input channel ignored, output uses target)
+        let result = apply_cc_mappings(&cc, &route);
+        assert_eq!(result, vec![vec![0xB0, 74, 100]]); // CC 74 on ch 0 (0-indexed)
+    }
+
+    #[test]
+    fn apply_cc_mappings_multiple_channels() {
+        let mapping = CcMapping {
+            source_cc: 1,
+            targets: vec![CcTarget {
+                cc: 74,
+                channels: vec![1, 2, 3], // Channels 1, 2, 3 (1-indexed)
+                ..Default::default()
+            }],
+            ..Default::default()
+        };
+        let route = make_test_route(true, vec![mapping]);
+        let cc = [0xB0, 1, 64];
+        let result = apply_cc_mappings(&cc, &route);
+        assert_eq!(result.len(), 3);
+        assert_eq!(result[0], vec![0xB0, 74, 64]); // Ch 0
+        assert_eq!(result[1], vec![0xB1, 74, 64]); // Ch 1
+        assert_eq!(result[2], vec![0xB2, 74, 64]); // Ch 2
+    }
+
+    #[test]
+    fn apply_cc_mappings_multiple_targets() {
+        let mapping = CcMapping {
+            source_cc: 1,
+            targets: vec![
+                CcTarget {
+                    cc: 74,
+                    channels: vec![1],
+                    ..Default::default()
+                },
+                CcTarget {
+                    cc: 71,
+                    channels: vec![1],
+                    ..Default::default()
+                },
+            ],
+            ..Default::default()
+        };
+        let route = make_test_route(true, vec![mapping]);
+        let cc = [0xB0, 1, 127];
+        let result = apply_cc_mappings(&cc, &route);
+        assert_eq!(result.len(), 2);
+        assert_eq!(result[0], vec![0xB0, 74, 127]); // CC 74
+        assert_eq!(result[1], vec![0xB0, 71, 127]); // CC 71
+    }
+
+    // ==========================================================================
+    // Additional parse_midi_message tests
+    // ==========================================================================
+
+    #[test]
+    fn parse_pitch_bend() {
+        // Pitch bend: 0xE0-0xEF, LSB, MSB
+        // Center is 0x2000 (8192), stored as LSB=0x00, MSB=0x40
+        let bytes = [0xE3, 0x00, 0x40]; // Ch 3, center position
+        let activity = parse_midi_message(1000, "Port", &bytes).unwrap();
+
+        assert_eq!(activity.channel, Some(3));
+        assert!(matches!(
+            activity.kind,
+            MessageKind::PitchBend { value: 8192 }
+        ));
+    }
+
+    #[test]
+    fn parse_pitch_bend_max() {
+        // Max pitch bend: LSB=0x7F, MSB=0x7F = 16383
+        let bytes = [0xE0, 0x7F, 0x7F];
+        let activity = parse_midi_message(1000, "Port", &bytes).unwrap();
+
+        assert!(matches!(
+            activity.kind,
+            MessageKind::PitchBend { value: 16383 }
+        ));
+    }
+
+    #[test]
+    fn parse_aftertouch() {
+        // Channel pressure (aftertouch): 0xD0-0xDF, value
+        let bytes = [0xD5, 100]; // Ch 5, pressure 100
+        let activity = parse_midi_message(1000, "Port", &bytes).unwrap();
+
+        assert_eq!(activity.channel, Some(5));
+        assert!(matches!(
+            activity.kind,
+            MessageKind::Aftertouch { value: 100 }
+        ));
+    }
+
+    #[test]
+    fn parse_poly_aftertouch() {
+        // Polyphonic key pressure: 0xA0-0xAF, note, value
+        let bytes = [0xA2, 64, 80]; // Ch 2, note 64, pressure 80
+        let activity = parse_midi_message(1000, "Port", &bytes).unwrap();
+
+        assert_eq!(activity.channel, Some(2));
+        assert!(matches!(
+            activity.kind,
+            MessageKind::PolyAftertouch { note: 64, value: 80 }
+        ));
+    }
+
+    #[test]
+    fn parse_sysex() {
+        // SysEx: starts with 0xF0, ends with 0xF7
+        let bytes = [0xF0, 0x7E, 0x00, 0x06, 0x01, 0xF7];
+        let activity = parse_midi_message(1000, "Port", &bytes).unwrap();
+
+        assert_eq!(activity.channel, None); // System message, no channel
+        assert!(matches!(activity.kind, MessageKind::SysEx));
+    }
+
+    #[test]
+    fn parse_song_select() {
+        let activity = parse_midi_message(1000, "Port", &[0xF3, 12]).unwrap();
+
+        assert_eq!(activity.channel, None);
+        assert!(matches!(
+            activity.kind,
+            MessageKind::SongSelect { song: 12 }
+        ));
+    }
+
+    #[test]
+    fn parse_show_control() {
+        // MSC GO cue 12, device 0x10
+        let bytes = [0xF0, 0x7F, 0x10, 0x02, 0x01, 0x01, b'1', b'2', 0xF7];
+        let activity = parse_midi_message(1000, "Port", &bytes).unwrap();
+
+        assert_eq!(activity.channel, None);
+        match activity.kind {
+            MessageKind::ShowControl {
+                device_id,
+                command,
+                cue_number,
+                ..
+            } => {
+                assert_eq!(device_id, 0x10);
+                assert_eq!(command, crate::types::MscCommand::Go);
+                assert_eq!(cue_number.as_deref(), Some("12"));
+            }
+            other => panic!("Expected ShowControl, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn parse_transport_start() {
+        let bytes = [0xFA];
+        let activity = parse_midi_message(1000, "Port", &bytes).unwrap();
+
+        assert_eq!(activity.channel, None);
+        assert!(matches!(activity.kind, MessageKind::Start));
+    }
+
+    #[test]
+    fn parse_transport_stop() {
+        let bytes = [0xFC];
+        let activity = parse_midi_message(1000, "Port", &bytes).unwrap();
+
+        assert_eq!(activity.channel, None);
+        assert!(matches!(activity.kind, MessageKind::Stop));
+    }
+
+    #[test]
+    fn parse_transport_continue() {
+        let bytes = [0xFB];
+        let activity = parse_midi_message(1000, "Port", &bytes).unwrap();
+
+        assert_eq!(activity.channel, None);
+        assert!(matches!(activity.kind, MessageKind::Continue));
+    }
+
+    #[test]
+    fn parse_transport_clock() {
+        let bytes = [0xF8];
+        let activity = parse_midi_message(1000, "Port", &bytes).unwrap();
+
+        assert_eq!(activity.channel, None);
+        assert!(matches!(activity.kind, MessageKind::Clock));
+    }
+
+    // ==========================================================================
+    // Additional should_route tests
+    // ==========================================================================
+
+    #[test]
+    fn should_route_except_blocks_listed() {
+        let filter = ChannelFilter::Except(vec![9, 10]); // Block channels 9, 10
+        assert!(should_route(&[0x90, 60, 100], &filter)); // Ch 0 - pass
+        assert!(should_route(&[0x98, 60, 100], &filter)); // Ch 8 - pass
+        assert!(!should_route(&[0x99, 60, 100], &filter)); // Ch 9 - block
+        assert!(!should_route(&[0x9A, 60, 100], &filter)); // Ch 10 - block
+        assert!(should_route(&[0x9B, 60, 100], &filter)); // Ch 11 - pass
+    }
+
+    #[test]
+    fn should_route_empty_bytes_passes() {
+        let filter = ChannelFilter::Only(vec![0]);
+        // Empty messages have no channel, so they should pass (treated as system)
+        assert!(should_route(&[], &filter));
+    }
+
+    #[test]
+    fn should_route_system_common_applies_filter() {
+        let filter = SystemCommonFilter::Only(vec![SystemCommon::SongPosition]);
+        assert!(should_route_system_common(&[0xF2, 0, 4], &filter));
+        // MTC quarter frame and Tune Request are blocked
+        assert!(!should_route_system_common(&[0xF1, 0x21], &filter));
+        assert!(!should_route_system_common(&[0xF6], &filter));
+        // Channel and real-time messages are unaffected
+        assert!(should_route_system_common(&[0x90, 60, 100], &filter));
+        assert!(should_route_system_common(&[0xF8], &filter));
+    }
+
+    #[test]
+    fn bank_program_messages_are_sent_in_order() {
+        let full = BankProgram {
+            msb: Some(1),
+            lsb: Some(2),
+            program: 30,
+        };
+        assert_eq!(
+            bank_program_messages(4, &full),
+            vec![vec![0xB4, 0, 1], vec![0xB4, 32, 2], vec![0xC4, 30]]
+        );
+        let program_only = BankProgram {
+            msb: None,
+            lsb: None,
+            program: 7,
+        };
+        assert_eq!(bank_program_messages(0, &program_only), vec![vec![0xC0, 7]]);
+    }
+
+    #[test]
+    fn channel_map_forces_or_remaps_channels() {
+        let force = ChannelMap::Force(10);
+        assert_eq!(remap_channel(&[0x92, 60, 100], &force), vec![0x99, 60, 100]);
+        assert_eq!(remap_channel(&[0xF8], &force), vec![0xF8]);
+
+        let remap = ChannelMap::Remap([(3, 10), (4, 17)].into_iter().collect());
+        assert_eq!(remap_channel(&[0xB2, 7, 90], &remap), vec![0xB9, 7, 90]);
+        // Unlisted and out-of-range targets keep their channel
+        assert_eq!(remap_channel(&[0xC0, 5], &remap), vec![0xC0, 5]);
+        assert_eq!(remap_channel(&[0xE3, 0, 64], &remap), vec![0xE3, 0, 64]);
+    }
+
+    #[test]
+    fn note_mapping_sends_velocity_as_cc() {
+        let mappings = vec![NoteMapping {
+            note: 36,
+            cc: 20,
+            channels: Vec::new(),
+            mode: NoteCcMode::Velocity,
+        }];
+        let mut toggles = HashMap::new();
+        let on = apply_note_mappings(&[0x92, 36, 90], &mappings, &mut toggles);
+        assert_eq!(on, Some(vec![vec![0xB2, 20, 90]]));
+        let off = apply_note_mappings(&[0x82, 36, 40], &mappings, &mut toggles);
+        assert_eq!(off, Some(vec![vec![0xB2, 20, 0]]));
+        let unmapped = apply_note_mappings(&[0x92, 37, 90], &mappings, &mut toggles);
+        assert_eq!(unmapped, None);
+    }
+
+    #[test]
+    fn note_mapping_toggles_on_presses() {
+        let mappings = vec![NoteMapping {
+            note: 40,
+            cc: 80,
+            channels: vec![1, 10],
+            mode: NoteCcMode::Toggle { value: 100 },
+        }];
+        let mut toggles = HashMap::new();
+        let press = |toggles: &mut HashMap<usize, bool>| {
+            apply_note_mappings(&[0x90, 40, 64], &mappings, toggles)
+        };
+        assert_eq!(
+            press(&mut toggles),
+            Some(vec![vec![0xB0, 80, 100], vec![0xB9, 80, 100]])
+        );
+        let release = apply_note_mappings(&[0x90, 40, 0], &mappings, &mut toggles);
+        assert_eq!(release, Some(Vec::new()));
+        assert_eq!(
+            press(&mut toggles),
+            Some(vec![vec![0xB0, 80, 0], vec![0xB9, 80, 0]])
+        );
+    }
+
+    #[test]
+    fn key_range_splits_notes_only() {
+        let lower = KeyRange {
+            low_note: 0,
+            high_note: 59,
+        };
+        assert!(should_route_key_range(&[0x90, 59, 100], Some(&lower)));
+        assert!(!should_route_key_range(&[0x90, 60, 100], Some(&lower)));
+        assert!(!should_route_key_range(&[0x80, 72, 0], Some(&lower)));
+        assert!(!should_route_key_range(&[0xA0, 72, 30], Some(&lower)));
+        assert!(should_route_key_range(&[0xB0, 64, 127], Some(&lower)));
+        assert!(should_route_key_range(&[0x90, 72, 100], None));
+    }
+
+    #[test]
+    fn velocity_gate_drops_soft_notes_and_their_releases() {
+        let mut gated = HashSet::new();
+        let mut gate = |bytes: &[u8]| should_route_velocity(bytes, Some(40), &mut gated);
+        assert!(!gate(&[0x99, 36, 20]));
+        assert!(!gate(&[0xA9, 36, 10]));
+        assert!(!gate(&[0x89, 36, 0]));
+        assert!(gate(&[0x99, 36, 40]));
+        assert!(gate(&[0x99, 36, 0]));
+        // Other channels and messages pass
+        assert!(!gate(&[0x99, 38, 5]));
+        assert!(gate(&[0x89, 39, 0]));
+        assert!(gate(&[0x98, 38, 0]));
+        assert!(gate(&[0xB9, 7, 1]));
+        assert!(should_route_velocity(&[0x90, 60, 1], None, &mut gated));
+    }
+
+    #[test]
+    fn layers_play_their_velocity_zone() {
+        let strings = Layer {
+            group: "Pad".to_string(),
+            low_velocity: 81,
+            high_velocity: 127,
+        };
+        let mut gated = HashSet::new();
+        let mut layer = |bytes: &[u8]| should_route_layer(bytes, Some(&strings), &mut gated);
+        assert!(!layer(&[0x90, 60, 80]));
+        assert!(!layer(&[0x80, 60, 0]));
+        assert!(layer(&[0x90, 60, 81]));
+        assert!(layer(&[0xA0, 60, 30]));
+        assert!(layer(&[0x80, 60, 0]));
+        assert!(layer(&[0xB0, 1, 10]));
+        assert!(should_route_layer(&[0x90, 60, 1], None, &mut gated));
+    }
+
+    #[test]
+    fn velocity_curve_only_touches_note_ons() {
+        let transform = VelocityTransform {
+            scale: 0.5,
+            ..Default::default()
+        };
+        let curve = |bytes: &[u8]| apply_velocity_curve(bytes, &transform);
+        assert_eq!(curve(&[0x93, 60, 100]), vec![0x93, 60, 50]);
+        assert_eq!(curve(&[0x93, 60, 0]), vec![0x93, 60, 0]);
+        assert_eq!(curve(&[0x83, 60, 100]), vec![0x83, 60, 100]);
+        assert_eq!(curve(&[0xB0, 7, 100]), vec![0xB0, 7, 100]);
+    }
+
+    #[test]
+    fn should_route_real_time_strips_selected_bytes() {
+        let strip = RealTimeStrip {
+            active_sensing: true,
+            system_reset: false,
+            undefined: true,
+        };
+        assert!(!should_route_real_time(&[0xFE], &strip));
+        assert!(!should_route_real_time(&[0xF9], &strip));
+        assert!(!should_route_real_time(&[0xFD], &strip));
+        assert!(should_route_real_time(&[0xFF], &strip));
+        // Nothing is stripped by default
+        assert!(should_route_real_time(&[0xFE], &RealTimeStrip::default()));
+        assert!(should_route_real_time(&[0x90, 60, 100], &strip));
+    }
+
+    #[test]
+    fn is_aftertouch_matches_both_kinds() {
+        assert!(is_aftertouch(&[0xD3, 100]));
+        assert!(is_aftertouch(&[0xA0, 60, 80]));
+        assert!(!is_aftertouch(&[0x90, 60, 100]));
+        assert!(!is_aftertouch(&[]));
+    }
+
+    // ==========================================================================
+    // Additional apply_cc_mappings edge case tests
+    // ==========================================================================
+
+    #[test]
+    fn apply_cc_mappings_preserves_value() {
+        // Ensure the CC value is preserved through mapping
+        let mapping = CcMapping {
+            source_cc: 1,
+            targets: vec![CcTarget {
+                cc: 74,
+                channels: vec![1],
+                ..Default::default()
+            }],
+            ..Default::default()
+        };
+        let route = make_test_route(true, vec![mapping]);
+
+        // Test various values
+        for value in [0, 1, 64, 126, 127] {
+            let cc = [0xB0, 1, value];
+            let result = apply_cc_mappings(&cc, &route);
+            assert_eq!(result[0][2], value, "Value {} should be preserved", value);
+        }
+    }
+
+    #[test]
+    fn apply_cc_mappings_channel_zero_edge_case() {
+        // Channel 0 in 1-indexed (UI) is actually channel 0 in MIDI
+        // But if user specifies channel 0, it should become channel -1 which is 0 after clamping
+        let mapping = CcMapping {
+            source_cc: 1,
+            targets: vec![CcTarget {
+                cc: 74,
+                channels: vec![0], // Edge case: 0 in 1-indexed
+                ..Default::default()
+            }],
+            ..Default::default()
+        };
+        let route = make_test_route(true, vec![mapping]);
+        let cc = [0xB5, 1, 64];
+        let result = apply_cc_mappings(&cc, &route);
+        // Channel 0 - 1 = -1, but since it's u8 and we check > 0, it becomes 0
+        assert_eq!(result[0][0], 0xB0); // Should be channel 0
+    }
+
+    #[test]
+    fn apply_cc_mappings_multiple_mappings_same_source() {
+        // Two different mappings for the same source CC
+        let mappings = vec![
+            CcMapping {
+                source_cc: 1,
+                targets: vec![CcTarget {
+                    cc: 74,
+                    channels: vec![1],
+                    ..Default::default()
+                }],
+                ..Default::default()
+            },
+            CcMapping {
+                source_cc: 1, // Same source
+                targets: vec![CcTarget {
+                    cc: 71,
+                    channels: vec![2],
+                    ..Default::default()
+                }],
+                ..Default::default()
+            },
+        ];
+        let route = make_test_route(true, mappings);
+        let cc = [0xB0, 1, 100];
+        let result = apply_cc_mappings(&cc, &route);
+
+        // Should only match the first mapping (find returns first match)
+        assert_eq!(result.len(), 1);
+        assert_eq!(result[0], vec![0xB0, 74, 100]);
+    }
+
+    // ==========================================================================
+    // CC value transform tests
+    // ==========================================================================
+
+    fn make_target(mode: CcValueMode, curve: CcCurve, offset: i8) -> CcTarget {
+        CcTarget {
+            cc: 74,
+            channels: vec![1],
+            mode,
+            curve,
+            offset,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn transform_continuous_applies_curve_and_offset() {
+        let mut toggle = false;
+        let target = make_target(CcValueMode::Continuous, CcCurve::Linear, 10);
+        assert_eq!(transform_cc_value(50, &target, &mut toggle), Some(60));
+        assert_eq!(transform_cc_value(127, &target, &mut toggle), Some(127)); // clamped
+
+        let target = make_target(CcValueMode::Continuous, CcCurve::Exponential, -5);
+        assert_eq!(transform_cc_value(0, &target, &mut toggle), Some(0)); // clamped
+        assert_eq!(transform_cc_value(127, &target, &mut toggle), Some(122));
+    }
+
+    #[test]
+    fn transform_rescales_into_output_range() {
+        let mut toggle = false;
+        let mut target = make_target(CcValueMode::Continuous, CcCurve::Linear, 0);
+        target.min = Some(20);
+        target.max = Some(90);
+        assert_eq!(transform_cc_value(0, &target, &mut toggle), Some(20));
+        assert_eq!(transform_cc_value(127, &target, &mut toggle), Some(90));
+        assert_eq!(transform_cc_value(64, &target, &mut toggle), Some(55));
+
+        // Reversed range
+        target.min = Some(100);
+        target.max = Some(0);
+        assert_eq!(transform_cc_value(0, &target, &mut toggle), Some(100));
+        assert_eq!(transform_cc_value(127, &target, &mut toggle), Some(0));
+    }
+
+    #[test]
+    fn transform_inverts_before_the_curve() {
+        let mut toggle = false;
+        let mut target = make_target(CcValueMode::Continuous, CcCurve::Exponential, 0);
+        target.invert = true;
+        assert_eq!(transform_cc_value(0, &target, &mut toggle), Some(127));
+        assert_eq!(transform_cc_value(127, &target, &mut toggle), Some(0));
+        // 127 - 100 = 27, then squared
+        assert_eq!(transform_cc_value(100, &target, &mut toggle), Some(6));
+
+        // A normally-closed switch presses when it opens
+        let mut target = make_target(CcValueMode::Toggle, CcCurve::Linear, 0);
+        target.invert = true;
+        assert_eq!(transform_cc_value(127, &target, &mut toggle), None);
+        assert_eq!(transform_cc_value(0, &target, &mut toggle), Some(127));
+    }
+
+    #[test]
+    fn transform_threshold_switches() {
+        let mut toggle = false;
+        let mode = CcValueMode::Threshold { threshold: 100 };
+        let target = make_target(mode, CcCurve::Linear, 0);
+        assert_eq!(transform_cc_value(99, &target, &mut toggle), Some(0));
+        assert_eq!(transform_cc_value(100, &target, &mut toggle), Some(127));
+    }
+
+    #[test]
+    fn transform_toggle_alternates_on_press() {
+        let mut toggle = false;
+        let target = make_target(CcValueMode::Toggle, CcCurve::Linear, 0);
+        assert_eq!(transform_cc_value(127, &target, &mut toggle), Some(127));
+        assert_eq!(transform_cc_value(0, &target, &mut toggle), None); // release
+        assert_eq!(transform_cc_value(127, &target, &mut toggle), Some(0));
+        assert_eq!(transform_cc_value(0, &target, &mut toggle), None);
+        assert_eq!(transform_cc_value(127, &target, &mut toggle), Some(127));
+    }
+
+    #[test]
+    fn momentary_pulses_on_every_latched_change() {
+        let mapping = CcMapping {
+            source_cc: 65,
+            targets: vec![make_target(
+                CcValueMode::Momentary { pulse_ms: 30 },
+                CcCurve::Linear,
+                0,
+            )],
+            ..Default::default()
+        };
+        let route = make_test_route(false, vec![mapping]);
+        let mut state = RouteState::default();
+
+        // Latched on and latched off are both one press
+        for value in [127, 0] {
+            let press = apply_cc_mappings_with_state(&[0xB0, 65, value], &route, &mut state);
+            assert_eq!(press, vec![vec![0xB0, 74, 127]]);
+            assert_eq!(
+                state.delayed.drain(..).collect::<Vec<_>>(),
+                vec![(Duration::from_millis(30), vec![0xB0, 74, 0])]
+            );
+        }
+    }
+
+    #[test]
+    fn apply_cc_mappings_with_state_keeps_toggle_per_target() {
+        let mapping = CcMapping {
+            source_cc: 64,
+            targets: vec![
+                make_target(CcValueMode::Toggle, CcCurve::Linear, 0),
+                make_target(CcValueMode::Continuous, CcCurve::Linear, 0),
+            ],
+            ..Default::default()
+        };
+        let route = make_test_route(true, vec![mapping]);
+        let mut state = RouteState::default();
+
+        let first = apply_cc_mappings_with_state(&[0xB0, 64, 127], &route, &mut state);
+        assert_eq!(first, vec![vec![0xB0, 74, 127], vec![0xB0, 74, 127]]);
+
+        // Release: toggle target stays silent, continuous target follows
+        let release = apply_cc_mappings_with_state(&[0xB0, 64, 0], &route, &mut state);
+        assert_eq!(release, vec![vec![0xB0, 74, 0]]);
+
+        let second = apply_cc_mappings_with_state(&[0xB0, 64, 127], &route, &mut state);
+        assert_eq!(second, vec![vec![0xB0, 74, 0], vec![0xB0, 74, 127]]);
+    }
+
+    // ==========================================================================
+    // CC -> note trigger tests
+    // ==========================================================================
+
+    fn make_note_route(velocity: Option<u8>, gate_ms: Option<u32>) -> Route {
+        let mapping = CcMapping {
+            source_cc: 80,
+            targets: vec![CcTarget {
+                cc: 0,
+                channels: vec![10],
+                note: Some(CcNoteTrigger {
+                    note: 36,
+                    velocity,
+                    gate_ms,
+                }),
+                ..Default::default()
+            }],
+            ..Default::default()
+        };
+        make_test_route(true, vec![mapping])
+    }
+
+    #[test]
+    fn cc_note_trigger_uses_value_as_velocity() {
+        let route = make_note_route(None, None);
+        let mut state = RouteState::default();
+        let on = apply_cc_mappings_with_state(&[0xB0, 80, 90], &route, &mut state);
+        assert_eq!(on, vec![vec![0x99, 36, 90]]);
+        let off = apply_cc_mappings_with_state(&[0xB0, 80, 0], &route, &mut state);
+        assert_eq!(off, vec![vec![0x89, 36, 0]]);
+        assert!(state.delayed.is_empty());
+    }
+
+    #[test]
+    fn cc_note_trigger_fixed_velocity() {
+        let route = make_note_route(Some(100), None);
+        let result = apply_cc_mappings(&[0xB0, 80, 5], &route);
+        assert_eq!(result, vec![vec![0x99, 36, 100]]);
+    }
+
+    #[test]
+    fn cc_note_trigger_fires_on_threshold_crossings() {
+        let mut route = make_note_route(Some(100), None);
+        route.cc_mappings[0].targets[0].mode = CcValueMode::Threshold { threshold: 64 };
+        let mut state = RouteState::default();
+        let mut send =
+            |value: u8| apply_cc_mappings_with_state(&[0xB0, 80, value], &route, &mut state);
+
+        assert_eq!(send(70), vec![vec![0x99, 36, 100]]);
+        assert!(send(90).is_empty());
+        assert!(send(127).is_empty());
+        assert_eq!(send(63), vec![vec![0x89, 36, 0]]);
+        assert!(send(10).is_empty());
+        assert_eq!(send(64), vec![vec![0x99, 36, 100]]);
+    }
+
+    #[test]
+    fn cc_note_trigger_gate_schedules_note_off() {
+        let route = make_note_route(Some(100), Some(50));
+        let mut state = RouteState::default();
+        let on = apply_cc_mappings_with_state(&[0xB0, 80, 127], &route, &mut state);
+        assert_eq!(on, vec![vec![0x99, 36, 100]]);
+        assert_eq!(
+            state.delayed,
+            vec![(Duration::from_millis(50), vec![0x89, 36, 0])]
+        );
+
+        // Release does nothing when gated
+        let release = apply_cc_mappings_with_state(&[0xB0, 80, 0], &route, &mut state);
+        assert!(release.is_empty());
+    }
+
+    // ==========================================================================
+    // Relative encoder tests
+    // ==========================================================================
+
+    fn make_encoder_route(encoder: EncoderMode) -> Route {
+        let mapping = CcMapping {
+            source_cc: 16,
+            targets: vec![CcTarget {
+                cc: 74,
+                channels: vec![1],
+                ..Default::default()
+            }],
+            encoder,
+            ..Default::default()
+        };
+        make_test_route(true, vec![mapping])
+    }
+
+    #[test]
+    fn relative_encoder_tracks_absolute_value() {
+        let route = make_encoder_route(EncoderMode::TwosComplement);
+        let mut state = RouteState::default();
+
+        // Starts at 64
+        let up = apply_cc_mappings_with_state(&[0xB0, 16, 3], &route, &mut state);
+        assert_eq!(up, vec![vec![0xB0, 74, 67]]);
+        let down = apply_cc_mappings_with_state(&[0xB0, 16, 127], &route, &mut state);
+        assert_eq!(down, vec![vec![0xB0, 74, 66]]);
+    }
+
+    #[test]
+    fn relative_encoder_clamps_to_range() {
+        let route = make_encoder_route(EncoderMode::BinaryOffset);
+        let mut state = RouteState::default();
+
+        for _ in 0..10 {
+            apply_cc_mappings_with_state(&[0xB0, 16, 127], &route, &mut state);
+        }
+        let result = apply_cc_mappings_with_state(&[0xB0, 16, 65], &route, &mut state);
+        assert_eq!(result, vec![vec![0xB0, 74, 127]]);
+    }
+
+    #[test]
+    fn inc_dec_encoder_uses_both_ccs() {
+        let route = make_encoder_route(EncoderMode::IncDec { decrement_cc: 17 });
+        let mut state = RouteState::default();
+
+        let inc = apply_cc_mappings_with_state(&[0xB0, 16, 127], &route, &mut state);
+        assert_eq!(inc, vec![vec![0xB0, 74, 65]]);
+        let dec = apply_cc_mappings_with_state(&[0xB0, 17, 127], &route, &mut state);
+        assert_eq!(dec, vec![vec![0xB0, 74, 64]]);
+    }
+
+    // ==========================================================================
+    // Calibration tests
+    // ==========================================================================
+
+    #[test]
+    fn calibration_stretches_measured_range() {
+        let mapping = CcMapping {
+            source_cc: 11,
+            targets: vec![CcTarget {
+                cc: 11,
+                channels: vec![1],
+                ..Default::default()
+            }],
+            calibration: Some(CcCalibration {
+                min: 10,
+                max: 115,
+                curve: CcCurve::Linear,
+            }),
+            ..Default::default()
+        };
+        let route = make_test_route(true, vec![mapping]);
+        let apply = |value| apply_cc_mappings(&[0xB0, 11, value], &route);
+
+        assert_eq!(apply(10), vec![vec![0xB0, 11, 0]]);
+        assert_eq!(apply(115), vec![vec![0xB0, 11, 127]]);
+        assert_eq!(apply(62), vec![vec![0xB0, 11, 63]]);
+        // Outside the measured range clamps to the ends
+        assert_eq!(apply(3), vec![vec![0xB0, 11, 0]]);
+        assert_eq!(apply(127), vec![vec![0xB0, 11, 127]]);
+    }
+
+    #[test]
+    fn calibration_curve_applies_after_stretching() {
+        let calibration = CcCalibration {
+            min: 10,
+            max: 110,
+            curve: CcCurve::Exponential,
+        };
+        assert_eq!(calibration.apply(10), 0);
+        assert_eq!(calibration.apply(60), 32);
+        assert_eq!(calibration.apply(110), 127);
+
+        // An empty range leaves values alone
+        let empty = CcCalibration {
+            min: 64,
+            max: 64,
+            curve: CcCurve::Linear,
+        };
+        assert_eq!(empty.apply(100), 100);
+    }
+
+    // ==========================================================================
+    // 14-bit upscaling tests
+    // ==========================================================================
+
+    fn make_high_res_route(smoothing_steps: u8) -> Route {
+        let mapping = CcMapping {
+            source_cc: 1,
+            targets: vec![CcTarget {
+                cc: 7,
+                channels: vec![1],
+                high_res: Some(CcHighRes {
+                    lsb_cc: None,
+                    smoothing_steps,
+                }),
+                ..Default::default()
+            }],
+            ..Default::default()
+        };
+        make_test_route(true, vec![mapping])
+    }
+
+    #[test]
+    fn upscale_covers_full_range() {
+        assert_eq!(upscale_to_14bit(0), 0);
+        assert_eq!(upscale_to_14bit(127), 16383);
+        assert_eq!(upscale_to_14bit(64), 8256);
+    }
+
+    #[test]
+    fn high_res_target_sends_msb_and_lsb() {
+        let route = make_high_res_route(0);
+        let result = apply_cc_mappings(&[0xB0, 1, 127], &route);
+        assert_eq!(result, vec![vec![0xB0, 7, 127], vec![0xB0, 39, 127]]);
+
+        let result = apply_cc_mappings(&[0xB0, 1, 64], &route);
+        // 8256 = 64 << 7 | 64
+        assert_eq!(result, vec![vec![0xB0, 7, 64], vec![0xB0, 39, 64]]);
+    }
+
+    #[test]
+    fn high_res_smoothing_ramps_from_previous_value() {
+        let route = make_high_res_route(3);
+        let mut state = RouteState::default();
+
+        // First value has nothing to ramp from
+        let first = apply_cc_mappings_with_state(&[0xB0, 1, 0], &route, &mut state);
+        assert_eq!(first.len(), 2);
+
+        let ramp = apply_cc_mappings_with_state(&[0xB0, 1, 1], &route, &mut state);
+        // 3 intermediate values plus the target, each as an MSB/LSB pair
+        assert_eq!(ramp.len(), 8);
+        let values: Vec<u16> = ramp
+            .chunks(2)
+            .map(|pair| ((pair[0][2] as u16) << 7) | pair[1][2] as u16)
+            .collect();
+        assert_eq!(values, vec![32, 64, 96, 129]);
+    }
+}
@@ -0,0 +1,204 @@
+//! Timestamped output scheduling
+//!
+//! Messages queued for a future instant are sent from a dedicated timing
+//! thread, so their timing doesn't depend on the engine loop's 1 ms tick.
+//! Sent messages are handed back to the engine for bookkeeping.
+
+use std::sync::{Arc, Condvar, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+use uuid::Uuid;
+
+/// A message waiting for its send time
+#[derive(Debug, Clone, PartialEq)]
+pub struct ScheduledSend {
+    pub due: Instant,
+    pub route_id: Uuid,
+    pub destination: String,
+    pub bytes: Vec<u8>,
+}
+
+#[derive(Default)]
+struct Queue {
+    /// Ordered by due time; equal times keep their scheduling order
+    pending: Vec<ScheduledSend>,
+    /// Sends made since the engine last collected them, with their result
+    sent: Vec<(ScheduledSend, Result<(), String>)>,
+    shutdown: bool,
+}
+
+type Shared = Arc<(Mutex<Queue>, Condvar)>;
+
+pub struct Scheduler {
+    shared: Shared,
+    thread_handle: Option<thread::JoinHandle<()>>,
+}
+
+impl Scheduler {
+    /// Below this the timing thread yields instead of sleeping, since
+    /// sleeps can overshoot by more than the remaining wait
+    const SPIN_WINDOW: Duration = Duration::from_micros(500);
+
+    /// Start the timing thread. `send` delivers a message to an output.
+    pub fn new<F>(send: F) -> Self
+    where
+        F: FnMut(&str, &[u8]) -> Result<(), String> + Send + 'static,
+    {
+        let shared: Shared = Arc::new((Mutex::new(Queue::default()), Condvar::new()));
+        let thread_shared = shared.clone();
+        let thread_handle = thread::spawn(move || timing_loop(thread_shared, send));
+
+        Self {
+            shared,
+            thread_handle: Some(thread_handle),
+        }
+    }
+
+    pub fn schedule(&self, send: ScheduledSend) {
+        let (lock, wake) = &*self.shared;
+        let mut queue = lock.lock().unwrap();
+        let index = queue.pending.partition_point(|s| s.due <= send.due);
+        queue.pending.insert(index, send);
+        wake.notify_one();
+    }
+
+    /// Drop a route's pending messages. Once this returns, none of them
+    /// will be sent.
+    pub fn cancel_route(&self, route_id: Uuid) {
+        let (lock, _) = &*self.shared;
+        lock.lock()
+            .unwrap()
+            .pending
+            .retain(|s| s.route_id != route_id);
+    }
+
+    /// Messages sent since the last call, oldest first
+    pub fn take_sent(&self) -> Vec<(ScheduledSend, Result<(), String>)> {
+        let (lock, _) = &*self.shared;
+        let mut queue = lock.lock().unwrap();
+        if queue.sent.is_empty() {
+            return Vec::new();
+        }
+        std::mem::take(&mut queue.sent)
+    }
+}
+
+impl Drop for Scheduler {
+    fn drop(&mut self) {
+        {
+            let (lock, wake) = &*self.shared;
+            lock.lock().unwrap().shutdown = true;
+            wake.notify_one();
+        }
+        if let Some(handle) = self.thread_handle.take() {
+            let _ = handle.join();
+        }
+    }
+}
+
+/// Sends happen with the queue locked, which is what makes `cancel_route`
+/// final: a cancel either runs before a send or waits for it to finish.
+fn timing_loop<F>(shared: Shared, mut send: F)
+where
+    F: FnMut(&str, &[u8]) -> Result<(), String>,
+{
+    let (lock, wake) = &*shared;
+    let mut queue = lock.lock().unwrap();
+    loop {
+        if queue.shutdown {
+            return;
+        }
+        let now = Instant::now();
+        match queue.pending.first().map(|s| s.due) {
+            None => queue = wake.wait(queue).unwrap(),
+            Some(due) if due > now => {
+                let wait = due - now;
+                if wait > Scheduler::SPIN_WINDOW {
+                    queue = wake
+                        .wait_timeout(queue, wait - Scheduler::SPIN_WINDOW)
+                        .unwrap()
+                        .0;
+                } else {
+                    drop(queue);
+                    thread::yield_now();
+                    queue = lock.lock().unwrap();
+                }
+            }
+            Some(_) => {
+                let next = queue.pending.remove(0);
+                let result = send(&next.destination, &next.bytes);
+                queue.sent.push((next, result));
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    type Log = Arc<Mutex<Vec<(String, Vec<u8>, Instant)>>>;
+
+    fn logging_scheduler() -> (Scheduler, Log) {
+        let log: Log = Arc::new(Mutex::new(Vec::new()));
+        let thread_log = log.clone();
+        let scheduler = Scheduler::new(move |destination, bytes| {
+            thread_log.lock().unwrap().push((
+                destination.to_string(),
+                bytes.to_vec(),
+                Instant::now(),
+            ));
+            Ok(())
+        });
+        (scheduler, log)
+    }
+
+    fn send_at(due: Instant, route_id: Uuid, bytes: &[u8]) -> ScheduledSend {
+        ScheduledSend {
+            due,
+            route_id,
+            destination: "Synth".to_string(),
+            bytes: bytes.to_vec(),
+        }
+    }
+
+    #[test]
+    fn sends_in_due_order_and_not_early() {
+        let (scheduler, log) = logging_scheduler();
+        let route_id = Uuid::new_v4();
+        let start = Instant::now();
+        let late = start + Duration::from_millis(30);
+        let early = start + Duration::from_millis(10);
+        scheduler.schedule(send_at(late, route_id, &[0x80, 60, 0]));
+        scheduler.schedule(send_at(early, route_id, &[0x90, 60, 100]));
+
+        thread::sleep(Duration::from_millis(80));
+
+        let log = log.lock().unwrap();
+        assert_eq!(log.len(), 2);
+        assert_eq!(log[0].1, vec![0x90, 60, 100]);
+        assert!(log[0].2 >= early);
+        assert!(log[1].2 >= late);
+
+        let sent = scheduler.take_sent();
+        assert_eq!(sent.len(), 2);
+        assert!(sent.iter().all(|(_, result)| result.is_ok()));
+        assert!(scheduler.take_sent().is_empty());
+    }
+
+    #[test]
+    fn cancel_route_drops_its_pending_sends() {
+        let (scheduler, log) = logging_scheduler();
+        let (kept, cancelled) = (Uuid::new_v4(), Uuid::new_v4());
+        let due = Instant::now() + Duration::from_millis(20);
+        scheduler.schedule(send_at(due, cancelled, &[0x80, 60, 0]));
+        scheduler.schedule(send_at(due, kept, &[0x80, 62, 0]));
+        scheduler.cancel_route(cancelled);
+
+        thread::sleep(Duration::from_millis(60));
+
+        let log = log.lock().unwrap();
+        assert_eq!(log.len(), 1);
+        assert_eq!(log[0].1, vec![0x80, 62, 0]);
+    }
+}
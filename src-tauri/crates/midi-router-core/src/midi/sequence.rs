@@ -0,0 +1,191 @@
+//! Per-kind sequence numbers on engine events
+//!
+//! Each kind of event is numbered on its own, so a monitor forwarding one
+//! kind can tell when some never reached it instead of silently showing
+//! an incomplete picture. Every subscriber gets its own copy of each
+//! event, so monitors don't take events from one another.
+
+use crate::midi::engine::EngineEvent;
+use crossbeam_channel::{bounded, Receiver, Sender, TrySendError};
+use serde::Serialize;
+use std::collections::{BTreeMap, HashMap};
+use std::sync::{Arc, Mutex};
+
+/// An engine event with its number among events of the same kind
+#[derive(Debug, Clone, Serialize)]
+pub struct SequencedEvent {
+    pub seq: u64,
+    #[serde(flatten)]
+    pub event: EngineEvent,
+}
+
+/// Channels of everything subscribed to engine events
+#[derive(Clone, Default)]
+pub struct EventSubscribers(Arc<Mutex<Vec<Sender<SequencedEvent>>>>);
+
+impl EventSubscribers {
+    /// Events each subscriber can fall behind by before it misses some
+    const QUEUE_LEN: usize = 256;
+
+    /// A new channel receiving every event sent from now on
+    pub fn subscribe(&self) -> Receiver<SequencedEvent> {
+        let (tx, rx) = bounded(Self::QUEUE_LEN);
+        self.0.lock().unwrap().push(tx);
+        rx
+    }
+}
+
+/// Numbers events and copies them to every subscriber
+pub(crate) struct EventSender {
+    subscribers: EventSubscribers,
+    next: HashMap<&'static str, u64>,
+}
+
+impl EventSender {
+    pub fn new(subscribers: EventSubscribers) -> Self {
+        Self {
+            subscribers,
+            next: HashMap::new(),
+        }
+    }
+
+    /// Send an event to every subscriber. One whose queue is full misses
+    /// it, which its gap detector reports; one that hung up is dropped.
+    /// Err when no subscriber received it.
+    pub fn send(&mut self, event: EngineEvent) -> Result<(), ()> {
+        let next = self.next.entry(event.kind()).or_default();
+        let event = SequencedEvent { seq: *next, event };
+        *next += 1;
+
+        let mut delivered = false;
+        self.subscribers
+            .0
+            .lock()
+            .unwrap()
+            .retain(|tx| match tx.try_send(event.clone()) {
+                Ok(()) => {
+                    delivered = true;
+                    true
+                }
+                Err(TrySendError::Full(_)) => true,
+                Err(TrySendError::Disconnected(_)) => false,
+            });
+        if delivered {
+            Ok(())
+        } else {
+            Err(())
+        }
+    }
+}
+
+/// Event kind -> events monitors never received, since startup
+pub type SharedEventGaps = Arc<Mutex<BTreeMap<String, u64>>>;
+
+/// Spots events missing from a monitor's stream of one kind
+pub struct GapDetector {
+    kind: &'static str,
+    last: Option<u64>,
+    gaps: SharedEventGaps,
+}
+
+impl GapDetector {
+    pub fn new(kind: &'static str, gaps: SharedEventGaps) -> Self {
+        Self {
+            kind,
+            last: None,
+            gaps,
+        }
+    }
+
+    /// Note a received event's number, returning how many of its kind were
+    /// missed since the previous one. Events sent before the monitor
+    /// started don't count.
+    pub fn observe(&mut self, seq: u64) -> u64 {
+        let missed = self
+            .last
+            .map_or(0, |last| seq.saturating_sub(last).saturating_sub(1));
+        self.last = Some(seq);
+        if missed > 0 {
+            eprintln!("[MONITOR] Missed {} {} events", missed, self.kind);
+            *self
+                .gaps
+                .lock()
+                .unwrap()
+                .entry(self.kind.to_string())
+                .or_default() += missed;
+        }
+        missed
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use uuid::Uuid;
+
+    #[test]
+    fn numbers_each_kind_separately() {
+        let subscribers = EventSubscribers::default();
+        let rx = subscribers.subscribe();
+        let mut sender = EventSender::new(subscribers);
+        sender
+            .send(EngineEvent::PresetSwitched(Uuid::nil()))
+            .unwrap();
+        sender.send(EngineEvent::Stats(Default::default())).unwrap();
+        sender
+            .send(EngineEvent::PresetSwitched(Uuid::nil()))
+            .unwrap();
+
+        let numbered: Vec<_> = rx.try_iter().map(|e| (e.event.kind(), e.seq)).collect();
+        assert_eq!(
+            numbered,
+            vec![("PresetSwitched", 0), ("Stats", 0), ("PresetSwitched", 1)]
+        );
+    }
+
+    #[test]
+    fn every_subscriber_gets_every_event() {
+        let subscribers = EventSubscribers::default();
+        let mut sender = EventSender::new(subscribers.clone());
+        assert!(sender.send(EngineEvent::Stats(Default::default())).is_err());
+
+        let first = subscribers.subscribe();
+        let second = subscribers.subscribe();
+        sender
+            .send(EngineEvent::PresetSwitched(Uuid::nil()))
+            .unwrap();
+        sender.send(EngineEvent::Stats(Default::default())).unwrap();
+        for rx in [&first, &second] {
+            let kinds: Vec<_> = rx.try_iter().map(|e| e.event.kind()).collect();
+            assert_eq!(kinds, vec!["PresetSwitched", "Stats"]);
+        }
+
+        // A subscriber that hung up is dropped
+        drop(first);
+        sender.send(EngineEvent::Stats(Default::default())).unwrap();
+        assert_eq!(subscribers.0.lock().unwrap().len(), 1);
+        assert_eq!(second.try_recv().unwrap().seq, 2);
+    }
+
+    #[test]
+    fn reports_missing_numbers() {
+        let gaps = SharedEventGaps::default();
+        let mut detector = GapDetector::new("TrapHit", gaps.clone());
+        assert_eq!(detector.observe(4), 0);
+        assert_eq!(detector.observe(5), 0);
+        assert_eq!(detector.observe(8), 2);
+        assert_eq!(detector.observe(10), 1);
+        assert_eq!(gaps.lock().unwrap().get("TrapHit"), Some(&3));
+    }
+
+    #[test]
+    fn serializes_alongside_the_event() {
+        let event = SequencedEvent {
+            seq: 3,
+            event: EngineEvent::PresetSwitched(Uuid::nil()),
+        };
+        let json = serde_json::to_value(&event).unwrap();
+        assert_eq!(json["seq"], 3);
+        assert_eq!(json["kind"], "PresetSwitched");
+    }
+}
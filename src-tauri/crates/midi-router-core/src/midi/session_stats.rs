@@ -0,0 +1,86 @@
+//! Playing statistics
+//!
+//! Tallies what arrives on each input over the session: which notes were
+//! played and how hard, and which controllers a device actually sends.
+
+use crate::types::{PortSessionStats, SessionStats};
+use std::collections::BTreeMap;
+
+/// Width of a velocity histogram bin
+const VELOCITY_BIN: usize = 8;
+
+#[derive(Debug)]
+pub struct SessionRecorder {
+    started_us: u64,
+    ports: BTreeMap<String, PortSessionStats>,
+}
+
+impl SessionRecorder {
+    pub fn new(started_us: u64) -> Self {
+        Self {
+            started_us,
+            ports: BTreeMap::new(),
+        }
+    }
+
+    pub fn record(&mut self, port: &str, bytes: &[u8]) {
+        let (kind, data1, data2) = match bytes {
+            [status, data1, data2, ..] if *status < 0xF0 => (status & 0xF0, *data1, *data2),
+            _ => return,
+        };
+        if !matches!(kind, 0x90 | 0xB0) {
+            return;
+        }
+        let stats = self
+            .ports
+            .entry(port.to_string())
+            .or_insert_with(|| PortSessionStats {
+                port: port.to_string(),
+                notes: vec![0; 128],
+                velocities: vec![0; 128 / VELOCITY_BIN],
+                controllers: BTreeMap::new(),
+            });
+        match kind {
+            // Velocity 0 is a note-off
+            0x90 if data2 > 0 => {
+                stats.notes[usize::from(data1 & 0x7F)] += 1;
+                stats.velocities[usize::from(data2 & 0x7F) / VELOCITY_BIN] += 1;
+            }
+            0xB0 => *stats.controllers.entry(data1 & 0x7F).or_default() += 1,
+            _ => {}
+        }
+    }
+
+    pub fn stats(&self) -> SessionStats {
+        SessionStats {
+            started_us: self.started_us,
+            ports: self.ports.values().cloned().collect(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tallies_notes_velocities_and_controllers() {
+        let mut recorder = SessionRecorder::new(1_000);
+        recorder.record("Keys", &[0x90, 60, 100]);
+        recorder.record("Keys", &[0x91, 60, 7]);
+        recorder.record("Keys", &[0x90, 60, 0]);
+        recorder.record("Keys", &[0xB0, 64, 127]);
+        recorder.record("Keys", &[0xB3, 64, 0]);
+        recorder.record("Keys", &[0xF8]);
+        recorder.record("Clock", &[0xF8]);
+
+        let stats = recorder.stats();
+        assert_eq!(stats.started_us, 1_000);
+        assert_eq!(stats.ports.len(), 1);
+        let keys = &stats.ports[0];
+        assert_eq!(keys.notes[60], 2);
+        assert_eq!(keys.velocities[0], 1);
+        assert_eq!(keys.velocities[12], 1);
+        assert_eq!(keys.controllers.get(&64), Some(&2));
+    }
+}
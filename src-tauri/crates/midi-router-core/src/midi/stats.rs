@@ -0,0 +1,107 @@
+//! Engine throughput statistics
+//!
+//! Counts messages and bytes per port and direction, and turns them into
+//! per-second rates once per measurement window.
+
+use crate::types::{PortDirection, PortThroughput};
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+pub struct ThroughputMeter {
+    window: Duration,
+    window_start: Instant,
+    /// (messages, bytes) counted in the current window
+    counts: HashMap<(String, PortDirection), (u64, u64)>,
+    /// Rates from the last completed window
+    rates: Vec<PortThroughput>,
+}
+
+impl ThroughputMeter {
+    pub const DEFAULT_WINDOW: Duration = Duration::from_secs(1);
+
+    pub fn new(window: Duration, now: Instant) -> Self {
+        Self {
+            window,
+            window_start: now,
+            counts: HashMap::new(),
+            rates: Vec::new(),
+        }
+    }
+
+    pub fn record(&mut self, port: &str, direction: PortDirection, bytes: usize) {
+        let entry = self
+            .counts
+            .entry((port.to_string(), direction))
+            .or_insert((0, 0));
+        entry.0 += 1;
+        entry.1 += bytes as u64;
+    }
+
+    /// Close the window if it has elapsed, computing new rates.
+    /// Returns true when the rates were updated.
+    pub fn roll(&mut self, now: Instant) -> bool {
+        let elapsed = now.duration_since(self.window_start);
+        if elapsed < self.window {
+            return false;
+        }
+        self.window_start = now;
+
+        let seconds = elapsed.as_secs_f64();
+        let mut rates: Vec<PortThroughput> = self
+            .counts
+            .drain()
+            .map(|((port, direction), (messages, bytes))| PortThroughput {
+                port,
+                direction,
+                messages_per_sec: messages as f64 / seconds,
+                bytes_per_sec: bytes as f64 / seconds,
+            })
+            .collect();
+        rates.sort_by(|a, b| a.port.cmp(&b.port));
+        self.rates = rates;
+        true
+    }
+
+    /// Rates from the last completed window (ports without traffic are omitted)
+    pub fn rates(&self) -> &[PortThroughput] {
+        &self.rates
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rates_are_computed_per_window() {
+        let t0 = Instant::now();
+        let mut meter = ThroughputMeter::new(Duration::from_secs(1), t0);
+        for _ in 0..10 {
+            meter.record("Keys", PortDirection::Input, 3);
+        }
+        meter.record("Synth", PortDirection::Output, 1);
+
+        assert!(!meter.roll(t0 + Duration::from_millis(500)));
+        assert!(meter.rates().is_empty());
+
+        assert!(meter.roll(t0 + Duration::from_secs(2)));
+        let rates = meter.rates();
+        assert_eq!(rates.len(), 2);
+        assert_eq!(rates[0].port, "Keys");
+        assert_eq!(rates[0].messages_per_sec, 5.0);
+        assert_eq!(rates[0].bytes_per_sec, 15.0);
+        assert_eq!(rates[1].direction, PortDirection::Output);
+    }
+
+    #[test]
+    fn idle_window_clears_rates() {
+        let t0 = Instant::now();
+        let mut meter = ThroughputMeter::new(Duration::from_secs(1), t0);
+        meter.record("Keys", PortDirection::Input, 3);
+        meter.roll(t0 + Duration::from_secs(1));
+        assert_eq!(meter.rates().len(), 1);
+
+        meter.roll(t0 + Duration::from_secs(2));
+        assert!(meter.rates().is_empty());
+    }
+}
@@ -0,0 +1,109 @@
+//! Sustain pedal in the router
+//!
+//! For destinations that ignore CC64: while a channel's pedal is down its
+//! Note Offs are held back, then sent together when the pedal comes up.
+//! The pedal itself isn't passed on, so a destination that does follow
+//! CC64 doesn't sustain twice.
+
+use std::collections::{BTreeMap, BTreeSet, HashSet};
+
+/// CC64 values at or above this hold the pedal down
+const PEDAL_DOWN: u8 = 64;
+
+#[derive(Debug, Default)]
+pub struct SustainState {
+    /// Channels with the pedal down
+    down: HashSet<u8>,
+    /// Note Offs held back, by channel
+    pending: BTreeMap<u8, BTreeSet<u8>>,
+}
+
+/// Apply the sustain pedal to a message. Anything other than notes and
+/// CC64 passes through unchanged.
+pub fn sustain(bytes: &[u8], state: &mut SustainState) -> Vec<Vec<u8>> {
+    let &[status, data, value] = bytes else {
+        return vec![bytes.to_vec()];
+    };
+    let channel = status & 0x0F;
+
+    match status & 0xF0 {
+        0xB0 if data == 64 => {
+            if value >= PEDAL_DOWN {
+                state.down.insert(channel);
+                return Vec::new();
+            }
+            state.down.remove(&channel);
+            let released = state.pending.remove(&channel).unwrap_or_default();
+            released
+                .into_iter()
+                .map(|note| vec![0x80 | channel, note, 0])
+                .collect()
+        }
+        0x80 | 0x90 if state.down.contains(&channel) => {
+            let held = state.pending.entry(channel).or_default();
+            if status & 0xF0 == 0x80 || value == 0 {
+                held.insert(data);
+                return Vec::new();
+            }
+            // Striking a sustained note again ends the old one first
+            if held.remove(&data) {
+                return vec![vec![0x80 | channel, data, 0], bytes.to_vec()];
+            }
+            vec![bytes.to_vec()]
+        }
+        _ => vec![bytes.to_vec()],
+    }
+}
+
+/// Note Offs for every held-back note, lifting every pedal
+pub fn release_all(state: &mut SustainState) -> Vec<Vec<u8>> {
+    state.down.clear();
+    std::mem::take(&mut state.pending)
+        .into_iter()
+        .flat_map(|(channel, notes)| {
+            notes
+                .into_iter()
+                .map(move |note| vec![0x80 | channel, note, 0])
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn holds_note_offs_until_the_pedal_lifts() {
+        let mut state = SustainState::default();
+        let mut play = |bytes: &[u8]| sustain(bytes, &mut state);
+
+        assert!(play(&[0xB0, 64, 127]).is_empty());
+        assert_eq!(play(&[0x90, 60, 100]), vec![vec![0x90, 60, 100]]);
+        assert!(play(&[0x80, 60, 40]).is_empty());
+        assert!(play(&[0x90, 64, 0]).is_empty());
+        // Other channels aren't held
+        assert_eq!(play(&[0x81, 60, 0]), vec![vec![0x81, 60, 0]]);
+        assert_eq!(
+            play(&[0xB0, 64, 0]),
+            vec![vec![0x80, 60, 0], vec![0x80, 64, 0]]
+        );
+        assert_eq!(play(&[0x80, 60, 0]), vec![vec![0x80, 60, 0]]);
+        assert_eq!(play(&[0xB0, 1, 90]), vec![vec![0xB0, 1, 90]]);
+    }
+
+    #[test]
+    fn restrikes_sustained_notes() {
+        let mut state = SustainState::default();
+        sustain(&[0xB2, 64, 100], &mut state);
+        sustain(&[0x92, 60, 100], &mut state);
+        sustain(&[0x82, 60, 0], &mut state);
+        assert_eq!(
+            sustain(&[0x92, 60, 90], &mut state),
+            vec![vec![0x82, 60, 0], vec![0x92, 60, 90]]
+        );
+        // Still held, so its Note Off waits again
+        assert!(sustain(&[0x82, 60, 0], &mut state).is_empty());
+        assert_eq!(release_all(&mut state), vec![vec![0x82, 60, 0]]);
+        assert_eq!(sustain(&[0x82, 60, 0], &mut state), vec![vec![0x82, 60, 0]]);
+    }
+}
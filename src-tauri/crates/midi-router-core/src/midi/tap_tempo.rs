@@ -0,0 +1,132 @@
+//! Tap tempo
+//!
+//! Derives a BPM from the average interval between recent taps.
+
+use crate::types::Bpm;
+use std::collections::VecDeque;
+use std::time::{Duration, Instant};
+
+/// Tracks recent taps and computes tempo from them
+pub struct TapTempo {
+    taps: VecDeque<Instant>,
+}
+
+impl TapTempo {
+    /// Number of taps averaged over
+    pub const MAX_TAPS: usize = 5;
+    /// A gap longer than this starts a new tap sequence
+    pub const TIMEOUT: Duration = Duration::from_secs(2);
+
+    pub fn new() -> Self {
+        Self {
+            taps: VecDeque::with_capacity(Self::MAX_TAPS),
+        }
+    }
+
+    /// Register a tap. Returns the new BPM once at least two taps are in the sequence.
+    pub fn tap(&mut self, now: Instant) -> Option<f64> {
+        if let Some(last) = self.taps.back() {
+            if now.duration_since(*last) > Self::TIMEOUT {
+                self.taps.clear();
+            }
+        }
+
+        if self.taps.len() == Self::MAX_TAPS {
+            self.taps.pop_front();
+        }
+        self.taps.push_back(now);
+
+        let first = self.taps.front()?;
+        let intervals = self.taps.len() - 1;
+        if intervals == 0 {
+            return None;
+        }
+
+        let average = now.duration_since(*first).as_secs_f64() / intervals as f64;
+        if average <= 0.0 {
+            return None;
+        }
+        Some(Bpm::clamped(60.0 / average).value())
+    }
+
+    /// Forget all taps
+    pub fn reset(&mut self) {
+        self.taps.clear();
+    }
+}
+
+impl Default for TapTempo {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ms(n: u64) -> Duration {
+        Duration::from_millis(n)
+    }
+
+    #[test]
+    fn first_tap_returns_none() {
+        let mut tap = TapTempo::new();
+        assert_eq!(tap.tap(Instant::now()), None);
+    }
+
+    #[test]
+    fn two_taps_give_bpm() {
+        let mut tap = TapTempo::new();
+        let t0 = Instant::now();
+        tap.tap(t0);
+        let bpm = tap.tap(t0 + ms(500)).unwrap();
+        assert!((bpm - 120.0).abs() < 0.001);
+    }
+
+    #[test]
+    fn taps_are_averaged() {
+        let mut tap = TapTempo::new();
+        let t0 = Instant::now();
+        tap.tap(t0);
+        tap.tap(t0 + ms(400));
+        let bpm = tap.tap(t0 + ms(1000)).unwrap();
+        // Average interval 500 ms
+        assert!((bpm - 120.0).abs() < 0.001);
+    }
+
+    #[test]
+    fn long_gap_starts_new_sequence() {
+        let mut tap = TapTempo::new();
+        let t0 = Instant::now();
+        tap.tap(t0);
+        tap.tap(t0 + ms(500));
+        assert_eq!(tap.tap(t0 + ms(5000)), None);
+        let bpm = tap.tap(t0 + ms(6000)).unwrap();
+        assert!((bpm - 60.0).abs() < 0.001);
+    }
+
+    #[test]
+    fn only_recent_taps_are_used() {
+        let mut tap = TapTempo::new();
+        let t0 = Instant::now();
+        // Slow taps first, then fast ones push them out
+        tap.tap(t0);
+        tap.tap(t0 + ms(1000));
+        let mut t = t0 + ms(1000);
+        let mut bpm = 0.0;
+        for _ in 0..TapTempo::MAX_TAPS {
+            t += ms(250);
+            bpm = tap.tap(t).unwrap();
+        }
+        assert!((bpm - 240.0).abs() < 0.001);
+    }
+
+    #[test]
+    fn result_is_clamped() {
+        let mut tap = TapTempo::new();
+        let t0 = Instant::now();
+        tap.tap(t0);
+        assert_eq!(tap.tap(t0 + ms(50)), Some(Bpm::MAX));
+    }
+}
@@ -0,0 +1,176 @@
+//! Test signal generator
+//!
+//! Plays a test pattern (scales, a repeated note, CC sweeps, clock) to an
+//! output or through the routes from a virtual input, so mappings and
+//! destinations can be checked without a controller on hand.
+
+use crate::types::{TestPattern, TestSignal, TestSignalTarget};
+use std::time::{Duration, Instant};
+
+/// Name of the virtual input test signals arrive on
+pub const TEST_SIGNAL_PORT: &str = "Test Signal";
+
+#[derive(Debug)]
+pub struct TestSignalGenerator {
+    signal: TestSignal,
+    started: Instant,
+    next_step: Instant,
+    step: u32,
+    /// Note left sounding by the last step
+    sounding: Option<u8>,
+}
+
+impl TestSignalGenerator {
+    /// Steps further behind than this are skipped rather than played in a burst
+    const MAX_CATCH_UP: Duration = Duration::from_secs(1);
+
+    pub fn new(signal: TestSignal, now: Instant) -> Self {
+        Self {
+            signal,
+            started: now,
+            next_step: now,
+            step: 0,
+            sounding: None,
+        }
+    }
+
+    pub fn target(&self) -> &TestSignalTarget {
+        &self.signal.target
+    }
+
+    /// Microseconds since the generator started, for stamping its messages
+    pub fn timestamp_us(&self, now: Instant) -> u64 {
+        now.duration_since(self.started).as_micros() as u64
+    }
+
+    /// Messages for the steps due by `now`
+    pub fn poll(&mut self, now: Instant) -> Vec<Vec<u8>> {
+        if now.saturating_duration_since(self.next_step) > Self::MAX_CATCH_UP {
+            self.next_step = now;
+        }
+        let mut out = Vec::new();
+        while self.next_step <= now {
+            out.extend(self.next_messages());
+            self.next_step += self.interval();
+        }
+        out
+    }
+
+    /// Note Off for the note still sounding, when the signal stops
+    pub fn release(&mut self) -> Vec<Vec<u8>> {
+        let channel = self.channel();
+        self.sounding
+            .take()
+            .map(|note| vec![0x80 | channel, note, 0])
+            .into_iter()
+            .collect()
+    }
+
+    fn interval(&self) -> Duration {
+        match self.signal.pattern {
+            TestPattern::Clock { bpm } => Duration::from_secs_f64(60.0 / (bpm.max(1.0) * 24.0)),
+            _ => Duration::from_millis(u64::from(self.signal.step_ms.max(1))),
+        }
+    }
+
+    fn channel(&self) -> u8 {
+        self.signal.channel.clamp(1, 16) - 1
+    }
+
+    fn next_messages(&mut self) -> Vec<Vec<u8>> {
+        let step = self.step;
+        self.step = self.step.wrapping_add(1);
+        match self.signal.pattern {
+            TestPattern::Chromatic { low, high } => {
+                let (low, high) = (low.min(high), low.max(high).min(127));
+                let span = u32::from(high - low) + 1;
+                self.play(low + (step % span) as u8)
+            }
+            TestPattern::FixedNote { note } => self.play(note.min(127)),
+            TestPattern::CcSweep { cc } => {
+                // Up 0-127, then back down to 1
+                let phase = step % 254;
+                let value = if phase <= 127 { phase } else { 254 - phase };
+                vec![vec![0xB0 | self.channel(), cc.min(127), value as u8]]
+            }
+            TestPattern::Clock { .. } => vec![vec![0xF8]],
+        }
+    }
+
+    /// End the last note and start the next
+    fn play(&mut self, note: u8) -> Vec<Vec<u8>> {
+        let mut out = self.release();
+        out.push(vec![
+            0x90 | self.channel(),
+            note,
+            self.signal.velocity.clamp(1, 127),
+        ]);
+        self.sounding = Some(note);
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn signal(pattern: TestPattern) -> TestSignal {
+        TestSignal {
+            pattern,
+            target: TestSignalTarget::VirtualInput,
+            channel: 2,
+            velocity: 100,
+            step_ms: 100,
+        }
+    }
+
+    #[test]
+    fn plays_a_scale_one_note_per_step() {
+        let t0 = Instant::now();
+        let at = |ms| t0 + Duration::from_millis(ms);
+        let pattern = TestPattern::Chromatic { low: 60, high: 61 };
+        let mut generator = TestSignalGenerator::new(signal(pattern), t0);
+
+        assert_eq!(generator.poll(at(0)), vec![vec![0x91, 60, 100]]);
+        assert!(generator.poll(at(50)).is_empty());
+        assert_eq!(
+            generator.poll(at(100)),
+            vec![vec![0x81, 60, 0], vec![0x91, 61, 100]]
+        );
+        // Around to the bottom again
+        assert_eq!(
+            generator.poll(at(200)),
+            vec![vec![0x81, 61, 0], vec![0x91, 60, 100]]
+        );
+        assert_eq!(generator.release(), vec![vec![0x81, 60, 0]]);
+        assert!(generator.release().is_empty());
+    }
+
+    #[test]
+    fn sweeps_up_and_back_down() {
+        let t0 = Instant::now();
+        let mut generator = TestSignalGenerator::new(signal(TestPattern::CcSweep { cc: 74 }), t0);
+        let values: Vec<u8> = (0..256)
+            .flat_map(|step| generator.poll(t0 + Duration::from_millis(step * 100)))
+            .map(|msg| msg[2])
+            .collect();
+
+        assert_eq!(values[..3], [0, 1, 2]);
+        assert_eq!(values[127], 127);
+        assert_eq!(values[128], 126);
+        assert_eq!(values[253], 1);
+        // The next cycle starts from 0
+        assert_eq!(values[254], 0);
+    }
+
+    #[test]
+    fn clock_follows_its_tempo() {
+        let t0 = Instant::now();
+        let pattern = TestPattern::Clock { bpm: 125.0 };
+        let mut generator = TestSignalGenerator::new(signal(pattern), t0);
+
+        // 125 BPM is 50 pulses a second, one every 20 ms
+        let pulses = generator.poll(t0 + Duration::from_millis(99));
+        assert_eq!(pulses, vec![vec![0xF8]; 5]);
+    }
+}
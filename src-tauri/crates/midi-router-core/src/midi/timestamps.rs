@@ -0,0 +1,125 @@
+//! Monitor timestamp normalization
+//!
+//! Backends stamp incoming messages in microseconds from an arbitrary,
+//! per-port epoch. Each port is anchored to the wall clock on its first
+//! message so monitor timestamps from different ports can be compared,
+//! while keeping the backend's precise spacing between messages.
+
+use std::collections::HashMap;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Re-anchor a port when its stamps drift this far from the wall clock
+/// (backend epoch reset, port reconnected, ...)
+const MAX_DRIFT_US: u64 = 1_000_000;
+
+/// Current wall-clock time in microseconds since the Unix epoch
+pub fn wall_clock_us() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_micros() as u64)
+        .unwrap_or(0)
+}
+
+#[derive(Debug, Default)]
+struct PortClock {
+    /// (raw stamp, wall-clock time) at the anchor point
+    anchor: (u64, u64),
+    /// Normalized time of the previous message
+    last: u64,
+}
+
+/// Converts raw backend stamps to wall-clock time per port
+#[derive(Debug, Default)]
+pub struct MonitorClock {
+    ports: HashMap<String, PortClock>,
+}
+
+impl MonitorClock {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Convert a raw stamp to wall-clock microseconds.
+    /// Returns the normalized time and the delta since the previous message
+    /// on the same port (None for the first message).
+    pub fn normalize(&mut self, port: &str, raw: u64, now: u64) -> (u64, Option<u64>) {
+        let Some(clock) = self.ports.get_mut(port) else {
+            self.ports.insert(
+                port.to_string(),
+                PortClock {
+                    anchor: (raw, now),
+                    last: now,
+                },
+            );
+            return (now, None);
+        };
+
+        let (anchor_raw, anchor_wall) = clock.anchor;
+        let mut time = match raw.checked_sub(anchor_raw) {
+            Some(elapsed) => anchor_wall + elapsed,
+            // The backend's epoch reset; later stamps count from here
+            None => {
+                clock.anchor = (raw, now);
+                now
+            }
+        };
+        if time.abs_diff(now) > MAX_DRIFT_US {
+            clock.anchor = (raw, now);
+            time = now;
+        }
+
+        // Never go backwards within a port
+        let time = time.max(clock.last);
+        let delta = time - clock.last;
+        clock.last = time;
+        (time, Some(delta))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const NOW: u64 = 1_700_000_000_000_000;
+
+    #[test]
+    fn first_message_anchors_to_wall_clock() {
+        let mut clock = MonitorClock::new();
+        assert_eq!(clock.normalize("A", 5_000, NOW), (NOW, None));
+    }
+
+    #[test]
+    fn raw_spacing_is_preserved() {
+        let mut clock = MonitorClock::new();
+        clock.normalize("A", 5_000, NOW);
+        // Delivered late, but the raw stamp says 250 us after the first
+        assert_eq!(
+            clock.normalize("A", 5_250, NOW + 2_000),
+            (NOW + 250, Some(250))
+        );
+    }
+
+    #[test]
+    fn ports_have_separate_epochs() {
+        let mut clock = MonitorClock::new();
+        clock.normalize("A", 5_000, NOW);
+        clock.normalize("B", 999_000_000, NOW + 100);
+
+        let (a, _) = clock.normalize("A", 6_000, NOW + 1_000);
+        let (b, delta) = clock.normalize("B", 999_000_900, NOW + 1_000);
+        assert_eq!(a, NOW + 1_000);
+        assert_eq!(b, NOW + 1_000);
+        assert_eq!(delta, Some(900));
+    }
+
+    #[test]
+    fn epoch_reset_reanchors() {
+        let mut clock = MonitorClock::new();
+        clock.normalize("A", 50_000_000, NOW);
+        // Backend restarted its counter
+        let (time, delta) = clock.normalize("A", 10, NOW + 500);
+        assert_eq!(time, NOW + 500);
+        assert_eq!(delta, Some(500));
+        assert_eq!(clock.normalize("A", 110, NOW + 700).0, NOW + 600);
+    }
+}
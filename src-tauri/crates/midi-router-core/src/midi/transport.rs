@@ -2,6 +2,10 @@
 //!
 //! Constants and helpers for MIDI transport messages (Start, Stop, Continue, Clock).
 
+use crate::midi::notes::SoundingNotes;
+use crate::types::Route;
+use std::collections::BTreeSet;
+
 /// MIDI System Real-Time message bytes
 pub mod messages {
     /// Timing Clock (24 PPQ)
@@ -39,6 +43,54 @@ pub fn get_transport_type(bytes: &[u8]) -> Option<TransportMessage> {
     }
 }
 
+/// Channel mode messages sent on every channel for a global panic:
+/// All Sound Off (CC 120), Reset All Controllers (CC 121), All Notes Off (CC 123)
+pub fn panic_messages() -> Vec<[u8; 3]> {
+    (0..16u8)
+        .flat_map(|ch| {
+            [
+                [0xB0 | ch, 120, 0],
+                [0xB0 | ch, 121, 0],
+                [0xB0 | ch, 123, 0],
+            ]
+        })
+        .collect()
+}
+
+/// Sent after Stop to outputs that opt in, for gear that keeps ringing once
+/// its sequencer stops: All Sound Off (CC 120) and All Notes Off (CC 123)
+/// on every channel
+pub fn stop_silence_messages() -> Vec<[u8; 3]> {
+    (0..16u8)
+        .flat_map(|ch| [[0xB0 | ch, 120, 0], [0xB0 | ch, 123, 0]])
+        .collect()
+}
+
+/// Messages that silence a single route: NoteOff for each note it left
+/// sounding, then All Sound Off and All Notes Off on the channels it uses.
+/// Controllers and sustain are left alone.
+pub fn route_panic_messages(route: &Route, sounding: &mut SoundingNotes) -> Vec<[u8; 3]> {
+    let mut channels: BTreeSet<u8> = (0..16u8).filter(|ch| route.channels.passes(*ch)).collect();
+    channels.extend(sounding.channels());
+    for mapping in &route.cc_mappings {
+        for target in &mapping.targets {
+            channels.extend(target.channels.iter().filter(|ch| **ch < 16));
+        }
+    }
+    if let Some(rotation) = &route.channel_rotation {
+        let rotated = rotation.channels.iter().filter(|ch| (1..=16).contains(*ch));
+        channels.extend(rotated.map(|ch| ch - 1));
+    }
+
+    let mut msgs = sounding.release_all();
+    msgs.extend(
+        channels
+            .into_iter()
+            .flat_map(|ch| [[0xB0 | ch, 120, 0], [0xB0 | ch, 123, 0]]),
+    );
+    msgs
+}
+
 /// Types of MIDI transport messages
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum TransportMessage {
@@ -115,4 +167,51 @@ mod tests {
         assert_eq!(TransportMessage::Start.as_bytes(), &[0xFA]);
         assert_eq!(TransportMessage::Stop.as_bytes(), &[0xFC]);
     }
+
+    #[test]
+    fn panic_messages_cover_all_channels() {
+        let msgs = panic_messages();
+        assert_eq!(msgs.len(), 48);
+        assert_eq!(msgs[0], [0xB0, 120, 0]);
+        assert_eq!(msgs[47], [0xBF, 123, 0]);
+    }
+
+    #[test]
+    fn stop_silence_leaves_controllers_alone() {
+        let msgs = stop_silence_messages();
+        assert_eq!(msgs.len(), 32);
+        assert_eq!(msgs[..2], [[0xB0, 120, 0], [0xB0, 123, 0]]);
+        assert!(msgs.iter().all(|m| m[1] != 121));
+    }
+
+    #[test]
+    fn route_panic_releases_notes_on_route_channels_only() {
+        use crate::types::{ChannelFilter, PortId};
+
+        let mut route = Route::new(
+            PortId::new("Keys".to_string()),
+            PortId::new("Synth".to_string()),
+        );
+        route.channels = ChannelFilter::Only(vec![0]);
+        let mut sounding = SoundingNotes::default();
+        sounding.track(&[0x90, 60, 100]);
+        // Held on a channel outside the filter (e.g. from a CC mapping)
+        sounding.track(&[0x93, 40, 100]);
+
+        let msgs = route_panic_messages(&route, &mut sounding);
+        assert_eq!(
+            msgs,
+            vec![
+                [0x80, 60, 0],
+                [0x83, 40, 0],
+                [0xB0, 120, 0],
+                [0xB0, 123, 0],
+                [0xB3, 120, 0],
+                [0xB3, 123, 0],
+            ]
+        );
+        // No Reset All Controllers
+        assert!(msgs.iter().all(|m| m[1] != 121));
+        assert!(sounding.release_all().is_empty());
+    }
 }
@@ -0,0 +1,195 @@
+//! Microtuning
+//!
+//! Applies a route's tuning table in one of two ways. Synths that support the
+//! MIDI Tuning Standard are retuned with SysEx and receive notes unchanged.
+//! For other synths each note is moved to its own channel from a pool and
+//! detuned there with pitch bend.
+
+use crate::midi::route_state::RouteState;
+use crate::types::{Microtuning, TuningMethod};
+
+/// Notes per MTS Single Note Tuning Change message (the count is 7-bit)
+const MTS_NOTES_PER_MESSAGE: usize = 64;
+
+/// MTS real-time Single Note Tuning Change messages retuning all 128 notes
+/// of tuning program 0 on every device
+pub fn mts_messages(cents: &[f64]) -> Vec<Vec<u8>> {
+    let notes: Vec<usize> = (0..cents.len().min(128)).collect();
+    notes
+        .chunks(MTS_NOTES_PER_MESSAGE)
+        .map(|chunk| {
+            let mut msg = vec![0xF0, 0x7F, 0x7F, 0x08, 0x02, 0x00, chunk.len() as u8];
+            for &note in chunk {
+                let [semitone, msb, lsb] = mts_frequency(note as f64 + cents[note] / 100.0);
+                msg.extend([note as u8, semitone, msb, lsb]);
+            }
+            msg.push(0xF7);
+            msg
+        })
+        .collect()
+}
+
+/// MTS frequency data: semitone, then the 14-bit fraction above it
+fn mts_frequency(pitch: f64) -> [u8; 3] {
+    let pitch = pitch.clamp(0.0, 127.0 + 16383.0 / 16384.0);
+    let mut semitone = pitch.floor() as u32;
+    let mut fraction = ((pitch - pitch.floor()) * 16384.0).round() as u32;
+    if fraction == 16384 {
+        semitone += 1;
+        fraction = 0;
+    }
+    [
+        semitone as u8,
+        (fraction >> 7) as u8,
+        (fraction & 0x7F) as u8,
+    ]
+}
+
+/// Apply a route's pitch-bend microtuning to an outgoing message.
+/// MTS tunings and routes without microtuning pass messages unchanged.
+pub fn retune(bytes: &[u8], tuning: Option<&Microtuning>, state: &mut RouteState) -> Vec<Vec<u8>> {
+    let (Some(tuning), [status, ..]) = (tuning, bytes) else {
+        return vec![bytes.to_vec()];
+    };
+    let TuningMethod::PitchBend {
+        channels,
+        bend_range,
+    } = &tuning.method
+    else {
+        return vec![bytes.to_vec()];
+    };
+    if channels.is_empty() || *status >= 0xF0 || bytes.len() < 2 {
+        return vec![bytes.to_vec()];
+    }
+
+    let in_channel = status & 0x0F;
+    match (status & 0xF0, bytes) {
+        (0x90, [_, note, velocity, ..]) if *velocity > 0 => {
+            let pitch =
+                *note as f64 + tuning.table.cents.get(*note as usize).unwrap_or(&0.0) / 100.0;
+            let out_note = pitch.round().clamp(0.0, 127.0);
+            let bend = pitch_bend(pitch - out_note, *bend_range);
+            let out_channel = allocate_channel(channels, state);
+            state
+                .tuning_voices
+                .insert((in_channel, *note), (out_channel, out_note as u8));
+            vec![
+                vec![0xE0 | out_channel, (bend & 0x7F) as u8, (bend >> 7) as u8],
+                vec![0x90 | out_channel, out_note as u8, *velocity],
+            ]
+        }
+        (0x80 | 0x90, [_, note, velocity, ..]) => {
+            match state.tuning_voices.remove(&(in_channel, *note)) {
+                Some((out_channel, out_note)) => {
+                    vec![vec![0x80 | out_channel, out_note, *velocity]]
+                }
+                None => vec![bytes.to_vec()],
+            }
+        }
+        (0xA0, [_, note, pressure, ..]) => match state.tuning_voices.get(&(in_channel, *note)) {
+            Some((out_channel, out_note)) => vec![vec![0xA0 | out_channel, *out_note, *pressure]],
+            None => vec![bytes.to_vec()],
+        },
+        // Pitch bend would undo the tuning, so the player's bends are dropped
+        (0xE0, _) => Vec::new(),
+        // Controllers, program changes and channel pressure reach every voice
+        (kind, [_, data @ ..]) => channels
+            .iter()
+            .map(|ch| [&[kind | ch][..], data].concat())
+            .collect(),
+        _ => vec![bytes.to_vec()],
+    }
+}
+
+/// 14-bit pitch bend for an offset in semitones
+fn pitch_bend(semitones: f64, bend_range: u8) -> u16 {
+    let range = f64::from(bend_range.max(1));
+    (8192.0 + semitones / range * 8192.0)
+        .round()
+        .clamp(0.0, 16383.0) as u16
+}
+
+/// Next pool channel without a sounding voice, or the oldest in rotation if
+/// every channel is busy
+fn allocate_channel(channels: &[u8], state: &mut RouteState) -> u8 {
+    let start = state.next_tuning_channel % channels.len();
+    let offset = (0..channels.len())
+        .find(|i| {
+            let ch = channels[(start + i) % channels.len()];
+            !state.tuning_voices.values().any(|(used, _)| *used == ch)
+        })
+        .unwrap_or(0);
+    let index = (start + offset) % channels.len();
+    state.next_tuning_channel = index + 1;
+    channels[index]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::TuningTable;
+
+    fn quarter_tone_sharp(method: TuningMethod) -> Microtuning {
+        Microtuning {
+            table: TuningTable {
+                name: "Test".to_string(),
+                cents: vec![50.0; 128],
+            },
+            method,
+        }
+    }
+
+    #[test]
+    fn mts_covers_all_notes() {
+        let messages = mts_messages(&[50.0; 128]);
+        assert_eq!(messages.len(), 2);
+        assert_eq!(messages[0].len(), 7 + 64 * 4 + 1);
+        // Note 0 tuned a quarter tone up: semitone 0, fraction 8192
+        assert_eq!(&messages[0][7..11], &[0, 0, 64, 0]);
+        assert_eq!(&messages[1][7..11], &[64, 64, 64, 0]);
+        assert_eq!(messages[1].last(), Some(&0xF7));
+    }
+
+    #[test]
+    fn pitch_bend_method_spreads_notes_over_channels() {
+        let tuning = quarter_tone_sharp(TuningMethod::PitchBend {
+            channels: vec![1, 2],
+            bend_range: 2,
+        });
+        let mut state = RouteState::default();
+
+        // 60 + 0.5 rounds up to 61, bent down a quarter tone (-0.5 of 2 semitones)
+        assert_eq!(
+            retune(&[0x90, 60, 100], Some(&tuning), &mut state),
+            vec![vec![0xE1, 0x00, 0x30], vec![0x91, 61, 100]]
+        );
+        assert_eq!(
+            retune(&[0x90, 64, 90], Some(&tuning), &mut state),
+            vec![vec![0xE2, 0x00, 0x30], vec![0x92, 65, 90]]
+        );
+        assert_eq!(
+            retune(&[0x80, 60, 0], Some(&tuning), &mut state),
+            vec![vec![0x81, 61, 0]]
+        );
+        // Channel 1 is free again
+        assert_eq!(
+            retune(&[0x90, 67, 80], Some(&tuning), &mut state)[1],
+            vec![0x91, 68, 80]
+        );
+        // Sustain pedal goes to every voice channel
+        assert_eq!(
+            retune(&[0xB0, 64, 127], Some(&tuning), &mut state),
+            vec![vec![0xB1, 64, 127], vec![0xB2, 64, 127]]
+        );
+    }
+
+    #[test]
+    fn mts_method_passes_notes_unchanged() {
+        let tuning = quarter_tone_sharp(TuningMethod::Mts);
+        let mut state = RouteState::default();
+        assert_eq!(
+            retune(&[0x90, 60, 100], Some(&tuning), &mut state),
+            vec![vec![0x90, 60, 100]]
+        );
+    }
+}
@@ -0,0 +1,309 @@
+//! Route configuration checks
+//!
+//! Finds problems in a route set before they show up as silent failures live.
+
+use crate::types::{MidiPort, PortAvailability, Route, RouteWarning, UnavailableRoute};
+use std::collections::{HashMap, HashSet, VecDeque};
+
+/// Validate routes against the currently available ports
+pub fn validate_routes(
+    routes: &[Route],
+    inputs: &[MidiPort],
+    outputs: &[MidiPort],
+) -> Vec<RouteWarning> {
+    let input_names: HashSet<&str> = inputs.iter().map(|p| p.id.name.as_str()).collect();
+    let output_names: HashSet<&str> = outputs.iter().map(|p| p.id.name.as_str()).collect();
+
+    let mut warnings = Vec::new();
+
+    for route in routes {
+        if !input_names.contains(route.source.name.as_str()) {
+            warnings.push(RouteWarning::MissingSource {
+                route_id: route.id,
+                port_name: route.source.name.clone(),
+            });
+        }
+        if !output_names.contains(route.destination.name.as_str()) {
+            warnings.push(RouteWarning::MissingDestination {
+                route_id: route.id,
+                port_name: route.destination.name.clone(),
+            });
+        }
+        if (0..16).all(|ch| !route.channels.passes(ch)) {
+            warnings.push(RouteWarning::NoChannels { route_id: route.id });
+        }
+        for mapping in &route.cc_mappings {
+            if mapping.targets.is_empty() || mapping.targets.iter().any(|t| t.channels.is_empty()) {
+                warnings.push(RouteWarning::EmptyCcTargets {
+                    route_id: route.id,
+                    source_cc: mapping.source_cc,
+                });
+            }
+        }
+    }
+
+    warnings.extend(find_duplicates(routes));
+    warnings.extend(find_cycles(routes));
+    warnings
+}
+
+/// Split routes into those whose ports are all present and those that
+/// reference missing devices
+pub fn check_port_availability(
+    routes: &[Route],
+    inputs: &[MidiPort],
+    outputs: &[MidiPort],
+) -> PortAvailability {
+    let missing = |ports: &[MidiPort], name: &str| {
+        (!ports.iter().any(|p| p.id.name == name)).then(|| name.to_string())
+    };
+
+    let mut availability = PortAvailability::default();
+    for route in routes {
+        let missing_source = missing(inputs, &route.source.name);
+        let missing_destination = missing(outputs, &route.destination.name);
+        if missing_source.is_none() && missing_destination.is_none() {
+            availability.available.push(route.id);
+        } else {
+            availability.unavailable.push(UnavailableRoute {
+                route_id: route.id,
+                missing_source,
+                missing_destination,
+            });
+        }
+    }
+    availability
+}
+
+fn find_duplicates(routes: &[Route]) -> Vec<RouteWarning> {
+    let mut pairs: Vec<((&str, &str), Vec<uuid::Uuid>)> = Vec::new();
+    for route in routes {
+        let key = (route.source.name.as_str(), route.destination.name.as_str());
+        match pairs.iter_mut().find(|(k, _)| *k == key) {
+            Some((_, ids)) => ids.push(route.id),
+            None => pairs.push((key, vec![route.id])),
+        }
+    }
+
+    pairs
+        .into_iter()
+        .filter(|(_, ids)| ids.len() > 1)
+        .map(
+            |((source, destination), route_ids)| RouteWarning::Duplicate {
+                route_ids,
+                source: source.to_string(),
+                destination: destination.to_string(),
+            },
+        )
+        .collect()
+}
+
+/// A route is part of a cycle when its destination leads back to its source
+/// (same port name on both sides, e.g. a virtual bus) through enabled routes.
+fn find_cycles(routes: &[Route]) -> Vec<RouteWarning> {
+    let mut edges: HashMap<&str, Vec<&str>> = HashMap::new();
+    for route in routes.iter().filter(|r| r.enabled) {
+        edges
+            .entry(route.source.name.as_str())
+            .or_default()
+            .push(route.destination.name.as_str());
+    }
+
+    routes
+        .iter()
+        .filter(|r| r.enabled)
+        .filter_map(|route| {
+            let path = shortest_path(&edges, &route.destination.name, &route.source.name)?;
+            let mut full = vec![route.source.name.clone()];
+            full.extend(path.into_iter().map(str::to_string));
+            Some(RouteWarning::Cycle {
+                route_id: route.id,
+                path: full,
+            })
+        })
+        .collect()
+}
+
+fn shortest_path<'a>(
+    edges: &HashMap<&'a str, Vec<&'a str>>,
+    from: &'a str,
+    to: &str,
+) -> Option<Vec<&'a str>> {
+    let mut previous: HashMap<&str, &str> = HashMap::new();
+    let mut queue = VecDeque::from([from]);
+    let mut visited = HashSet::from([from]);
+
+    while let Some(node) = queue.pop_front() {
+        if node == to {
+            let mut path = vec![node];
+            let mut current = node;
+            while let Some(&prev) = previous.get(current) {
+                path.push(prev);
+                current = prev;
+            }
+            path.reverse();
+            return Some(path);
+        }
+        for &next in edges.get(node).into_iter().flatten() {
+            if visited.insert(next) {
+                previous.insert(next, node);
+                queue.push_back(next);
+            }
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::{CcMapping, CcTarget, ChannelFilter, PortId};
+
+    fn port(name: &str, is_input: bool) -> MidiPort {
+        MidiPort {
+            id: PortId::new(name.to_string()),
+            is_input,
+        }
+    }
+
+    fn route(source: &str, dest: &str) -> Route {
+        Route::new(
+            PortId::new(source.to_string()),
+            PortId::new(dest.to_string()),
+        )
+    }
+
+    #[test]
+    fn valid_routes_have_no_warnings() {
+        let routes = vec![route("Keys", "Synth")];
+        let warnings = validate_routes(&routes, &[port("Keys", true)], &[port("Synth", false)]);
+        assert!(warnings.is_empty());
+    }
+
+    #[test]
+    fn missing_ports_are_reported() {
+        let routes = vec![route("Keys", "Synth")];
+        let warnings = validate_routes(&routes, &[], &[]);
+        assert!(warnings.iter().any(
+            |w| matches!(w, RouteWarning::MissingSource { port_name, .. } if port_name == "Keys")
+        ));
+        assert!(warnings
+            .iter()
+            .any(|w| matches!(w, RouteWarning::MissingDestination { port_name, .. } if port_name == "Synth")));
+    }
+
+    #[test]
+    fn availability_splits_routes_by_missing_ports() {
+        let ok = route("Keys", "Synth");
+        let no_dest = route("Keys", "Drums");
+        let neither = route("Pads", "Lights");
+        let availability = check_port_availability(
+            &[ok.clone(), no_dest.clone(), neither.clone()],
+            &[port("Keys", true)],
+            &[port("Synth", false)],
+        );
+
+        assert_eq!(availability.available, vec![ok.id]);
+        assert_eq!(
+            availability.unavailable,
+            vec![
+                UnavailableRoute {
+                    route_id: no_dest.id,
+                    missing_source: None,
+                    missing_destination: Some("Drums".to_string()),
+                },
+                UnavailableRoute {
+                    route_id: neither.id,
+                    missing_source: Some("Pads".to_string()),
+                    missing_destination: Some("Lights".to_string()),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn duplicates_are_reported_once() {
+        let routes = vec![
+            route("Keys", "Synth"),
+            route("Keys", "Synth"),
+            route("Keys", "Drums"),
+        ];
+        let warnings = find_duplicates(&routes);
+        assert_eq!(warnings.len(), 1);
+        match &warnings[0] {
+            RouteWarning::Duplicate { route_ids, .. } => assert_eq!(route_ids.len(), 2),
+            other => panic!("Expected Duplicate, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn cycle_through_bus_is_reported() {
+        let routes = vec![
+            route("Bus 1", "Bus 2"),
+            route("Bus 2", "Bus 1"),
+            route("Keys", "Bus 1"),
+        ];
+        let warnings = find_cycles(&routes);
+        assert_eq!(warnings.len(), 2);
+        match &warnings[0] {
+            RouteWarning::Cycle { path, .. } => {
+                assert_eq!(
+                    path,
+                    &vec![
+                        "Bus 1".to_string(),
+                        "Bus 2".to_string(),
+                        "Bus 1".to_string()
+                    ]
+                );
+            }
+            other => panic!("Expected Cycle, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn disabled_routes_do_not_form_cycles() {
+        let mut back = route("Bus 2", "Bus 1");
+        back.enabled = false;
+        let routes = vec![route("Bus 1", "Bus 2"), back];
+        assert!(find_cycles(&routes).is_empty());
+    }
+
+    #[test]
+    fn channel_filter_blocking_everything_is_reported() {
+        let mut r = route("Keys", "Synth");
+        r.channels = ChannelFilter::Except((0..16).collect());
+        let warnings = validate_routes(&[r], &[port("Keys", true)], &[port("Synth", false)]);
+        assert!(matches!(warnings[0], RouteWarning::NoChannels { .. }));
+    }
+
+    #[test]
+    fn empty_cc_targets_are_reported() {
+        let mut r = route("Keys", "Synth");
+        r.cc_mappings = vec![
+            CcMapping {
+                source_cc: 1,
+                targets: vec![],
+                ..Default::default()
+            },
+            CcMapping {
+                source_cc: 2,
+                targets: vec![CcTarget {
+                    cc: 74,
+                    channels: vec![],
+                    ..Default::default()
+                }],
+                ..Default::default()
+            },
+        ];
+        let warnings = validate_routes(&[r], &[port("Keys", true)], &[port("Synth", false)]);
+        assert_eq!(warnings.len(), 2);
+        assert!(matches!(
+            warnings[0],
+            RouteWarning::EmptyCcTargets { source_cc: 1, .. }
+        ));
+        assert!(matches!(
+            warnings[1],
+            RouteWarning::EmptyCcTargets { source_cc: 2, .. }
+        ));
+    }
+}
@@ -0,0 +1,286 @@
+//! Voice distribution
+//!
+//! Turns a rack of mono synths into one polyphonic instrument: each note
+//! goes to a port that isn't playing, preferring the one idle longest so
+//! release tails ring out, and steals a voice when all are busy. Note Offs
+//! go to the port playing their note; channel-wide messages go everywhere.
+
+use crate::types::VoiceStealing;
+use std::collections::HashMap;
+
+#[derive(Debug)]
+struct Voice {
+    port: String,
+    channel: u8,
+    note: u8,
+    /// When the note started, in note events
+    started: u64,
+}
+
+impl Voice {
+    fn plays(&self, channel: u8, note: u8) -> bool {
+        self.channel == channel && self.note == note
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct VoiceAllocator {
+    sounding: Vec<Voice>,
+    /// When each port's last note ended, in note events
+    released: HashMap<String, u64>,
+    /// Note Ons and Offs seen so far
+    clock: u64,
+    /// Notes dealt so far, picking the next port in turn
+    dealt: usize,
+}
+
+impl VoiceAllocator {
+    /// Address a message to the ports that should play it
+    pub fn distribute(
+        &mut self,
+        bytes: &[u8],
+        ports: &[String],
+        stealing: VoiceStealing,
+    ) -> Vec<(String, Vec<u8>)> {
+        let everywhere = || {
+            ports
+                .iter()
+                .map(|port| (port.clone(), bytes.to_vec()))
+                .collect()
+        };
+        let status = match bytes.first() {
+            Some(status) if (0x80..0xF0).contains(status) => *status,
+            _ => return everywhere(),
+        };
+        let channel = status & 0x0F;
+        if status & 0xE0 == 0x80 {
+            self.clock += 1;
+        }
+
+        match (status & 0xF0, bytes) {
+            (0x90, [_, note, velocity]) if *velocity > 0 => {
+                // A retriggered note stays on its port
+                if let Some(voice) = self.sounding.iter_mut().find(|v| v.plays(channel, *note)) {
+                    voice.started = self.clock;
+                    return vec![(voice.port.clone(), bytes.to_vec())];
+                }
+
+                let mut out = Vec::new();
+                let free = ports
+                    .iter()
+                    .filter(|port| !self.sounding.iter().any(|v| &v.port == *port))
+                    .min_by_key(|port| self.released.get(*port).copied().unwrap_or(0));
+                let port = match free {
+                    Some(port) => port.clone(),
+                    None => {
+                        let stolen = match stealing {
+                            VoiceStealing::Oldest => self.oldest(),
+                            VoiceStealing::Newest => self.newest(),
+                            VoiceStealing::Never => None,
+                        };
+                        let Some(index) = stolen else {
+                            return out;
+                        };
+                        let voice = self.sounding.remove(index);
+                        out.push((
+                            voice.port.clone(),
+                            vec![0x80 | voice.channel, voice.note, 0],
+                        ));
+                        voice.port
+                    }
+                };
+                self.sounding.push(Voice {
+                    port: port.clone(),
+                    channel,
+                    note: *note,
+                    started: self.clock,
+                });
+                out.push((port, bytes.to_vec()));
+                out
+            }
+            // Notes that were stolen or never played have nothing to end
+            (0x80 | 0x90, [_, note, _]) => {
+                match self.sounding.iter().position(|v| v.plays(channel, *note)) {
+                    Some(index) => {
+                        let voice = self.sounding.remove(index);
+                        self.released.insert(voice.port.clone(), self.clock);
+                        vec![(voice.port, bytes.to_vec())]
+                    }
+                    None => Vec::new(),
+                }
+            }
+            (0xA0, [_, note, _]) => self
+                .sounding
+                .iter()
+                .filter(|v| v.plays(channel, *note))
+                .map(|v| (v.port.clone(), bytes.to_vec()))
+                .collect(),
+            _ => everywhere(),
+        }
+    }
+
+    /// Address a message like `distribute`, but deal Note Ons to the ports
+    /// in turn, however many are already sounding there
+    pub fn deal(&mut self, bytes: &[u8], ports: &[String]) -> Vec<(String, Vec<u8>)> {
+        let &[status, note, velocity] = bytes else {
+            return self.distribute(bytes, ports, VoiceStealing::Never);
+        };
+        if status & 0xF0 != 0x90 || velocity == 0 || ports.is_empty() {
+            return self.distribute(bytes, ports, VoiceStealing::Never);
+        }
+        let channel = status & 0x0F;
+        self.clock += 1;
+
+        // A retriggered note stays on its port
+        if let Some(voice) = self.sounding.iter_mut().find(|v| v.plays(channel, note)) {
+            voice.started = self.clock;
+            return vec![(voice.port.clone(), bytes.to_vec())];
+        }
+        let port = ports[self.dealt % ports.len()].clone();
+        self.dealt = self.dealt.wrapping_add(1);
+        self.sounding.push(Voice {
+            port: port.clone(),
+            channel,
+            note,
+            started: self.clock,
+        });
+        vec![(port, bytes.to_vec())]
+    }
+
+    /// Note Offs for every sounding voice
+    pub fn release_all(&mut self) -> Vec<(String, Vec<u8>)> {
+        self.sounding
+            .drain(..)
+            .map(|v| (v.port, vec![0x80 | v.channel, v.note, 0]))
+            .collect()
+    }
+
+    fn oldest(&self) -> Option<usize> {
+        (0..self.sounding.len()).min_by_key(|i| self.sounding[*i].started)
+    }
+
+    fn newest(&self) -> Option<usize> {
+        (0..self.sounding.len()).max_by_key(|i| self.sounding[*i].started)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ports() -> Vec<String> {
+        vec!["Mono A".to_string(), "Mono B".to_string()]
+    }
+
+    fn port(name: &str, bytes: &[u8]) -> (String, Vec<u8>) {
+        (name.to_string(), bytes.to_vec())
+    }
+
+    #[test]
+    fn one_note_per_port() {
+        let mut voices = VoiceAllocator::default();
+        let mut play = |bytes: &[u8]| voices.distribute(bytes, &ports(), VoiceStealing::Oldest);
+
+        assert_eq!(
+            play(&[0x90, 60, 100]),
+            vec![port("Mono A", &[0x90, 60, 100])]
+        );
+        assert_eq!(
+            play(&[0x90, 64, 100]),
+            vec![port("Mono B", &[0x90, 64, 100])]
+        );
+        assert_eq!(play(&[0x80, 60, 0]), vec![port("Mono A", &[0x80, 60, 0])]);
+        assert_eq!(
+            play(&[0x90, 67, 100]),
+            vec![port("Mono A", &[0x90, 67, 100])]
+        );
+        assert_eq!(
+            play(&[0xB0, 1, 64]),
+            vec![
+                port("Mono A", &[0xB0, 1, 64]),
+                port("Mono B", &[0xB0, 1, 64])
+            ]
+        );
+    }
+
+    #[test]
+    fn steals_by_policy() {
+        let mut voices = VoiceAllocator::default();
+        voices.distribute(&[0x90, 60, 100], &ports(), VoiceStealing::Oldest);
+        voices.distribute(&[0x90, 64, 100], &ports(), VoiceStealing::Oldest);
+
+        // The oldest note gives up its port
+        assert_eq!(
+            voices.distribute(&[0x90, 67, 100], &ports(), VoiceStealing::Oldest),
+            vec![
+                port("Mono A", &[0x80, 60, 0]),
+                port("Mono A", &[0x90, 67, 100])
+            ]
+        );
+        // Its Note Off no longer has a voice to end
+        assert!(voices
+            .distribute(&[0x80, 60, 0], &ports(), VoiceStealing::Oldest)
+            .is_empty());
+
+        assert_eq!(
+            voices.distribute(&[0x90, 72, 100], &ports(), VoiceStealing::Newest),
+            vec![
+                port("Mono A", &[0x80, 67, 0]),
+                port("Mono A", &[0x90, 72, 100])
+            ]
+        );
+        assert!(voices
+            .distribute(&[0x90, 76, 100], &ports(), VoiceStealing::Never)
+            .is_empty());
+    }
+
+    #[test]
+    fn prefers_the_port_idle_longest() {
+        let mut voices = VoiceAllocator::default();
+        let mut play = |bytes: &[u8]| voices.distribute(bytes, &ports(), VoiceStealing::Oldest);
+
+        play(&[0x90, 60, 100]);
+        play(&[0x90, 64, 100]);
+        play(&[0x80, 64, 0]);
+        play(&[0x80, 60, 0]);
+        // Mono B has been releasing longer
+        assert_eq!(
+            play(&[0x90, 67, 100]),
+            vec![port("Mono B", &[0x90, 67, 100])]
+        );
+        assert_eq!(voices.release_all(), vec![port("Mono B", &[0x80, 67, 0])]);
+    }
+
+    #[test]
+    fn deals_notes_in_turn() {
+        let mut voices = VoiceAllocator::default();
+        let mut play = |bytes: &[u8]| voices.deal(bytes, &ports());
+
+        assert_eq!(
+            play(&[0x90, 60, 100]),
+            vec![port("Mono A", &[0x90, 60, 100])]
+        );
+        assert_eq!(
+            play(&[0x90, 64, 100]),
+            vec![port("Mono B", &[0x90, 64, 100])]
+        );
+        // Busy ports take more notes rather than stealing
+        assert_eq!(
+            play(&[0x90, 67, 100]),
+            vec![port("Mono A", &[0x90, 67, 100])]
+        );
+        assert_eq!(play(&[0x80, 64, 0]), vec![port("Mono B", &[0x80, 64, 0])]);
+        assert_eq!(play(&[0x90, 60, 0]), vec![port("Mono A", &[0x90, 60, 0])]);
+        assert_eq!(
+            play(&[0x90, 72, 100]),
+            vec![port("Mono B", &[0x90, 72, 100])]
+        );
+        assert_eq!(
+            voices.release_all(),
+            vec![
+                port("Mono A", &[0x80, 67, 0]),
+                port("Mono B", &[0x80, 72, 0])
+            ]
+        );
+    }
+}
@@ -0,0 +1,58 @@
+//! System wake detection
+//!
+//! Connections often die across system sleep (CoreMIDI especially) without
+//! reporting any error. The monotonic clock stops while the system sleeps
+//! and the wall clock doesn't, so the engine notices a wake as the wall clock
+//! jumping ahead of the monotonic one, without any OS notification hooks.
+
+use std::time::{Duration, Instant};
+
+#[derive(Debug)]
+pub struct WakeDetector {
+    last: Instant,
+    last_wall_us: u64,
+}
+
+impl WakeDetector {
+    /// Wall-clock time gained on the monotonic clock that counts as a sleep;
+    /// well beyond any clock adjustment or a stalled loop iteration
+    const MIN_SLEEP: Duration = Duration::from_secs(5);
+
+    pub fn new(now: Instant, wall_us: u64) -> Self {
+        Self {
+            last: now,
+            last_wall_us: wall_us,
+        }
+    }
+
+    /// How long the system slept since the last check, if it did
+    pub fn check(&mut self, now: Instant, wall_us: u64) -> Option<Duration> {
+        let monotonic = now.duration_since(self.last);
+        let wall = Duration::from_micros(wall_us.saturating_sub(self.last_wall_us));
+        self.last = now;
+        self.last_wall_us = wall_us;
+        let slept = wall.checked_sub(monotonic)?;
+        (slept >= Self::MIN_SLEEP).then_some(slept)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn wall_clock_jump_is_a_wake() {
+        let t0 = Instant::now();
+        let mut detector = WakeDetector::new(t0, 1_000_000);
+
+        // Both clocks moved together
+        assert_eq!(detector.check(t0 + Duration::from_secs(1), 2_000_000), None);
+        // A small adjustment isn't a sleep
+        assert_eq!(detector.check(t0 + Duration::from_secs(2), 4_000_000), None);
+        // An hour passed on the wall clock during one second of uptime
+        let slept = detector.check(t0 + Duration::from_secs(3), 3_605_000_000);
+        assert_eq!(slept, Some(Duration::from_secs(3600)));
+        // Clock set backwards
+        assert_eq!(detector.check(t0 + Duration::from_secs(4), 1_000_000), None);
+    }
+}
@@ -0,0 +1,234 @@
+//! Web MIDI bridge
+//!
+//! Serves chosen ports to browser-based editors and librarians over a local
+//! WebSocket, so they reach hardware through the router without extra
+//! drivers. A client first gets the exposed port names as JSON text,
+//! `{"inputs": [...], "outputs": [...]}`. After that every binary frame is a
+//! port index byte followed by one MIDI message: an index into `inputs` for
+//! messages the router received, into `outputs` for messages to send.
+
+use crate::types::WebBridgeSettings;
+use crossbeam_channel::{unbounded, Receiver, Sender};
+use std::io::ErrorKind;
+use std::net::{TcpListener, TcpStream};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+use tungstenite::Message;
+
+/// Outgoing frame queues of the connected clients
+type Clients = Arc<Mutex<Vec<Sender<Vec<u8>>>>>;
+
+pub struct WebBridge {
+    settings: WebBridgeSettings,
+    clients: Clients,
+    stop: Arc<AtomicBool>,
+    thread_handle: Option<thread::JoinHandle<()>>,
+}
+
+impl WebBridge {
+    /// How long the server threads wait for a connection or a frame before
+    /// checking for outgoing frames and shutdown
+    const POLL_INTERVAL: Duration = Duration::from_millis(5);
+    /// Time a client has to complete the WebSocket handshake
+    const HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(5);
+
+    /// Start serving on localhost. Messages browsers send to an exposed
+    /// output are handed to `to_engine` as (output name, bytes).
+    pub fn start(
+        settings: WebBridgeSettings,
+        to_engine: Sender<(String, Vec<u8>)>,
+    ) -> Result<Self, String> {
+        let listener = TcpListener::bind(("127.0.0.1", settings.port))
+            .map_err(|e| format!("Failed to open web bridge port {}: {}", settings.port, e))?;
+        listener
+            .set_nonblocking(true)
+            .map_err(|e| format!("Failed to open web bridge port {}: {}", settings.port, e))?;
+        eprintln!("[BRIDGE] Listening on ws://127.0.0.1:{}", settings.port);
+
+        let clients: Clients = Arc::new(Mutex::new(Vec::new()));
+        let stop = Arc::new(AtomicBool::new(false));
+        let thread_handle = {
+            let (settings, clients, stop) = (settings.clone(), clients.clone(), stop.clone());
+            thread::spawn(move || accept_loop(listener, settings, clients, stop, to_engine))
+        };
+
+        Ok(Self {
+            settings,
+            clients,
+            stop,
+            thread_handle: Some(thread_handle),
+        })
+    }
+
+    pub fn settings(&self) -> &WebBridgeSettings {
+        &self.settings
+    }
+
+    /// Send a message the router received to every client, if its input
+    /// is exposed
+    pub fn forward(&self, port: &str, bytes: &[u8]) {
+        let Some(index) = self.settings.inputs.iter().position(|p| p == port) else {
+            return;
+        };
+        let frame = encode_frame(index, bytes);
+        // Clients that went away have dropped their queue
+        self.clients
+            .lock()
+            .unwrap()
+            .retain(|client| client.send(frame.clone()).is_ok());
+    }
+}
+
+impl Drop for WebBridge {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+        if let Some(handle) = self.thread_handle.take() {
+            let _ = handle.join();
+        }
+        eprintln!("[BRIDGE] Stopped");
+    }
+}
+
+/// A frame carrying a message from the exposed port at `index`
+pub fn encode_frame(index: usize, bytes: &[u8]) -> Vec<u8> {
+    let mut frame = Vec::with_capacity(bytes.len() + 1);
+    frame.push(index as u8);
+    frame.extend_from_slice(bytes);
+    frame
+}
+
+/// The port and message a client's frame addresses, if the port is one of
+/// `ports` and the message starts with a status byte
+pub fn decode_frame<'a>(frame: &'a [u8], ports: &'a [String]) -> Option<(&'a str, &'a [u8])> {
+    match frame {
+        [index, bytes @ ..] if bytes.first().is_some_and(|status| *status >= 0x80) => {
+            let port = ports.get(usize::from(*index))?;
+            Some((port, bytes))
+        }
+        _ => None,
+    }
+}
+
+fn accept_loop(
+    listener: TcpListener,
+    settings: WebBridgeSettings,
+    clients: Clients,
+    stop: Arc<AtomicBool>,
+    to_engine: Sender<(String, Vec<u8>)>,
+) {
+    let mut client_threads = Vec::new();
+    while !stop.load(Ordering::Relaxed) {
+        match listener.accept() {
+            Ok((stream, addr)) => {
+                eprintln!("[BRIDGE] Client connected from {}", addr);
+                let (tx, rx) = unbounded();
+                clients.lock().unwrap().push(tx);
+                let (settings, stop, to_engine) =
+                    (settings.clone(), stop.clone(), to_engine.clone());
+                client_threads.push(thread::spawn(move || {
+                    if let Err(e) = serve_client(stream, &settings, rx, &stop, &to_engine) {
+                        eprintln!("[BRIDGE] Client {} disconnected: {}", addr, e);
+                    }
+                }));
+            }
+            Err(e) if e.kind() == ErrorKind::WouldBlock => {
+                thread::sleep(WebBridge::POLL_INTERVAL);
+            }
+            Err(e) => {
+                eprintln!("[BRIDGE] Accept failed: {}", e);
+                thread::sleep(WebBridge::POLL_INTERVAL);
+            }
+        }
+        client_threads.retain(|handle| !handle.is_finished());
+    }
+    for handle in client_threads {
+        let _ = handle.join();
+    }
+}
+
+/// Exchange frames with one client until it disconnects or the bridge stops
+fn serve_client(
+    stream: TcpStream,
+    settings: &WebBridgeSettings,
+    outgoing: Receiver<Vec<u8>>,
+    stop: &AtomicBool,
+    to_engine: &Sender<(String, Vec<u8>)>,
+) -> Result<(), String> {
+    stream.set_nonblocking(false).map_err(|e| e.to_string())?;
+    stream
+        .set_read_timeout(Some(WebBridge::HANDSHAKE_TIMEOUT))
+        .map_err(|e| e.to_string())?;
+    let mut ws = tungstenite::accept(stream).map_err(|e| e.to_string())?;
+    // Reads give up quickly from here on so outgoing frames aren't held up
+    ws.get_ref()
+        .set_read_timeout(Some(WebBridge::POLL_INTERVAL))
+        .map_err(|e| e.to_string())?;
+
+    let hello = serde_json::json!({
+        "inputs": settings.inputs,
+        "outputs": settings.outputs,
+    });
+    ws.send(Message::Text(hello.to_string()))
+        .map_err(|e| e.to_string())?;
+
+    while !stop.load(Ordering::Relaxed) {
+        let mut wrote = false;
+        while let Ok(frame) = outgoing.try_recv() {
+            ws.write(Message::Binary(frame))
+                .map_err(|e| e.to_string())?;
+            wrote = true;
+        }
+        if wrote {
+            ws.flush().map_err(|e| e.to_string())?;
+        }
+
+        match ws.read() {
+            Ok(Message::Binary(frame)) => match decode_frame(&frame, &settings.outputs) {
+                Some((port, bytes)) => {
+                    let _ = to_engine.send((port.to_string(), bytes.to_vec()));
+                }
+                None => eprintln!("[BRIDGE] Ignoring malformed frame {:02X?}", frame),
+            },
+            // Pings are answered by tungstenite; text isn't part of the protocol
+            Ok(_) => {}
+            Err(tungstenite::Error::Io(e))
+                if matches!(e.kind(), ErrorKind::WouldBlock | ErrorKind::TimedOut) => {}
+            Err(tungstenite::Error::ConnectionClosed) => return Ok(()),
+            Err(e) => return Err(e.to_string()),
+        }
+    }
+    let _ = ws.close(None);
+    let _ = ws.flush();
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn frames_carry_a_port_index_and_one_message() {
+        let ports = vec!["Synth".to_string(), "Drum Machine".to_string()];
+
+        let frame = encode_frame(1, &[0x90, 60, 100]);
+        assert_eq!(frame, vec![1, 0x90, 60, 100]);
+        assert_eq!(
+            decode_frame(&frame, &ports),
+            Some(("Drum Machine", &[0x90, 60, 100][..]))
+        );
+
+        let sysex = [0, 0xF0, 0x7E, 0x7F, 0x06, 0x01, 0xF7];
+        assert_eq!(decode_frame(&sysex, &ports), Some(("Synth", &sysex[1..])));
+    }
+
+    #[test]
+    fn rejects_unknown_ports_and_bare_data() {
+        let ports = vec!["Synth".to_string()];
+        assert_eq!(decode_frame(&[1, 0x90, 60, 100], &ports), None);
+        assert_eq!(decode_frame(&[0, 60, 100], &ports), None);
+        assert_eq!(decode_frame(&[0], &ports), None);
+        assert_eq!(decode_frame(&[], &ports), None);
+    }
+}
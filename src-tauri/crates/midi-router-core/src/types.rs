@@ -0,0 +1,2464 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
+use uuid::Uuid;
+
+// =============================================================================
+// Validation Error
+// =============================================================================
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub enum ValidationError {
+    BpmOutOfRange { value: f64, min: f64, max: f64 },
+    CcOutOfRange { value: u8, max: u8 },
+    ChannelOutOfRange { value: u8, max: u8 },
+}
+
+impl fmt::Display for ValidationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::BpmOutOfRange { value, min, max } => {
+                write!(f, "BPM {} is out of range ({}-{})", value, min, max)
+            }
+            Self::CcOutOfRange { value, max } => {
+                write!(f, "CC number {} is out of range (0-{})", value, max)
+            }
+            Self::ChannelOutOfRange { value, max } => {
+                write!(f, "Channel {} is out of range (0-{})", value, max)
+            }
+        }
+    }
+}
+
+impl std::error::Error for ValidationError {}
+
+// =============================================================================
+// Engine Error
+// =============================================================================
+
+/// Errors that can occur in the MIDI engine, surfaced to the frontend
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub enum EngineError {
+    /// Failed to connect to a MIDI port
+    PortConnectionFailed { port_name: String, reason: String },
+    /// A MIDI port was disconnected (device unplugged)
+    PortDisconnected { port_name: String },
+    /// Failed to send MIDI message
+    SendFailed { port_name: String, reason: String },
+    /// Invalid configuration
+    ValidationFailed(ValidationError),
+    /// Engine loop iterations keep taking longer than the clock interval
+    EngineOverloaded {
+        loop_us: u64,
+        clock_interval_us: u64,
+    },
+}
+
+impl fmt::Display for EngineError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::PortConnectionFailed { port_name, reason } => {
+                write!(f, "Failed to connect to '{}': {}", port_name, reason)
+            }
+            Self::PortDisconnected { port_name } => {
+                write!(f, "Port '{}' was disconnected", port_name)
+            }
+            Self::SendFailed { port_name, reason } => {
+                write!(f, "Failed to send to '{}': {}", port_name, reason)
+            }
+            Self::ValidationFailed(err) => write!(f, "Validation error: {}", err),
+            Self::EngineOverloaded {
+                loop_us,
+                clock_interval_us,
+            } => write!(
+                f,
+                "Engine loop took {} µs, longer than the {} µs clock interval",
+                loop_us, clock_interval_us
+            ),
+        }
+    }
+}
+
+impl std::error::Error for EngineError {}
+
+impl From<ValidationError> for EngineError {
+    fn from(err: ValidationError) -> Self {
+        Self::ValidationFailed(err)
+    }
+}
+
+/// An error the engine reported, kept for the problem history
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct RecentError {
+    /// Microseconds since the Unix epoch
+    pub timestamp_us: u64,
+    /// Human-readable description
+    pub message: String,
+    pub error: EngineError,
+}
+
+// =============================================================================
+// Validated Newtypes
+// =============================================================================
+
+/// BPM value, guaranteed to be within valid range (20.0-300.0)
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+pub struct Bpm(f64);
+
+impl Bpm {
+    pub const MIN: f64 = 20.0;
+    pub const MAX: f64 = 300.0;
+    pub const DEFAULT: f64 = 120.0;
+
+    pub fn new(value: f64) -> Result<Self, ValidationError> {
+        if !(Self::MIN..=Self::MAX).contains(&value) {
+            Err(ValidationError::BpmOutOfRange {
+                value,
+                min: Self::MIN,
+                max: Self::MAX,
+            })
+        } else {
+            Ok(Self(value))
+        }
+    }
+
+    /// Create a BPM value, clamping to valid range
+    pub fn clamped(value: f64) -> Self {
+        Self(value.clamp(Self::MIN, Self::MAX))
+    }
+
+    pub fn value(&self) -> f64 {
+        self.0
+    }
+}
+
+impl Default for Bpm {
+    fn default() -> Self {
+        Self(Self::DEFAULT)
+    }
+}
+
+/// MIDI Control Change number (0-127)
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Hash)]
+pub struct CcNumber(u8);
+
+impl CcNumber {
+    pub const MAX: u8 = 127;
+
+    pub fn new(value: u8) -> Result<Self, ValidationError> {
+        if value > Self::MAX {
+            Err(ValidationError::CcOutOfRange {
+                value,
+                max: Self::MAX,
+            })
+        } else {
+            Ok(Self(value))
+        }
+    }
+
+    pub fn value(&self) -> u8 {
+        self.0
+    }
+}
+
+impl From<CcNumber> for u8 {
+    fn from(cc: CcNumber) -> u8 {
+        cc.0
+    }
+}
+
+/// MIDI Channel (0-15 internally, typically displayed as 1-16)
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Hash)]
+pub struct Channel(u8);
+
+impl Channel {
+    pub const MAX: u8 = 15;
+
+    /// Create from 0-indexed channel (0-15)
+    pub fn new(value: u8) -> Result<Self, ValidationError> {
+        if value > Self::MAX {
+            Err(ValidationError::ChannelOutOfRange {
+                value,
+                max: Self::MAX,
+            })
+        } else {
+            Ok(Self(value))
+        }
+    }
+
+    /// Create from 1-indexed channel (1-16), as typically shown in UI
+    pub fn from_one_indexed(value: u8) -> Result<Self, ValidationError> {
+        if value == 0 || value > 16 {
+            Err(ValidationError::ChannelOutOfRange {
+                value,
+                max: 16,
+            })
+        } else {
+            Ok(Self(value - 1))
+        }
+    }
+
+    /// Get 0-indexed value (0-15) for MIDI protocol
+    pub fn value(&self) -> u8 {
+        self.0
+    }
+
+    /// Get 1-indexed value (1-16) for display
+    pub fn display_value(&self) -> u8 {
+        self.0 + 1
+    }
+}
+
+impl From<Channel> for u8 {
+    fn from(ch: Channel) -> u8 {
+        ch.0
+    }
+}
+
+// =============================================================================
+// Port Types
+// =============================================================================
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Hash)]
+pub struct PortId {
+    pub name: String,
+    pub display_name: String,
+}
+
+impl PortId {
+    pub fn new(name: String) -> Self {
+        let display_name = name.clone();
+        Self { name, display_name }
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub enum ChannelFilter {
+    #[default]
+    All,
+    Only(Vec<u8>),
+    Except(Vec<u8>),
+}
+
+impl ChannelFilter {
+    pub fn passes(&self, channel: u8) -> bool {
+        match self {
+            Self::All => true,
+            Self::Only(channels) => channels.contains(&channel),
+            Self::Except(channels) => !channels.contains(&channel),
+        }
+    }
+}
+
+/// MIDI Show Control command (universal real-time SysEx sub-ID 0x02)
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Hash)]
+pub enum MscCommand {
+    Go,
+    Stop,
+    Resume,
+    TimedGo,
+    Load,
+    Set,
+    Fire,
+    AllOff,
+    Restore,
+    Reset,
+    GoOff,
+    Other(u8),
+}
+
+impl MscCommand {
+    pub fn from_byte(byte: u8) -> Self {
+        match byte {
+            0x01 => Self::Go,
+            0x02 => Self::Stop,
+            0x03 => Self::Resume,
+            0x04 => Self::TimedGo,
+            0x05 => Self::Load,
+            0x06 => Self::Set,
+            0x07 => Self::Fire,
+            0x08 => Self::AllOff,
+            0x09 => Self::Restore,
+            0x0A => Self::Reset,
+            0x0B => Self::GoOff,
+            other => Self::Other(other),
+        }
+    }
+}
+
+/// Per-route filter for MIDI Show Control messages
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub enum MscFilter {
+    #[default]
+    All,
+    Only(Vec<MscCommand>),
+    Except(Vec<MscCommand>),
+}
+
+impl MscFilter {
+    pub fn passes(&self, command: MscCommand) -> bool {
+        match self {
+            Self::All => true,
+            Self::Only(commands) => commands.contains(&command),
+            Self::Except(commands) => !commands.contains(&command),
+        }
+    }
+}
+
+/// System Common messages (0xF1-0xF6)
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+pub enum SystemCommon {
+    MtcQuarterFrame,
+    SongPosition,
+    SongSelect,
+    TuneRequest,
+}
+
+impl SystemCommon {
+    pub fn from_status(status: u8) -> Option<Self> {
+        match status {
+            0xF1 => Some(Self::MtcQuarterFrame),
+            0xF2 => Some(Self::SongPosition),
+            0xF3 => Some(Self::SongSelect),
+            0xF6 => Some(Self::TuneRequest),
+            _ => None,
+        }
+    }
+}
+
+/// Per-route filter for System Common messages
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub enum SystemCommonFilter {
+    #[default]
+    All,
+    Only(Vec<SystemCommon>),
+    Except(Vec<SystemCommon>),
+}
+
+impl SystemCommonFilter {
+    pub fn passes(&self, message: SystemCommon) -> bool {
+        match self {
+            Self::All => true,
+            Self::Only(messages) => messages.contains(&message),
+            Self::Except(messages) => !messages.contains(&message),
+        }
+    }
+}
+
+/// System Real-Time messages a route drops. Clock, Start, Continue and Stop
+/// never go through routes (transport handles them); these cover the rest.
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct RealTimeStrip {
+    /// Active Sensing (0xFE)
+    #[serde(default)]
+    pub active_sensing: bool,
+    /// System Reset (0xFF)
+    #[serde(default)]
+    pub system_reset: bool,
+    /// The undefined real-time bytes 0xF9 and 0xFD
+    #[serde(default)]
+    pub undefined: bool,
+}
+
+impl RealTimeStrip {
+    pub fn strips(&self, status: u8) -> bool {
+        match status {
+            0xFE => self.active_sensing,
+            0xFF => self.system_reset,
+            0xF9 | 0xFD => self.undefined,
+            _ => false,
+        }
+    }
+}
+
+/// Per-note tuning loaded from a Scala or AnaMark file
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct TuningTable {
+    pub name: String,
+    /// Deviation from 12-tone equal temperament in cents, for each MIDI note
+    pub cents: Vec<f64>,
+}
+
+/// How a route's microtuning reaches the destination
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(tag = "kind", content = "data")]
+pub enum TuningMethod {
+    /// MIDI Tuning Standard SysEx, for synths that support it
+    Mts,
+    /// Each note on its own channel from the pool, detuned with pitch bend.
+    /// `bend_range` must match the synth's pitch bend range in semitones.
+    PitchBend { channels: Vec<u8>, bend_range: u8 },
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct Microtuning {
+    pub table: TuningTable,
+    pub method: TuningMethod,
+}
+
+/// How an incoming CC value is interpreted before it reaches a target
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq)]
+#[serde(tag = "kind", content = "data")]
+pub enum CcValueMode {
+    /// Value passes through (after curve and offset)
+    #[default]
+    Continuous,
+    /// Each press (value >= 64) alternates the output between 127 and 0
+    Toggle,
+    /// Output 127 at or above the threshold, 0 below
+    Threshold { threshold: u8 },
+    /// Every message is one press of a latching switch: output 127, then
+    /// 0 after `pulse_ms`, for destinations that expect a momentary switch
+    Momentary { pulse_ms: u32 },
+}
+
+/// Response curve applied to CC values
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq)]
+pub enum CcCurve {
+    #[default]
+    Linear,
+    /// Slow start, fast finish (x²)
+    Exponential,
+    /// Fast start, slow finish (√x)
+    Logarithmic,
+    /// Slow at both ends, fast through the middle (smoothstep)
+    SCurve,
+}
+
+impl CcCurve {
+    pub fn apply(&self, value: u8) -> u8 {
+        let x = value.min(127) as f64 / 127.0;
+        let y = match self {
+            Self::Linear => return value.min(127),
+            Self::Exponential => x * x,
+            Self::Logarithmic => x.sqrt(),
+            Self::SCurve => x * x * (3.0 - 2.0 * x),
+        };
+        (y * 127.0).round() as u8
+    }
+}
+
+/// Makes a CC target emit notes instead of a CC. In threshold mode the
+/// note plays when the value rises past the threshold and stops when it
+/// falls back below.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct CcNoteTrigger {
+    pub note: u8,
+    /// Fixed NoteOn velocity, or None to use the (transformed) CC value
+    #[serde(default)]
+    pub velocity: Option<u8>,
+    /// Send NoteOff automatically after this many ms. When None, a CC value
+    /// of 0 sends the NoteOff instead.
+    #[serde(default)]
+    pub gate_ms: Option<u32>,
+}
+
+/// Makes a CC target send a 14-bit MSB/LSB pair instead of a 7-bit value
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct CcHighRes {
+    /// LSB controller number, or None for the standard `cc + 32`
+    #[serde(default)]
+    pub lsb_cc: Option<u8>,
+    /// Intermediate values sent between the previous and new value
+    /// so each 7-bit step becomes a ramp instead of a jump
+    #[serde(default)]
+    pub smoothing_steps: u8,
+}
+
+impl CcHighRes {
+    pub fn lsb_for(&self, msb_cc: u8) -> u8 {
+        self.lsb_cc.unwrap_or(msb_cc.saturating_add(32)).min(127)
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct CcTarget {
+    pub cc: u8,
+    pub channels: Vec<u8>,
+    #[serde(default)]
+    pub mode: CcValueMode,
+    #[serde(default)]
+    pub curve: CcCurve,
+    /// Flip the incoming value (127 - value) before anything else, for
+    /// reversed pedals and normally-closed switches
+    #[serde(default)]
+    pub invert: bool,
+    /// Output range the curved value is rescaled into, before the offset.
+    /// Unset ends stay at 0 and 127; a max below min runs the range backwards.
+    #[serde(default)]
+    pub min: Option<u8>,
+    #[serde(default)]
+    pub max: Option<u8>,
+    /// Added to the value after the curve, result clamped to 0-127
+    #[serde(default)]
+    pub offset: i8,
+    /// Emit NoteOn/NoteOff instead of a CC (`cc` is then ignored)
+    #[serde(default)]
+    pub note: Option<CcNoteTrigger>,
+    /// Send `cc` as the MSB of a 14-bit pair
+    #[serde(default)]
+    pub high_res: Option<CcHighRes>,
+}
+
+impl CcTarget {
+    /// Rescale a 0-127 value into the target's output range
+    pub fn scale_to_range(&self, value: u8) -> u8 {
+        let min = self.min.unwrap_or(0).min(127) as i32;
+        let max = self.max.unwrap_or(127).min(127) as i32;
+        let span = (max - min) * value.min(127) as i32;
+        // Round half away from zero so both directions stay symmetric
+        let step = (span + span.signum() * 63) / 127;
+        (min + step) as u8
+    }
+}
+
+/// How the source CC of a mapping encodes its value
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq)]
+#[serde(tag = "kind", content = "data")]
+pub enum EncoderMode {
+    /// Plain absolute CC value
+    #[default]
+    Absolute,
+    /// Relative: 1-63 increment, 65-127 decrement (127 = -1)
+    TwosComplement,
+    /// Relative: 1-63 increment, 65-127 decrement (65 = -1)
+    SignedBit,
+    /// Relative: 65-127 increment, 1-63 decrement (64 = no change)
+    BinaryOffset,
+    /// Source CC increments by one, `decrement_cc` decrements by one
+    IncDec { decrement_cc: u8 },
+}
+
+impl EncoderMode {
+    /// Decode the change for a relative encoder message.
+    /// Returns None for absolute mode.
+    pub fn delta(&self, cc: u8, value: u8) -> Option<i16> {
+        let value = value as i16;
+        match self {
+            Self::Absolute => None,
+            Self::TwosComplement => Some(if value < 64 { value } else { value - 128 }),
+            Self::SignedBit => Some(if value < 64 { value } else { -(value - 64) }),
+            Self::BinaryOffset => Some(value - 64),
+            Self::IncDec { decrement_cc } => Some(if cc == *decrement_cc { -1 } else { 1 }),
+        }
+    }
+}
+
+/// Measured range of a source that doesn't reach 0-127 (worn expression
+/// pedals often output something like 10-115), stretched to the full range
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+pub struct CcCalibration {
+    pub min: u8,
+    pub max: u8,
+    /// Applied after stretching, so the pedal's travel can be reshaped too
+    #[serde(default)]
+    pub curve: CcCurve,
+}
+
+impl CcCalibration {
+    pub fn apply(&self, value: u8) -> u8 {
+        let max = self.max.min(127);
+        if max <= self.min {
+            return self.curve.apply(value);
+        }
+        let value = value.clamp(self.min, max);
+        let span = (max - self.min) as u32;
+        let stretched = ((value - self.min) as u32 * 127 + span / 2) / span;
+        self.curve.apply(stretched as u8)
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct CcMapping {
+    pub source_cc: u8,
+    pub targets: Vec<CcTarget>,
+    /// Relative encoder handling; the router keeps the absolute value
+    #[serde(default)]
+    pub encoder: EncoderMode,
+    /// Stretch an absolute source's measured range before the targets see it
+    #[serde(default)]
+    pub calibration: Option<CcCalibration>,
+}
+
+impl CcMapping {
+    /// Whether this mapping handles the given incoming CC number
+    pub fn handles(&self, cc: u8) -> bool {
+        match self.encoder {
+            EncoderMode::IncDec { decrement_cc } => cc == self.source_cc || cc == decrement_cc,
+            _ => cc == self.source_cc,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Route {
+    pub id: Uuid,
+    pub source: PortId,
+    pub destination: PortId,
+    pub enabled: bool,
+    pub channels: ChannelFilter,
+    #[serde(default)]
+    pub cc_passthrough: bool,
+    #[serde(default)]
+    pub cc_mappings: Vec<CcMapping>,
+    #[serde(default)]
+    pub msc_filter: MscFilter,
+    #[serde(default)]
+    pub system_common_filter: SystemCommonFilter,
+    #[serde(default)]
+    pub real_time_strip: RealTimeStrip,
+    /// Only forward notes inside this zone
+    #[serde(default)]
+    pub key_range: Option<KeyRange>,
+    /// Send channel voice messages on other channels
+    #[serde(default)]
+    pub channel_map: Option<ChannelMap>,
+    #[serde(default)]
+    pub microtuning: Option<Microtuning>,
+    /// Drop channel pressure and poly aftertouch
+    #[serde(default)]
+    pub strip_aftertouch: bool,
+    /// Note Ons toggle their note on and off; the source's Note Offs are dropped
+    #[serde(default)]
+    pub latch: bool,
+    /// Hold Note Offs back while the sustain pedal is down, for destinations
+    /// that ignore CC64
+    #[serde(default)]
+    pub sustain: bool,
+    /// Collapse to one note per channel, choosing between held keys by
+    /// priority. None plays polyphonically.
+    #[serde(default)]
+    pub mono: Option<NotePriority>,
+    #[serde(default)]
+    pub program_change_filter: ProgramChangeFilter,
+    /// Buttons that step the destination's program up and down
+    #[serde(default)]
+    pub program_steppers: Vec<ProgramStepper>,
+    /// Notes sent on as CCs
+    #[serde(default)]
+    pub note_mappings: Vec<NoteMapping>,
+    #[serde(default)]
+    pub bank_select: BankSelectSettings,
+    /// Banks and programs sent to the destination when the route's preset loads
+    #[serde(default)]
+    pub program_recall: Vec<ProgramRecall>,
+    /// Deal successive notes out across several channels
+    #[serde(default)]
+    pub channel_rotation: Option<ChannelRotation>,
+    /// Transposed copies played with each note
+    #[serde(default)]
+    pub harmony: Vec<HarmonyVoice>,
+    /// Play one voice per port across the destination and more outputs
+    #[serde(default)]
+    pub voice_split: Option<VoiceSplit>,
+    /// Drop double-triggered notes from bouncy pads and flaky keybeds
+    #[serde(default)]
+    pub duplicate_filter: Option<DuplicateFilter>,
+    /// Reshape Note On velocities before they're sent
+    #[serde(default)]
+    pub velocity_curve: Option<VelocityTransform>,
+    /// Randomly nudge Note On velocities, after any curve
+    #[serde(default)]
+    pub velocity_humanize: Option<VelocityHumanize>,
+    /// Repeat notes in time with the clock
+    #[serde(default)]
+    pub echo: Option<NoteEcho>,
+    /// Drop Note Ons softer than this, and their Note Offs, so grazed
+    /// pads don't sound
+    #[serde(default)]
+    pub velocity_gate: Option<u8>,
+    /// The layer group the route plays in, if any
+    #[serde(default)]
+    pub layer: Option<Layer>,
+}
+
+/// A route's part in a layer group: routes from one source that are
+/// switched on and off together, each playing its own velocity zone
+/// (e.g. strings only above 80)
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct Layer {
+    /// Name shared by the group's routes
+    pub group: String,
+    /// Softest and hardest Note On the layer plays (1-127)
+    pub low_velocity: u8,
+    pub high_velocity: u8,
+}
+
+/// Keyboard zone a route plays, for splits. Notes outside it are dropped.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+pub struct KeyRange {
+    pub low_note: u8,
+    pub high_note: u8,
+}
+
+impl KeyRange {
+    pub fn contains(&self, note: u8) -> bool {
+        (self.low_note..=self.high_note).contains(&note)
+    }
+}
+
+/// Rewrites the channel of a route's channel voice messages. Channels are
+/// 1-16.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub enum ChannelMap {
+    /// Everything goes out on this channel
+    Force(u8),
+    /// Source channel -> output channel; unlisted channels are unchanged
+    Remap(BTreeMap<u8, u8>),
+}
+
+impl ChannelMap {
+    /// Output channel for a 0-15 channel, also 0-15
+    pub fn output_channel(&self, channel: u8) -> u8 {
+        let mapped = match self {
+            Self::Force(to) => Some(*to),
+            Self::Remap(map) => map.get(&(channel + 1)).copied(),
+        };
+        match mapped {
+            Some(to @ 1..=16) => to - 1,
+            _ => channel,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct DuplicateFilter {
+    /// A Note On for a note that started this recently is dropped, along
+    /// with its Note Off
+    pub window_ms: u32,
+    /// Also drop any message identical to the previous one within the window
+    #[serde(default)]
+    pub identical: bool,
+}
+
+/// Repeats of a route's notes at a beat interval of the clock tempo
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+pub struct NoteEcho {
+    /// Repeats after the played note
+    pub repeats: u8,
+    /// Time between repeats in beats, e.g. 0.5 for eighth notes
+    pub beats: f64,
+    /// Each repeat's velocity as a fraction of the one before
+    pub decay: f64,
+}
+
+/// Random velocity variation for a route's Note Ons
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+pub struct VelocityHumanize {
+    /// Largest nudge either way
+    pub amount: u8,
+    /// Makes the nudges repeat run to run; None varies them
+    #[serde(default)]
+    pub seed: Option<u64>,
+}
+
+/// Reshapes a route's Note On velocities: scaled, then curved, then clamped
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct VelocityTransform {
+    /// Multiplier; 1.0 leaves velocities as played
+    #[serde(default = "default_velocity_scale")]
+    pub scale: f64,
+    /// Exponent on the 0-1 velocity: above 1 plays softer, below 1 harder
+    #[serde(default = "default_velocity_gamma")]
+    pub gamma: f64,
+    #[serde(default = "default_velocity_min")]
+    pub min: u8,
+    #[serde(default = "default_velocity_max")]
+    pub max: u8,
+}
+
+fn default_velocity_scale() -> f64 {
+    1.0
+}
+
+fn default_velocity_gamma() -> f64 {
+    1.0
+}
+
+fn default_velocity_min() -> u8 {
+    1
+}
+
+fn default_velocity_max() -> u8 {
+    127
+}
+
+impl Default for VelocityTransform {
+    fn default() -> Self {
+        Self {
+            scale: default_velocity_scale(),
+            gamma: default_velocity_gamma(),
+            min: default_velocity_min(),
+            max: default_velocity_max(),
+        }
+    }
+}
+
+impl VelocityTransform {
+    /// Never returns 0, which would turn the Note On into a Note Off
+    pub fn apply(&self, velocity: u8) -> u8 {
+        let x = (velocity.min(127) as f64 * self.scale / 127.0).clamp(0.0, 1.0);
+        let y = (x.powf(self.gamma) * 127.0).round() as u8;
+        y.max(self.min).min(self.max).max(1)
+    }
+}
+
+/// Which sounding voice a new note takes over when every port is busy
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub enum VoiceStealing {
+    /// The note that started longest ago
+    #[default]
+    Oldest,
+    /// The most recently started note
+    Newest,
+    /// The new note isn't played
+    Never,
+}
+
+/// Polyphony spread across several mono synths, one note per port. Notes
+/// go to the route's destination and `outputs`; everything else on a
+/// channel goes to all of them.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct VoiceSplit {
+    /// Output ports playing a voice each, after the route's destination
+    pub outputs: Vec<String>,
+    #[serde(default)]
+    pub stealing: VoiceStealing,
+    /// Deal notes to the ports in turn, any number to a port, for spreading
+    /// voices across multitimbral devices. Nothing is stolen.
+    #[serde(default)]
+    pub round_robin: bool,
+}
+
+/// Round-robin note channels, spreading polyphony across the mono-timbral
+/// parts of one device
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct ChannelRotation {
+    /// Output channels (1-16), in the order notes are dealt to them
+    pub channels: Vec<u8>,
+}
+
+/// A transposed copy a route plays with each note
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+pub struct HarmonyVoice {
+    /// Semitones from the played note
+    pub interval: i8,
+    /// Output channel (1-16); None plays on the note's own channel
+    #[serde(default)]
+    pub channel: Option<u8>,
+}
+
+/// Program Change thinning, for controllers that repeat them or send them
+/// in bursts
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct ProgramChangeFilter {
+    /// Drop Program Changes for the program the channel is already on
+    pub dedupe: bool,
+    /// Hold Program Changes until none has arrived for this long, then send
+    /// only the last. 0 sends them right away.
+    pub debounce_ms: u32,
+}
+
+/// A bank and program, as selected by CC0 (MSB), CC32 (LSB) and Program Change
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+pub struct BankProgram {
+    /// None leaves the MSB out (or, to match, accepts any)
+    #[serde(default)]
+    pub msb: Option<u8>,
+    #[serde(default)]
+    pub lsb: Option<u8>,
+    pub program: u8,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+pub struct BankRemap {
+    pub from: BankProgram,
+    pub to: BankProgram,
+}
+
+/// Bank Select handling. While either option is in use, CC0/CC32 are held
+/// until the Program Change that completes them and sent together with it.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct BankSelectSettings {
+    /// Drop bank changes entirely, passing only Program Changes
+    #[serde(default)]
+    pub block: bool,
+    /// The first remap matching a bank and program replaces both
+    #[serde(default)]
+    pub remaps: Vec<BankRemap>,
+}
+
+impl BankSelectSettings {
+    pub fn is_active(&self) -> bool {
+        self.block || !self.remaps.is_empty()
+    }
+}
+
+/// A bank and program a route selects on its destination when its preset
+/// loads
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+pub struct ProgramRecall {
+    /// Destination channel, 1-16
+    pub channel: u8,
+    pub bank: BankProgram,
+}
+
+/// A note or CC acting as a button. CCs are pressed at values of 64 and up.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(tag = "kind", content = "data")]
+pub enum StepButton {
+    Note { note: u8 },
+    Cc { cc: u8 },
+}
+
+/// Two buttons that send Program Change +1 / -1, for synths with no other
+/// way to step through patches
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct ProgramStepper {
+    pub increment: StepButton,
+    pub decrement: StepButton,
+    /// Destination channel, 1-16
+    pub channel: u8,
+    /// Step from 127 to 0 and back instead of stopping at the ends
+    #[serde(default)]
+    pub wrap: bool,
+}
+
+/// How a note mapping turns presses into CC values
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq)]
+#[serde(tag = "kind", content = "data")]
+pub enum NoteCcMode {
+    /// Note On velocity is the value; Note Off sends 0
+    #[default]
+    Velocity,
+    /// Each press alternates the output between `value` and 0; releases
+    /// send nothing
+    Toggle { value: u8 },
+}
+
+/// Turns a note, such as a pad, into a CC for gear that only takes CCs.
+/// The note itself isn't forwarded.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct NoteMapping {
+    pub note: u8,
+    pub cc: u8,
+    /// Output channels, 1-16; empty keeps the note's channel
+    #[serde(default)]
+    pub channels: Vec<u8>,
+    #[serde(default)]
+    pub mode: NoteCcMode,
+}
+
+/// Which held key a mono route sounds
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub enum NotePriority {
+    /// The most recently pressed
+    #[default]
+    Last,
+    Low,
+    High,
+}
+
+impl Default for Route {
+    fn default() -> Self {
+        Self {
+            id: Uuid::new_v4(),
+            source: PortId::new(String::new()),
+            destination: PortId::new(String::new()),
+            enabled: true,
+            channels: ChannelFilter::default(),
+            cc_passthrough: true,
+            cc_mappings: Vec::new(),
+            msc_filter: MscFilter::default(),
+            system_common_filter: SystemCommonFilter::default(),
+            real_time_strip: RealTimeStrip::default(),
+            key_range: None,
+            channel_map: None,
+            microtuning: None,
+            strip_aftertouch: false,
+            latch: false,
+            sustain: false,
+            mono: None,
+            program_change_filter: ProgramChangeFilter::default(),
+            program_steppers: Vec::new(),
+            note_mappings: Vec::new(),
+            bank_select: BankSelectSettings::default(),
+            program_recall: Vec::new(),
+            channel_rotation: None,
+            harmony: Vec::new(),
+            voice_split: None,
+            duplicate_filter: None,
+            velocity_curve: None,
+            velocity_humanize: None,
+            echo: None,
+            velocity_gate: None,
+            layer: None,
+        }
+    }
+}
+
+impl Route {
+    pub fn new(source: PortId, destination: PortId) -> Self {
+        Self {
+            source,
+            destination,
+            ..Self::default()
+        }
+    }
+
+    /// Every port the route sends to: its destination, then any voice
+    /// split outputs
+    pub fn output_ports(&self) -> Vec<String> {
+        let mut ports = vec![self.destination.name.clone()];
+        if let Some(split) = &self.voice_split {
+            for output in &split.outputs {
+                if !ports.contains(output) {
+                    ports.push(output.clone());
+                }
+            }
+        }
+        ports
+    }
+}
+
+/// A potential problem in the route configuration
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(tag = "kind", content = "data")]
+pub enum RouteWarning {
+    /// Route source is not a currently available input
+    MissingSource { route_id: Uuid, port_name: String },
+    /// Route destination is not a currently available output
+    MissingDestination { route_id: Uuid, port_name: String },
+    /// Route feeds back into its own source through other routes
+    Cycle { route_id: Uuid, path: Vec<String> },
+    /// More than one route connects the same source and destination
+    Duplicate {
+        route_ids: Vec<Uuid>,
+        source: String,
+        destination: String,
+    },
+    /// Channel filter blocks every channel
+    NoChannels { route_id: Uuid },
+    /// CC mapping has no targets, or a target has no channels
+    EmptyCcTargets { route_id: Uuid, source_cc: u8 },
+}
+
+/// A connected source/destination pair in the routing matrix
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct MatrixCell {
+    pub source: String,
+    pub destination: String,
+    pub route_id: Uuid,
+    pub enabled: bool,
+}
+
+/// Routing viewed as a source x destination grid.
+/// Only connected pairs appear in `cells`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RoutingMatrix {
+    pub sources: Vec<String>,
+    pub destinations: Vec<String>,
+    pub cells: Vec<MatrixCell>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct MidiPort {
+    pub id: PortId,
+    pub is_input: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(tag = "kind", content = "data")]
+pub enum MessageKind {
+    NoteOn { note: u8, velocity: u8 },
+    NoteOff { note: u8, velocity: u8 },
+    ControlChange { controller: u8, value: u8 },
+    ProgramChange { program: u8 },
+    PitchBend { value: u16 },
+    Aftertouch { value: u8 },
+    PolyAftertouch { note: u8, value: u8 },
+    SysEx,
+    SongSelect { song: u8 },
+    ShowControl {
+        device_id: u8,
+        command: MscCommand,
+        cue_number: Option<String>,
+        cue_list: Option<String>,
+    },
+    // Transport/Clock messages
+    Clock,
+    Start,
+    Continue,
+    Stop,
+    Other,
+}
+
+impl MessageKind {
+    /// Note number for note-carrying messages
+    pub fn note(&self) -> Option<u8> {
+        match self {
+            Self::NoteOn { note, .. }
+            | Self::NoteOff { note, .. }
+            | Self::PolyAftertouch { note, .. } => Some(*note),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MidiActivity {
+    /// Wall-clock microseconds since the Unix epoch
+    pub timestamp: u64,
+    /// Microseconds since the previous message on the same port
+    #[serde(default)]
+    pub delta_us: Option<u64>,
+    pub port: String,
+    pub channel: Option<u8>,
+    pub kind: MessageKind,
+    pub raw: Vec<u8>,
+    /// Device-specific name for the message (e.g. "Filter Cutoff" for CC 74)
+    #[serde(default)]
+    pub label: Option<String>,
+    /// Note name for note messages, using the configured middle-C convention
+    #[serde(default)]
+    pub note_name: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Hash)]
+pub enum PortDirection {
+    Input,
+    Output,
+}
+
+/// Notes that started together on a port, with the chord they form
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct DetectedChord {
+    pub port: String,
+    pub notes: Vec<u8>,
+    /// Chord or interval name, None if not recognized
+    pub name: Option<String>,
+}
+
+/// Number of messages seen on a port during the last window
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct PortPulse {
+    pub port: String,
+    pub direction: PortDirection,
+    pub count: u32,
+    pub window_ms: u32,
+}
+
+/// Connections rebuilt after the system woke from sleep
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct WakeReport {
+    pub slept_ms: u64,
+    /// Connections reopened, by port name
+    pub inputs: Vec<String>,
+    pub outputs: Vec<String>,
+    /// Ports still needed that didn't come back
+    pub missing: Vec<String>,
+}
+
+/// Message and byte rates for a port over the last measurement window
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct PortThroughput {
+    pub port: String,
+    pub direction: PortDirection,
+    pub messages_per_sec: f64,
+    pub bytes_per_sec: f64,
+}
+
+/// Runtime status of a route, separate from the user's `enabled` flag
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub enum RouteStatus {
+    #[default]
+    Active,
+    /// The source or destination is not present; activates when it appears
+    Pending,
+    /// The destination kept failing to send; resumes when it reconnects
+    Errored,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct RouteStatusChange {
+    pub route_id: Uuid,
+    pub status: RouteStatus,
+}
+
+/// A route as returned to the frontend, with its runtime status
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RouteWithStatus {
+    #[serde(flatten)]
+    pub route: Route,
+    pub status: RouteStatus,
+}
+
+/// Traffic counters for a route since it was created (or the app started)
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct RouteStats {
+    pub route_id: Uuid,
+    #[serde(default)]
+    pub status: RouteStatus,
+    /// Messages that produced output
+    pub forwarded: u64,
+    /// Messages from the source that were filtered or mapped away
+    pub dropped: u64,
+    /// Wall-clock microseconds of the last message from the source
+    pub last_activity: Option<u64>,
+}
+
+/// Notes a route has left held on one channel of its destination
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct HeldNotes {
+    pub route_id: Uuid,
+    pub destination: String,
+    /// 0-indexed
+    pub channel: u8,
+    pub notes: Vec<u8>,
+}
+
+/// Engine loop processing time, excluding the wait for commands
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct LoopStats {
+    /// Over the last 1000 iterations
+    pub worst_us: u64,
+    pub p99_us: u64,
+    /// Share of the engine thread's time spent working
+    pub cpu_percent: f64,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct EngineStats {
+    pub ports: Vec<PortThroughput>,
+    pub routes: Vec<RouteStats>,
+    #[serde(default)]
+    pub engine_loop: LoopStats,
+}
+
+/// What was played into one input since the engine started
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct PortSessionStats {
+    pub port: String,
+    /// Note-ons per note number (128 entries)
+    pub notes: Vec<u64>,
+    /// Note-on velocities in 16 bins of 8 (0-7, 8-15, ..., 120-127)
+    pub velocities: Vec<u64>,
+    /// Control changes per controller number
+    pub controllers: BTreeMap<u8, u64>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct SessionStats {
+    pub started_us: u64,
+    pub ports: Vec<PortSessionStats>,
+}
+
+/// Why a route did or didn't forward a captured message
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+pub enum RouteDecision {
+    /// Forwarded, possibly transformed into other messages
+    Forwarded,
+    /// The route is held because a port is missing or failing
+    Inactive,
+    ChannelFiltered,
+    AftertouchStripped,
+    MscFiltered,
+    SystemCommonFiltered,
+    RealTimeStripped,
+    /// A note outside the route's key range
+    OutOfKeyRange,
+    /// A Note On softer than the route's velocity gate, or its release
+    BelowVelocityGate,
+    /// A Note On outside the velocities its layer plays, or its release
+    OutsideLayerVelocity,
+    /// Dropped as a double trigger or repeat
+    Duplicate,
+    /// Passed the filters, but the transforms produced no output
+    Consumed,
+}
+
+/// A message a route sent (or scheduled) for a captured input
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct TracedOutput {
+    pub bytes: Vec<u8>,
+    /// Set for sends deferred to the scheduler; their result isn't traced
+    pub delay_us: Option<u64>,
+    /// Send failure, if any
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct RouteTrace {
+    pub route_id: Uuid,
+    pub destination: String,
+    pub decision: RouteDecision,
+    /// Transform stages that changed the message, in order
+    pub transforms: Vec<String>,
+    pub outputs: Vec<TracedOutput>,
+}
+
+impl RouteTrace {
+    pub fn new(route_id: Uuid, destination: &str, decision: RouteDecision) -> Self {
+        Self {
+            route_id,
+            destination: destination.to_string(),
+            decision,
+            transforms: Vec::new(),
+            outputs: Vec::new(),
+        }
+    }
+}
+
+/// How the engine handled a captured message before routing
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+pub enum CaptureHandling {
+    /// Offered to the routes from its input port
+    Routed,
+    /// Transport or clock, handled by the engine's clock
+    Transport,
+    /// Consumed by a control binding
+    Control,
+}
+
+/// One incoming message and everything the engine did with it
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct CaptureEntry {
+    /// Microseconds since the Unix epoch
+    pub timestamp_us: u64,
+    pub port: String,
+    pub bytes: Vec<u8>,
+    pub handling: CaptureHandling,
+    pub routes: Vec<RouteTrace>,
+}
+
+/// Messages recorded during a debug capture session
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct DebugCapture {
+    pub started_us: u64,
+    pub duration_ms: u64,
+    /// The entry limit was reached and later messages weren't recorded
+    pub truncated: bool,
+    pub entries: Vec<CaptureEntry>,
+}
+
+/// Condition that fires a message trap. Unset fields match anything.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct TrapCondition {
+    #[serde(default)]
+    pub port: Option<String>,
+    /// Channel 0-15; never matches system messages
+    #[serde(default)]
+    pub channel: Option<u8>,
+    /// Status without the channel for channel messages (0x80-0xE0), or the
+    /// whole status byte for system messages
+    #[serde(default)]
+    pub message_type: Option<u8>,
+    /// First data byte: note or controller number
+    #[serde(default)]
+    pub data1: Option<u8>,
+}
+
+impl TrapCondition {
+    pub fn matches(&self, port: &str, bytes: &[u8]) -> bool {
+        let Some(&status) = bytes.first() else {
+            return false;
+        };
+        let (message_type, channel) = if status < 0xF0 {
+            (status & 0xF0, Some(status & 0x0F))
+        } else {
+            (status, None)
+        };
+        self.port.as_deref().is_none_or(|p| p == port)
+            && self.channel.is_none_or(|ch| Some(ch) == channel)
+            && self.message_type.is_none_or(|t| t == message_type)
+            && self.data1.is_none_or(|d| bytes.get(1) == Some(&d))
+    }
+}
+
+/// A message received shortly before a trap fired
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct PrecedingMessage {
+    pub timestamp_us: u64,
+    pub port: String,
+    pub bytes: Vec<u8>,
+}
+
+/// The message that fired a trap, with what the engine did with it
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct TrapHit {
+    pub entry: CaptureEntry,
+    /// Oldest first
+    pub preceding: Vec<PrecedingMessage>,
+}
+
+/// A capture with the configuration it ran under, exported for bug reports
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DebugBundle {
+    pub app_version: String,
+    pub capture: DebugCapture,
+    pub routes: Vec<Route>,
+    pub inputs: Vec<MidiPort>,
+    pub outputs: Vec<MidiPort>,
+}
+
+/// How a session replay ended
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct ReplayReport {
+    /// Messages played back
+    pub replayed: usize,
+    /// Stopped before every message was played back
+    pub stopped: bool,
+    /// What the routes would have sent, for dry runs
+    pub dry_run: Option<DebugCapture>,
+}
+
+/// Octave numbering convention: which name MIDI note 60 gets
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq)]
+pub enum MiddleC {
+    /// Note 60 is C3 (Yamaha, many DAWs)
+    C3,
+    /// Note 60 is C4 (scientific pitch notation)
+    #[default]
+    C4,
+}
+
+/// Note names without octave, indexed by pitch class (note % 12)
+pub const PITCH_CLASSES: [&str; 12] = [
+    "C", "C#", "D", "D#", "E", "F", "F#", "G", "G#", "A", "A#", "B",
+];
+
+impl MiddleC {
+    pub fn note_name(&self, note: u8) -> String {
+        let base_octave = match self {
+            Self::C3 => -2,
+            Self::C4 => -1,
+        };
+        let octave = (note / 12) as i8 + base_octave;
+        format!("{}{}", PITCH_CLASSES[(note % 12) as usize], octave)
+    }
+}
+
+/// How Note Offs are written to an output. Some older gear only
+/// understands one of the two conventions.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+pub enum NoteOffStyle {
+    /// Note On with velocity 0 is sent as a Note Off
+    NoteOff,
+    /// Note Off is sent as Note On with velocity 0 (release velocity is lost)
+    VelocityZero,
+}
+
+/// A pattern the test signal generator plays, one step at a time
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(tag = "kind", content = "data")]
+pub enum TestPattern {
+    /// Every note from `low` to `high` in turn, then around again
+    Chromatic { low: u8, high: u8 },
+    /// The same note on every step
+    FixedNote { note: u8 },
+    /// A CC ramping from 0 to 127 and back
+    CcSweep { cc: u8 },
+    /// Timing Clock at a tempo, ignoring the step time
+    Clock { bpm: f64 },
+}
+
+/// Where the test signal generator sends its pattern
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(tag = "kind", content = "data")]
+pub enum TestSignalTarget {
+    /// Straight to an output port
+    Output { port: String },
+    /// Through the routes from the virtual "Test Signal" input
+    VirtualInput,
+}
+
+/// A test pattern to play without hardware attached
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct TestSignal {
+    pub pattern: TestPattern,
+    pub target: TestSignalTarget,
+    /// Channel 1-16 for note and CC patterns
+    pub channel: u8,
+    /// Note On velocity
+    pub velocity: u8,
+    /// Time between steps in ms
+    pub step_ms: u32,
+}
+
+/// A gamepad button or axis, named for the standard controller layout
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Hash)]
+pub enum GamepadControl {
+    South,
+    East,
+    North,
+    West,
+    C,
+    Z,
+    LeftTrigger,
+    LeftTrigger2,
+    RightTrigger,
+    RightTrigger2,
+    Select,
+    Start,
+    Mode,
+    LeftThumb,
+    RightThumb,
+    DPadUp,
+    DPadDown,
+    DPadLeft,
+    DPadRight,
+    LeftStickX,
+    LeftStickY,
+    LeftZ,
+    RightStickX,
+    RightStickY,
+    RightZ,
+    DPadX,
+    DPadY,
+}
+
+/// What a gamepad control plays
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(tag = "kind", content = "data")]
+pub enum GamepadTarget {
+    /// On while the control is past halfway (a pressed button)
+    Note { note: u8, velocity: u8 },
+    /// Follows the control's travel, 0-127; sticks rest at 64
+    Cc { cc: u8 },
+}
+
+/// Maps a gamepad control to a note or CC on the virtual Gamepad input
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct GamepadMapping {
+    pub control: GamepadControl,
+    /// 0-indexed
+    pub channel: u8,
+    pub target: GamepadTarget,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Preset {
+    pub id: Uuid,
+    pub name: String,
+    pub routes: Vec<Route>,
+    /// Gamepad controls played on the virtual Gamepad input
+    #[serde(default)]
+    pub gamepad: Vec<GamepadMapping>,
+    pub created_at: DateTime<Utc>,
+    pub modified_at: DateTime<Utc>,
+}
+
+impl Preset {
+    pub fn new(name: String, routes: Vec<Route>, gamepad: Vec<GamepadMapping>) -> Self {
+        let now = Utc::now();
+        Self {
+            id: Uuid::new_v4(),
+            name,
+            routes,
+            gamepad,
+            created_at: now,
+            modified_at: now,
+        }
+    }
+}
+
+/// A port the user picks when instantiating a setup template
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct TemplateSlot {
+    pub id: String,
+    pub label: String,
+    pub direction: PortDirection,
+}
+
+/// A route between two template slots
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct TemplateRoute {
+    pub source: String,
+    pub destination: String,
+    #[serde(default)]
+    pub channels: ChannelFilter,
+    #[serde(default)]
+    pub strip_aftertouch: bool,
+}
+
+/// A common rig, described in terms of slots rather than concrete ports
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct SetupTemplate {
+    pub id: String,
+    pub name: String,
+    pub description: String,
+    pub slots: Vec<TemplateSlot>,
+    pub routes: Vec<TemplateRoute>,
+    /// Output slot id -> clock mode
+    #[serde(default)]
+    pub clock: BTreeMap<String, ClockMode>,
+}
+
+/// A route that references ports not currently present
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct UnavailableRoute {
+    pub route_id: Uuid,
+    pub missing_source: Option<String>,
+    pub missing_destination: Option<String>,
+}
+
+/// Which routes can run with the ports currently available
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct PortAvailability {
+    pub available: Vec<Uuid>,
+    pub unavailable: Vec<UnavailableRoute>,
+}
+
+/// A loaded preset, with what its routes need that isn't connected
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LoadedPreset {
+    pub preset: Preset,
+    pub availability: PortAvailability,
+}
+
+/// Maps a CC on a control input to the clock tempo
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct TempoCcBinding {
+    /// Input port name
+    pub port: String,
+    /// 0-indexed channel, or None for any channel
+    pub channel: Option<u8>,
+    pub cc: u8,
+    pub min_bpm: f64,
+    pub max_bpm: f64,
+}
+
+impl TempoCcBinding {
+    /// Linearly map a CC value (0-127) onto the configured BPM range
+    pub fn bpm_for_value(&self, value: u8) -> f64 {
+        let t = value.min(127) as f64 / 127.0;
+        Bpm::clamped(self.min_bpm + (self.max_bpm - self.min_bpm) * t).value()
+    }
+}
+
+/// A note or CC on a control input that acts as a button
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(tag = "kind", content = "data")]
+pub enum ControlTrigger {
+    /// Fires on NoteOn (velocity > 0)
+    Note { note: u8 },
+    /// Fires when the value is 64 or above (button press)
+    Cc { cc: u8 },
+}
+
+/// Designates a note or CC on a control input as the tap-tempo button
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct TapTempoBinding {
+    /// Input port name
+    pub port: String,
+    /// 0-indexed channel, or None for any channel
+    pub channel: Option<u8>,
+    pub trigger: ControlTrigger,
+}
+
+/// Transport action that can be triggered from a control input
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+pub enum TransportAction {
+    Start,
+    Stop,
+    Continue,
+    /// All Sound Off, Reset All Controllers and All Notes Off on every output
+    Panic,
+}
+
+/// Maps a note (or CC button) on a control input to a transport action
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct TransportTriggerBinding {
+    /// Input port name
+    pub port: String,
+    /// 0-indexed channel, or None for any channel
+    pub channel: Option<u8>,
+    pub trigger: ControlTrigger,
+    pub action: TransportAction,
+}
+
+/// Maps Song Select numbers on an input to router presets, for hardware
+/// sequencers that organize songs this way
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct SongSelectBinding {
+    /// Input port name
+    pub port: String,
+    /// Song number (0-127) -> preset id
+    pub presets: BTreeMap<u8, Uuid>,
+}
+
+/// A mapped Song Select arrived and its preset should be loaded
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct SongSelectChange {
+    pub port: String,
+    pub song: u8,
+    pub preset_id: Uuid,
+}
+
+/// Activity on an input no route uses, seen in learn mode
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct RouteSuggestion {
+    pub port: String,
+    /// The first message that arrived
+    pub sample: Vec<u8>,
+    pub kind: Option<MessageKind>,
+}
+
+/// Engine-level bindings for messages arriving on control inputs.
+/// Matching messages are consumed by the engine instead of being routed.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct ControlBindings {
+    #[serde(default)]
+    pub tempo_cc: Option<TempoCcBinding>,
+    #[serde(default)]
+    pub tap_tempo: Option<TapTempoBinding>,
+    #[serde(default)]
+    pub transport_triggers: Vec<TransportTriggerBinding>,
+    /// Song Select is still routed; it only requests a preset change
+    #[serde(default)]
+    pub song_select: Option<SongSelectBinding>,
+}
+
+// =============================================================================
+// Device Definitions
+// =============================================================================
+
+/// A named patch on a device
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct PatchName {
+    pub bank_msb: Option<u8>,
+    pub bank_lsb: Option<u8>,
+    pub program: u8,
+    pub name: String,
+}
+
+/// Controller and patch names for a device, attached to the ports it is connected to
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct DeviceDefinition {
+    pub id: Uuid,
+    pub name: String,
+    /// Port names (inputs or outputs) this device is connected to
+    #[serde(default)]
+    pub ports: Vec<String>,
+    #[serde(default)]
+    pub cc_names: BTreeMap<u8, String>,
+    #[serde(default)]
+    pub patches: Vec<PatchName>,
+    /// Starting filters and transforms for new routes to this device
+    #[serde(default)]
+    pub profile: Option<DeviceProfile>,
+    /// Messages sent to the device's outputs once connected, e.g. Local Off
+    /// or a mode-select SysEx
+    #[serde(default)]
+    pub on_connect: Vec<Vec<u8>>,
+    /// Messages sent to the device's outputs before the router closes them.
+    /// A device that was unplugged can't be sent anything.
+    #[serde(default)]
+    pub on_disconnect: Vec<Vec<u8>>,
+}
+
+/// Defaults applied to a route when it's created with a device's port as
+/// its destination. The route can be edited freely afterwards.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct DeviceProfile {
+    #[serde(default)]
+    pub channels: Option<ChannelFilter>,
+    #[serde(default)]
+    pub strip_aftertouch: bool,
+    #[serde(default)]
+    pub cc_mappings: Vec<CcMapping>,
+    /// Clock mode for the device's outputs
+    #[serde(default)]
+    pub clock: Option<ClockMode>,
+}
+
+impl DeviceProfile {
+    pub fn apply_to(&self, route: &mut Route) {
+        if let Some(channels) = &self.channels {
+            route.channels = channels.clone();
+        }
+        route.strip_aftertouch |= self.strip_aftertouch;
+        route.cc_mappings.extend(self.cc_mappings.iter().cloned());
+    }
+}
+
+impl DeviceDefinition {
+    pub fn new(name: String) -> Self {
+        Self {
+            id: Uuid::new_v4(),
+            name,
+            ports: Vec::new(),
+            cc_names: BTreeMap::new(),
+            patches: Vec::new(),
+            profile: None,
+            on_connect: Vec::new(),
+            on_disconnect: Vec::new(),
+        }
+    }
+
+    pub fn cc_name(&self, cc: u8) -> Option<&str> {
+        self.cc_names.get(&cc).map(String::as_str)
+    }
+
+    /// Patch name for a program, preferring an exact bank match
+    pub fn patch_name(&self, bank: Option<(u8, u8)>, program: u8) -> Option<&str> {
+        let mut candidates = self.patches.iter().filter(|p| p.program == program);
+        let found = match bank {
+            Some((msb, lsb)) => candidates
+                .clone()
+                .find(|p| p.bank_msb == Some(msb) && p.bank_lsb.unwrap_or(lsb) == lsb)
+                .or_else(|| candidates.next()),
+            None => candidates.next(),
+        };
+        found.map(|p| p.name.as_str())
+    }
+
+    /// Name for a monitored message, if the device defines one
+    pub fn label(&self, kind: &MessageKind) -> Option<String> {
+        match kind {
+            MessageKind::ControlChange { controller, .. } => {
+                self.cc_name(*controller).map(str::to_string)
+            }
+            MessageKind::ProgramChange { program } => {
+                self.patch_name(None, *program).map(str::to_string)
+            }
+            _ => None,
+        }
+    }
+}
+
+/// Find the device attached to a port
+pub fn device_for_port<'a>(
+    devices: &'a [DeviceDefinition],
+    port: &str,
+) -> Option<&'a DeviceDefinition> {
+    devices.iter().find(|d| d.ports.iter().any(|p| p == port))
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AppConfig {
+    pub presets: Vec<Preset>,
+    pub active_preset_id: Option<Uuid>,
+    pub port_aliases: std::collections::HashMap<String, String>,
+    #[serde(default = "default_clock_bpm")]
+    pub clock_bpm: f64,
+    #[serde(default)]
+    pub control_bindings: ControlBindings,
+    #[serde(default)]
+    pub device_definitions: Vec<DeviceDefinition>,
+    #[serde(default)]
+    pub middle_c: MiddleC,
+    #[serde(default)]
+    pub clock_settings: ClockSettings,
+    /// Input port name -> jitter buffer latency in ms (network / BLE inputs)
+    #[serde(default)]
+    pub jitter_buffers: BTreeMap<String, u32>,
+    /// Output port name -> how its Note Offs are written
+    #[serde(default)]
+    pub note_off_styles: BTreeMap<String, NoteOffStyle>,
+    /// Internal buses for chaining routes
+    #[serde(default)]
+    pub buses: BTreeSet<String>,
+    /// Whether the clock was left running by the app's transport controls
+    #[serde(default)]
+    pub clock_running: bool,
+    /// Local WebSocket bridge for browser-based editors; off when None
+    #[serde(default)]
+    pub web_bridge: Option<WebBridgeSettings>,
+    /// MQTT client for automation systems; off when None
+    #[serde(default)]
+    pub mqtt: Option<MqttSettings>,
+}
+
+fn default_clock_bpm() -> f64 {
+    120.0
+}
+
+impl Default for AppConfig {
+    fn default() -> Self {
+        Self {
+            presets: Vec::new(),
+            active_preset_id: None,
+            port_aliases: std::collections::HashMap::new(),
+            clock_bpm: default_clock_bpm(),
+            control_bindings: ControlBindings::default(),
+            device_definitions: Vec::new(),
+            middle_c: MiddleC::default(),
+            clock_settings: ClockSettings::default(),
+            jitter_buffers: BTreeMap::new(),
+            note_off_styles: BTreeMap::new(),
+            buses: BTreeSet::new(),
+            clock_running: false,
+            web_bridge: None,
+            mqtt: None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ClockState {
+    pub bpm: f64,
+    pub running: bool,
+}
+
+/// Position of a clock pulse since Start: bar and beat count from 1, tick is
+/// the pulse within the beat (0-23)
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+pub struct ClockPosition {
+    pub bar: u32,
+    pub beat: u32,
+    pub tick: u32,
+}
+
+/// When generated clock pulses (0xF8) are sent to an output
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub enum ClockMode {
+    /// Only between Start/Continue and Stop
+    #[default]
+    WhileRunning,
+    /// Continuously, so devices stay locked to the tempo while stopped
+    Always,
+}
+
+/// Whether the clock starts when the app launches
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub enum ClockLaunch {
+    #[default]
+    Stopped,
+    Start,
+    /// Running if it was running when last started or stopped from the app
+    Restore,
+}
+
+impl ClockLaunch {
+    pub fn should_start(self, was_running: bool) -> bool {
+        match self {
+            ClockLaunch::Stopped => false,
+            ClockLaunch::Start => true,
+            ClockLaunch::Restore => was_running,
+        }
+    }
+}
+
+/// Ports served to browsers over the local WebSocket bridge
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct WebBridgeSettings {
+    /// TCP port on 127.0.0.1
+    pub port: u16,
+    /// Inputs whose messages are sent to browsers
+    pub inputs: Vec<String>,
+    /// Outputs browsers may send to
+    pub outputs: Vec<String>,
+}
+
+/// MQTT broker connection and the ports published to and driven from it
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct MqttSettings {
+    pub host: String,
+    pub port: u16,
+    #[serde(default)]
+    pub username: Option<String>,
+    #[serde(default)]
+    pub password: Option<String>,
+    /// First level of every topic the router uses
+    pub topic_prefix: String,
+    /// Inputs whose messages are published
+    pub inputs: Vec<String>,
+    /// Outputs that messages on command topics are sent to
+    pub outputs: Vec<String>,
+    pub publish_notes: bool,
+    pub publish_ccs: bool,
+    pub publish_transport: bool,
+}
+
+impl Default for MqttSettings {
+    fn default() -> Self {
+        Self {
+            host: "localhost".to_string(),
+            port: 1883,
+            username: None,
+            password: None,
+            topic_prefix: "midi-router".to_string(),
+            inputs: Vec::new(),
+            outputs: Vec::new(),
+            publish_notes: true,
+            publish_ccs: true,
+            publish_transport: true,
+        }
+    }
+}
+
+/// Global clock mode with per-output overrides
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct ClockSettings {
+    #[serde(default)]
+    pub mode: ClockMode,
+    /// Output port name -> mode, overriding the global mode
+    #[serde(default)]
+    pub output_modes: BTreeMap<String, ClockMode>,
+    /// Defer Stop until the end of the current bar, so downstream loops
+    /// finish their phrase
+    #[serde(default)]
+    pub quantize_stop: bool,
+    #[serde(default)]
+    pub on_launch: ClockLaunch,
+    /// Output port names sent All Sound Off and All Notes Off after Stop
+    #[serde(default)]
+    pub silence_on_stop: BTreeSet<String>,
+}
+
+impl ClockSettings {
+    pub fn mode_for(&self, output: &str) -> ClockMode {
+        self.output_modes.get(output).copied().unwrap_or(self.mode)
+    }
+
+    /// Whether any output wants clock while stopped
+    pub fn any_always(&self) -> bool {
+        self.mode == ClockMode::Always
+            || self.output_modes.values().any(|m| *m == ClockMode::Always)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // ChannelFilter::All tests
+    #[test]
+    fn channel_filter_all_passes_any_channel() {
+        let filter = ChannelFilter::All;
+        assert!(filter.passes(0));
+        assert!(filter.passes(7));
+        assert!(filter.passes(15));
+    }
+
+    // ChannelFilter::Only tests
+    #[test]
+    fn channel_filter_only_passes_listed_channels() {
+        let filter = ChannelFilter::Only(vec![0, 1, 2]);
+        assert!(filter.passes(0));
+        assert!(filter.passes(1));
+        assert!(filter.passes(2));
+    }
+
+    #[test]
+    fn channel_filter_only_blocks_unlisted_channels() {
+        let filter = ChannelFilter::Only(vec![0, 1, 2]);
+        assert!(!filter.passes(3));
+        assert!(!filter.passes(15));
+    }
+
+    #[test]
+    fn channel_filter_only_empty_blocks_all() {
+        let filter = ChannelFilter::Only(vec![]);
+        assert!(!filter.passes(0));
+        assert!(!filter.passes(15));
+    }
+
+    // ChannelFilter::Except tests
+    #[test]
+    fn channel_filter_except_blocks_listed_channels() {
+        let filter = ChannelFilter::Except(vec![9, 10]);
+        assert!(!filter.passes(9));
+        assert!(!filter.passes(10));
+    }
+
+    #[test]
+    fn channel_filter_except_passes_unlisted_channels() {
+        let filter = ChannelFilter::Except(vec![9, 10]);
+        assert!(filter.passes(0));
+        assert!(filter.passes(8));
+        assert!(filter.passes(15));
+    }
+
+    #[test]
+    fn channel_filter_except_empty_passes_all() {
+        let filter = ChannelFilter::Except(vec![]);
+        assert!(filter.passes(0));
+        assert!(filter.passes(15));
+    }
+
+    #[test]
+    fn channel_filter_default_is_all() {
+        let filter = ChannelFilter::default();
+        assert!(matches!(filter, ChannelFilter::All));
+    }
+
+    // CcCurve tests
+    #[test]
+    fn cc_curve_linear_is_identity() {
+        for v in [0, 1, 64, 127] {
+            assert_eq!(CcCurve::Linear.apply(v), v);
+        }
+    }
+
+    #[test]
+    fn cc_curve_endpoints_are_fixed() {
+        for curve in [CcCurve::Exponential, CcCurve::Logarithmic, CcCurve::SCurve] {
+            assert_eq!(curve.apply(0), 0);
+            assert_eq!(curve.apply(127), 127);
+        }
+    }
+
+    #[test]
+    fn cc_curve_shapes_midpoint() {
+        assert!(CcCurve::Exponential.apply(64) < 64);
+        assert!(CcCurve::Logarithmic.apply(64) > 64);
+        assert!(CcCurve::SCurve.apply(32) < 32);
+        assert!(CcCurve::SCurve.apply(96) > 96);
+    }
+
+    // VelocityTransform tests
+    #[test]
+    fn velocity_transform_default_is_identity() {
+        for v in [1, 64, 127] {
+            assert_eq!(VelocityTransform::default().apply(v), v);
+        }
+    }
+
+    #[test]
+    fn velocity_transform_scales_curves_and_clamps() {
+        let halved = VelocityTransform {
+            scale: 0.5,
+            ..Default::default()
+        };
+        assert_eq!(halved.apply(100), 50);
+        let soft = VelocityTransform {
+            gamma: 2.0,
+            ..Default::default()
+        };
+        assert!(soft.apply(64) < 64);
+        assert_eq!(soft.apply(127), 127);
+        let clamped = VelocityTransform {
+            min: 40,
+            max: 100,
+            ..Default::default()
+        };
+        assert_eq!(clamped.apply(10), 40);
+        assert_eq!(clamped.apply(120), 100);
+    }
+
+    #[test]
+    fn velocity_transform_never_returns_zero() {
+        let silent = VelocityTransform {
+            scale: 0.0,
+            min: 0,
+            ..Default::default()
+        };
+        assert_eq!(silent.apply(100), 1);
+    }
+
+    // EncoderMode tests
+    #[test]
+    fn encoder_absolute_has_no_delta() {
+        assert_eq!(EncoderMode::Absolute.delta(1, 10), None);
+    }
+
+    #[test]
+    fn encoder_relative_deltas() {
+        assert_eq!(EncoderMode::TwosComplement.delta(1, 1), Some(1));
+        assert_eq!(EncoderMode::TwosComplement.delta(1, 127), Some(-1));
+        assert_eq!(EncoderMode::SignedBit.delta(1, 3), Some(3));
+        assert_eq!(EncoderMode::SignedBit.delta(1, 65), Some(-1));
+        assert_eq!(EncoderMode::BinaryOffset.delta(1, 66), Some(2));
+        assert_eq!(EncoderMode::BinaryOffset.delta(1, 63), Some(-1));
+    }
+
+    #[test]
+    fn encoder_inc_dec_uses_cc_number() {
+        let mode = EncoderMode::IncDec { decrement_cc: 97 };
+        assert_eq!(mode.delta(96, 0), Some(1));
+        assert_eq!(mode.delta(97, 0), Some(-1));
+
+        let mapping = CcMapping {
+            source_cc: 96,
+            targets: vec![],
+            encoder: mode,
+            ..Default::default()
+        };
+        assert!(mapping.handles(96));
+        assert!(mapping.handles(97));
+        assert!(!mapping.handles(98));
+    }
+
+    // MiddleC tests
+    #[test]
+    fn middle_c_conventions() {
+        assert_eq!(MiddleC::C4.note_name(60), "C4");
+        assert_eq!(MiddleC::C3.note_name(60), "C3");
+        assert_eq!(MiddleC::C4.note_name(0), "C-1");
+        assert_eq!(MiddleC::C3.note_name(0), "C-2");
+        assert_eq!(MiddleC::C4.note_name(127), "G9");
+        assert_eq!(MiddleC::C4.note_name(61), "C#4");
+    }
+
+    #[test]
+    fn message_kind_note() {
+        assert_eq!(
+            MessageKind::NoteOn {
+                note: 60,
+                velocity: 100
+            }
+            .note(),
+            Some(60)
+        );
+        assert_eq!(MessageKind::ProgramChange { program: 60 }.note(), None);
+    }
+
+    // DeviceDefinition tests
+    fn test_device() -> DeviceDefinition {
+        let mut device = DeviceDefinition::new("Synth".to_string());
+        device.ports.push("Synth Out".to_string());
+        device.cc_names.insert(74, "Filter Cutoff".to_string());
+        let patch = |bank_msb, program, name: &str| PatchName {
+            bank_msb,
+            bank_lsb: None,
+            program,
+            name: name.to_string(),
+        };
+        device.patches = vec![patch(Some(0), 0, "Piano"), patch(Some(1), 0, "Strings")];
+        device
+    }
+
+    #[test]
+    fn device_names_ccs_and_patches() {
+        let device = test_device();
+        assert_eq!(device.cc_name(74), Some("Filter Cutoff"));
+        assert_eq!(device.cc_name(1), None);
+        assert_eq!(device.patch_name(None, 0), Some("Piano"));
+        assert_eq!(device.patch_name(Some((1, 0)), 0), Some("Strings"));
+        assert_eq!(device.patch_name(Some((5, 0)), 0), Some("Piano"));
+        assert_eq!(device.patch_name(None, 1), None);
+    }
+
+    #[test]
+    fn device_labels_monitor_messages() {
+        let devices = vec![test_device()];
+        let device = device_for_port(&devices, "Synth Out").unwrap();
+        assert_eq!(
+            device.label(&MessageKind::ControlChange {
+                controller: 74,
+                value: 10
+            }),
+            Some("Filter Cutoff".to_string())
+        );
+        assert_eq!(device.label(&MessageKind::Clock), None);
+        assert!(device_for_port(&devices, "Other").is_none());
+    }
+
+    // MscFilter tests
+    #[test]
+    fn msc_filter_only_passes_listed_commands() {
+        let filter = MscFilter::Only(vec![MscCommand::Go, MscCommand::Stop]);
+        assert!(filter.passes(MscCommand::Go));
+        assert!(filter.passes(MscCommand::Stop));
+        assert!(!filter.passes(MscCommand::AllOff));
+    }
+
+    #[test]
+    fn msc_filter_except_blocks_listed_commands() {
+        let filter = MscFilter::Except(vec![MscCommand::Reset]);
+        assert!(!filter.passes(MscCommand::Reset));
+        assert!(filter.passes(MscCommand::Go));
+    }
+
+    #[test]
+    fn system_common_filter_blocks_listed_messages() {
+        let filter = SystemCommonFilter::Except(vec![SystemCommon::MtcQuarterFrame]);
+        assert!(!filter.passes(SystemCommon::MtcQuarterFrame));
+        assert!(filter.passes(SystemCommon::SongPosition));
+        assert_eq!(
+            SystemCommon::from_status(0xF6),
+            Some(SystemCommon::TuneRequest)
+        );
+        assert_eq!(SystemCommon::from_status(0xF8), None);
+    }
+
+    #[test]
+    fn msc_command_from_byte() {
+        assert_eq!(MscCommand::from_byte(0x01), MscCommand::Go);
+        assert_eq!(MscCommand::from_byte(0x0B), MscCommand::GoOff);
+        assert_eq!(MscCommand::from_byte(0x7F), MscCommand::Other(0x7F));
+    }
+
+    // ==========================================================================
+    // Bpm tests
+    // ==========================================================================
+
+    #[test]
+    fn bpm_new_valid() {
+        assert!(Bpm::new(120.0).is_ok());
+        assert!(Bpm::new(20.0).is_ok()); // min
+        assert!(Bpm::new(300.0).is_ok()); // max
+    }
+
+    #[test]
+    fn bpm_new_invalid() {
+        assert!(Bpm::new(19.9).is_err());
+        assert!(Bpm::new(300.1).is_err());
+        assert!(Bpm::new(0.0).is_err());
+        assert!(Bpm::new(-10.0).is_err());
+    }
+
+    #[test]
+    fn bpm_clamped() {
+        assert_eq!(Bpm::clamped(10.0).value(), 20.0);
+        assert_eq!(Bpm::clamped(500.0).value(), 300.0);
+        assert_eq!(Bpm::clamped(120.0).value(), 120.0);
+    }
+
+    #[test]
+    fn bpm_default() {
+        assert_eq!(Bpm::default().value(), 120.0);
+    }
+
+    // ==========================================================================
+    // CcNumber tests
+    // ==========================================================================
+
+    #[test]
+    fn cc_number_valid() {
+        assert!(CcNumber::new(0).is_ok());
+        assert!(CcNumber::new(64).is_ok());
+        assert!(CcNumber::new(127).is_ok());
+    }
+
+    #[test]
+    fn cc_number_invalid() {
+        assert!(CcNumber::new(128).is_err());
+        assert!(CcNumber::new(255).is_err());
+    }
+
+    #[test]
+    fn cc_number_into_u8() {
+        let cc = CcNumber::new(74).unwrap();
+        let val: u8 = cc.into();
+        assert_eq!(val, 74);
+    }
+
+    // ==========================================================================
+    // Channel tests
+    // ==========================================================================
+
+    #[test]
+    fn channel_new_valid() {
+        assert!(Channel::new(0).is_ok());
+        assert!(Channel::new(9).is_ok());
+        assert!(Channel::new(15).is_ok());
+    }
+
+    #[test]
+    fn channel_new_invalid() {
+        assert!(Channel::new(16).is_err());
+        assert!(Channel::new(255).is_err());
+    }
+
+    #[test]
+    fn channel_from_one_indexed_valid() {
+        let ch = Channel::from_one_indexed(1).unwrap();
+        assert_eq!(ch.value(), 0);
+        assert_eq!(ch.display_value(), 1);
+
+        let ch = Channel::from_one_indexed(16).unwrap();
+        assert_eq!(ch.value(), 15);
+        assert_eq!(ch.display_value(), 16);
+    }
+
+    #[test]
+    fn channel_from_one_indexed_invalid() {
+        assert!(Channel::from_one_indexed(0).is_err());
+        assert!(Channel::from_one_indexed(17).is_err());
+    }
+
+    #[test]
+    fn channel_display_value() {
+        let ch = Channel::new(0).unwrap();
+        assert_eq!(ch.display_value(), 1);
+
+        let ch = Channel::new(9).unwrap();
+        assert_eq!(ch.display_value(), 10);
+    }
+
+    // ==========================================================================
+    // ValidationError tests
+    // ==========================================================================
+
+    #[test]
+    fn validation_error_display() {
+        let err = ValidationError::BpmOutOfRange {
+            value: 10.0,
+            min: 20.0,
+            max: 300.0,
+        };
+        assert!(err.to_string().contains("10"));
+        assert!(err.to_string().contains("20"));
+        assert!(err.to_string().contains("300"));
+    }
+
+    // ==========================================================================
+    // EngineError tests
+    // ==========================================================================
+
+    #[test]
+    fn engine_error_port_connection_failed_display() {
+        let err = EngineError::PortConnectionFailed {
+            port_name: "USB MIDI".to_string(),
+            reason: "Device busy".to_string(),
+        };
+        let msg = err.to_string();
+        assert!(msg.contains("USB MIDI"));
+        assert!(msg.contains("Device busy"));
+    }
+
+    #[test]
+    fn engine_error_from_validation_error() {
+        let validation_err = ValidationError::BpmOutOfRange {
+            value: 10.0,
+            min: 20.0,
+            max: 300.0,
+        };
+        let engine_err: EngineError = validation_err.into();
+        assert!(matches!(engine_err, EngineError::ValidationFailed(_)));
+    }
+
+    #[test]
+    fn device_profile_sets_route_defaults() {
+        let profile = DeviceProfile {
+            channels: Some(ChannelFilter::Only(vec![9])),
+            strip_aftertouch: true,
+            ..Default::default()
+        };
+        let mut route = Route::new(
+            PortId::new("Keys".to_string()),
+            PortId::new("TR-8S".to_string()),
+        );
+        profile.apply_to(&mut route);
+
+        assert_eq!(route.channels, ChannelFilter::Only(vec![9]));
+        assert!(route.strip_aftertouch);
+        assert!(route.cc_passthrough);
+    }
+
+    #[test]
+    fn clock_settings_output_override() {
+        let mut settings = ClockSettings::default();
+        assert_eq!(settings.mode_for("Drums"), ClockMode::WhileRunning);
+        assert!(!settings.any_always());
+
+        settings
+            .output_modes
+            .insert("Drums".to_string(), ClockMode::Always);
+        assert_eq!(settings.mode_for("Drums"), ClockMode::Always);
+        assert_eq!(settings.mode_for("Synth"), ClockMode::WhileRunning);
+        assert!(settings.any_always());
+    }
+
+    #[test]
+    fn clock_launch_restores_running_state() {
+        assert!(!ClockLaunch::default().should_start(true));
+        assert!(ClockLaunch::Start.should_start(false));
+        assert!(ClockLaunch::Restore.should_start(true));
+        assert!(!ClockLaunch::Restore.should_start(false));
+    }
+}
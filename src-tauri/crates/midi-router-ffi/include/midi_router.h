@@ -0,0 +1,58 @@
+/*
+ * C API for the rust-midi-router engine.
+ *
+ * Routes, commands and events are JSON strings in the same shapes the app
+ * uses. Functions returning int give 0 on success and -1 on failure; the
+ * reason is available from mr_last_error() on the same thread.
+ */
+#ifndef MIDI_ROUTER_H
+#define MIDI_ROUTER_H
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+typedef struct MrEngine MrEngine;
+
+/* Start an engine. Free it with mr_engine_free(). */
+MrEngine *mr_engine_new(void);
+
+/* Shut the engine down and free its handle. NULL is ignored. */
+void mr_engine_free(MrEngine *engine);
+
+/* Replace the engine's routes with a JSON array of routes. */
+int mr_engine_set_routes(const MrEngine *engine, const char *routes_json);
+
+/*
+ * Send a JSON command: RefreshPorts, Start, Stop,
+ * {"kind": "SetBpm", "data": {"bpm": 128}},
+ * {"kind": "PanicRoute", "data": {"route_id": "..."}},
+ * {"kind": "ReleaseLatch", "data": {"route_id": "..."}},
+ * {"kind": "InjectToRoute", "data": {"route_id": "...", "bytes": [144, 60, 100]}},
+ * {"kind": "SendCcRamp", "data": {"port": "...", "channel": 1, "cc": 7,
+ *   "from": 100, "to": 0, "duration_ms": 2000}}
+ */
+int mr_engine_send_command(const MrEngine *engine, const char *command_json);
+
+/*
+ * The next pending event as {"seq": ..., "kind": ..., "data": ...}, or NULL
+ * when there is none. "seq" counts up per kind, so a jump means events were
+ * missed, e.g. because events piled up unpolled. Free the string with
+ * mr_string_free().
+ */
+char *mr_engine_poll_event(const MrEngine *engine);
+
+/* Free a string returned by mr_engine_poll_event(). NULL is ignored. */
+void mr_string_free(char *text);
+
+/*
+ * The last error on this thread, or NULL. Valid until the next failing
+ * call on the same thread; don't free it.
+ */
+const char *mr_last_error(void);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif /* MIDI_ROUTER_H */
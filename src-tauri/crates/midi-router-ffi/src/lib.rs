@@ -0,0 +1,262 @@
+//! C API for the routing engine
+//!
+//! Lets non-Rust hosts (a Max/MSP external, a Python script through ctypes)
+//! embed the engine. Routes, commands and events cross the boundary as JSON
+//! in the same shapes the app uses; see `include/midi_router.h`.
+//!
+//! Functions returning `int` give 0 on success and -1 on failure, with the
+//! reason available from `mr_last_error` on the same thread.
+
+use midi_router_core::types::{Bpm, Route};
+use midi_router_core::{MidiEngine, SequencedEvent};
+use serde::Deserialize;
+use std::cell::RefCell;
+use std::ffi::{c_char, c_int, CStr, CString};
+use std::ptr;
+use std::time::Duration;
+use uuid::Uuid;
+
+/// Opaque engine handle owned by the host
+pub struct MrEngine {
+    engine: MidiEngine,
+}
+
+thread_local! {
+    static LAST_ERROR: RefCell<Option<CString>> = const { RefCell::new(None) };
+}
+
+/// Commands a host can send, as `{"kind": ..., "data": {...}}`
+#[derive(Debug, Deserialize, PartialEq)]
+#[serde(tag = "kind", content = "data")]
+enum Command {
+    RefreshPorts,
+    SetBpm { bpm: f64 },
+    Start,
+    Stop,
+    PanicRoute { route_id: Uuid },
+    ReleaseLatch { route_id: Uuid },
+    InjectToRoute { route_id: Uuid, bytes: Vec<u8> },
+    SendCcRamp(CcRamp),
+}
+
+/// A CC sweep on an output; `channel` is 1-16
+#[derive(Debug, Deserialize, PartialEq)]
+struct CcRamp {
+    port: String,
+    channel: u8,
+    cc: u8,
+    from: u8,
+    to: u8,
+    duration_ms: u64,
+}
+
+impl Command {
+    fn run(self, engine: &MidiEngine) -> Result<(), String> {
+        match self {
+            Self::RefreshPorts => engine.refresh_ports(),
+            Self::SetBpm { bpm } => {
+                let bpm = Bpm::new(bpm).map_err(|e| e.to_string())?;
+                engine.set_bpm(bpm.value())
+            }
+            Self::Start => engine.send_start(),
+            Self::Stop => engine.send_stop(),
+            Self::PanicRoute { route_id } => engine.panic_route(route_id),
+            Self::ReleaseLatch { route_id } => engine.release_latch(route_id),
+            Self::InjectToRoute { route_id, bytes } => engine.inject_to_route(route_id, bytes),
+            Self::SendCcRamp(ramp) => {
+                let duration = Duration::from_millis(ramp.duration_ms);
+                engine.send_cc_ramp(
+                    ramp.port,
+                    ramp.channel,
+                    ramp.cc,
+                    ramp.from,
+                    ramp.to,
+                    duration,
+                )
+            }
+        }
+    }
+}
+
+/// Record the error for `mr_last_error` and return the failure code
+fn fail(message: String) -> c_int {
+    let message = CString::new(message.replace('\0', "")).ok();
+    LAST_ERROR.with(|last| *last.borrow_mut() = message);
+    -1
+}
+
+fn status(result: Result<(), String>) -> c_int {
+    match result {
+        Ok(()) => 0,
+        Err(e) => fail(e),
+    }
+}
+
+/// # Safety
+/// `engine` must be null or a handle from `mr_engine_new` not yet freed.
+unsafe fn engine_ref<'a>(engine: *const MrEngine) -> Result<&'a MidiEngine, String> {
+    engine
+        .as_ref()
+        .map(|handle| &handle.engine)
+        .ok_or_else(|| "Engine handle is null".to_string())
+}
+
+/// # Safety
+/// `text` must be null or a NUL-terminated string.
+unsafe fn str_arg<'a>(text: *const c_char) -> Result<&'a str, String> {
+    if text.is_null() {
+        return Err("Argument is null".to_string());
+    }
+    CStr::from_ptr(text).to_str().map_err(|e| e.to_string())
+}
+
+fn event_json(event: &SequencedEvent) -> Option<CString> {
+    let json = serde_json::to_string(event).ok()?;
+    CString::new(json).ok()
+}
+
+/// Start an engine. Free it with `mr_engine_free`.
+#[no_mangle]
+pub extern "C" fn mr_engine_new() -> *mut MrEngine {
+    Box::into_raw(Box::new(MrEngine {
+        engine: MidiEngine::new(),
+    }))
+}
+
+/// Shut the engine down and free its handle. Null is ignored.
+///
+/// # Safety
+/// `engine` must be null or a handle from `mr_engine_new` not yet freed.
+#[no_mangle]
+pub unsafe extern "C" fn mr_engine_free(engine: *mut MrEngine) {
+    if !engine.is_null() {
+        drop(Box::from_raw(engine));
+    }
+}
+
+/// Replace the engine's routes with a JSON array of routes
+///
+/// # Safety
+/// `engine` must be a live handle and `routes_json` a NUL-terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn mr_engine_set_routes(
+    engine: *const MrEngine,
+    routes_json: *const c_char,
+) -> c_int {
+    let set_routes = || {
+        let engine = engine_ref(engine)?;
+        let routes: Vec<Route> =
+            serde_json::from_str(str_arg(routes_json)?).map_err(|e| e.to_string())?;
+        engine.set_routes(routes)
+    };
+    status(set_routes())
+}
+
+/// Send a JSON command, such as `{"kind": "SetBpm", "data": {"bpm": 128}}`
+///
+/// # Safety
+/// `engine` must be a live handle and `command_json` a NUL-terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn mr_engine_send_command(
+    engine: *const MrEngine,
+    command_json: *const c_char,
+) -> c_int {
+    let send_command = || {
+        let engine = engine_ref(engine)?;
+        let command: Command =
+            serde_json::from_str(str_arg(command_json)?).map_err(|e| e.to_string())?;
+        command.run(engine)
+    };
+    status(send_command())
+}
+
+/// The next pending event as JSON, or null when there is none. Free the
+/// string with `mr_string_free`.
+///
+/// # Safety
+/// `engine` must be null or a live handle.
+#[no_mangle]
+pub unsafe extern "C" fn mr_engine_poll_event(engine: *const MrEngine) -> *mut c_char {
+    let Ok(engine) = engine_ref(engine) else {
+        return ptr::null_mut();
+    };
+    engine
+        .try_recv_event()
+        .and_then(|event| event_json(&event))
+        .map_or(ptr::null_mut(), CString::into_raw)
+}
+
+/// Free a string returned by the engine. Null is ignored.
+///
+/// # Safety
+/// `text` must be null or a string from `mr_engine_poll_event` not yet freed.
+#[no_mangle]
+pub unsafe extern "C" fn mr_string_free(text: *mut c_char) {
+    if !text.is_null() {
+        drop(CString::from_raw(text));
+    }
+}
+
+/// The last error on this thread, or null. Valid until the next failing call
+/// on the same thread; don't free it.
+#[no_mangle]
+pub extern "C" fn mr_last_error() -> *const c_char {
+    LAST_ERROR.with(|last| last.borrow().as_ref().map_or(ptr::null(), |e| e.as_ptr()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use midi_router_core::EngineEvent;
+
+    fn last_error() -> String {
+        let error = mr_last_error();
+        assert!(!error.is_null());
+        unsafe { CStr::from_ptr(error) }
+            .to_string_lossy()
+            .into_owned()
+    }
+
+    #[test]
+    fn parses_commands() {
+        let command: Command =
+            serde_json::from_str(r#"{"kind": "SetBpm", "data": {"bpm": 128}}"#).unwrap();
+        assert_eq!(command, Command::SetBpm { bpm: 128.0 });
+        let command: Command = serde_json::from_str(r#"{"kind": "Start"}"#).unwrap();
+        assert_eq!(command, Command::Start);
+        assert!(serde_json::from_str::<Command>(r#"{"kind": "Explode"}"#).is_err());
+
+        let ramp = r#"{"kind": "SendCcRamp", "data": {"port": "Synth", "channel": 1,
+            "cc": 7, "from": 100, "to": 0, "duration_ms": 2000}}"#;
+        let Command::SendCcRamp(ramp) = serde_json::from_str(ramp).unwrap() else {
+            panic!("Expected a CC ramp");
+        };
+        assert_eq!((ramp.cc, ramp.duration_ms), (7, 2000));
+    }
+
+    #[test]
+    fn failures_are_reported_through_last_error() {
+        let routes = CString::new("[]").unwrap();
+        let status = unsafe { mr_engine_set_routes(ptr::null(), routes.as_ptr()) };
+        assert_eq!(status, -1);
+        assert_eq!(last_error(), "Engine handle is null");
+
+        let status = unsafe { mr_engine_send_command(ptr::null(), ptr::null()) };
+        assert_eq!(status, -1);
+        assert!(unsafe { mr_engine_poll_event(ptr::null()) }.is_null());
+    }
+
+    #[test]
+    fn events_serialize_with_their_kind() {
+        let id = Uuid::nil();
+        let event = SequencedEvent {
+            seq: 2,
+            event: EngineEvent::PresetSwitched(id),
+        };
+        let json = event_json(&event).unwrap();
+        assert_eq!(
+            json.to_str().unwrap(),
+            format!(r#"{{"seq":2,"kind":"PresetSwitched","data":"{}"}}"#, id)
+        );
+    }
+}
@@ -1,42 +1,107 @@
 //! Tauri command handlers
 
-use crate::config::preset;
-use crate::midi::engine::{EngineEvent, MidiEngine};
-use crate::types::{Bpm, CcMapping, ChannelFilter, ClockState, EngineError, MidiActivity, MidiPort, PortId, Preset, Route};
-use std::sync::Mutex;
-use tauri::{ipc::Channel, State};
+use midi_router_core::config::{midnam, preset, tuning_file};
+use midi_router_core::midi::monitor::MonitorHistory;
+use midi_router_core::midi::port_manager::connection_hooks;
+use midi_router_core::midi::preset_queue::QueuedPreset;
+use midi_router_core::midi::sequence::{GapDetector, SharedEventGaps};
+use midi_router_core::midi::{matrix, validation};
+use midi_router_core::types::{
+    device_for_port, BankProgram, BankSelectSettings, Bpm, CcCalibration, CcMapping, ChannelFilter,
+    ChannelMap, ChannelRotation, ClockPosition, ClockSettings, ClockState, ControlBindings,
+    DebugBundle, DetectedChord, DeviceDefinition, DuplicateFilter, EngineError, EngineStats,
+    GamepadMapping, GamepadTarget, HarmonyVoice, HeldNotes, KeyRange, Layer, LoadedPreset,
+    Microtuning, MiddleC, MidiActivity, MidiPort, MqttSettings, MscFilter, NoteCcMode, NoteEcho,
+    NoteMapping, NoteOffStyle, NotePriority, PortId, PortPulse, Preset, ProgramChangeFilter,
+    ProgramRecall, ProgramStepper, RealTimeStrip, RecentError, ReplayReport, Route, RouteStats,
+    RouteStatus, RouteStatusChange, RouteSuggestion, RouteWarning, RouteWithStatus, RoutingMatrix,
+    SessionStats, SetupTemplate, SongSelectBinding, SongSelectChange, StepButton,
+    SystemCommonFilter, TapTempoBinding, TempoCcBinding, TestPattern, TestSignal,
+    TransportTriggerBinding, TrapCondition, TrapHit, TuningTable, VelocityHumanize,
+    VelocityTransform, VoiceSplit, WakeReport, WebBridgeSettings,
+};
+use midi_router_core::{EngineEvent, MidiEngine, SequencedEvent};
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use tauri::{
+    ipc::{Channel, IpcResponse},
+    State,
+};
 use uuid::Uuid;
 
 pub struct AppState {
     pub engine: MidiEngine,
     pub routes: Mutex<Vec<Route>>,
     pub clock_bpm: Mutex<f64>,
+    pub clock_settings: Mutex<ClockSettings>,
+    pub jitter_buffers: Mutex<BTreeMap<String, u32>>,
+    pub note_off_styles: Mutex<BTreeMap<String, NoteOffStyle>>,
+    pub buses: Mutex<BTreeSet<String>>,
+    pub web_bridge: Mutex<Option<WebBridgeSettings>>,
+    pub mqtt: Mutex<Option<MqttSettings>>,
+    /// Gamepad mappings of the current setup, saved with presets
+    pub gamepad: Mutex<Vec<GamepadMapping>>,
+    pub control_bindings: Mutex<ControlBindings>,
+    /// Shared with the monitor thread to label events
+    pub device_definitions: Arc<Mutex<Vec<DeviceDefinition>>>,
+    pub middle_c: Mutex<MiddleC>,
+    /// Shared with the monitor thread, which records every event
+    pub monitor_history: Arc<Mutex<MonitorHistory>>,
+    /// Shared with the monitor threads, which count events they never received
+    pub event_gaps: SharedEventGaps,
 }
 
+/// The engine's current port list, without rescanning
 #[tauri::command]
-pub fn get_ports(state: State<AppState>) -> Result<(Vec<MidiPort>, Vec<MidiPort>), String> {
-    use crate::midi::ports::{list_input_ports, list_output_ports};
+pub fn list_ports(state: State<AppState>) -> Result<(Vec<MidiPort>, Vec<MidiPort>), String> {
+    state.engine.get_ports()
+}
 
-    // Trigger engine to close connections so CoreMIDI refreshes port list
+#[tauri::command]
+pub fn rescan_ports(state: State<AppState>) -> Result<(Vec<MidiPort>, Vec<MidiPort>), String> {
+    // Let the engine reconcile connections with the current port list
     // Use sync version to ensure refresh is complete before listing ports
     state.engine.refresh_ports_sync()?;
 
-    let inputs = list_input_ports();
-    let outputs = list_output_ports();
-    eprintln!("[CMD] get_ports: {} inputs, {} outputs", inputs.len(), outputs.len());
+    let (inputs, outputs) = state.engine.get_ports()?;
+    eprintln!("[CMD] rescan_ports: {} inputs, {} outputs", inputs.len(), outputs.len());
 
-    // Re-apply existing routes to reconnect to ports
+    // Re-apply existing routes to reconnect to ports (control inputs too,
+    // so this runs even when there are no routes)
     let routes = state.routes.lock().unwrap().clone();
-    if !routes.is_empty() {
-        state.engine.set_routes(routes)?;
-    }
+    state.engine.set_routes(routes)?;
 
     Ok((inputs, outputs))
 }
 
 #[tauri::command]
-pub fn get_routes(state: State<AppState>) -> Vec<Route> {
-    state.routes.lock().unwrap().clone()
+pub fn get_routes(state: State<AppState>) -> Vec<RouteWithStatus> {
+    routes_with_status(&state)
+}
+
+/// Current routes with the engine's runtime status for each
+fn routes_with_status(state: &AppState) -> Vec<RouteWithStatus> {
+    let routes = state.routes.lock().unwrap().clone();
+    let statuses: HashMap<Uuid, RouteStatus> = state
+        .engine
+        .get_stats()
+        .map(|stats| {
+            stats
+                .routes
+                .into_iter()
+                .map(|r| (r.route_id, r.status))
+                .collect()
+        })
+        .unwrap_or_default();
+
+    routes
+        .into_iter()
+        .map(|route| RouteWithStatus {
+            status: statuses.get(&route.id).copied().unwrap_or_default(),
+            route,
+        })
+        .collect()
 }
 
 #[tauri::command]
@@ -47,7 +112,8 @@ pub fn add_route(
 ) -> Result<Route, String> {
     let source = PortId::new(source_name);
     let destination = PortId::new(dest_name);
-    let route = Route::new(source, destination);
+    let mut route = Route::new(source, destination);
+    apply_device_profile(&state, &mut route)?;
 
     {
         let mut routes = state.routes.lock().unwrap();
@@ -58,6 +124,42 @@ pub fn add_route(
     Ok(route)
 }
 
+/// Give a new route the starting filters and transforms of its destination
+/// device's profile, and the profile's clock mode to the destination
+fn apply_device_profile(state: &AppState, route: &mut Route) -> Result<(), String> {
+    let devices = state.device_definitions.lock().unwrap();
+    let Some(profile) =
+        device_for_port(&devices, &route.destination.name).and_then(|d| d.profile.as_ref())
+    else {
+        return Ok(());
+    };
+    profile.apply_to(route);
+
+    if let Some(mode) = profile.clock {
+        let mut settings = state.clock_settings.lock().unwrap();
+        if !settings.output_modes.contains_key(&route.destination.name) {
+            settings
+                .output_modes
+                .insert(route.destination.name.clone(), mode);
+            state.engine.set_clock_settings(settings.clone())?;
+            preset::set_clock_settings(settings.clone())?;
+        }
+    }
+    Ok(())
+}
+
+/// Apply device profiles to routes that aren't in `existing`
+fn apply_device_profiles(
+    state: &AppState,
+    routes: &mut [Route],
+    existing: &HashSet<Uuid>,
+) -> Result<(), String> {
+    for route in routes.iter_mut().filter(|r| !existing.contains(&r.id)) {
+        apply_device_profile(state, route)?;
+    }
+    Ok(())
+}
+
 #[tauri::command]
 pub fn remove_route(state: State<AppState>, route_id: String) -> Result<(), String> {
     let uuid = Uuid::parse_str(&route_id).map_err(|e| e.to_string())?;
@@ -88,6 +190,24 @@ pub fn toggle_route(state: State<AppState>, route_id: String) -> Result<bool, St
     Ok(new_enabled)
 }
 
+/// Switch every route in a layer group on, or off if any is on. Returns
+/// whether the group is now on.
+#[tauri::command]
+pub fn toggle_layer_group(state: State<AppState>, group: String) -> Result<bool, String> {
+    let mut routes = state.routes.lock().unwrap();
+    let in_group = |route: &Route| route.layer.as_ref().is_some_and(|l| l.group == group);
+    if !routes.iter().any(in_group) {
+        return Err(format!("No layer group named {}", group));
+    }
+    let enabled = !routes.iter().filter(|r| in_group(r)).any(|r| r.enabled);
+    for route in routes.iter_mut().filter(|r| in_group(r)) {
+        route.enabled = enabled;
+    }
+    state.engine.set_routes(routes.clone())?;
+
+    Ok(enabled)
+}
+
 #[tauri::command]
 pub fn set_route_channels(
     state: State<AppState>,
@@ -128,147 +248,1807 @@ pub fn set_route_cc_mappings(
     Ok(())
 }
 
+/// Start recording the range a route's source CC sweeps. Move the control
+/// end to end, then call `finish_cc_range_learn`.
 #[tauri::command]
-pub fn start_midi_monitor(
+pub fn start_cc_range_learn(
     state: State<AppState>,
-    on_event: Channel<MidiActivity>,
+    route_id: String,
+    source_cc: u8,
 ) -> Result<(), String> {
-    let event_rx = state.engine.event_receiver();
+    let uuid = Uuid::parse_str(&route_id).map_err(|e| e.to_string())?;
+    if source_cc > 127 {
+        return Err("CC number must be 0-127".to_string());
+    }
+    state.engine.start_range_learn(uuid, source_cc)
+}
 
-    std::thread::spawn(move || {
-        loop {
-            match event_rx.recv() {
-                Ok(EngineEvent::MidiActivity(activity)) => {
-                    if on_event.send(activity).is_err() {
-                        break;
-                    }
-                }
-                Ok(_) => {}
-                Err(_) => break,
-            }
-        }
-    });
+/// End the sweep and store the recorded range as the calibration of the
+/// route's mapping for `source_cc`, keeping its curve
+#[tauri::command]
+pub fn finish_cc_range_learn(
+    state: State<AppState>,
+    route_id: String,
+    source_cc: u8,
+) -> Result<CcCalibration, String> {
+    let uuid = Uuid::parse_str(&route_id).map_err(|e| e.to_string())?;
+    let (min, max) = state
+        .engine
+        .finish_range_learn()?
+        .ok_or("No values were received from the control")?;
+    if min == max {
+        return Err("Move the control through its full range".to_string());
+    }
 
-    Ok(())
+    let mut routes = state.routes.lock().unwrap();
+    let mapping = routes
+        .iter_mut()
+        .find(|r| r.id == uuid)
+        .ok_or("Route not found")?
+        .cc_mappings
+        .iter_mut()
+        .find(|m| m.source_cc == source_cc)
+        .ok_or("The route has no mapping for this CC")?;
+    let calibration = CcCalibration {
+        min,
+        max,
+        curve: mapping.calibration.map(|c| c.curve).unwrap_or_default(),
+    };
+    mapping.calibration = Some(calibration);
+    state.engine.set_routes(routes.clone())?;
+
+    Ok(calibration)
 }
 
 #[tauri::command]
-pub fn start_error_monitor(
+pub fn export_cc_mappings(
     state: State<AppState>,
-    on_error: Channel<EngineError>,
+    route_id: String,
+    path: String,
 ) -> Result<(), String> {
-    let event_rx = state.engine.event_receiver();
+    use midi_router_core::config::mapping_file;
 
-    std::thread::spawn(move || {
-        loop {
-            match event_rx.recv() {
-                Ok(EngineEvent::Error(error)) => {
-                    if on_error.send(error).is_err() {
-                        break;
-                    }
-                }
-                Ok(_) => {}
-                Err(_) => break,
-            }
+    let uuid = Uuid::parse_str(&route_id).map_err(|e| e.to_string())?;
+    let routes = state.routes.lock().unwrap();
+    let route = routes
+        .iter()
+        .find(|r| r.id == uuid)
+        .ok_or("Route not found")?;
+    mapping_file::export_cc_mappings(&route.cc_mappings, Path::new(&path))
+}
+
+/// Import mappings from a file onto a route.
+/// With `replace`, existing mappings are dropped first; otherwise imported
+/// mappings replace existing ones for the same source CC.
+#[tauri::command]
+pub fn import_cc_mappings(
+    state: State<AppState>,
+    route_id: String,
+    path: String,
+    replace: bool,
+) -> Result<Vec<CcMapping>, String> {
+    use midi_router_core::config::mapping_file;
+
+    let uuid = Uuid::parse_str(&route_id).map_err(|e| e.to_string())?;
+    let imported = mapping_file::import_cc_mappings(Path::new(&path))?;
+
+    let mut routes = state.routes.lock().unwrap();
+    let route = routes
+        .iter_mut()
+        .find(|r| r.id == uuid)
+        .ok_or("Route not found")?;
+    if replace {
+        route.cc_mappings.clear();
+    }
+    mapping_file::merge_cc_mappings(&mut route.cc_mappings, imported);
+    let mappings = route.cc_mappings.clone();
+    state.engine.set_routes(routes.clone())?;
+
+    Ok(mappings)
+}
+
+#[tauri::command]
+pub fn set_route_msc_filter(
+    state: State<AppState>,
+    route_id: String,
+    filter: MscFilter,
+) -> Result<(), String> {
+    let uuid = Uuid::parse_str(&route_id).map_err(|e| e.to_string())?;
+
+    {
+        let mut routes = state.routes.lock().unwrap();
+        if let Some(route) = routes.iter_mut().find(|r| r.id == uuid) {
+            route.msc_filter = filter;
         }
-    });
+        state.engine.set_routes(routes.clone())?;
+    }
 
     Ok(())
 }
 
+/// Read a Scala (.scl) or AnaMark (.tun) tuning file
 #[tauri::command]
-pub fn list_presets() -> Vec<Preset> {
-    preset::list_presets()
+pub fn load_tuning_file(path: String) -> Result<TuningTable, String> {
+    tuning_file::load_tuning(Path::new(&path))
 }
 
 #[tauri::command]
-pub fn save_preset(state: State<AppState>, name: String) -> Result<Preset, String> {
-    let routes = state.routes.lock().unwrap().clone();
-    preset::save_preset(name, routes)
+pub fn set_route_microtuning(
+    state: State<AppState>,
+    route_id: String,
+    microtuning: Option<Microtuning>,
+) -> Result<(), String> {
+    let uuid = Uuid::parse_str(&route_id).map_err(|e| e.to_string())?;
+
+    {
+        let mut routes = state.routes.lock().unwrap();
+        if let Some(route) = routes.iter_mut().find(|r| r.id == uuid) {
+            route.microtuning = microtuning;
+        }
+        state.engine.set_routes(routes.clone())?;
+    }
+
+    Ok(())
 }
 
+/// Choose which System Real-Time messages a route drops
 #[tauri::command]
-pub fn update_preset(state: State<AppState>, preset_id: String) -> Result<Preset, String> {
-    let id = Uuid::parse_str(&preset_id).map_err(|e| e.to_string())?;
-    let routes = state.routes.lock().unwrap().clone();
-    preset::update_preset(id, routes)
+pub fn set_route_real_time_strip(
+    state: State<AppState>,
+    route_id: String,
+    strip: RealTimeStrip,
+) -> Result<(), String> {
+    let uuid = Uuid::parse_str(&route_id).map_err(|e| e.to_string())?;
+
+    {
+        let mut routes = state.routes.lock().unwrap();
+        if let Some(route) = routes.iter_mut().find(|r| r.id == uuid) {
+            route.real_time_strip = strip;
+        }
+        state.engine.set_routes(routes.clone())?;
+    }
+
+    Ok(())
 }
 
+/// Send a route's channel voice messages on other channels, or keep their
+/// channels with None
 #[tauri::command]
-pub fn load_preset(state: State<AppState>, preset_id: String) -> Result<Preset, String> {
-    let id = Uuid::parse_str(&preset_id).map_err(|e| e.to_string())?;
-    let p = preset::get_preset(id).ok_or_else(|| "Preset not found".to_string())?;
+pub fn set_route_channel_map(
+    state: State<AppState>,
+    route_id: String,
+    map: Option<ChannelMap>,
+) -> Result<(), String> {
+    let uuid = Uuid::parse_str(&route_id).map_err(|e| e.to_string())?;
+    let valid = match &map {
+        Some(ChannelMap::Force(channel)) => (1..=16).contains(channel),
+        Some(ChannelMap::Remap(map)) => map
+            .iter()
+            .all(|(from, to)| (1..=16).contains(from) && (1..=16).contains(to)),
+        None => true,
+    };
+    if !valid {
+        return Err("Mapped channels must be 1-16".to_string());
+    }
 
     {
         let mut routes = state.routes.lock().unwrap();
-        *routes = p.routes.clone();
+        if let Some(route) = routes.iter_mut().find(|r| r.id == uuid) {
+            route.channel_map = map;
+        }
         state.engine.set_routes(routes.clone())?;
     }
 
-    preset::set_active_preset(Some(id))?;
-    Ok(p)
+    Ok(())
 }
 
+/// Limit a route to a keyboard zone, or play the whole keyboard with None
 #[tauri::command]
-pub fn delete_preset(preset_id: String) -> Result<(), String> {
-    let id = Uuid::parse_str(&preset_id).map_err(|e| e.to_string())?;
-    preset::delete_preset(id)
+pub fn set_route_key_range(
+    state: State<AppState>,
+    route_id: String,
+    range: Option<KeyRange>,
+) -> Result<(), String> {
+    let uuid = Uuid::parse_str(&route_id).map_err(|e| e.to_string())?;
+    if range.is_some_and(|r| r.low_note > r.high_note || r.high_note > 127) {
+        return Err("Key range must run from a low note to a high note within 0-127".to_string());
+    }
+
+    {
+        let mut routes = state.routes.lock().unwrap();
+        if let Some(route) = routes.iter_mut().find(|r| r.id == uuid) {
+            route.key_range = range;
+        }
+        state.engine.set_routes(routes.clone())?;
+    }
+
+    Ok(())
 }
 
 #[tauri::command]
-pub fn get_active_preset_id() -> Option<String> {
-    preset::get_active_preset().map(|p| p.id.to_string())
+pub fn set_route_strip_aftertouch(
+    state: State<AppState>,
+    route_id: String,
+    strip: bool,
+) -> Result<(), String> {
+    let uuid = Uuid::parse_str(&route_id).map_err(|e| e.to_string())?;
+
+    {
+        let mut routes = state.routes.lock().unwrap();
+        if let Some(route) = routes.iter_mut().find(|r| r.id == uuid) {
+            route.strip_aftertouch = strip;
+        }
+        state.engine.set_routes(routes.clone())?;
+    }
+
+    Ok(())
 }
 
 #[tauri::command]
-pub fn set_bpm(state: State<AppState>, bpm: f64) -> Result<(), String> {
-    // Validate BPM using the newtype
-    let validated_bpm = Bpm::new(bpm).map_err(|e| e.to_string())?;
-    let bpm_value = validated_bpm.value();
+pub fn set_route_latch(
+    state: State<AppState>,
+    route_id: String,
+    latch: bool,
+) -> Result<(), String> {
+    let uuid = Uuid::parse_str(&route_id).map_err(|e| e.to_string())?;
 
-    *state.clock_bpm.lock().unwrap() = bpm_value;
-    state.engine.set_bpm(bpm_value)?;
+    {
+        let mut routes = state.routes.lock().unwrap();
+        if let Some(route) = routes.iter_mut().find(|r| r.id == uuid) {
+            route.latch = latch;
+        }
+        state.engine.set_routes(routes.clone())?;
+    }
 
-    // Persist to config
-    crate::config::preset::set_clock_bpm(bpm_value)?;
+    Ok(())
+}
+
+/// Have the router sustain a route's notes with its pedal, for destinations
+/// that ignore CC64
+#[tauri::command]
+pub fn set_route_sustain(
+    state: State<AppState>,
+    route_id: String,
+    sustain: bool,
+) -> Result<(), String> {
+    let uuid = Uuid::parse_str(&route_id).map_err(|e| e.to_string())?;
+
+    {
+        let mut routes = state.routes.lock().unwrap();
+        if let Some(route) = routes.iter_mut().find(|r| r.id == uuid) {
+            route.sustain = sustain;
+        }
+        state.engine.set_routes(routes.clone())?;
+    }
 
     Ok(())
 }
 
+/// Play a route monophonically with the given note priority, or
+/// polyphonically with None
 #[tauri::command]
-pub fn get_clock_bpm(state: State<AppState>) -> f64 {
-    *state.clock_bpm.lock().unwrap()
+pub fn set_route_mono(
+    state: State<AppState>,
+    route_id: String,
+    priority: Option<NotePriority>,
+) -> Result<(), String> {
+    let uuid = Uuid::parse_str(&route_id).map_err(|e| e.to_string())?;
+
+    {
+        let mut routes = state.routes.lock().unwrap();
+        if let Some(route) = routes.iter_mut().find(|r| r.id == uuid) {
+            route.mono = priority;
+        }
+        state.engine.set_routes(routes.clone())?;
+    }
+
+    Ok(())
 }
 
+/// Deal a route's notes across several channels, or stop with None
 #[tauri::command]
-pub fn send_transport_start(state: State<AppState>) -> Result<(), String> {
-    state.engine.send_start()
+pub fn set_route_channel_rotation(
+    state: State<AppState>,
+    route_id: String,
+    rotation: Option<ChannelRotation>,
+) -> Result<(), String> {
+    let uuid = Uuid::parse_str(&route_id).map_err(|e| e.to_string())?;
+    if let Some(rotation) = &rotation {
+        if rotation.channels.is_empty() {
+            return Err("Choose at least one channel to rotate through".to_string());
+        }
+        if rotation.channels.iter().any(|ch| !(1..=16).contains(ch)) {
+            return Err("Rotation channels must be 1-16".to_string());
+        }
+    }
+
+    {
+        let mut routes = state.routes.lock().unwrap();
+        if let Some(route) = routes.iter_mut().find(|r| r.id == uuid) {
+            route.channel_rotation = rotation;
+        }
+        state.engine.set_routes(routes.clone())?;
+    }
+
+    Ok(())
 }
 
+/// Play transposed copies of each of a route's notes; empty plays them alone
 #[tauri::command]
-pub fn send_transport_stop(state: State<AppState>) -> Result<(), String> {
-    state.engine.send_stop()
+pub fn set_route_harmony(
+    state: State<AppState>,
+    route_id: String,
+    voices: Vec<HarmonyVoice>,
+) -> Result<(), String> {
+    let uuid = Uuid::parse_str(&route_id).map_err(|e| e.to_string())?;
+    if voices
+        .iter()
+        .any(|voice| !(-127..=127).contains(&voice.interval))
+    {
+        return Err("Harmony intervals must be within 127 semitones".to_string());
+    }
+    if voices
+        .iter()
+        .any(|voice| voice.channel.is_some_and(|ch| !(1..=16).contains(&ch)))
+    {
+        return Err("Harmony channels must be 1-16".to_string());
+    }
+
+    {
+        let mut routes = state.routes.lock().unwrap();
+        if let Some(route) = routes.iter_mut().find(|r| r.id == uuid) {
+            route.harmony = voices;
+        }
+        state.engine.set_routes(routes.clone())?;
+    }
+
+    Ok(())
 }
 
+/// Spread a route's notes across its destination and more outputs, one
+/// voice per port or dealt in turn, or stop with None
 #[tauri::command]
-pub fn start_clock_monitor(
+pub fn set_route_voice_split(
     state: State<AppState>,
-    on_event: Channel<ClockState>,
+    route_id: String,
+    split: Option<VoiceSplit>,
 ) -> Result<(), String> {
-    let event_rx = state.engine.event_receiver();
+    let uuid = Uuid::parse_str(&route_id).map_err(|e| e.to_string())?;
+    if split
+        .as_ref()
+        .is_some_and(|split| split.outputs.iter().any(|o| o.is_empty()))
+    {
+        return Err("Voice outputs need a port name".to_string());
+    }
 
-    std::thread::spawn(move || {
-        loop {
-            match event_rx.recv() {
-                Ok(EngineEvent::ClockStateChanged(clock_state)) => {
-                    if on_event.send(clock_state).is_err() {
-                        break;
-                    }
-                }
-                Ok(_) => {}
-                Err(_) => break,
-            }
+    {
+        let mut routes = state.routes.lock().unwrap();
+        if let Some(route) = routes.iter_mut().find(|r| r.id == uuid) {
+            route.voice_split = split;
         }
+        state.engine.set_routes(routes.clone())?;
+    }
+
+    Ok(())
+}
+
+/// Drop double-triggered notes on a route, or stop with None
+#[tauri::command]
+pub fn set_route_duplicate_filter(
+    state: State<AppState>,
+    route_id: String,
+    filter: Option<DuplicateFilter>,
+) -> Result<(), String> {
+    let uuid = Uuid::parse_str(&route_id).map_err(|e| e.to_string())?;
+    if filter.as_ref().is_some_and(|f| f.window_ms == 0) {
+        return Err("Duplicate window must be at least 1 ms".to_string());
+    }
+
+    {
+        let mut routes = state.routes.lock().unwrap();
+        if let Some(route) = routes.iter_mut().find(|r| r.id == uuid) {
+            route.duplicate_filter = filter;
+        }
+        state.engine.set_routes(routes.clone())?;
+    }
+
+    Ok(())
+}
+
+/// Reshape a route's Note On velocities, or stop with None
+#[tauri::command]
+pub fn set_route_velocity_curve(
+    state: State<AppState>,
+    route_id: String,
+    curve: Option<VelocityTransform>,
+) -> Result<(), String> {
+    let uuid = Uuid::parse_str(&route_id).map_err(|e| e.to_string())?;
+    if let Some(curve) = &curve {
+        if !(curve.scale.is_finite() && curve.scale > 0.0) {
+            return Err("Velocity scale must be greater than 0".to_string());
+        }
+        if !(curve.gamma.is_finite() && curve.gamma > 0.0) {
+            return Err("Velocity curve must be greater than 0".to_string());
+        }
+        if curve.min == 0 || curve.min > curve.max || curve.max > 127 {
+            return Err("Velocity range must be within 1-127".to_string());
+        }
+    }
+
+    {
+        let mut routes = state.routes.lock().unwrap();
+        if let Some(route) = routes.iter_mut().find(|r| r.id == uuid) {
+            route.velocity_curve = curve;
+        }
+        state.engine.set_routes(routes.clone())?;
+    }
+
+    Ok(())
+}
+
+/// Drop Note Ons softer than `gate` on a route, or let every note through
+/// with None
+#[tauri::command]
+pub fn set_route_velocity_gate(
+    state: State<AppState>,
+    route_id: String,
+    gate: Option<u8>,
+) -> Result<(), String> {
+    let uuid = Uuid::parse_str(&route_id).map_err(|e| e.to_string())?;
+    if gate.is_some_and(|gate| !(1..=127).contains(&gate)) {
+        return Err("Velocity gate must be 1-127".to_string());
+    }
+
+    {
+        let mut routes = state.routes.lock().unwrap();
+        if let Some(route) = routes.iter_mut().find(|r| r.id == uuid) {
+            route.velocity_gate = gate;
+        }
+        state.engine.set_routes(routes.clone())?;
+    }
+
+    Ok(())
+}
+
+/// Put a route in a layer group, playing only the layer's velocities, or
+/// take it out with None. A group's routes share their source.
+#[tauri::command]
+pub fn set_route_layer(
+    state: State<AppState>,
+    route_id: String,
+    layer: Option<Layer>,
+) -> Result<(), String> {
+    let uuid = Uuid::parse_str(&route_id).map_err(|e| e.to_string())?;
+    if let Some(layer) = &layer {
+        if layer.group.trim().is_empty() {
+            return Err("Layer group needs a name".to_string());
+        }
+        let (low, high) = (layer.low_velocity, layer.high_velocity);
+        if low == 0 || high > 127 || low > high {
+            return Err("Layer velocities must be 1-127, softest first".to_string());
+        }
+    }
+
+    {
+        let mut routes = state.routes.lock().unwrap();
+        let source = match routes.iter().find(|r| r.id == uuid) {
+            Some(route) => route.source.clone(),
+            None => return Ok(()),
+        };
+        if let Some(layer) = &layer {
+            let other_source = routes.iter().any(|r| {
+                r.id != uuid
+                    && r.source != source
+                    && r.layer.as_ref().is_some_and(|l| l.group == layer.group)
+            });
+            if other_source {
+                return Err(format!(
+                    "Layer group {} plays from another source",
+                    layer.group
+                ));
+            }
+        }
+        if let Some(route) = routes.iter_mut().find(|r| r.id == uuid) {
+            route.layer = layer;
+        }
+        state.engine.set_routes(routes.clone())?;
+    }
+
+    Ok(())
+}
+
+/// Randomly vary a route's Note On velocities by up to ±amount, or stop
+/// with None
+#[tauri::command]
+pub fn set_route_velocity_humanize(
+    state: State<AppState>,
+    route_id: String,
+    humanize: Option<VelocityHumanize>,
+) -> Result<(), String> {
+    let uuid = Uuid::parse_str(&route_id).map_err(|e| e.to_string())?;
+    if humanize.is_some_and(|humanize| !(1..=127).contains(&humanize.amount)) {
+        return Err("Humanize amount must be 1-127".to_string());
+    }
+
+    {
+        let mut routes = state.routes.lock().unwrap();
+        if let Some(route) = routes.iter_mut().find(|r| r.id == uuid) {
+            route.velocity_humanize = humanize;
+        }
+        state.engine.set_routes(routes.clone())?;
+    }
+
+    Ok(())
+}
+
+/// Repeat a route's notes in time with the clock, or stop with None
+#[tauri::command]
+pub fn set_route_echo(
+    state: State<AppState>,
+    route_id: String,
+    echo: Option<NoteEcho>,
+) -> Result<(), String> {
+    let uuid = Uuid::parse_str(&route_id).map_err(|e| e.to_string())?;
+    if let Some(echo) = &echo {
+        if !(1..=16).contains(&echo.repeats) {
+            return Err("Echo repeats must be 1-16".to_string());
+        }
+        if !(echo.beats.is_finite() && echo.beats > 0.0 && echo.beats <= 4.0) {
+            return Err("Echo interval must be more than 0 and at most 4 beats".to_string());
+        }
+        if !(echo.decay.is_finite() && echo.decay > 0.0 && echo.decay <= 1.0) {
+            return Err("Echo decay must be more than 0 and at most 1".to_string());
+        }
+    }
+
+    {
+        let mut routes = state.routes.lock().unwrap();
+        if let Some(route) = routes.iter_mut().find(|r| r.id == uuid) {
+            route.echo = echo;
+        }
+        state.engine.set_routes(routes.clone())?;
+    }
+
+    Ok(())
+}
+
+#[tauri::command]
+pub fn set_route_program_change_filter(
+    state: State<AppState>,
+    route_id: String,
+    filter: ProgramChangeFilter,
+) -> Result<(), String> {
+    let uuid = Uuid::parse_str(&route_id).map_err(|e| e.to_string())?;
+
+    {
+        let mut routes = state.routes.lock().unwrap();
+        if let Some(route) = routes.iter_mut().find(|r| r.id == uuid) {
+            route.program_change_filter = filter;
+        }
+        state.engine.set_routes(routes.clone())?;
+    }
+
+    Ok(())
+}
+
+/// Set the notes a route sends on as CCs
+#[tauri::command]
+pub fn set_route_note_mappings(
+    state: State<AppState>,
+    route_id: String,
+    mappings: Vec<NoteMapping>,
+) -> Result<(), String> {
+    let uuid = Uuid::parse_str(&route_id).map_err(|e| e.to_string())?;
+    for mapping in &mappings {
+        if mapping.note > 127 || mapping.cc > 127 {
+            return Err("Note and CC numbers must be 0-127".to_string());
+        }
+        if mapping.channels.iter().any(|ch| !(1..=16).contains(ch)) {
+            return Err("Note mapping channels must be 1-16".to_string());
+        }
+        if matches!(mapping.mode, NoteCcMode::Toggle { value } if value > 127) {
+            return Err("Toggle value must be 0-127".to_string());
+        }
+    }
+
+    {
+        let mut routes = state.routes.lock().unwrap();
+        if let Some(route) = routes.iter_mut().find(|r| r.id == uuid) {
+            route.note_mappings = mappings;
+        }
+        state.engine.set_routes(routes.clone())?;
+    }
+
+    Ok(())
+}
+
+/// Set the buttons that step a route's destination program up and down
+#[tauri::command]
+pub fn set_route_program_steppers(
+    state: State<AppState>,
+    route_id: String,
+    steppers: Vec<ProgramStepper>,
+) -> Result<(), String> {
+    let uuid = Uuid::parse_str(&route_id).map_err(|e| e.to_string())?;
+    for stepper in &steppers {
+        if !(1..=16).contains(&stepper.channel) {
+            return Err("Program stepper channel must be 1-16".to_string());
+        }
+        if stepper.increment == stepper.decrement {
+            return Err("Program up and down need different buttons".to_string());
+        }
+        for button in [stepper.increment, stepper.decrement] {
+            let (StepButton::Note { note: number } | StepButton::Cc { cc: number }) = button;
+            if number > 127 {
+                return Err("Note and CC numbers must be 0-127".to_string());
+            }
+        }
+    }
+
+    {
+        let mut routes = state.routes.lock().unwrap();
+        if let Some(route) = routes.iter_mut().find(|r| r.id == uuid) {
+            route.program_steppers = steppers;
+        }
+        state.engine.set_routes(routes.clone())?;
+    }
+
+    Ok(())
+}
+
+#[tauri::command]
+pub fn set_route_bank_select(
+    state: State<AppState>,
+    route_id: String,
+    settings: BankSelectSettings,
+) -> Result<(), String> {
+    let uuid = Uuid::parse_str(&route_id).map_err(|e| e.to_string())?;
+    let in_range = |selected: &BankProgram| {
+        selected.program <= 127
+            && selected.msb.is_none_or(|m| m <= 127)
+            && selected.lsb.is_none_or(|l| l <= 127)
+    };
+    if !settings
+        .remaps
+        .iter()
+        .all(|remap| in_range(&remap.from) && in_range(&remap.to))
+    {
+        return Err("Bank and program numbers must be 0-127".to_string());
+    }
+
+    {
+        let mut routes = state.routes.lock().unwrap();
+        if let Some(route) = routes.iter_mut().find(|r| r.id == uuid) {
+            route.bank_select = settings;
+        }
+        state.engine.set_routes(routes.clone())?;
+    }
+
+    Ok(())
+}
+
+/// Banks and programs a route sends to its destination when its preset loads
+#[tauri::command]
+pub fn set_route_program_recall(
+    state: State<AppState>,
+    route_id: String,
+    recalls: Vec<ProgramRecall>,
+) -> Result<(), String> {
+    let uuid = Uuid::parse_str(&route_id).map_err(|e| e.to_string())?;
+    if recalls
+        .iter()
+        .any(|recall| !(1..=16).contains(&recall.channel))
+    {
+        return Err("Recall channels must be 1-16".to_string());
+    }
+    if !recalls.iter().all(|recall| {
+        recall.bank.program <= 127
+            && recall.bank.msb.is_none_or(|m| m <= 127)
+            && recall.bank.lsb.is_none_or(|l| l <= 127)
+    }) {
+        return Err("Bank and program numbers must be 0-127".to_string());
+    }
+
+    {
+        let mut routes = state.routes.lock().unwrap();
+        if let Some(route) = routes.iter_mut().find(|r| r.id == uuid) {
+            route.program_recall = recalls;
+        }
+        state.engine.set_routes(routes.clone())?;
+    }
+
+    Ok(())
+}
+
+/// Release every note a latching route holds
+#[tauri::command]
+pub fn release_route_latch(state: State<AppState>, route_id: String) -> Result<(), String> {
+    let uuid = Uuid::parse_str(&route_id).map_err(|e| e.to_string())?;
+    state.engine.release_latch(uuid)
+}
+
+#[tauri::command]
+pub fn set_route_system_common_filter(
+    state: State<AppState>,
+    route_id: String,
+    filter: SystemCommonFilter,
+) -> Result<(), String> {
+    let uuid = Uuid::parse_str(&route_id).map_err(|e| e.to_string())?;
+
+    {
+        let mut routes = state.routes.lock().unwrap();
+        if let Some(route) = routes.iter_mut().find(|r| r.id == uuid) {
+            route.system_common_filter = filter;
+        }
+        state.engine.set_routes(routes.clone())?;
+    }
+
+    Ok(())
+}
+
+#[tauri::command]
+pub fn validate_routes(state: State<AppState>) -> Vec<RouteWarning> {
+    use midi_router_core::midi::ports::{list_input_ports, list_output_ports};
+
+    let routes = state.routes.lock().unwrap().clone();
+    validation::validate_routes(&routes, &list_input_ports(), &list_output_ports())
+}
+
+#[tauri::command]
+pub fn get_routing_matrix(state: State<AppState>) -> RoutingMatrix {
+    use midi_router_core::midi::ports::{list_input_ports, list_output_ports};
+
+    let routes = state.routes.lock().unwrap().clone();
+    matrix::build_matrix(&routes, &list_input_ports(), &list_output_ports())
+}
+
+#[tauri::command]
+pub fn set_matrix_cell(
+    state: State<AppState>,
+    source: String,
+    destination: String,
+    enabled: bool,
+) -> Result<Vec<Route>, String> {
+    let mut routes = state.routes.lock().unwrap();
+    let existing: HashSet<Uuid> = routes.iter().map(|r| r.id).collect();
+    if matrix::set_matrix_cell(&mut routes, &source, &destination, enabled) {
+        apply_device_profiles(&state, &mut routes, &existing)?;
+        state.engine.set_routes(routes.clone())?;
+    }
+    Ok(routes.clone())
+}
+
+/// Route each of `inputs` to `output` in one call
+#[tauri::command]
+pub fn quick_connect(
+    state: State<AppState>,
+    inputs: Vec<String>,
+    output: String,
+) -> Result<Vec<Route>, String> {
+    let mut routes = state.routes.lock().unwrap();
+    let existing: HashSet<Uuid> = routes.iter().map(|r| r.id).collect();
+    if matrix::quick_connect(&mut routes, &inputs, &[output]) {
+        apply_device_profiles(&state, &mut routes, &existing)?;
+        state.engine.set_routes(routes.clone())?;
+    }
+    Ok(routes.clone())
+}
+
+/// Route `input` to each of `outputs` in one call
+#[tauri::command]
+pub fn quick_connect_outputs(
+    state: State<AppState>,
+    input: String,
+    outputs: Vec<String>,
+) -> Result<Vec<Route>, String> {
+    let mut routes = state.routes.lock().unwrap();
+    let existing: HashSet<Uuid> = routes.iter().map(|r| r.id).collect();
+    if matrix::quick_connect(&mut routes, &[input], &outputs) {
+        apply_device_profiles(&state, &mut routes, &existing)?;
+        state.engine.set_routes(routes.clone())?;
+    }
+    Ok(routes.clone())
+}
+
+/// Stream one kind of engine event to the frontend from a thread of its
+/// own, counting events of that kind the monitor never received. `forward`
+/// turns an event into what's sent, or None to skip it.
+fn spawn_monitor<T, F>(state: &AppState, kind: &'static str, on_event: Channel<T>, mut forward: F)
+where
+    T: IpcResponse + Send + 'static,
+    F: FnMut(EngineEvent) -> Option<T> + Send + 'static,
+{
+    let event_rx = state.engine.event_receiver();
+    let mut gaps = GapDetector::new(kind, state.event_gaps.clone());
+
+    std::thread::spawn(move || {
+        while let Ok(SequencedEvent { seq, event }) = event_rx.recv() {
+            if event.kind() != kind {
+                continue;
+            }
+            gaps.observe(seq);
+            let Some(payload) = forward(event) else {
+                continue;
+            };
+            if on_event.send(payload).is_err() {
+                break;
+            }
+        }
+    });
+}
+
+#[tauri::command]
+pub fn start_midi_monitor(
+    state: State<AppState>,
+    on_event: Channel<MidiActivity>,
+) -> Result<(), String> {
+    let devices = state.device_definitions.clone();
+    let history = state.monitor_history.clone();
+
+    spawn_monitor(&state, "MidiActivity", on_event, move |event| {
+        let EngineEvent::MidiActivity(mut activity) = event else {
+            return None;
+        };
+        activity.label = device_for_port(&devices.lock().unwrap(), &activity.port)
+            .and_then(|d| d.label(&activity.kind));
+        // Paused: kept in the history, sent on resume
+        history
+            .lock()
+            .unwrap()
+            .record(activity.clone())
+            .then_some(activity)
+    });
+
+    Ok(())
+}
+
+/// Events each monitor missed since startup, by kind. Anything here means
+/// the frontend is showing an incomplete picture.
+#[tauri::command]
+pub fn get_event_gaps(state: State<AppState>) -> BTreeMap<String, u64> {
+    state.event_gaps.lock().unwrap().clone()
+}
+
+/// Stop streaming monitor events; they keep being buffered
+#[tauri::command]
+pub fn pause_monitor(state: State<AppState>) {
+    state.monitor_history.lock().unwrap().pause();
+}
+
+/// Resume streaming. Returns the events missed while paused, oldest first.
+#[tauri::command]
+pub fn resume_monitor(state: State<AppState>) -> Vec<MidiActivity> {
+    state.monitor_history.lock().unwrap().resume()
+}
+
+#[tauri::command]
+pub fn start_error_monitor(
+    state: State<AppState>,
+    on_error: Channel<EngineError>,
+) -> Result<(), String> {
+    spawn_monitor(&state, "Error", on_error, |event| match event {
+        EngineEvent::Error(error) => Some(error),
+        _ => None,
+    });
+
+    Ok(())
+}
+
+#[tauri::command]
+pub fn list_presets() -> Vec<Preset> {
+    preset::list_presets()
+}
+
+#[tauri::command]
+pub fn save_preset(state: State<AppState>, name: String) -> Result<Preset, String> {
+    let routes = state.routes.lock().unwrap().clone();
+    let gamepad = state.gamepad.lock().unwrap().clone();
+    preset::save_preset(name, routes, gamepad)
+}
+
+/// Errors the engine reported recently, oldest first, for the problem history
+#[tauri::command]
+pub fn get_recent_errors(state: State<AppState>) -> Result<Vec<RecentError>, String> {
+    state.engine.get_recent_errors()
+}
+
+/// Record every message's routing trail for `duration_ms`, replacing any
+/// earlier capture
+#[tauri::command]
+pub fn start_debug_capture(state: State<AppState>, duration_ms: u64) -> Result<(), String> {
+    use midi_router_core::midi::capture::CaptureRecorder;
+
+    let duration = std::time::Duration::from_millis(duration_ms);
+    if duration.is_zero() || duration > CaptureRecorder::MAX_DURATION {
+        return Err(format!(
+            "Capture duration must be 1-{} ms",
+            CaptureRecorder::MAX_DURATION.as_millis()
+        ));
+    }
+    state.engine.start_capture(duration)
+}
+
+/// End the debug capture and write it, with the routes and ports it ran
+/// under, as a JSON bundle. Returns the number of captured messages.
+#[tauri::command]
+pub fn export_debug_capture(state: State<AppState>, path: String) -> Result<usize, String> {
+    use midi_router_core::midi::ports::{list_input_ports, list_output_ports};
+
+    let capture = state
+        .engine
+        .take_capture()?
+        .ok_or("No debug capture has been recorded")?;
+    let captured = capture.entries.len();
+    let bundle = DebugBundle {
+        app_version: env!("CARGO_PKG_VERSION").to_string(),
+        capture,
+        routes: state.routes.lock().unwrap().clone(),
+        inputs: list_input_ports(),
+        outputs: list_output_ports(),
+    };
+    let json = serde_json::to_string_pretty(&bundle).map_err(|e| e.to_string())?;
+    std::fs::write(&path, json).map_err(|e| e.to_string())?;
+    Ok(captured)
+}
+
+/// Arm a trap that traces the first message matching `condition`, or disarm
+/// it with `None`. Hits arrive on the trap monitor.
+#[tauri::command]
+pub fn set_message_trap(
+    state: State<AppState>,
+    condition: Option<TrapCondition>,
+) -> Result<(), String> {
+    state.engine.set_trap(condition)
+}
+
+/// Stream trap hits
+#[tauri::command]
+pub fn start_trap_monitor(
+    state: State<AppState>,
+    on_event: Channel<TrapHit>,
+) -> Result<(), String> {
+    spawn_monitor(&state, "TrapHit", on_event, |event| match event {
+        EngineEvent::TrapHit(hit) => Some(hit),
+        _ => None,
+    });
+
+    Ok(())
+}
+
+/// Replay the messages in an exported debug bundle through the current
+/// routes with their original timing. A dry run sends nothing and traces
+/// what each route would have sent. Returns the number of messages queued.
+#[tauri::command]
+pub fn start_replay(state: State<AppState>, path: String, dry_run: bool) -> Result<usize, String> {
+    let json = std::fs::read_to_string(&path).map_err(|e| e.to_string())?;
+    let bundle: DebugBundle = serde_json::from_str(&json).map_err(|e| e.to_string())?;
+    if bundle.capture.entries.is_empty() {
+        return Err("The capture has no messages to replay".to_string());
+    }
+    let messages = bundle.capture.entries.len();
+    state.engine.start_replay(bundle.capture, dry_run)?;
+    Ok(messages)
+}
+
+#[tauri::command]
+pub fn stop_replay(state: State<AppState>) -> Result<(), String> {
+    state.engine.stop_replay()
+}
+
+/// Stream a report each time a replay finishes or is stopped
+#[tauri::command]
+pub fn start_replay_monitor(
+    state: State<AppState>,
+    on_event: Channel<ReplayReport>,
+) -> Result<(), String> {
+    spawn_monitor(&state, "ReplayFinished", on_event, |event| match event {
+        EngineEvent::ReplayFinished(report) => Some(report),
+        _ => None,
+    });
+
+    Ok(())
+}
+
+/// Notes, velocities and controllers played into each input this session
+#[tauri::command]
+pub fn get_session_stats(state: State<AppState>) -> Result<SessionStats, String> {
+    state.engine.get_session_stats()
+}
+
+/// Write the last minutes of incoming MIDI as a Standard MIDI File, one
+/// track per input. Returns the number of messages kept.
+#[tauri::command]
+pub fn capture_retrospective(state: State<AppState>, path: String) -> Result<usize, String> {
+    use midi_router_core::config::smf::encode_smf;
+
+    let messages = state.engine.get_retrospective()?;
+    if messages.is_empty() {
+        return Err("No MIDI has been received recently".to_string());
+    }
+    let bpm = *state.clock_bpm.lock().unwrap();
+    std::fs::write(&path, encode_smf(&messages, bpm)).map_err(|e| e.to_string())?;
+    Ok(messages.len())
+}
+
+/// Notes currently held on each route's destination, per channel
+#[tauri::command]
+pub fn get_sounding_notes(state: State<AppState>) -> Result<Vec<HeldNotes>, String> {
+    state.engine.get_sounding_notes()
+}
+
+/// Silence one route's destination without resetting other outputs
+#[tauri::command]
+pub fn panic_route(state: State<AppState>, route_id: String) -> Result<(), String> {
+    let uuid = Uuid::parse_str(&route_id).map_err(|e| e.to_string())?;
+    state.engine.panic_route(uuid)
+}
+
+/// Send a message through one route's filters and transforms to its
+/// destination, without its source device
+#[tauri::command]
+pub fn inject_to_route(
+    state: State<AppState>,
+    route_id: String,
+    bytes: Vec<u8>,
+) -> Result<(), String> {
+    let uuid = Uuid::parse_str(&route_id).map_err(|e| e.to_string())?;
+    match state.routes.lock().unwrap().iter().find(|r| r.id == uuid) {
+        Some(route) if route.enabled => {}
+        Some(_) => return Err("Route is disabled".to_string()),
+        None => return Err("Route not found".to_string()),
+    }
+    let valid = match bytes.as_slice() {
+        [0xF0, data @ .., 0xF7] => data.iter().all(|b| *b < 0x80),
+        [status, data @ ..] => *status >= 0x80 && *status != 0xF0 && data.iter().all(|b| *b < 0x80),
+        [] => false,
+    };
+    if !valid {
+        return Err("Not a MIDI message".to_string());
+    }
+    state.engine.inject_to_route(uuid, bytes)
+}
+
+/// Write a Markdown summary of the current setup (routes, filters, CC maps, clock)
+#[tauri::command]
+pub fn export_setup_report(state: State<AppState>, path: String) -> Result<(), String> {
+    use midi_router_core::config::report::{setup_report, SetupSnapshot};
+
+    let active_preset = preset::get_active_preset();
+    let report = setup_report(&SetupSnapshot {
+        preset_name: active_preset.as_ref().map(|p| p.name.as_str()),
+        bpm: *state.clock_bpm.lock().unwrap(),
+        control_bindings: &state.control_bindings.lock().unwrap(),
+        routes: &routes_with_status(&state),
+        generated_at: chrono::Utc::now(),
+    });
+    std::fs::write(&path, report).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub fn list_setup_templates() -> Vec<SetupTemplate> {
+    midi_router_core::config::templates::builtin_templates()
+}
+
+/// Add a template's routes, with `ports` mapping each of its slot ids to a
+/// port name. Returns the created routes.
+#[tauri::command]
+pub fn create_from_template(
+    state: State<AppState>,
+    template_id: String,
+    ports: HashMap<String, String>,
+) -> Result<Vec<Route>, String> {
+    use midi_router_core::config::templates;
+
+    let template = templates::builtin_templates()
+        .into_iter()
+        .find(|t| t.id == template_id)
+        .ok_or("Template not found")?;
+    let mut setup = templates::instantiate(&template, &ports)?;
+    for route in &mut setup.routes {
+        apply_device_profile(&state, route)?;
+    }
+
+    if !setup.clock_modes.is_empty() {
+        let mut settings = state.clock_settings.lock().unwrap();
+        settings.output_modes.extend(setup.clock_modes);
+        state.engine.set_clock_settings(settings.clone())?;
+        preset::set_clock_settings(settings.clone())?;
+    }
+
+    {
+        let mut routes = state.routes.lock().unwrap();
+        routes.extend(setup.routes.iter().cloned());
+        state.engine.set_routes(routes.clone())?;
+    }
+
+    Ok(setup.routes)
+}
+
+/// Create a preset from another tool's setup (connection list or `aconnect -l` output)
+#[tauri::command]
+pub fn import_setup(path: String, name: String) -> Result<Preset, String> {
+    let routes = midi_router_core::config::setup_import::import_setup(Path::new(&path))?;
+    preset::save_preset(name, routes, Vec::new())
+}
+
+#[tauri::command]
+pub fn update_preset(state: State<AppState>, preset_id: String) -> Result<Preset, String> {
+    let id = Uuid::parse_str(&preset_id).map_err(|e| e.to_string())?;
+    let routes = state.routes.lock().unwrap().clone();
+    let gamepad = state.gamepad.lock().unwrap().clone();
+    preset::update_preset(id, routes, gamepad)
+}
+
+#[tauri::command]
+pub fn load_preset(state: State<AppState>, preset_id: String) -> Result<LoadedPreset, String> {
+    use midi_router_core::midi::ports::{list_input_ports, list_output_ports};
+
+    let id = Uuid::parse_str(&preset_id).map_err(|e| e.to_string())?;
+    let p = preset::get_preset(id).ok_or_else(|| "Preset not found".to_string())?;
+
+    {
+        let mut routes = state.routes.lock().unwrap();
+        *routes = p.routes.clone();
+        state.engine.set_routes(routes.clone())?;
+    }
+    state.engine.recall_programs()?;
+    {
+        let mut gamepad = state.gamepad.lock().unwrap();
+        *gamepad = p.gamepad.clone();
+        state.engine.set_gamepad_mappings(gamepad.clone())?;
+    }
+
+    preset::set_active_preset(Some(id))?;
+    let availability = midi_router_core::midi::validation::check_port_availability(
+        &p.routes,
+        &list_input_ports(),
+        &list_output_ports(),
+    );
+    Ok(LoadedPreset {
+        preset: p,
+        availability,
+    })
+}
+
+#[tauri::command]
+pub fn delete_preset(preset_id: String) -> Result<(), String> {
+    let id = Uuid::parse_str(&preset_id).map_err(|e| e.to_string())?;
+    preset::delete_preset(id)
+}
+
+#[tauri::command]
+pub fn get_active_preset_id() -> Option<String> {
+    preset::get_active_preset().map(|p| p.id.to_string())
+}
+
+/// Switch to a preset at the `bars`th bar line of the running clock (the
+/// next one for 1). The UI loads it once the switch is reported.
+#[tauri::command]
+pub fn queue_preset(state: State<AppState>, preset_id: String, bars: u32) -> Result<(), String> {
+    let id = Uuid::parse_str(&preset_id).map_err(|e| e.to_string())?;
+    let p = preset::get_preset(id).ok_or_else(|| "Preset not found".to_string())?;
+    state
+        .engine
+        .queue_preset(Some(QueuedPreset::new(id, p.routes, bars)))
+}
+
+#[tauri::command]
+pub fn cancel_queued_preset(state: State<AppState>) -> Result<(), String> {
+    state.engine.queue_preset(None)
+}
+
+/// Stream the ids of queued presets as their routes take effect
+#[tauri::command]
+pub fn start_preset_switch_monitor(
+    state: State<AppState>,
+    on_event: Channel<Uuid>,
+) -> Result<(), String> {
+    spawn_monitor(&state, "PresetSwitched", on_event, |event| match event {
+        EngineEvent::PresetSwitched(preset_id) => Some(preset_id),
+        _ => None,
+    });
+
+    Ok(())
+}
+
+#[tauri::command]
+pub fn set_bpm(state: State<AppState>, bpm: f64) -> Result<(), String> {
+    // Validate BPM using the newtype
+    let validated_bpm = Bpm::new(bpm).map_err(|e| e.to_string())?;
+    let bpm_value = validated_bpm.value();
+
+    *state.clock_bpm.lock().unwrap() = bpm_value;
+    state.engine.set_bpm(bpm_value)?;
+
+    // Persist to config
+    midi_router_core::config::preset::set_clock_bpm(bpm_value)?;
+
+    Ok(())
+}
+
+#[tauri::command]
+pub fn get_clock_bpm(state: State<AppState>) -> f64 {
+    *state.clock_bpm.lock().unwrap()
+}
+
+#[tauri::command]
+pub fn get_control_bindings(state: State<AppState>) -> ControlBindings {
+    state.control_bindings.lock().unwrap().clone()
+}
+
+#[tauri::command]
+pub fn set_tempo_cc_binding(
+    state: State<AppState>,
+    binding: Option<TempoCcBinding>,
+) -> Result<(), String> {
+    if let Some(b) = &binding {
+        Bpm::new(b.min_bpm).map_err(|e| e.to_string())?;
+        Bpm::new(b.max_bpm).map_err(|e| e.to_string())?;
+    }
+
+    let bindings = {
+        let mut bindings = state.control_bindings.lock().unwrap();
+        bindings.tempo_cc = binding;
+        bindings.clone()
+    };
+    state.engine.set_control_bindings(bindings.clone())?;
+
+    // Persist to config
+    preset::set_control_bindings(bindings)
+}
+
+#[tauri::command]
+pub fn set_tap_tempo_binding(
+    state: State<AppState>,
+    binding: Option<TapTempoBinding>,
+) -> Result<(), String> {
+    let bindings = {
+        let mut bindings = state.control_bindings.lock().unwrap();
+        bindings.tap_tempo = binding;
+        bindings.clone()
+    };
+    state.engine.set_control_bindings(bindings.clone())?;
+
+    // Persist to config
+    preset::set_control_bindings(bindings)
+}
+
+#[tauri::command]
+pub fn set_transport_triggers(
+    state: State<AppState>,
+    triggers: Vec<TransportTriggerBinding>,
+) -> Result<(), String> {
+    let bindings = {
+        let mut bindings = state.control_bindings.lock().unwrap();
+        bindings.transport_triggers = triggers;
+        bindings.clone()
+    };
+    state.engine.set_control_bindings(bindings.clone())?;
+
+    // Persist to config
+    preset::set_control_bindings(bindings)
+}
+
+#[tauri::command]
+pub fn set_song_select_binding(
+    state: State<AppState>,
+    binding: Option<SongSelectBinding>,
+) -> Result<(), String> {
+    let bindings = {
+        let mut bindings = state.control_bindings.lock().unwrap();
+        bindings.song_select = binding;
+        bindings.clone()
+    };
+    state.engine.set_control_bindings(bindings.clone())?;
+
+    // Persist to config
+    preset::set_control_bindings(bindings)
+}
+
+#[tauri::command]
+pub fn get_clock_settings(state: State<AppState>) -> ClockSettings {
+    state.clock_settings.lock().unwrap().clone()
+}
+
+/// Set the global clock mode and per-output overrides
+#[tauri::command]
+pub fn set_clock_settings(state: State<AppState>, settings: ClockSettings) -> Result<(), String> {
+    state.engine.set_clock_settings(settings.clone())?;
+    *state.clock_settings.lock().unwrap() = settings.clone();
+
+    // Persist to config
+    preset::set_clock_settings(settings)
+}
+
+#[tauri::command]
+pub fn get_jitter_buffers(state: State<AppState>) -> BTreeMap<String, u32> {
+    state.jitter_buffers.lock().unwrap().clone()
+}
+
+/// Enable (Some latency in ms) or disable the jitter buffer on an input
+#[tauri::command]
+pub fn set_jitter_buffer(
+    state: State<AppState>,
+    port: String,
+    latency_ms: Option<u32>,
+) -> Result<(), String> {
+    use midi_router_core::midi::jitter::JitterBuffer;
+
+    let max_ms = JitterBuffer::MAX_LATENCY.as_millis();
+    if latency_ms.is_some_and(|ms| ms == 0 || u128::from(ms) > max_ms) {
+        return Err(format!("Jitter buffer latency must be 1-{} ms", max_ms));
+    }
+
+    let buffers = {
+        let mut buffers = state.jitter_buffers.lock().unwrap();
+        match latency_ms {
+            Some(ms) => buffers.insert(port, ms),
+            None => buffers.remove(&port),
+        };
+        buffers.clone()
+    };
+    state.engine.set_jitter_buffers(buffers.clone())?;
+
+    // Persist to config
+    preset::set_jitter_buffers(buffers)
+}
+
+#[tauri::command]
+pub fn get_note_off_styles(state: State<AppState>) -> BTreeMap<String, NoteOffStyle> {
+    state.note_off_styles.lock().unwrap().clone()
+}
+
+/// Set how Note Offs are written to an output, or send them as routed with None
+#[tauri::command]
+pub fn set_note_off_style(
+    state: State<AppState>,
+    port: String,
+    style: Option<NoteOffStyle>,
+) -> Result<(), String> {
+    let styles = {
+        let mut styles = state.note_off_styles.lock().unwrap();
+        match style {
+            Some(style) => styles.insert(port, style),
+            None => styles.remove(&port),
+        };
+        styles.clone()
+    };
+    state.engine.set_note_off_styles(styles.clone())?;
+
+    // Persist to config
+    preset::set_note_off_styles(styles)
+}
+
+#[tauri::command]
+pub fn get_buses(state: State<AppState>) -> BTreeSet<String> {
+    state.buses.lock().unwrap().clone()
+}
+
+/// Add an internal bus, usable as both a route destination and source
+#[tauri::command]
+pub fn add_bus(state: State<AppState>, name: String) -> Result<(), String> {
+    let name = name.trim().to_string();
+    if name.is_empty() {
+        return Err("Bus name can't be empty".to_string());
+    }
+    let (inputs, outputs) = state.engine.get_ports()?;
+    if inputs.iter().chain(&outputs).any(|p| p.id.name == name) {
+        return Err(format!("A port named {} already exists", name));
+    }
+
+    let buses = {
+        let mut buses = state.buses.lock().unwrap();
+        buses.insert(name);
+        buses.clone()
+    };
+    state.engine.set_buses(buses.clone())?;
+    preset::set_buses(buses)
+}
+
+/// Remove an internal bus. Routes through it wait as if its port were missing.
+#[tauri::command]
+pub fn remove_bus(state: State<AppState>, name: String) -> Result<(), String> {
+    let buses = {
+        let mut buses = state.buses.lock().unwrap();
+        if !buses.remove(&name) {
+            return Err(format!("No bus named {}", name));
+        }
+        buses.clone()
+    };
+    state.engine.set_buses(buses.clone())?;
+    preset::set_buses(buses)
+}
+
+#[tauri::command]
+pub fn get_web_bridge(state: State<AppState>) -> Option<WebBridgeSettings> {
+    state.web_bridge.lock().unwrap().clone()
+}
+
+/// Serve ports to browsers over a local WebSocket, or stop with None
+#[tauri::command]
+pub fn set_web_bridge(
+    state: State<AppState>,
+    settings: Option<WebBridgeSettings>,
+) -> Result<(), String> {
+    if settings.as_ref().is_some_and(|s| s.port == 0) {
+        return Err("Web bridge port must be 1-65535".to_string());
+    }
+    if settings
+        .as_ref()
+        .is_some_and(|s| s.inputs.len() > 256 || s.outputs.len() > 256)
+    {
+        return Err("The web bridge can serve at most 256 inputs and outputs".to_string());
+    }
+
+    state.engine.set_web_bridge(settings.clone())?;
+    *state.web_bridge.lock().unwrap() = settings.clone();
+
+    // Persist to config
+    preset::set_web_bridge(settings)
+}
+
+#[tauri::command]
+pub fn get_gamepad_mappings(state: State<AppState>) -> Vec<GamepadMapping> {
+    state.gamepad.lock().unwrap().clone()
+}
+
+/// Replace the gamepad mappings of the current setup. Gamepads are read
+/// only while at least one control is mapped.
+#[tauri::command]
+pub fn set_gamepad_mappings(
+    state: State<AppState>,
+    mappings: Vec<GamepadMapping>,
+) -> Result<(), String> {
+    for mapping in &mappings {
+        let valid = match mapping.target {
+            GamepadTarget::Note { note, velocity } => note <= 127 && (1..=127).contains(&velocity),
+            GamepadTarget::Cc { cc } => cc <= 127,
+        };
+        if mapping.channel > 15 || !valid {
+            return Err(format!("Invalid mapping for {:?}", mapping.control));
+        }
+    }
+
+    state.engine.set_gamepad_mappings(mappings.clone())?;
+    *state.gamepad.lock().unwrap() = mappings;
+    Ok(())
+}
+
+/// Play a test pattern to an output or through the routes from the virtual
+/// Test Signal input, replacing any pattern already playing
+#[tauri::command]
+pub fn start_test_signal(state: State<AppState>, signal: TestSignal) -> Result<(), String> {
+    let valid = match signal.pattern {
+        TestPattern::Chromatic { low, high } => low <= high && high <= 127,
+        TestPattern::FixedNote { note } => note <= 127,
+        TestPattern::CcSweep { cc } => cc <= 127,
+        TestPattern::Clock { bpm } => Bpm::new(bpm).is_ok(),
+    };
+    if !valid {
+        return Err(format!("Invalid test pattern {:?}", signal.pattern));
+    }
+    if !(1..=16).contains(&signal.channel) {
+        return Err("Test signal channel must be 1-16".to_string());
+    }
+    if !(1..=127).contains(&signal.velocity) || signal.step_ms == 0 {
+        return Err("Test signal velocity must be 1-127 and steps at least 1 ms".to_string());
+    }
+    state.engine.set_test_signal(Some(signal))
+}
+
+#[tauri::command]
+pub fn stop_test_signal(state: State<AppState>) -> Result<(), String> {
+    state.engine.set_test_signal(None)
+}
+
+/// Sweep a CC on an output from one value to another over `duration_ms`,
+/// replacing any ramp already running on that controller
+#[tauri::command]
+pub fn send_cc_ramp(
+    state: State<AppState>,
+    port: String,
+    channel: u8,
+    cc: u8,
+    from: u8,
+    to: u8,
+    duration_ms: u32,
+) -> Result<(), String> {
+    if !(1..=16).contains(&channel) {
+        return Err("Ramp channel must be 1-16".to_string());
+    }
+    if cc > 127 || from > 127 || to > 127 {
+        return Err("Ramp CC and values must be 0-127".to_string());
+    }
+    let duration = std::time::Duration::from_millis(u64::from(duration_ms));
+    state
+        .engine
+        .send_cc_ramp(port, channel, cc, from, to, duration)
+}
+
+#[tauri::command]
+pub fn get_mqtt(state: State<AppState>) -> Option<MqttSettings> {
+    state.mqtt.lock().unwrap().clone()
+}
+
+/// Connect to an MQTT broker, or disconnect with None
+#[tauri::command]
+pub fn set_mqtt(state: State<AppState>, settings: Option<MqttSettings>) -> Result<(), String> {
+    if let Some(settings) = &settings {
+        if settings.host.trim().is_empty() || settings.port == 0 {
+            return Err("MQTT needs a broker host and port".to_string());
+        }
+        let prefix = &settings.topic_prefix;
+        if prefix.is_empty() || prefix.contains(['+', '#']) {
+            return Err("MQTT topic prefix must be non-empty, without + or #".to_string());
+        }
+    }
+
+    state.engine.set_mqtt(settings.clone())?;
+    *state.mqtt.lock().unwrap() = settings.clone();
+
+    // Persist to config
+    preset::set_mqtt(settings)
+}
+
+#[tauri::command]
+pub fn get_middle_c(state: State<AppState>) -> MiddleC {
+    *state.middle_c.lock().unwrap()
+}
+
+#[tauri::command]
+pub fn set_middle_c(state: State<AppState>, middle_c: MiddleC) -> Result<(), String> {
+    state.engine.set_middle_c(middle_c)?;
+    *state.middle_c.lock().unwrap() = middle_c;
+
+    // Persist to config
+    preset::set_middle_c(middle_c)
+}
+
+#[tauri::command]
+pub fn list_device_definitions(state: State<AppState>) -> Vec<DeviceDefinition> {
+    state.device_definitions.lock().unwrap().clone()
+}
+
+/// Import a .midnam file as a new device attached to the given ports
+#[tauri::command]
+pub fn import_midnam(
+    state: State<AppState>,
+    path: String,
+    ports: Vec<String>,
+) -> Result<DeviceDefinition, String> {
+    let mut device = midnam::import_midnam(Path::new(&path))?;
+    device.ports = ports;
+
+    let devices = {
+        let mut devices = state.device_definitions.lock().unwrap();
+        devices.push(device.clone());
+        devices.clone()
+    };
+    state
+        .engine
+        .set_connection_hooks(connection_hooks(&devices))?;
+    preset::set_device_definitions(devices)?;
+
+    Ok(device)
+}
+
+/// Add or replace a device definition (e.g. after editing names or ports)
+#[tauri::command]
+pub fn save_device_definition(
+    state: State<AppState>,
+    device: DeviceDefinition,
+) -> Result<(), String> {
+    let devices = {
+        let mut devices = state.device_definitions.lock().unwrap();
+        match devices.iter_mut().find(|d| d.id == device.id) {
+            Some(existing) => *existing = device,
+            None => devices.push(device),
+        }
+        devices.clone()
+    };
+    state
+        .engine
+        .set_connection_hooks(connection_hooks(&devices))?;
+    preset::set_device_definitions(devices)
+}
+
+#[tauri::command]
+pub fn delete_device_definition(state: State<AppState>, device_id: String) -> Result<(), String> {
+    let uuid = Uuid::parse_str(&device_id).map_err(|e| e.to_string())?;
+    let devices = {
+        let mut devices = state.device_definitions.lock().unwrap();
+        devices.retain(|d| d.id != uuid);
+        devices.clone()
+    };
+    state
+        .engine
+        .set_connection_hooks(connection_hooks(&devices))?;
+    preset::set_device_definitions(devices)
+}
+
+/// CC names of the device attached to a port (empty if none)
+#[tauri::command]
+pub fn get_device_cc_names(state: State<AppState>, port: String) -> BTreeMap<u8, String> {
+    let devices = state.device_definitions.lock().unwrap();
+    device_for_port(&devices, &port)
+        .map(|d| d.cc_names.clone())
+        .unwrap_or_default()
+}
+
+#[tauri::command]
+pub fn send_transport_start(state: State<AppState>) -> Result<(), String> {
+    state.engine.send_start()?;
+    // Remembered for restoring the clock on launch
+    preset::set_clock_running(true)
+}
+
+#[tauri::command]
+pub fn send_transport_stop(state: State<AppState>) -> Result<(), String> {
+    state.engine.send_stop()?;
+    preset::set_clock_running(false)
+}
+
+#[tauri::command]
+pub fn get_engine_stats(state: State<AppState>) -> Result<EngineStats, String> {
+    state.engine.get_stats()
+}
+
+#[tauri::command]
+pub fn get_route_stats(state: State<AppState>) -> Result<Vec<RouteStats>, String> {
+    Ok(state.engine.get_stats()?.routes)
+}
+
+/// Stream engine statistics once per measurement window
+#[tauri::command]
+pub fn start_stats_monitor(
+    state: State<AppState>,
+    on_event: Channel<EngineStats>,
+) -> Result<(), String> {
+    spawn_monitor(&state, "Stats", on_event, |event| match event {
+        EngineEvent::Stats(stats) => Some(stats),
+        _ => None,
+    });
+
+    Ok(())
+}
+
+/// Stream route status changes (routes paused for a failing destination)
+#[tauri::command]
+pub fn start_route_status_monitor(
+    state: State<AppState>,
+    on_event: Channel<RouteStatusChange>,
+) -> Result<(), String> {
+    spawn_monitor(
+        &state,
+        "RouteStatusChanged",
+        on_event,
+        |event| match event {
+            EngineEvent::RouteStatusChanged(change) => Some(change),
+            _ => None,
+        },
+    );
+
+    Ok(())
+}
+
+/// Turn chord detection in the monitor path on or off
+#[tauri::command]
+pub fn set_chord_detection(state: State<AppState>, enabled: bool) -> Result<(), String> {
+    state.engine.set_chord_detection(enabled)
+}
+
+/// Stream chords detected on input ports (while chord detection is enabled)
+#[tauri::command]
+pub fn start_chord_monitor(
+    state: State<AppState>,
+    on_event: Channel<DetectedChord>,
+) -> Result<(), String> {
+    spawn_monitor(&state, "ChordDetected", on_event, |event| match event {
+        EngineEvent::ChordDetected(chord) => Some(chord),
+        _ => None,
+    });
+
+    Ok(())
+}
+
+/// Stream preset changes requested by mapped Song Select messages
+#[tauri::command]
+pub fn start_song_select_monitor(
+    state: State<AppState>,
+    on_event: Channel<SongSelectChange>,
+) -> Result<(), String> {
+    spawn_monitor(&state, "SongSelected", on_event, |event| match event {
+        EngineEvent::SongSelected(change) => Some(change),
+        _ => None,
+    });
+
+    Ok(())
+}
+
+/// Listen on every input and suggest routes for inputs nothing uses yet
+#[tauri::command]
+pub fn set_route_learn(state: State<AppState>, enabled: bool) -> Result<(), String> {
+    state.engine.set_route_learn(enabled)
+}
+
+/// Stream route suggestions while learn mode is on
+#[tauri::command]
+pub fn start_route_suggestion_monitor(
+    state: State<AppState>,
+    on_event: Channel<RouteSuggestion>,
+) -> Result<(), String> {
+    spawn_monitor(&state, "RouteSuggested", on_event, |event| match event {
+        EngineEvent::RouteSuggested(suggestion) => Some(suggestion),
+        _ => None,
+    });
+
+    Ok(())
+}
+
+/// Stream per-port message counts (for activity LEDs)
+#[tauri::command]
+pub fn start_port_activity_monitor(
+    state: State<AppState>,
+    on_event: Channel<Vec<PortPulse>>,
+) -> Result<(), String> {
+    spawn_monitor(&state, "PortActivity", on_event, |event| match event {
+        EngineEvent::PortActivity(pulses) => Some(pulses),
+        _ => None,
+    });
+
+    Ok(())
+}
+
+/// Stream what was reconnected each time the system wakes from sleep
+#[tauri::command]
+pub fn start_wake_monitor(
+    state: State<AppState>,
+    on_event: Channel<WakeReport>,
+) -> Result<(), String> {
+    spawn_monitor(&state, "Woke", on_event, |event| match event {
+        EngineEvent::Woke(report) => Some(report),
+        _ => None,
+    });
+
+    Ok(())
+}
+
+/// Stream the port list whenever devices appear or disappear
+#[tauri::command]
+pub fn start_ports_monitor(
+    state: State<AppState>,
+    on_event: Channel<(Vec<MidiPort>, Vec<MidiPort>)>,
+) -> Result<(), String> {
+    spawn_monitor(&state, "PortsChanged", on_event, |event| match event {
+        EngineEvent::PortsChanged { inputs, outputs } => Some((inputs, outputs)),
+        _ => None,
+    });
+
+    Ok(())
+}
+
+/// Stream the bar/beat/tick position of every clock pulse while running
+#[tauri::command]
+pub fn start_clock_position_monitor(
+    state: State<AppState>,
+    on_event: Channel<ClockPosition>,
+) -> Result<(), String> {
+    spawn_monitor(&state, "ClockPosition", on_event, |event| match event {
+        EngineEvent::ClockPosition(position) => Some(position),
+        _ => None,
+    });
+
+    Ok(())
+}
+
+#[tauri::command]
+pub fn start_clock_monitor(
+    state: State<AppState>,
+    on_event: Channel<ClockState>,
+) -> Result<(), String> {
+    spawn_monitor(&state, "ClockStateChanged", on_event, |event| match event {
+        EngineEvent::ClockStateChanged(clock_state) => Some(clock_state),
+        _ => None,
     });
 
     Ok(())
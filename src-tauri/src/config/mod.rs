@@ -1,2 +0,0 @@
-pub mod preset;
-pub mod storage;
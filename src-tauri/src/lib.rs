@@ -1,24 +1,35 @@
 #![cfg_attr(not(debug_assertions), windows_subsystem = "windows")]
 
 mod commands;
-mod config;
-mod midi;
-mod types;
 
 use commands::AppState;
-use config::preset::{get_active_preset, get_clock_bpm};
-use midi::engine::MidiEngine;
-use std::sync::Mutex;
-use types::Bpm;
+use midi_router_core::config::preset::{
+    get_active_preset, get_buses, get_clock_bpm, get_clock_running, get_clock_settings,
+    get_control_bindings, get_device_definitions, get_jitter_buffers, get_middle_c, get_mqtt,
+    get_note_off_styles, get_web_bridge,
+};
+use midi_router_core::midi::monitor::MonitorHistory;
+use midi_router_core::midi::port_manager::connection_hooks;
+use midi_router_core::midi::sequence::SharedEventGaps;
+use midi_router_core::types::Bpm;
+use midi_router_core::MidiEngine;
+use std::sync::{Arc, Mutex};
 
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
 pub fn run() {
     let engine = MidiEngine::new();
 
+    // Buses first, so routes through them connect
+    let buses = get_buses();
+    let _ = engine.set_buses(buses.clone());
+
     // Load active preset if one exists
-    let initial_routes = get_active_preset()
-        .map(|p| p.routes)
+    let active_preset = get_active_preset();
+    let initial_routes = active_preset
+        .as_ref()
+        .map(|p| p.routes.clone())
         .unwrap_or_default();
+    let gamepad = active_preset.map(|p| p.gamepad).unwrap_or_default();
 
     // Apply routes to engine
     if !initial_routes.is_empty() {
@@ -29,34 +40,195 @@ pub fn run() {
     let clock_bpm = Bpm::clamped(get_clock_bpm()).value();
     let _ = engine.set_bpm(clock_bpm);
 
+    // Load clock mode (while running / always) and per-output overrides
+    let clock_settings = get_clock_settings();
+    let _ = engine.set_clock_settings(clock_settings.clone());
+
+    // Load jitter buffers for network / BLE inputs
+    let jitter_buffers = get_jitter_buffers();
+    let _ = engine.set_jitter_buffers(jitter_buffers.clone());
+
+    // Load Note Off conventions for outputs that only understand one
+    let note_off_styles = get_note_off_styles();
+    let _ = engine.set_note_off_styles(note_off_styles.clone());
+
+    // Serve ports to browser-based editors if the bridge was left on
+    let web_bridge = get_web_bridge();
+    if let Some(settings) = web_bridge.clone() {
+        if let Err(e) = engine.set_web_bridge(Some(settings)) {
+            eprintln!("[BRIDGE] {}", e);
+        }
+    }
+
+    // Start reading gamepads if the preset maps any
+    if !gamepad.is_empty() {
+        let _ = engine.set_gamepad_mappings(gamepad.clone());
+    }
+
+    // Reconnect to the automation broker
+    let mqtt = get_mqtt();
+    let _ = engine.set_mqtt(mqtt.clone());
+
+    // Load control input bindings (tempo CC, etc.)
+    let control_bindings = get_control_bindings();
+    let _ = engine.set_control_bindings(control_bindings.clone());
+
+    // Load note naming convention for monitor events
+    let middle_c = get_middle_c();
+    let _ = engine.set_middle_c(middle_c);
+
+    // Devices with connect messages are connected (and greeted) as they appear
+    let device_definitions = get_device_definitions();
+    let _ = engine.set_connection_hooks(connection_hooks(&device_definitions));
+
+    // Always-on rigs come back from a power cycle already sending clock
+    if clock_settings.on_launch.should_start(get_clock_running()) {
+        let _ = engine.send_start();
+    }
+
     let app_state = AppState {
         engine,
         routes: Mutex::new(initial_routes),
         clock_bpm: Mutex::new(clock_bpm),
+        clock_settings: Mutex::new(clock_settings),
+        jitter_buffers: Mutex::new(jitter_buffers),
+        note_off_styles: Mutex::new(note_off_styles),
+        buses: Mutex::new(buses),
+        web_bridge: Mutex::new(web_bridge),
+        mqtt: Mutex::new(mqtt),
+        gamepad: Mutex::new(gamepad),
+        control_bindings: Mutex::new(control_bindings),
+        device_definitions: Arc::new(Mutex::new(device_definitions)),
+        middle_c: Mutex::new(middle_c),
+        monitor_history: Arc::new(Mutex::new(MonitorHistory::default())),
+        event_gaps: SharedEventGaps::default(),
     };
 
     tauri::Builder::default()
         .plugin(tauri_plugin_opener::init())
         .manage(app_state)
         .invoke_handler(tauri::generate_handler![
-            commands::get_ports,
+            commands::list_ports,
+            commands::rescan_ports,
             commands::get_routes,
             commands::add_route,
             commands::remove_route,
             commands::toggle_route,
+            commands::toggle_layer_group,
             commands::set_route_channels,
             commands::set_route_cc_mappings,
+            commands::start_cc_range_learn,
+            commands::finish_cc_range_learn,
+            commands::export_cc_mappings,
+            commands::import_cc_mappings,
+            commands::set_route_msc_filter,
+            commands::set_route_system_common_filter,
+            commands::set_route_real_time_strip,
+            commands::set_route_key_range,
+            commands::set_route_channel_map,
+            commands::set_route_strip_aftertouch,
+            commands::set_route_latch,
+            commands::set_route_sustain,
+            commands::set_route_mono,
+            commands::set_route_channel_rotation,
+            commands::set_route_harmony,
+            commands::set_route_voice_split,
+            commands::set_route_duplicate_filter,
+            commands::set_route_velocity_curve,
+            commands::set_route_velocity_gate,
+            commands::set_route_layer,
+            commands::set_route_velocity_humanize,
+            commands::set_route_echo,
+            commands::set_route_program_change_filter,
+            commands::set_route_program_steppers,
+            commands::set_route_note_mappings,
+            commands::set_route_bank_select,
+            commands::set_route_program_recall,
+            commands::release_route_latch,
+            commands::load_tuning_file,
+            commands::set_route_microtuning,
+            commands::validate_routes,
+            commands::get_routing_matrix,
+            commands::set_matrix_cell,
+            commands::quick_connect,
+            commands::quick_connect_outputs,
             commands::start_midi_monitor,
+            commands::pause_monitor,
+            commands::resume_monitor,
             commands::start_error_monitor,
+            commands::get_recent_errors,
+            commands::start_debug_capture,
+            commands::export_debug_capture,
+            commands::set_message_trap,
+            commands::start_trap_monitor,
+            commands::get_session_stats,
+            commands::capture_retrospective,
             commands::list_presets,
             commands::save_preset,
             commands::update_preset,
+            commands::import_setup,
+            commands::list_setup_templates,
+            commands::create_from_template,
+            commands::export_setup_report,
+            commands::panic_route,
+            commands::inject_to_route,
+            commands::get_sounding_notes,
+            commands::set_chord_detection,
+            commands::get_clock_settings,
+            commands::set_clock_settings,
+            commands::get_jitter_buffers,
+            commands::set_jitter_buffer,
+            commands::get_note_off_styles,
+            commands::set_note_off_style,
+            commands::get_buses,
+            commands::add_bus,
+            commands::remove_bus,
+            commands::get_web_bridge,
+            commands::set_web_bridge,
+            commands::get_mqtt,
+            commands::set_mqtt,
+            commands::get_gamepad_mappings,
+            commands::set_gamepad_mappings,
+            commands::start_test_signal,
+            commands::stop_test_signal,
+            commands::send_cc_ramp,
+            commands::start_replay,
+            commands::stop_replay,
+            commands::start_replay_monitor,
+            commands::get_event_gaps,
+            commands::start_chord_monitor,
+            commands::start_song_select_monitor,
+            commands::start_preset_switch_monitor,
+            commands::set_route_learn,
+            commands::start_route_suggestion_monitor,
             commands::load_preset,
+            commands::queue_preset,
+            commands::cancel_queued_preset,
             commands::delete_preset,
             commands::get_active_preset_id,
             commands::set_bpm,
             commands::get_clock_bpm,
+            commands::start_ports_monitor,
+            commands::start_wake_monitor,
             commands::start_clock_monitor,
+            commands::start_clock_position_monitor,
+            commands::start_port_activity_monitor,
+            commands::get_engine_stats,
+            commands::get_route_stats,
+            commands::start_stats_monitor,
+            commands::start_route_status_monitor,
+            commands::get_control_bindings,
+            commands::set_tempo_cc_binding,
+            commands::set_tap_tempo_binding,
+            commands::set_transport_triggers,
+            commands::set_song_select_binding,
+            commands::get_middle_c,
+            commands::set_middle_c,
+            commands::list_device_definitions,
+            commands::import_midnam,
+            commands::save_device_definition,
+            commands::delete_device_definition,
+            commands::get_device_cc_names,
             commands::send_transport_start,
             commands::send_transport_stop,
         ])
@@ -1,6 +0,0 @@
-pub mod clock;
-pub mod engine;
-pub mod port_manager;
-pub mod ports;
-pub mod router;
-pub mod transport;
@@ -1,6 +1,21 @@
 import { useState, useEffect } from "react";
-import { Route, ChannelFilter, CcMapping } from "../types";
-import { removeRoute } from "../hooks/useMidi";
+import {
+  Route,
+  ChannelFilter,
+  CcMapping,
+  SystemCommon,
+  NotePriority,
+  ProgramChangeFilter,
+} from "../types";
+import {
+  removeRoute,
+  panicRoute,
+  setRouteSystemCommonFilter,
+  setRouteLatch,
+  setRouteMono,
+  setRouteProgramChangeFilter,
+  releaseRouteLatch,
+} from "../hooks/useMidi";
 import { useAppStore } from "../stores/appStore";
 import { CcMappingsEditor } from "./CcMappingsEditor";
 import {
@@ -16,7 +31,28 @@ import { ToggleGroup, ToggleGroupItem } from "@/components/ui/toggle-group";
 import { Toggle } from "@/components/ui/toggle";
 import { Button } from "@/components/ui/button";
 import { Separator } from "@/components/ui/separator";
-import { ArrowRight, Trash2, Check } from "lucide-react";
+import { Input } from "@/components/ui/input";
+import { ArrowRight, Trash2, Check, OctagonX, Lock, Unlock } from "lucide-react";
+
+const SYSTEM_COMMON: { message: SystemCommon; label: string }[] = [
+  { message: "MtcQuarterFrame", label: "MTC" },
+  { message: "SongPosition", label: "SPP" },
+  { message: "SongSelect", label: "Song" },
+  { message: "TuneRequest", label: "Tune" },
+];
+
+const NO_PC_FILTER: ProgramChangeFilter = { dedupe: false, debounce_ms: 0 };
+
+function blockedSystemCommon(route: Route): Set<SystemCommon> {
+  const filter = route.system_common_filter ?? "All";
+  if (filter === "All") return new Set();
+  if ("Only" in filter) {
+    return new Set(
+      SYSTEM_COMMON.map((s) => s.message).filter((m) => !filter.Only.includes(m))
+    );
+  }
+  return new Set(filter.Except);
+}
 
 interface Props {
   route: Route;
@@ -41,6 +77,19 @@ export function ChannelPopup({ route, onClose }: Props) {
     route.cc_mappings ?? []
   );
 
+  // System Common messages blocked on this route
+  const [blocked, setBlocked] = useState<Set<SystemCommon>>(
+    blockedSystemCommon(route)
+  );
+
+  // Note Ons toggle held notes instead of playing momentarily
+  const [latch, setLatch] = useState(route.latch ?? false);
+  // One note per channel, chosen by priority; null plays polyphonically
+  const [mono, setMono] = useState<NotePriority | null>(route.mono ?? null);
+  const [pcFilter, setPcFilter] = useState<ProgramChangeFilter>(
+    route.program_change_filter ?? NO_PC_FILTER
+  );
+
   useEffect(() => {
     const channels = route.channels;
     if (channels === "All") {
@@ -56,6 +105,10 @@ export function ChannelPopup({ route, onClose }: Props) {
 
     setCcPassthrough(route.cc_passthrough ?? true);
     setCcMappings(route.cc_mappings ?? []);
+    setBlocked(blockedSystemCommon(route));
+    setLatch(route.latch ?? false);
+    setMono(route.mono ?? null);
+    setPcFilter(route.program_change_filter ?? NO_PC_FILTER);
   }, [route]);
 
   const toggleChannel = (ch: number) => {
@@ -81,6 +134,23 @@ export function ChannelPopup({ route, onClose }: Props) {
     }
     await updateRouteChannels(route.id, filter);
     await updateRouteCcMappings(route.id, ccPassthrough, ccMappings);
+    await setRouteSystemCommonFilter(
+      route.id,
+      blocked.size === 0 ? "All" : { Except: Array.from(blocked) }
+    );
+    if (latch !== (route.latch ?? false)) {
+      await setRouteLatch(route.id, latch);
+    }
+    if (mono !== (route.mono ?? null)) {
+      await setRouteMono(route.id, mono);
+    }
+    const currentPcFilter = route.program_change_filter ?? NO_PC_FILTER;
+    if (
+      pcFilter.dedupe !== currentPcFilter.dedupe ||
+      pcFilter.debounce_ms !== currentPcFilter.debounce_ms
+    ) {
+      await setRouteProgramChangeFilter(route.id, pcFilter);
+    }
     onClose();
   };
 
@@ -202,6 +272,103 @@ export function ChannelPopup({ route, onClose }: Props) {
                   </div>
                 </div>
               )}
+
+              {/* System Common forwarding */}
+              <div className="space-y-2">
+                <label className="text-[10px] font-medium text-muted-foreground uppercase tracking-widest">
+                  System Common
+                </label>
+                <div className="grid grid-cols-4 gap-1">
+                  {SYSTEM_COMMON.map(({ message, label }) => (
+                    <Toggle
+                      key={message}
+                      size="sm"
+                      pressed={!blocked.has(message)}
+                      onPressedChange={(pressed) =>
+                        setBlocked((prev) => {
+                          const next = new Set(prev);
+                          if (pressed) {
+                            next.delete(message);
+                          } else {
+                            next.add(message);
+                          }
+                          return next;
+                        })
+                      }
+                      className="h-8 w-full text-xs data-[state=on]:bg-emerald-500/20 data-[state=on]:text-emerald-400 data-[state=on]:border-emerald-500/30"
+                    >
+                      {label}
+                    </Toggle>
+                  ))}
+                </div>
+              </div>
+
+              {/* Note latch */}
+              <div className="space-y-2">
+                <label className="text-[10px] font-medium text-muted-foreground uppercase tracking-widest">
+                  Notes
+                </label>
+                <Toggle
+                  size="sm"
+                  pressed={latch}
+                  onPressedChange={setLatch}
+                  title="Each Note On toggles its note; Note Offs are ignored"
+                  className="h-8 w-full text-xs data-[state=on]:bg-emerald-500/20 data-[state=on]:text-emerald-400 data-[state=on]:border-emerald-500/30"
+                >
+                  <Lock className="size-3.5" />
+                  Latch
+                </Toggle>
+                <ToggleGroup
+                  type="single"
+                  value={mono ?? "Poly"}
+                  onValueChange={(value) => {
+                    if (value) setMono(value === "Poly" ? null : (value as NotePriority));
+                  }}
+                  variant="outline"
+                  size="sm"
+                  className="w-full"
+                  title="Mono note priority"
+                >
+                  <ToggleGroupItem value="Poly" className="flex-1 text-xs">Poly</ToggleGroupItem>
+                  <ToggleGroupItem value="Last" className="flex-1 text-xs">Mono Last</ToggleGroupItem>
+                  <ToggleGroupItem value="Low" className="flex-1 text-xs">Mono Low</ToggleGroupItem>
+                  <ToggleGroupItem value="High" className="flex-1 text-xs">Mono High</ToggleGroupItem>
+                </ToggleGroup>
+              </div>
+
+              {/* Program Change thinning */}
+              <div className="space-y-2">
+                <label className="text-[10px] font-medium text-muted-foreground uppercase tracking-widest">
+                  Program Change
+                </label>
+                <div className="flex items-center gap-2">
+                  <Toggle
+                    size="sm"
+                    pressed={pcFilter.dedupe}
+                    onPressedChange={(dedupe) => setPcFilter((f) => ({ ...f, dedupe }))}
+                    title="Drop Program Changes for the program already selected"
+                    className="h-8 flex-1 text-xs data-[state=on]:bg-emerald-500/20 data-[state=on]:text-emerald-400 data-[state=on]:border-emerald-500/30"
+                  >
+                    Drop repeats
+                  </Toggle>
+                  <span className="text-xs text-muted-foreground">Debounce</span>
+                  <Input
+                    type="number"
+                    min={0}
+                    max={2000}
+                    value={pcFilter.debounce_ms}
+                    onChange={(e) =>
+                      setPcFilter((f) => ({
+                        ...f,
+                        debounce_ms: Math.max(0, Number(e.target.value) || 0),
+                      }))
+                    }
+                    title="Send only the last of a burst, once none arrived for this long"
+                    className="h-7 w-16 text-xs font-mono text-center"
+                  />
+                  <span className="text-xs text-muted-foreground">ms</span>
+                </div>
+              </div>
             </div>
           </TabsContent>
 
@@ -219,15 +386,39 @@ export function ChannelPopup({ route, onClose }: Props) {
         {/* Footer */}
         <Separator />
         <DialogFooter className="flex-row items-center justify-between px-5 py-3">
-          <Button
-            variant="ghost"
-            size="sm"
-            className="text-muted-foreground hover:text-destructive hover:bg-destructive/10"
-            onClick={handleDelete}
-          >
-            <Trash2 className="size-3.5" />
-            Delete
-          </Button>
+          <div className="flex items-center gap-1">
+            <Button
+              variant="ghost"
+              size="sm"
+              className="text-muted-foreground hover:text-destructive hover:bg-destructive/10"
+              onClick={handleDelete}
+            >
+              <Trash2 className="size-3.5" />
+              Delete
+            </Button>
+            <Button
+              variant="ghost"
+              size="sm"
+              className="text-muted-foreground hover:text-amber-400 hover:bg-amber-500/10"
+              onClick={() => panicRoute(route.id)}
+              title="Release stuck notes on this route's destination"
+            >
+              <OctagonX className="size-3.5" />
+              Panic
+            </Button>
+            {route.latch && (
+              <Button
+                variant="ghost"
+                size="sm"
+                className="text-muted-foreground hover:text-sky-400 hover:bg-sky-500/10"
+                onClick={() => releaseRouteLatch(route.id)}
+                title="Release every note the latch is holding"
+              >
+                <Unlock className="size-3.5" />
+                Release
+              </Button>
+            )}
+          </div>
           <Button
             size="sm"
             className="bg-emerald-600 hover:bg-emerald-500 text-white shadow-[0_0_12px_rgba(16,185,129,0.15)]"
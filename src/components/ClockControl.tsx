@@ -1,16 +1,34 @@
 import { useState, useEffect } from "react";
-import { Play, Square } from "lucide-react";
+import {
+  Play,
+  Square,
+  Infinity as AlwaysOn,
+  AlignEndVertical as BarEnd,
+  VolumeX,
+} from "lucide-react";
 import { Button } from "@/components/ui/button";
 import { Input } from "@/components/ui/input";
+import {
+  DropdownMenu,
+  DropdownMenuCheckboxItem,
+  DropdownMenuContent,
+  DropdownMenuLabel,
+  DropdownMenuTrigger,
+} from "@/components/ui/dropdown-menu";
 import * as api from "../hooks/useMidi";
+import { useAppStore } from "../stores/appStore";
+import { ClockSettings } from "../types";
 
 export function ClockControl() {
   const [bpm, setBpm] = useState(120);
   const [running, setRunning] = useState(false);
+  const [settings, setSettings] = useState<ClockSettings | null>(null);
+  const { outputPorts } = useAppStore();
 
   useEffect(() => {
     // Load initial BPM
     api.getClockBpm().then(setBpm);
+    api.getClockSettings().then(setSettings);
 
     // Subscribe to clock state changes
     api.startClockMonitor((state) => {
@@ -35,6 +53,43 @@ export function ClockControl() {
     api.sendTransportStop();
   };
 
+  const toggleAlwaysOn = () => {
+    if (!settings) return;
+    const next: ClockSettings = {
+      ...settings,
+      mode: settings.mode === "Always" ? "WhileRunning" : "Always",
+    };
+    setSettings(next);
+    api.setClockSettings(next);
+  };
+
+  const toggleQuantizeStop = () => {
+    if (!settings) return;
+    const next: ClockSettings = {
+      ...settings,
+      quantize_stop: !settings.quantize_stop,
+    };
+    setSettings(next);
+    api.setClockSettings(next);
+  };
+
+  const toggleSilenceOnStop = (output: string) => {
+    if (!settings) return;
+    const silenced = settings.silence_on_stop ?? [];
+    const next: ClockSettings = {
+      ...settings,
+      silence_on_stop: silenced.includes(output)
+        ? silenced.filter((name) => name !== output)
+        : [...silenced, output],
+    };
+    setSettings(next);
+    api.setClockSettings(next);
+  };
+
+  const alwaysOn = settings?.mode === "Always";
+  const silenced = settings?.silence_on_stop ?? [];
+  const quantizeStop = settings?.quantize_stop ?? false;
+
   return (
     <div className="flex items-center gap-2 pl-4 border-l">
       <div className="flex gap-0.5">
@@ -56,6 +111,54 @@ export function ClockControl() {
         >
           <Square className="h-3 w-3" />
         </Button>
+        <Button
+          variant={alwaysOn ? "default" : "outline"}
+          size="icon"
+          className="h-7 w-7"
+          onClick={toggleAlwaysOn}
+          disabled={!settings}
+          title="Send clock even while stopped"
+        >
+          <AlwaysOn className="h-3.5 w-3.5" />
+        </Button>
+        <Button
+          variant={quantizeStop ? "default" : "outline"}
+          size="icon"
+          className="h-7 w-7"
+          onClick={toggleQuantizeStop}
+          disabled={!settings}
+          title="Stop at the end of the bar"
+        >
+          <BarEnd className="h-3.5 w-3.5" />
+        </Button>
+        <DropdownMenu>
+          <DropdownMenuTrigger asChild>
+            <Button
+              variant={silenced.length > 0 ? "default" : "outline"}
+              size="icon"
+              className="h-7 w-7"
+              disabled={!settings}
+              title="Silence outputs on Stop"
+            >
+              <VolumeX className="h-3.5 w-3.5" />
+            </Button>
+          </DropdownMenuTrigger>
+          <DropdownMenuContent align="end">
+            <DropdownMenuLabel className="text-xs">
+              Send All Sound Off on Stop
+            </DropdownMenuLabel>
+            {outputPorts.map((port) => (
+              <DropdownMenuCheckboxItem
+                key={port.id.name}
+                checked={silenced.includes(port.id.name)}
+                onCheckedChange={() => toggleSilenceOnStop(port.id.name)}
+                onSelect={(e) => e.preventDefault()}
+              >
+                {port.id.display_name}
+              </DropdownMenuCheckboxItem>
+            ))}
+          </DropdownMenuContent>
+        </DropdownMenu>
       </div>
       <div className="flex items-center gap-1.5">
         <span className="text-xs text-muted-foreground">BPM</span>
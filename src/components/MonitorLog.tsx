@@ -12,12 +12,13 @@ import {
   TableRow,
 } from "@/components/ui/table";
 
-function formatMessage(kind: MessageKind): string {
+function formatMessage(kind: MessageKind, noteName: string | null): string {
+  const note = (noteName ?? "").padEnd(4);
   if (kind.kind === "NoteOn") {
-    return `NoteOn  ${noteName(kind.data.note)} vel=${kind.data.velocity}`;
+    return `NoteOn  ${note} vel=${kind.data.velocity}`;
   }
   if (kind.kind === "NoteOff") {
-    return `NoteOff ${noteName(kind.data.note)} vel=${kind.data.velocity}`;
+    return `NoteOff ${note} vel=${kind.data.velocity}`;
   }
   if (kind.kind === "ControlChange") {
     return `CC ${kind.data.controller} val=${kind.data.value}`;
@@ -32,11 +33,21 @@ function formatMessage(kind: MessageKind): string {
     return `AT ${kind.data.value}`;
   }
   if (kind.kind === "PolyAftertouch") {
-    return `PolyAT ${noteName(kind.data.note)} ${kind.data.value}`;
+    return `PolyAT ${note} ${kind.data.value}`;
   }
   if (kind.kind === "SysEx") {
     return "SysEx";
   }
+  if (kind.kind === "SongSelect") {
+    return `Song ${kind.data.song}`;
+  }
+  if (kind.kind === "ShowControl") {
+    const { command, cue_number, cue_list } = kind.data;
+    const name = typeof command === "string" ? command : `Cmd ${command.Other}`;
+    const cue = cue_number ? ` cue=${cue_number}` : "";
+    const list = cue_list ? ` list=${cue_list}` : "";
+    return `MSC ${name}${cue}${list}`;
+  }
   if (kind.kind === "Clock") {
     return "Clock";
   }
@@ -52,13 +63,6 @@ function formatMessage(kind: MessageKind): string {
   return "Other";
 }
 
-function noteName(note: number): string {
-  const names = ["C", "C#", "D", "D#", "E", "F", "F#", "G", "G#", "A", "A#", "B"];
-  const octave = Math.floor(note / 12) - 1;
-  const name = names[note % 12];
-  return `${name}${octave}`.padEnd(4);
-}
-
 function formatTimestamp(ts: number): string {
   const date = new Date(ts / 1000); // Convert microseconds to milliseconds
   const timeStr = date.toLocaleTimeString("en-US", {
@@ -106,7 +110,8 @@ function ActivityRow({ activity }: { activity: MidiActivity }) {
           variant={getBadgeVariant(activity.kind.kind)}
           className="text-xs font-mono"
         >
-          {formatMessage(activity.kind)}
+          {formatMessage(activity.kind, activity.note_name)}
+          {activity.label && ` (${activity.label})`}
         </Badge>
       </TableCell>
     </TableRow>
@@ -114,7 +119,15 @@ function ActivityRow({ activity }: { activity: MidiActivity }) {
 }
 
 export function MonitorLog() {
-  const { activityLog, monitorActive, startMonitor, clearLog } = useAppStore();
+  const {
+    activityLog,
+    monitorActive,
+    monitorPaused,
+    startMonitor,
+    pauseMonitor,
+    resumeMonitor,
+    clearLog,
+  } = useAppStore();
 
   useEffect(() => {
     if (!monitorActive) {
@@ -126,9 +139,18 @@ export function MonitorLog() {
     <div className="flex flex-col h-full">
       <div className="flex items-center justify-between mb-2">
         <span className="text-sm font-medium">MIDI Monitor</span>
-        <Button variant="outline" size="sm" onClick={clearLog}>
-          Clear
-        </Button>
+        <div className="flex gap-2">
+          <Button
+            variant="outline"
+            size="sm"
+            onClick={monitorPaused ? resumeMonitor : pauseMonitor}
+          >
+            {monitorPaused ? "Resume" : "Pause"}
+          </Button>
+          <Button variant="outline" size="sm" onClick={clearLog}>
+            Clear
+          </Button>
+        </div>
       </div>
       <div className="flex-1 overflow-auto rounded-md border">
         <Table>
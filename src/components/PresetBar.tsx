@@ -24,7 +24,15 @@ import {
   DropdownMenuItem,
   DropdownMenuTrigger,
 } from "@/components/ui/dropdown-menu";
-import { Save, MoreVertical, Plus, Trash2 } from "lucide-react";
+import { Save, MoreVertical, Plus, Trash2, AlertTriangle, Timer } from "lucide-react";
+
+// How many bar lines a preset change waits for; 0 switches right away
+const SWITCH_DELAYS = [
+  { bars: 0, label: "Now" },
+  { bars: 1, label: "Next bar" },
+  { bars: 2, label: "2 bars" },
+  { bars: 4, label: "4 bars" },
+];
 
 export function PresetBar() {
   const { refreshRoutes } = useAppStore();
@@ -32,9 +40,19 @@ export function PresetBar() {
   const [activePresetId, setActivePresetId] = useState<string | null>(null);
   const [showSaveDialog, setShowSaveDialog] = useState(false);
   const [newPresetName, setNewPresetName] = useState("");
+  const [missingPorts, setMissingPorts] = useState<string[]>([]);
+  const [switchBars, setSwitchBars] = useState(0);
+  const [queuedPresetId, setQueuedPresetId] = useState<string | null>(null);
 
   useEffect(() => {
     loadPresets();
+    // Hardware sequencers can switch presets with Song Select
+    api.startSongSelectMonitor((change) => handleLoad(change.preset_id));
+    // The engine already switched routes on the bar; catch up with it
+    api.startPresetSwitchMonitor((presetId) => {
+      setQueuedPresetId(null);
+      handleLoad(presetId);
+    });
   }, []);
 
   const loadPresets = async () => {
@@ -47,11 +65,32 @@ export function PresetBar() {
   };
 
   const handleLoad = async (presetId: string) => {
-    const preset = await api.loadPreset(presetId);
+    const { preset, availability } = await api.loadPreset(presetId);
     setActivePresetId(preset.id);
+    const missing = availability.unavailable.flatMap((r) =>
+      [r.missing_source, r.missing_destination].filter((p): p is string => p !== null)
+    );
+    setMissingPorts([...new Set(missing)]);
     await refreshRoutes();
   };
 
+  const handleSelect = async (presetId: string) => {
+    if (switchBars === 0) {
+      await handleLoad(presetId);
+      return;
+    }
+    // Set first: with the clock stopped the switch is reported right away
+    setQueuedPresetId(presetId);
+    await api.queuePreset(presetId, switchBars);
+  };
+
+  const handleCancelQueued = async () => {
+    await api.cancelQueuedPreset();
+    setQueuedPresetId(null);
+  };
+
+  const queuedPreset = presets.find((p) => p.id === queuedPresetId);
+
   const handleSaveAs = async () => {
     if (!newPresetName.trim()) return;
     const preset = await api.savePreset(newPresetName.trim());
@@ -71,6 +110,7 @@ export function PresetBar() {
     if (!activePresetId) return;
     await api.deletePreset(activePresetId);
     setActivePresetId(null);
+    setMissingPorts([]);
     loadPresets();
   };
 
@@ -82,8 +122,9 @@ export function PresetBar() {
         onValueChange={(value) => {
           if (value === "__none__") {
             setActivePresetId(null);
+            setMissingPorts([]);
           } else {
-            handleLoad(value);
+            handleSelect(value);
           }
         }}
       >
@@ -100,6 +141,47 @@ export function PresetBar() {
         </SelectContent>
       </Select>
 
+      {/* When a chosen preset takes effect */}
+      <Select
+        value={String(switchBars)}
+        onValueChange={(value) => setSwitchBars(Number(value))}
+      >
+        <SelectTrigger className="h-7 w-[84px] text-xs" title="Switch presets on a bar line">
+          <SelectValue />
+        </SelectTrigger>
+        <SelectContent>
+          {SWITCH_DELAYS.map((d) => (
+            <SelectItem key={d.bars} value={String(d.bars)}>
+              {d.label}
+            </SelectItem>
+          ))}
+        </SelectContent>
+      </Select>
+
+      {/* Preset waiting for its bar line; click to cancel */}
+      {queuedPreset && (
+        <Button
+          variant="ghost"
+          size="sm"
+          className="h-7 gap-1 px-2 text-xs text-sky-400"
+          onClick={handleCancelQueued}
+          title="Cancel queued preset"
+        >
+          <Timer />
+          {queuedPreset.name}
+        </Button>
+      )}
+
+      {/* Devices the loaded preset needs but are not connected */}
+      {missingPorts.length > 0 && (
+        <span
+          className="text-amber-400"
+          title={`Missing devices: ${missingPorts.join(", ")}`}
+        >
+          <AlertTriangle className="h-4 w-4" />
+        </span>
+      )}
+
       {/* Save button - only when preset selected */}
       {activePresetId && (
         <Button
@@ -12,6 +12,7 @@ export function RoutingMatrix() {
     routes,
     portActivity,
     loadingPorts,
+    watchPorts,
     refreshPorts,
     addRoute,
     toggleRoute,
@@ -22,7 +23,7 @@ export function RoutingMatrix() {
   } | null>(null);
 
   useEffect(() => {
-    refreshPorts().catch(console.error);
+    watchPorts().catch(console.error);
     // eslint-disable-next-line react-hooks/exhaustive-deps
   }, []);
 
@@ -152,6 +153,7 @@ export function RoutingMatrix() {
                 {outputPorts.map((output) => {
                   const route = getRoute(input.id.name, output.id.name);
                   const enabled = route?.enabled;
+                  const held = enabled && route?.status && route.status !== "Active";
                   const hasRoute = !!route;
 
                   return (
@@ -174,9 +176,12 @@ export function RoutingMatrix() {
                       onContextMenu={(e) =>
                         handleCellRightClick(e, input.id.name, output.id.name)
                       }
+                      title={held ? `Route ${route?.status?.toLowerCase()}` : undefined}
                     >
                       {/* Crosspoint indicator */}
-                      {enabled ? (
+                      {held ? (
+                        <div className="size-5 rounded-sm bg-amber-500/70 transition-all" />
+                      ) : enabled ? (
                         <div className="size-5 rounded-sm bg-emerald-500 shadow-[0_0_8px_rgba(16,185,129,0.4)] transition-all" />
                       ) : hasRoute ? (
                         <div className="size-5 rounded-sm bg-white/[0.06] border border-white/10 transition-all" />
@@ -1,8 +1,28 @@
 import { invoke, Channel } from "@tauri-apps/api/core";
-import { MidiPort, Route, ChannelFilter, MidiActivity, Preset, ClockState, CcMapping } from "../types";
+import { MidiPort, Route, ChannelFilter, MidiActivity, Preset, ClockState, ClockPosition, ClockSettings, CcMapping, CcCalibration, MscFilter, SystemCommonFilter, RealTimeStrip, Microtuning, TuningTable, ControlBindings, TempoCcBinding, TapTempoBinding, RouteWarning, RoutingMatrix, SetupTemplate, TransportTriggerBinding, SongSelectBinding, SongSelectChange, RouteSuggestion, DeviceDefinition, MiddleC, NoteOffStyle, PortPulse, EngineStats, SessionStats, RecentError, TrapCondition, TrapHit, ReplayReport, RouteStats, RouteStatusChange, LoadedPreset, HeldNotes, DetectedChord, NotePriority, ProgramChangeFilter, ProgramStepper, NoteMapping, BankSelectSettings, ProgramRecall, ChannelRotation, HarmonyVoice, VoiceSplit, DuplicateFilter, VelocityTransform, VelocityHumanize, NoteEcho, KeyRange, Layer, ChannelMap, WakeReport, WebBridgeSettings, MqttSettings, GamepadMapping, TestSignal } from "../types";
 
-export async function getPorts(): Promise<[MidiPort[], MidiPort[]]> {
-  return invoke("get_ports");
+export async function listPorts(): Promise<[MidiPort[], MidiPort[]]> {
+  return invoke("list_ports");
+}
+
+export async function rescanPorts(): Promise<[MidiPort[], MidiPort[]]> {
+  return invoke("rescan_ports");
+}
+
+export async function startPortsMonitor(
+  onPorts: (ports: [MidiPort[], MidiPort[]]) => void
+): Promise<void> {
+  const channel = new Channel<[MidiPort[], MidiPort[]]>();
+  channel.onmessage = onPorts;
+  return invoke("start_ports_monitor", { onEvent: channel });
+}
+
+export async function startWakeMonitor(
+  onWake: (report: WakeReport) => void
+): Promise<void> {
+  const channel = new Channel<WakeReport>();
+  channel.onmessage = onWake;
+  return invoke("start_wake_monitor", { onEvent: channel });
 }
 
 export async function getRoutes(): Promise<Route[]> {
@@ -24,6 +44,11 @@ export async function toggleRoute(routeId: string): Promise<boolean> {
   return invoke("toggle_route", { routeId });
 }
 
+/** Switch a layer group's routes on, or off if any is on */
+export async function toggleLayerGroup(group: string): Promise<boolean> {
+  return invoke("toggle_layer_group", { group });
+}
+
 export async function setRouteChannels(
   routeId: string,
   filter: ChannelFilter
@@ -39,6 +64,235 @@ export async function setRouteCcMappings(
   return invoke("set_route_cc_mappings", { routeId, ccPassthrough, ccMappings });
 }
 
+export async function startCcRangeLearn(
+  routeId: string,
+  sourceCc: number
+): Promise<void> {
+  return invoke("start_cc_range_learn", { routeId, sourceCc });
+}
+
+export async function finishCcRangeLearn(
+  routeId: string,
+  sourceCc: number
+): Promise<CcCalibration> {
+  return invoke("finish_cc_range_learn", { routeId, sourceCc });
+}
+
+export async function exportCcMappings(
+  routeId: string,
+  path: string
+): Promise<void> {
+  return invoke("export_cc_mappings", { routeId, path });
+}
+
+export async function importCcMappings(
+  routeId: string,
+  path: string,
+  replace: boolean
+): Promise<CcMapping[]> {
+  return invoke("import_cc_mappings", { routeId, path, replace });
+}
+
+export async function setRouteMscFilter(
+  routeId: string,
+  filter: MscFilter
+): Promise<void> {
+  return invoke("set_route_msc_filter", { routeId, filter });
+}
+
+export async function setRouteRealTimeStrip(
+  routeId: string,
+  strip: RealTimeStrip
+): Promise<void> {
+  return invoke("set_route_real_time_strip", { routeId, strip });
+}
+
+export async function setRouteStripAftertouch(
+  routeId: string,
+  strip: boolean
+): Promise<void> {
+  return invoke("set_route_strip_aftertouch", { routeId, strip });
+}
+
+export async function setRouteLatch(routeId: string, latch: boolean): Promise<void> {
+  return invoke("set_route_latch", { routeId, latch });
+}
+
+export async function setRouteSustain(routeId: string, sustain: boolean): Promise<void> {
+  return invoke("set_route_sustain", { routeId, sustain });
+}
+
+export async function setRouteMono(
+  routeId: string,
+  priority: NotePriority | null
+): Promise<void> {
+  return invoke("set_route_mono", { routeId, priority });
+}
+
+export async function setRouteChannelRotation(
+  routeId: string,
+  rotation: ChannelRotation | null
+): Promise<void> {
+  return invoke("set_route_channel_rotation", { routeId, rotation });
+}
+
+export async function setRouteHarmony(
+  routeId: string,
+  voices: HarmonyVoice[]
+): Promise<void> {
+  return invoke("set_route_harmony", { routeId, voices });
+}
+
+export async function setRouteVoiceSplit(
+  routeId: string,
+  split: VoiceSplit | null
+): Promise<void> {
+  return invoke("set_route_voice_split", { routeId, split });
+}
+
+export async function setRouteDuplicateFilter(
+  routeId: string,
+  filter: DuplicateFilter | null
+): Promise<void> {
+  return invoke("set_route_duplicate_filter", { routeId, filter });
+}
+
+export async function setRouteChannelMap(
+  routeId: string,
+  map: ChannelMap | null
+): Promise<void> {
+  return invoke("set_route_channel_map", { routeId, map });
+}
+
+export async function setRouteKeyRange(
+  routeId: string,
+  range: KeyRange | null
+): Promise<void> {
+  return invoke("set_route_key_range", { routeId, range });
+}
+
+export async function setRouteVelocityCurve(
+  routeId: string,
+  curve: VelocityTransform | null
+): Promise<void> {
+  return invoke("set_route_velocity_curve", { routeId, curve });
+}
+
+export async function setRouteVelocityGate(
+  routeId: string,
+  gate: number | null
+): Promise<void> {
+  return invoke("set_route_velocity_gate", { routeId, gate });
+}
+
+export async function setRouteLayer(
+  routeId: string,
+  layer: Layer | null
+): Promise<void> {
+  return invoke("set_route_layer", { routeId, layer });
+}
+
+export async function setRouteVelocityHumanize(
+  routeId: string,
+  humanize: VelocityHumanize | null
+): Promise<void> {
+  return invoke("set_route_velocity_humanize", { routeId, humanize });
+}
+
+export async function setRouteEcho(
+  routeId: string,
+  echo: NoteEcho | null
+): Promise<void> {
+  return invoke("set_route_echo", { routeId, echo });
+}
+
+export async function setRouteProgramChangeFilter(
+  routeId: string,
+  filter: ProgramChangeFilter
+): Promise<void> {
+  return invoke("set_route_program_change_filter", { routeId, filter });
+}
+
+export async function setRouteProgramSteppers(
+  routeId: string,
+  steppers: ProgramStepper[]
+): Promise<void> {
+  return invoke("set_route_program_steppers", { routeId, steppers });
+}
+
+export async function setRouteNoteMappings(
+  routeId: string,
+  mappings: NoteMapping[]
+): Promise<void> {
+  return invoke("set_route_note_mappings", { routeId, mappings });
+}
+
+export async function setRouteBankSelect(
+  routeId: string,
+  settings: BankSelectSettings
+): Promise<void> {
+  return invoke("set_route_bank_select", { routeId, settings });
+}
+
+export async function setRouteProgramRecall(
+  routeId: string,
+  recalls: ProgramRecall[]
+): Promise<void> {
+  return invoke("set_route_program_recall", { routeId, recalls });
+}
+
+export async function releaseRouteLatch(routeId: string): Promise<void> {
+  return invoke("release_route_latch", { routeId });
+}
+
+export async function setRouteSystemCommonFilter(
+  routeId: string,
+  filter: SystemCommonFilter
+): Promise<void> {
+  return invoke("set_route_system_common_filter", { routeId, filter });
+}
+
+export async function loadTuningFile(path: string): Promise<TuningTable> {
+  return invoke("load_tuning_file", { path });
+}
+
+export async function setRouteMicrotuning(
+  routeId: string,
+  microtuning: Microtuning | null
+): Promise<void> {
+  return invoke("set_route_microtuning", { routeId, microtuning });
+}
+
+export async function validateRoutes(): Promise<RouteWarning[]> {
+  return invoke("validate_routes");
+}
+
+export async function getRoutingMatrix(): Promise<RoutingMatrix> {
+  return invoke("get_routing_matrix");
+}
+
+export async function setMatrixCell(
+  source: string,
+  destination: string,
+  enabled: boolean
+): Promise<Route[]> {
+  return invoke("set_matrix_cell", { source, destination, enabled });
+}
+
+export async function quickConnect(
+  inputs: string[],
+  output: string
+): Promise<Route[]> {
+  return invoke("quick_connect", { inputs, output });
+}
+
+export async function quickConnectOutputs(
+  input: string,
+  outputs: string[]
+): Promise<Route[]> {
+  return invoke("quick_connect_outputs", { input, outputs });
+}
+
 export async function startMidiMonitor(
   onActivity: (activity: MidiActivity) => void
 ): Promise<void> {
@@ -47,6 +301,19 @@ export async function startMidiMonitor(
   return invoke("start_midi_monitor", { onEvent: channel });
 }
 
+/** Events each monitor missed since startup, by kind */
+export async function getEventGaps(): Promise<Record<string, number>> {
+  return invoke("get_event_gaps");
+}
+
+export async function pauseMonitor(): Promise<void> {
+  return invoke("pause_monitor");
+}
+
+export async function resumeMonitor(): Promise<MidiActivity[]> {
+  return invoke("resume_monitor");
+}
+
 export async function listPresets(): Promise<Preset[]> {
   return invoke("list_presets");
 }
@@ -59,7 +326,26 @@ export async function updatePreset(presetId: string): Promise<Preset> {
   return invoke("update_preset", { presetId });
 }
 
-export async function loadPreset(presetId: string): Promise<Preset> {
+export async function importSetup(path: string, name: string): Promise<Preset> {
+  return invoke("import_setup", { path, name });
+}
+
+export async function listSetupTemplates(): Promise<SetupTemplate[]> {
+  return invoke("list_setup_templates");
+}
+
+export async function createFromTemplate(
+  templateId: string,
+  ports: Record<string, string>
+): Promise<Route[]> {
+  return invoke("create_from_template", { templateId, ports });
+}
+
+export async function exportSetupReport(path: string): Promise<void> {
+  return invoke("export_setup_report", { path });
+}
+
+export async function loadPreset(presetId: string): Promise<LoadedPreset> {
   return invoke("load_preset", { presetId });
 }
 
@@ -79,6 +365,99 @@ export async function getClockBpm(): Promise<number> {
   return invoke("get_clock_bpm");
 }
 
+export async function getClockSettings(): Promise<ClockSettings> {
+  return invoke("get_clock_settings");
+}
+
+export async function setClockSettings(settings: ClockSettings): Promise<void> {
+  return invoke("set_clock_settings", { settings });
+}
+
+export async function getJitterBuffers(): Promise<Record<string, number>> {
+  return invoke("get_jitter_buffers");
+}
+
+export async function setJitterBuffer(
+  port: string,
+  latencyMs: number | null
+): Promise<void> {
+  return invoke("set_jitter_buffer", { port, latencyMs });
+}
+
+export async function getNoteOffStyles(): Promise<Record<string, NoteOffStyle>> {
+  return invoke("get_note_off_styles");
+}
+
+export async function setNoteOffStyle(
+  port: string,
+  style: NoteOffStyle | null
+): Promise<void> {
+  return invoke("set_note_off_style", { port, style });
+}
+
+export async function getBuses(): Promise<string[]> {
+  return invoke("get_buses");
+}
+
+/** Add an internal bus; it is listed with the ports as both an input and an output */
+export async function addBus(name: string): Promise<void> {
+  return invoke("add_bus", { name });
+}
+
+export async function removeBus(name: string): Promise<void> {
+  return invoke("remove_bus", { name });
+}
+
+export async function getWebBridge(): Promise<WebBridgeSettings | null> {
+  return invoke("get_web_bridge");
+}
+
+/** Serve ports to browsers over a local WebSocket, or stop with null */
+export async function setWebBridge(
+  settings: WebBridgeSettings | null
+): Promise<void> {
+  return invoke("set_web_bridge", { settings });
+}
+
+export async function getGamepadMappings(): Promise<GamepadMapping[]> {
+  return invoke("get_gamepad_mappings");
+}
+
+/** Replace the current setup's gamepad mappings; saved with presets */
+export async function setGamepadMappings(
+  mappings: GamepadMapping[]
+): Promise<void> {
+  return invoke("set_gamepad_mappings", { mappings });
+}
+
+export async function startTestSignal(signal: TestSignal): Promise<void> {
+  return invoke("start_test_signal", { signal });
+}
+
+export async function stopTestSignal(): Promise<void> {
+  return invoke("stop_test_signal");
+}
+
+export async function sendCcRamp(
+  port: string,
+  channel: number,
+  cc: number,
+  from: number,
+  to: number,
+  durationMs: number
+): Promise<void> {
+  return invoke("send_cc_ramp", { port, channel, cc, from, to, durationMs });
+}
+
+export async function getMqtt(): Promise<MqttSettings | null> {
+  return invoke("get_mqtt");
+}
+
+/** Connect to an MQTT broker, or disconnect with null */
+export async function setMqtt(settings: MqttSettings | null): Promise<void> {
+  return invoke("set_mqtt", { settings });
+}
+
 export async function startClockMonitor(
   onClockState: (state: ClockState) => void
 ): Promise<void> {
@@ -87,6 +466,151 @@ export async function startClockMonitor(
   return invoke("start_clock_monitor", { onEvent: channel });
 }
 
+export async function startClockPositionMonitor(
+  onPosition: (position: ClockPosition) => void
+): Promise<void> {
+  const channel = new Channel<ClockPosition>();
+  channel.onmessage = onPosition;
+  return invoke("start_clock_position_monitor", { onEvent: channel });
+}
+
+export async function getMiddleC(): Promise<MiddleC> {
+  return invoke("get_middle_c");
+}
+
+export async function setMiddleC(middleC: MiddleC): Promise<void> {
+  return invoke("set_middle_c", { middleC });
+}
+
+export async function listDeviceDefinitions(): Promise<DeviceDefinition[]> {
+  return invoke("list_device_definitions");
+}
+
+export async function importMidnam(
+  path: string,
+  ports: string[]
+): Promise<DeviceDefinition> {
+  return invoke("import_midnam", { path, ports });
+}
+
+export async function saveDeviceDefinition(
+  device: DeviceDefinition
+): Promise<void> {
+  return invoke("save_device_definition", { device });
+}
+
+export async function deleteDeviceDefinition(deviceId: string): Promise<void> {
+  return invoke("delete_device_definition", { deviceId });
+}
+
+export async function getDeviceCcNames(
+  port: string
+): Promise<Record<string, string>> {
+  return invoke("get_device_cc_names", { port });
+}
+
+export async function startPortActivityMonitor(
+  onPulses: (pulses: PortPulse[]) => void
+): Promise<void> {
+  const channel = new Channel<PortPulse[]>();
+  channel.onmessage = onPulses;
+  return invoke("start_port_activity_monitor", { onEvent: channel });
+}
+
+export async function getEngineStats(): Promise<EngineStats> {
+  return invoke("get_engine_stats");
+}
+
+export async function getSessionStats(): Promise<SessionStats> {
+  return invoke("get_session_stats");
+}
+
+export async function getRouteStats(): Promise<RouteStats[]> {
+  return invoke("get_route_stats");
+}
+
+export async function startStatsMonitor(
+  onStats: (stats: EngineStats) => void
+): Promise<void> {
+  const channel = new Channel<EngineStats>();
+  channel.onmessage = onStats;
+  return invoke("start_stats_monitor", { onEvent: channel });
+}
+
+export async function startRouteStatusMonitor(
+  onChange: (change: RouteStatusChange) => void
+): Promise<void> {
+  const channel = new Channel<RouteStatusChange>();
+  channel.onmessage = onChange;
+  return invoke("start_route_status_monitor", { onEvent: channel });
+}
+
+export async function setChordDetection(enabled: boolean): Promise<void> {
+  return invoke("set_chord_detection", { enabled });
+}
+
+export async function startChordMonitor(
+  onChord: (chord: DetectedChord) => void
+): Promise<void> {
+  const channel = new Channel<DetectedChord>();
+  channel.onmessage = onChord;
+  return invoke("start_chord_monitor", { onEvent: channel });
+}
+
+export async function getSoundingNotes(): Promise<HeldNotes[]> {
+  return invoke("get_sounding_notes");
+}
+
+export async function getRecentErrors(): Promise<RecentError[]> {
+  return invoke("get_recent_errors");
+}
+
+export async function startDebugCapture(durationMs: number): Promise<void> {
+  return invoke("start_debug_capture", { durationMs });
+}
+
+export async function setMessageTrap(condition: TrapCondition | null): Promise<void> {
+  return invoke("set_message_trap", { condition });
+}
+
+export async function startTrapMonitor(onHit: (hit: TrapHit) => void): Promise<void> {
+  const channel = new Channel<TrapHit>();
+  channel.onmessage = onHit;
+  return invoke("start_trap_monitor", { onEvent: channel });
+}
+
+export async function exportDebugCapture(path: string): Promise<number> {
+  return invoke("export_debug_capture", { path });
+}
+
+export async function startReplay(path: string, dryRun: boolean): Promise<number> {
+  return invoke("start_replay", { path, dryRun });
+}
+
+export async function stopReplay(): Promise<void> {
+  return invoke("stop_replay");
+}
+
+export async function startReplayMonitor(
+  onReport: (report: ReplayReport) => void
+): Promise<void> {
+  const channel = new Channel<ReplayReport>();
+  channel.onmessage = onReport;
+  return invoke("start_replay_monitor", { onEvent: channel });
+}
+
+export async function captureRetrospective(path: string): Promise<number> {
+  return invoke("capture_retrospective", { path });
+}
+
+export async function panicRoute(routeId: string): Promise<void> {
+  return invoke("panic_route", { routeId });
+}
+
+export async function injectToRoute(routeId: string, bytes: number[]): Promise<void> {
+  return invoke("inject_to_route", { routeId, bytes });
+}
+
 export async function sendTransportStart(): Promise<void> {
   return invoke("send_transport_start");
 }
@@ -94,3 +618,67 @@ export async function sendTransportStart(): Promise<void> {
 export async function sendTransportStop(): Promise<void> {
   return invoke("send_transport_stop");
 }
+
+export async function getControlBindings(): Promise<ControlBindings> {
+  return invoke("get_control_bindings");
+}
+
+export async function setTempoCcBinding(
+  binding: TempoCcBinding | null
+): Promise<void> {
+  return invoke("set_tempo_cc_binding", { binding });
+}
+
+export async function setTapTempoBinding(
+  binding: TapTempoBinding | null
+): Promise<void> {
+  return invoke("set_tap_tempo_binding", { binding });
+}
+
+export async function setTransportTriggers(
+  triggers: TransportTriggerBinding[]
+): Promise<void> {
+  return invoke("set_transport_triggers", { triggers });
+}
+
+export async function setSongSelectBinding(
+  binding: SongSelectBinding | null
+): Promise<void> {
+  return invoke("set_song_select_binding", { binding });
+}
+
+export async function startSongSelectMonitor(
+  onSongSelect: (change: SongSelectChange) => void
+): Promise<void> {
+  const channel = new Channel<SongSelectChange>();
+  channel.onmessage = onSongSelect;
+  return invoke("start_song_select_monitor", { onEvent: channel });
+}
+
+export async function queuePreset(presetId: string, bars: number): Promise<void> {
+  return invoke("queue_preset", { presetId, bars });
+}
+
+export async function cancelQueuedPreset(): Promise<void> {
+  return invoke("cancel_queued_preset");
+}
+
+export async function startPresetSwitchMonitor(
+  onSwitch: (presetId: string) => void
+): Promise<void> {
+  const channel = new Channel<string>();
+  channel.onmessage = onSwitch;
+  return invoke("start_preset_switch_monitor", { onEvent: channel });
+}
+
+export async function setRouteLearn(enabled: boolean): Promise<void> {
+  return invoke("set_route_learn", { enabled });
+}
+
+export async function startRouteSuggestionMonitor(
+  onSuggestion: (suggestion: RouteSuggestion) => void
+): Promise<void> {
+  const channel = new Channel<RouteSuggestion>();
+  channel.onmessage = onSuggestion;
+  return invoke("start_route_suggestion_monitor", { onEvent: channel });
+}
@@ -13,10 +13,12 @@ interface AppState {
 
   // Monitor
   monitorActive: boolean;
+  monitorPaused: boolean;
   activityLog: MidiActivity[];
   portActivity: Record<string, number>; // port name -> last activity timestamp
 
   // Actions
+  watchPorts: () => Promise<void>;
   refreshPorts: () => Promise<void>;
   refreshRoutes: () => Promise<void>;
   addRoute: (sourceName: string, destName: string) => Promise<void>;
@@ -25,6 +27,8 @@ interface AppState {
   updateRouteChannels: (routeId: string, filter: ChannelFilter) => Promise<void>;
   updateRouteCcMappings: (routeId: string, ccPassthrough: boolean, ccMappings: CcMapping[]) => Promise<void>;
   startMonitor: () => Promise<void>;
+  pauseMonitor: () => Promise<void>;
+  resumeMonitor: () => Promise<void>;
   clearLog: () => void;
 }
 
@@ -36,13 +40,32 @@ export const useAppStore = create<AppState>((set, get) => ({
   loadingPorts: false,
   routes: [],
   monitorActive: false,
+  monitorPaused: false,
   activityLog: [],
   portActivity: {},
 
+  watchPorts: async () => {
+    try {
+      const [inputs, outputs] = await api.listPorts();
+      set({ inputPorts: inputs, outputPorts: outputs });
+      await get().refreshRoutes();
+      await api.startPortsMonitor(([inputs, outputs]) => {
+        set({ inputPorts: inputs, outputPorts: outputs });
+      });
+      // Route status changes as connections come back after sleep
+      await api.startWakeMonitor((report) => {
+        console.log("[Store] Reconnected after sleep:", report);
+        get().refreshRoutes();
+      });
+    } catch (e) {
+      console.error("Failed to watch ports:", e);
+    }
+  },
+
   refreshPorts: async () => {
     set({ loadingPorts: true });
     try {
-      const [inputs, outputs] = await api.getPorts();
+      const [inputs, outputs] = await api.rescanPorts();
       console.log("[Store] refreshPorts: inputs=", inputs.map(p => p.id.name));
       console.log("[Store] refreshPorts: outputs=", outputs.map(p => p.id.name));
       // Force new array references to ensure React re-renders
@@ -121,6 +144,19 @@ export const useAppStore = create<AppState>((set, get) => ({
     set({ monitorActive: true });
   },
 
+  pauseMonitor: async () => {
+    await api.pauseMonitor();
+    set({ monitorPaused: true });
+  },
+
+  resumeMonitor: async () => {
+    const missed = await api.resumeMonitor();
+    set((state) => ({
+      monitorPaused: false,
+      activityLog: [...missed.reverse(), ...state.activityLog].slice(0, MAX_LOG_SIZE),
+    }));
+  },
+
   clearLog: () => {
     set({ activityLog: [] });
   },
@@ -14,14 +14,110 @@ export type ChannelFilter =
   | { Only: number[] }
   | { Except: number[] };
 
+export type MscCommand =
+  | "Go"
+  | "Stop"
+  | "Resume"
+  | "TimedGo"
+  | "Load"
+  | "Set"
+  | "Fire"
+  | "AllOff"
+  | "Restore"
+  | "Reset"
+  | "GoOff"
+  | { Other: number };
+
+export type MscFilter =
+  | "All"
+  | { Only: MscCommand[] }
+  | { Except: MscCommand[] };
+
+export type SystemCommon =
+  | "MtcQuarterFrame"
+  | "SongPosition"
+  | "SongSelect"
+  | "TuneRequest";
+
+export type SystemCommonFilter =
+  | "All"
+  | { Only: SystemCommon[] }
+  | { Except: SystemCommon[] };
+
+// Clock and transport never go through routes; these cover the rest
+export interface RealTimeStrip {
+  active_sensing: boolean;
+  system_reset: boolean;
+  undefined: boolean;
+}
+
+export interface TuningTable {
+  name: string;
+  // Deviation from 12-TET in cents, per MIDI note
+  cents: number[];
+}
+
+export type TuningMethod =
+  | { kind: "Mts" }
+  | { kind: "PitchBend"; data: { channels: number[]; bend_range: number } };
+
+export interface Microtuning {
+  table: TuningTable;
+  method: TuningMethod;
+}
+
+export type CcValueMode =
+  | { kind: "Continuous" }
+  | { kind: "Toggle" }
+  | { kind: "Threshold"; data: { threshold: number } }
+  | { kind: "Momentary"; data: { pulse_ms: number } };
+
+export type CcCurve = "Linear" | "Exponential" | "Logarithmic" | "SCurve";
+
+export interface CcNoteTrigger {
+  note: number;
+  velocity: number | null;
+  gate_ms: number | null;
+}
+
+export interface CcHighRes {
+  lsb_cc: number | null;
+  smoothing_steps: number;
+}
+
 export interface CcTarget {
   cc: number;
   channels: number[];
+  // Optional on the wire - the backend defaults to a plain linear passthrough
+  mode?: CcValueMode;
+  curve?: CcCurve;
+  invert?: boolean; // flip the incoming value before the curve
+  // Output range the curved value is rescaled into; max below min reverses it
+  min?: number | null;
+  max?: number | null;
+  offset?: number;
+  note?: CcNoteTrigger | null;
+  high_res?: CcHighRes | null;
+}
+
+export type EncoderMode =
+  | { kind: "Absolute" }
+  | { kind: "TwosComplement" }
+  | { kind: "SignedBit" }
+  | { kind: "BinaryOffset" }
+  | { kind: "IncDec"; data: { decrement_cc: number } };
+
+export interface CcCalibration {
+  min: number;
+  max: number;
+  curve?: CcCurve;
 }
 
 export interface CcMapping {
   source_cc: number;
   targets: CcTarget[];
+  encoder?: EncoderMode;
+  calibration?: CcCalibration | null;
 }
 
 export interface Route {
@@ -32,8 +128,172 @@ export interface Route {
   channels: ChannelFilter;
   cc_passthrough: boolean;
   cc_mappings: CcMapping[];
+  msc_filter: MscFilter;
+  system_common_filter: SystemCommonFilter;
+  real_time_strip?: RealTimeStrip;
+  microtuning?: Microtuning | null;
+  strip_aftertouch?: boolean;
+  latch?: boolean;
+  sustain?: boolean;
+  mono?: NotePriority | null;
+  program_change_filter?: ProgramChangeFilter;
+  program_steppers?: ProgramStepper[];
+  note_mappings?: NoteMapping[];
+  bank_select?: BankSelectSettings;
+  program_recall?: ProgramRecall[];
+  channel_rotation?: ChannelRotation | null;
+  harmony?: HarmonyVoice[];
+  voice_split?: VoiceSplit | null;
+  duplicate_filter?: DuplicateFilter | null;
+  velocity_curve?: VelocityTransform | null;
+  velocity_gate?: number | null;
+  layer?: Layer | null;
+  velocity_humanize?: VelocityHumanize | null;
+  echo?: NoteEcho | null;
+  key_range?: KeyRange | null;
+  channel_map?: ChannelMap | null;
+  status?: RouteStatus; // Runtime status, set by get_routes
+}
+
+// Program Change thinning for controllers that repeat or burst them
+export interface ProgramChangeFilter {
+  dedupe: boolean;
+  debounce_ms: number; // 0 sends right away
+}
+
+export interface ChannelRotation {
+  channels: number[]; // 1-16, in the order notes are dealt
+}
+
+export interface HarmonyVoice {
+  interval: number; // semitones from the played note
+  channel?: number | null; // 1-16; null plays on the note's channel
+}
+
+// Channels 1-16; Remap keys are source channels, unlisted ones are unchanged
+export type ChannelMap =
+  | { Force: number }
+  | { Remap: Record<number, number> };
+
+export interface DuplicateFilter {
+  window_ms: number;
+  identical?: boolean; // also drop exact repeats of other messages
+}
+
+// A route's part in a layer group: routes from one source toggled together,
+// each playing its own velocity zone
+export interface Layer {
+  group: string;
+  low_velocity: number;
+  high_velocity: number;
+}
+
+// Keyboard zone for splits; notes outside it are dropped
+export interface KeyRange {
+  low_note: number;
+  high_note: number;
 }
 
+// Note On velocities are scaled, curved, then clamped to min-max
+export interface VelocityTransform {
+  scale?: number; // default 1.0
+  gamma?: number; // above 1 plays softer, below 1 harder; default 1.0
+  min?: number; // default 1
+  max?: number; // default 127
+}
+
+export interface NoteEcho {
+  repeats: number; // 1-16
+  beats: number; // between repeats, e.g. 0.5 for eighth notes
+  decay: number; // each repeat's velocity as a fraction of the last
+}
+
+export interface VelocityHumanize {
+  amount: number; // largest nudge either way
+  seed?: number | null; // repeats the nudges run to run
+}
+
+export type VoiceStealing = "Oldest" | "Newest" | "Never";
+
+// One note per port across the route's destination and these outputs
+export interface VoiceSplit {
+  outputs: string[];
+  stealing?: VoiceStealing;
+  /** Deal notes to the ports in turn, any number to a port */
+  round_robin?: boolean;
+}
+
+export interface BankProgram {
+  msb?: number | null; // null leaves it out, or matches any
+  lsb?: number | null;
+  program: number;
+}
+
+export interface BankRemap {
+  from: BankProgram;
+  to: BankProgram;
+}
+
+export interface BankSelectSettings {
+  block: boolean;
+  remaps: BankRemap[];
+}
+
+export interface ProgramRecall {
+  channel: number; // 1-16
+  bank: BankProgram;
+}
+
+export type StepButton =
+  | { kind: "Note"; data: { note: number } }
+  | { kind: "Cc"; data: { cc: number } };
+
+export interface ProgramStepper {
+  increment: StepButton;
+  decrement: StepButton;
+  channel: number; // 1-16
+  wrap?: boolean;
+}
+
+export type NoteCcMode =
+  | { kind: "Velocity" }
+  | { kind: "Toggle"; data: { value: number } };
+
+// A note (such as a pad) sent on as a CC instead
+export interface NoteMapping {
+  note: number;
+  cc: number;
+  channels?: number[]; // 1-16; empty keeps the note's channel
+  mode?: NoteCcMode;
+}
+
+// Which held key a mono route sounds
+export type NotePriority = "Last" | "Low" | "High";
+
+export interface MatrixCell {
+  source: string;
+  destination: string;
+  route_id: string;
+  enabled: boolean;
+}
+
+export interface RoutingMatrix {
+  sources: string[];
+  destinations: string[];
+  cells: MatrixCell[];
+}
+
+export type RouteWarning =
+  | { kind: "MissingSource"; data: { route_id: string; port_name: string } }
+  | { kind: "MissingDestination"; data: { route_id: string; port_name: string } }
+  | { kind: "Cycle"; data: { route_id: string; path: string[] } }
+  | {
+      kind: "Duplicate";
+      data: { route_ids: string[]; source: string; destination: string };
+    }
+  | { kind: "NoChannels"; data: { route_id: string } }
+  | { kind: "EmptyCcTargets"; data: { route_id: string; source_cc: number } };
+
 export type MessageKind =
   | { kind: "NoteOn"; data: { note: number; velocity: number } }
   | { kind: "NoteOff"; data: { note: number; velocity: number } }
@@ -43,6 +303,16 @@ export type MessageKind =
   | { kind: "Aftertouch"; data: { value: number } }
   | { kind: "PolyAftertouch"; data: { note: number; value: number } }
   | { kind: "SysEx" }
+  | { kind: "SongSelect"; data: { song: number } }
+  | {
+      kind: "ShowControl";
+      data: {
+        device_id: number;
+        command: MscCommand;
+        cue_number: string | null;
+        cue_list: string | null;
+      };
+    }
   | { kind: "Clock" }
   | { kind: "Start" }
   | { kind: "Continue" }
@@ -50,22 +320,407 @@ export type MessageKind =
   | { kind: "Other" };
 
 export interface MidiActivity {
-  timestamp: number;
+  timestamp: number; // Wall-clock microseconds since the Unix epoch
+  delta_us: number | null; // Since the previous message on the same port
   port: string;
   channel: number | null;
   kind: MessageKind;
   raw: number[];
+  label: string | null;
+  note_name: string | null;
+}
+
+export type MiddleC = "C3" | "C4";
+
+// How Note Offs are written to an output: 0x8n, or Note On with velocity 0
+export type NoteOffStyle = "NoteOff" | "VelocityZero";
+
+export type PortDirection = "Input" | "Output";
+
+// Connections rebuilt after the system woke from sleep
+export interface WakeReport {
+  slept_ms: number;
+  inputs: string[];
+  outputs: string[];
+  missing: string[]; // Needed ports that didn't come back
+}
+
+export interface PortThroughput {
+  port: string;
+  direction: PortDirection;
+  messages_per_sec: number;
+  bytes_per_sec: number;
+}
+
+export type RouteStatus = "Active" | "Pending" | "Errored";
+
+export interface RouteStatusChange {
+  route_id: string;
+  status: RouteStatus;
+}
+
+export interface RouteStats {
+  route_id: string;
+  status: RouteStatus;
+  forwarded: number;
+  dropped: number;
+  last_activity: number | null; // Wall-clock microseconds
+}
+
+export interface DetectedChord {
+  port: string;
+  notes: number[];
+  name: string | null; // Chord or interval, null if not recognized
+}
+
+export interface HeldNotes {
+  route_id: string;
+  destination: string;
+  channel: number; // 0-indexed
+  notes: number[];
+}
+
+export interface LoopStats {
+  worst_us: number;
+  p99_us: number;
+  cpu_percent: number;
+}
+
+export type ValidationError =
+  | { BpmOutOfRange: { value: number; min: number; max: number } }
+  | { CcOutOfRange: { value: number; max: number } }
+  | { ChannelOutOfRange: { value: number; max: number } };
+
+export type EngineError =
+  | { PortConnectionFailed: { port_name: string; reason: string } }
+  | { PortDisconnected: { port_name: string } }
+  | { SendFailed: { port_name: string; reason: string } }
+  | { ValidationFailed: ValidationError }
+  | { EngineOverloaded: { loop_us: number; clock_interval_us: number } };
+
+export interface RecentError {
+  timestamp_us: number;
+  message: string;
+  error: EngineError;
+}
+
+export type RouteDecision =
+  | "Forwarded"
+  | "Inactive"
+  | "ChannelFiltered"
+  | "AftertouchStripped"
+  | "MscFiltered"
+  | "SystemCommonFiltered"
+  | "RealTimeStripped"
+  | "OutOfKeyRange"
+  | "BelowVelocityGate"
+  | "OutsideLayerVelocity"
+  | "Duplicate"
+  | "Consumed";
+
+export interface TracedOutput {
+  bytes: number[];
+  delay_us: number | null;
+  error: string | null;
+}
+
+export interface RouteTrace {
+  route_id: string;
+  destination: string;
+  decision: RouteDecision;
+  transforms: string[];
+  outputs: TracedOutput[];
+}
+
+export interface CaptureEntry {
+  timestamp_us: number;
+  port: string;
+  bytes: number[];
+  handling: "Routed" | "Transport" | "Control";
+  routes: RouteTrace[];
+}
+
+/** Fires a trap; unset fields match anything */
+export interface TrapCondition {
+  port?: string | null;
+  /** 0-15 */
+  channel?: number | null;
+  /** Status without the channel (0x80-0xE0), or the whole status for system messages */
+  message_type?: number | null;
+  /** Note or controller number */
+  data1?: number | null;
+}
+
+export interface PrecedingMessage {
+  timestamp_us: number;
+  port: string;
+  bytes: number[];
+}
+
+export interface TrapHit {
+  entry: CaptureEntry;
+  preceding: PrecedingMessage[];
+}
+
+export interface DebugCapture {
+  started_us: number;
+  duration_ms: number;
+  truncated: boolean;
+  entries: CaptureEntry[];
+}
+
+export interface ReplayReport {
+  replayed: number;
+  /** Stopped before every message was played back */
+  stopped: boolean;
+  /** What the routes would have sent, for dry runs */
+  dry_run: DebugCapture | null;
+}
+
+export interface EngineStats {
+  ports: PortThroughput[];
+  routes: RouteStats[];
+  engine_loop: LoopStats;
+}
+
+export interface PortSessionStats {
+  port: string;
+  /** Note-ons per note number (128 entries) */
+  notes: number[];
+  /** Note-on velocities in 16 bins of 8 */
+  velocities: number[];
+  /** Control changes per controller number */
+  controllers: Record<string, number>;
+}
+
+export interface SessionStats {
+  started_us: number;
+  ports: PortSessionStats[];
+}
+
+export interface PortPulse {
+  port: string;
+  direction: PortDirection;
+  count: number;
+  window_ms: number;
+}
+
+export interface PatchName {
+  bank_msb: number | null;
+  bank_lsb: number | null;
+  program: number;
+  name: string;
+}
+
+export interface DeviceDefinition {
+  id: string;
+  name: string;
+  ports: string[];
+  cc_names: Record<string, string>;
+  patches: PatchName[];
+  profile?: DeviceProfile | null;
+  // Raw messages sent to the device's outputs once connected / before closing
+  on_connect?: number[][];
+  on_disconnect?: number[][];
+}
+
+// Starting filters and transforms for new routes to a device
+export interface DeviceProfile {
+  channels?: ChannelFilter | null;
+  strip_aftertouch?: boolean;
+  cc_mappings?: CcMapping[];
+  clock?: ClockMode | null;
+}
+
+/** Gamepad buttons and axes, named for the standard controller layout */
+export type GamepadControl =
+  | "South" | "East" | "North" | "West" | "C" | "Z"
+  | "LeftTrigger" | "LeftTrigger2" | "RightTrigger" | "RightTrigger2"
+  | "Select" | "Start" | "Mode" | "LeftThumb" | "RightThumb"
+  | "DPadUp" | "DPadDown" | "DPadLeft" | "DPadRight"
+  | "LeftStickX" | "LeftStickY" | "LeftZ"
+  | "RightStickX" | "RightStickY" | "RightZ" | "DPadX" | "DPadY";
+
+/** Note: on while the control is past halfway. Cc: follows its travel. */
+export type GamepadTarget =
+  | { kind: "Note"; data: { note: number; velocity: number } }
+  | { kind: "Cc"; data: { cc: number } };
+
+/** Plays a gamepad control on the virtual "Gamepad" input */
+export interface GamepadMapping {
+  control: GamepadControl;
+  channel: number; // 0-indexed
+  target: GamepadTarget;
+}
+
+/** Clock ignores the step time and plays at its own tempo */
+export type TestPattern =
+  | { kind: "Chromatic"; data: { low: number; high: number } }
+  | { kind: "FixedNote"; data: { note: number } }
+  | { kind: "CcSweep"; data: { cc: number } }
+  | { kind: "Clock"; data: { bpm: number } };
+
+/** VirtualInput plays through the routes from the "Test Signal" input */
+export type TestSignalTarget =
+  | { kind: "Output"; data: { port: string } }
+  | { kind: "VirtualInput" };
+
+export interface TestSignal {
+  pattern: TestPattern;
+  target: TestSignalTarget;
+  channel: number; // 1-16
+  velocity: number;
+  step_ms: number;
 }
 
 export interface Preset {
   id: string;
   name: string;
   routes: Route[];
+  gamepad: GamepadMapping[];
   created_at: string;
   modified_at: string;
 }
 
+export interface TemplateSlot {
+  id: string;
+  label: string;
+  direction: PortDirection;
+}
+
+export interface TemplateRoute {
+  source: string;
+  destination: string;
+  channels: ChannelFilter;
+  strip_aftertouch: boolean;
+}
+
+export interface SetupTemplate {
+  id: string;
+  name: string;
+  description: string;
+  slots: TemplateSlot[];
+  routes: TemplateRoute[];
+  clock: Record<string, ClockMode>;
+}
+
+export interface UnavailableRoute {
+  route_id: string;
+  missing_source: string | null;
+  missing_destination: string | null;
+}
+
+export interface PortAvailability {
+  available: string[];
+  unavailable: UnavailableRoute[];
+}
+
+export interface LoadedPreset {
+  preset: Preset;
+  availability: PortAvailability;
+}
+
 export interface ClockState {
   bpm: number;
   running: boolean;
 }
+
+export interface ClockPosition {
+  bar: number;
+  beat: number;
+  tick: number;
+}
+
+export type ClockMode = "WhileRunning" | "Always";
+
+/** Whether the clock starts on launch; Restore uses the last transport state */
+export type ClockLaunch = "Stopped" | "Start" | "Restore";
+
+export interface ClockSettings {
+  mode: ClockMode;
+  /** Per-output overrides of the global mode, keyed by port name */
+  output_modes: Record<string, ClockMode>;
+  /** Defer Stop until the end of the current bar */
+  quantize_stop: boolean;
+  on_launch: ClockLaunch;
+  /** Output port names sent All Sound Off and All Notes Off after Stop */
+  silence_on_stop: string[];
+}
+
+/** Ports served to browser-based editors over a WebSocket on 127.0.0.1 */
+export interface WebBridgeSettings {
+  port: number;
+  /** Inputs whose messages are sent to browsers */
+  inputs: string[];
+  /** Outputs browsers may send to */
+  outputs: string[];
+}
+
+/**
+ * MQTT broker for automation systems. Events from `inputs` are published to
+ * `{topic_prefix}/in/{port}/note|cc|transport`; messages on
+ * `{topic_prefix}/out/{port}` are sent to `outputs`.
+ */
+export interface MqttSettings {
+  host: string;
+  port: number;
+  username: string | null;
+  password: string | null;
+  topic_prefix: string;
+  inputs: string[];
+  outputs: string[];
+  publish_notes: boolean;
+  publish_ccs: boolean;
+  publish_transport: boolean;
+}
+
+export interface TempoCcBinding {
+  port: string;
+  channel: number | null;
+  cc: number;
+  min_bpm: number;
+  max_bpm: number;
+}
+
+export type ControlTrigger =
+  | { kind: "Note"; data: { note: number } }
+  | { kind: "Cc"; data: { cc: number } };
+
+export interface TapTempoBinding {
+  port: string;
+  channel: number | null;
+  trigger: ControlTrigger;
+}
+
+export type TransportAction = "Start" | "Stop" | "Continue" | "Panic";
+
+export interface TransportTriggerBinding {
+  port: string;
+  channel: number | null;
+  trigger: ControlTrigger;
+  action: TransportAction;
+}
+
+export interface SongSelectBinding {
+  port: string;
+  presets: Record<number, string>; // Song number -> preset id
+}
+
+export interface SongSelectChange {
+  port: string;
+  song: number;
+  preset_id: string;
+}
+
+export interface RouteSuggestion {
+  port: string;
+  sample: number[];
+  kind: MessageKind | null;
+}
+
+export interface ControlBindings {
+  tempo_cc: TempoCcBinding | null;
+  tap_tempo: TapTempoBinding | null;
+  transport_triggers: TransportTriggerBinding[];
+  song_select: SongSelectBinding | null;
+}